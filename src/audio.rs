@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{SampleFormat, Stream};
+
+use tracing::error;
+
+use chip8_vm::chip8::tone::{Tone, ToneSettings};
+use chip8_vm::chip8::AudioSink;
+
+// The latest XO-CHIP pattern and pitch, picked up by the stream.
+type SharedPattern = Arc<Mutex<Option<([u8; 16], u8)>>>;
+
+// A MegaChip sample being played, mixed over the tone.
+struct Sample {
+    samples: Vec<u8>,
+    // Input samples per output sample.
+    step: f32,
+    position: f32,
+    looped: bool,
+}
+
+impl Sample {
+    fn next_sample(&mut self) -> Option<f32> {
+        if self.position as usize >= self.samples.len() {
+            if !self.looped || self.samples.is_empty() {
+                return None;
+            }
+
+            self.position = 0.0;
+        }
+
+        let value = self.samples[self.position as usize];
+
+        self.position += self.step;
+
+        Some((value as f32 - 128.0) / 128.0)
+    }
+}
+
+// The buzzer, played through the default output device. The stream runs
+// continuously and outputs silence while the tone is stopped.
+pub struct CpalAudio {
+    playing: Arc<AtomicBool>,
+    pattern: SharedPattern,
+    sample: Arc<Mutex<Option<Sample>>>,
+    sample_rate: f32,
+    _stream: Stream,
+}
+
+impl CpalAudio {
+    pub fn new(settings: ToneSettings, volume: u32) -> Result<Self, Box<dyn Error>> {
+        let host = cpal::default_host();
+
+        let device = host
+            .default_output_device()
+            .ok_or("No audio output device available")?;
+
+        let config = device.default_output_config()?;
+
+        if config.sample_format() != SampleFormat::F32 {
+            return Err(format!("Unsupported sample format: {}", config.sample_format()).into());
+        }
+
+        let sample_rate = config.sample_rate().0 as f32;
+        let channels = config.channels() as usize;
+
+        let playing = Arc::new(AtomicBool::new(false));
+
+        let stream_playing = Arc::clone(&playing);
+
+        let pattern: SharedPattern = Arc::new(Mutex::new(None));
+
+        let stream_pattern = Arc::clone(&pattern);
+
+        let sample: Arc<Mutex<Option<Sample>>> = Arc::new(Mutex::new(None));
+
+        let stream_sample = Arc::clone(&sample);
+
+        let mut tone = Tone::new(settings, sample_rate);
+
+        let volume = volume as f32 / 100.0;
+
+        let stream = device.build_output_stream(
+            &config.into(),
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                let playing = stream_playing.load(Ordering::Relaxed);
+
+                // Never block the audio thread; a missed update is picked up
+                // with the next buffer.
+                if let Ok(Some((pattern, pitch))) = stream_pattern.try_lock().as_deref() {
+                    tone.set_pattern(*pattern, *pitch);
+                }
+
+                let mut sample = stream_sample.try_lock().ok();
+
+                for frame in data.chunks_mut(channels) {
+                    let mut value = tone.next_sample(playing);
+
+                    if let Some(playback) = sample.as_deref_mut() {
+                        match playback.as_mut().and_then(Sample::next_sample) {
+                            Some(sample) => value += sample,
+                            None => *playback = None,
+                        }
+                    }
+
+                    frame.fill(value.clamp(-1.0, 1.0) * volume);
+                }
+            },
+            |error| error!("Audio stream error: {}", error),
+            None,
+        )?;
+
+        stream.play()?;
+
+        Ok(Self {
+            playing,
+            pattern,
+            sample,
+            sample_rate,
+            _stream: stream,
+        })
+    }
+}
+
+impl AudioSink for CpalAudio {
+    fn start_tone(&mut self) {
+        self.playing.store(true, Ordering::Relaxed);
+    }
+
+    fn stop_tone(&mut self) {
+        self.playing.store(false, Ordering::Relaxed);
+    }
+
+    fn set_pattern(&mut self, pattern: [u8; 16], pitch: u8) {
+        if let Ok(mut shared) = self.pattern.lock() {
+            *shared = Some((pattern, pitch));
+        }
+    }
+
+    fn play_sample(&mut self, samples: &[u8], rate: u32, looped: bool) {
+        if let Ok(mut shared) = self.sample.lock() {
+            *shared = Some(Sample {
+                samples: samples.to_vec(),
+                step: rate as f32 / self.sample_rate,
+                position: 0.0,
+                looped,
+            });
+        }
+    }
+
+    fn stop_sample(&mut self) {
+        if let Ok(mut shared) = self.sample.lock() {
+            *shared = None;
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+
+use chip8_vm::chip8::assembler::{assemble_with_images, Image};
+use chip8_vm::chip8::disassembler::Syntax;
+use chip8_vm::chip8::DebugInfo;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let mut source_path = None;
+    let mut output_path = None;
+    let mut syntax = None;
+    let mut debug_info = false;
+
+    let mut arguments = args.iter().skip(1);
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "-o" => output_path = Some(arguments.next().ok_or("-o needs a path")?.clone()),
+            "--debug-info" => debug_info = true,
+            "--syntax" => {
+                syntax = Some(
+                    arguments
+                        .next()
+                        .ok_or("--syntax needs one of: classic, octo")?
+                        .parse()?,
+                );
+            }
+            flag if flag.starts_with('-') => usage(&args[0]),
+            path => source_path = Some(path.to_string()),
+        }
+    }
+
+    let source_path = source_path.unwrap_or_else(|| usage(&args[0]));
+    let source_path = Path::new(&source_path);
+
+    // Octo sources are conventionally saved as .8o.
+    let syntax = syntax.unwrap_or(match source_path.extension() {
+        Some(extension) if extension == "8o" => Syntax::Octo,
+        _ => Syntax::Classic,
+    });
+
+    let output_path = output_path
+        .map(Into::into)
+        .unwrap_or_else(|| source_path.with_extension("ch8"));
+
+    let source = fs::read_to_string(source_path)?;
+
+    // Images are found relative to the source, like includes in most
+    // assemblers.
+    let directory = source_path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+    let (rom, mut info) = assemble_with_images(&source, syntax, move |path| {
+        load_image(&directory.join(path)).map_err(|error| format!("{}: {}", path, error))
+    })?;
+
+    fs::write(&output_path, &rom)?;
+
+    println!("Wrote {} bytes to {}", rom.len(), output_path.display());
+
+    // Next to the ROM, where the emulator and its debugger look for it.
+    if debug_info {
+        let info_path = DebugInfo::path_for(&output_path);
+
+        info.source = source_path
+            .canonicalize()
+            .ok()
+            .map(|path| path.display().to_string());
+
+        fs::write(&info_path, info.to_string())?;
+
+        println!("Wrote debug info to {}", info_path.display());
+    }
+
+    Ok(())
+}
+
+// Light, opaque pixels are set, as they're lit on the display; a 1-bit
+// screenshot from the emulator reads back as the same sprites.
+fn load_image(path: &Path) -> Result<Image, Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+
+    let info = reader.next_frame(&mut buffer)?;
+
+    let channels = info.color_type.samples();
+
+    let pixels = buffer[..info.buffer_size()]
+        .chunks_exact(channels)
+        .map(|pixel| {
+            let (color, alpha) = match info.color_type {
+                png::ColorType::GrayscaleAlpha | png::ColorType::Rgba => {
+                    pixel.split_at(channels - 1)
+                }
+                _ => (pixel, &[u8::MAX][..]),
+            };
+
+            let brightness = color.iter().map(|&value| value as usize).sum::<usize>() / color.len();
+
+            alpha[0] >= 0x80 && brightness >= 0x80
+        })
+        .collect();
+
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+    })
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} <path_to_source> [-o <path_to_rom>] [--syntax <classic|octo>] [--debug-info]",
+        program
+    );
+
+    std::process::exit(1);
+}
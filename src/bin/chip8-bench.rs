@@ -0,0 +1,279 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use chip8_vm::chip8::Interpreter;
+
+const DEFAULT_SECONDS: f64 = 3.0;
+
+// How often to look at the clock in the throughput run.
+const CLOCK_CHECK_INTERVAL: u64 = 10_000;
+
+// Exercises the ALU, random numbers, fonts, drawing and branches in a tight
+// loop. Each pass draws a font digit at a moving position.
+const SYNTHETIC_PROGRAM: [u16; 11] = [
+    0xA220, // 200: LD I, 0x220
+    0x6101, // 202: LD V1, 0x01
+    0x7001, // 204: ADD V0, 0x01
+    0x8014, // 206: ADD V0, V1
+    0x8212, // 208: AND V2, V1
+    0xC3FF, // 20A: RND V3, 0xFF
+    0xA000, // 20C: LD I, 0x000
+    0xD235, // 20E: DRW V2, V3, 5
+    0x3000, // 210: SE V0, 0x00
+    0x1204, // 212: JP 0x204
+    0x1200, // 214: JP 0x200
+];
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let mut rom_path = None;
+    let mut seconds = DEFAULT_SECONDS;
+    let mut decode_cache = false;
+    let mut jit = false;
+
+    let mut arguments = args.iter().skip(1);
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--seconds" => {
+                seconds = arguments
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|value: &f64| *value > 0.0)
+                    .ok_or("--seconds needs a positive number")?;
+            }
+            "--decode-cache" => decode_cache = true,
+            "--jit" => jit = true,
+            flag if flag.starts_with("--") => {
+                eprintln!(
+                    "Usage: {} [path_to_rom] [--seconds <n>] [--decode-cache] [--jit]",
+                    args[0]
+                );
+
+                std::process::exit(1);
+            }
+            path => rom_path = Some(path.to_string()),
+        }
+    }
+
+    if jit && !cfg!(feature = "jit") {
+        return Err("--jit needs chip8-bench to be built with --features jit".into());
+    }
+
+    let rom_data: Vec<u8> = match &rom_path {
+        Some(path) => fs::read(path)?,
+        None => SYNTHETIC_PROGRAM
+            .iter()
+            .flat_map(|opcode| opcode.to_be_bytes())
+            .collect(),
+    };
+
+    println!("ROM: {}", rom_path.as_deref().unwrap_or("synthetic"));
+
+    let duration = Duration::from_secs_f64(seconds);
+
+    #[cfg(feature = "jit")]
+    let (instructions, elapsed) = if jit {
+        measure_jit_throughput(&rom_data, duration)?
+    } else {
+        measure_throughput(&rom_data, duration, decode_cache)?
+    };
+
+    #[cfg(not(feature = "jit"))]
+    let (instructions, elapsed) = measure_throughput(&rom_data, duration, decode_cache)?;
+
+    println!(
+        "Executed {} instructions in {:.2} s: {:.2} MIPS",
+        instructions,
+        elapsed.as_secs_f64(),
+        instructions as f64 / elapsed.as_secs_f64() / 1_000_000.0
+    );
+
+    // Timing each instruction individually adds clock overhead, so the
+    // breakdown gets its own run and is only good for relative comparisons.
+    let breakdown = measure_breakdown(&rom_data, duration, decode_cache)?;
+
+    let total: Duration = breakdown.values().map(|(_, time)| *time).sum();
+
+    let mut rows: Vec<_> = breakdown.into_iter().collect();
+
+    rows.sort_by_key(|(_, (_, time))| Reverse(*time));
+
+    println!();
+    println!(
+        "{:<6} {:>12} {:>10} {:>7}",
+        "Opcode", "Count", "Avg ns", "Time"
+    );
+
+    for (name, (count, time)) in rows {
+        println!(
+            "{:<6} {:>12} {:>10.1} {:>6.1}%",
+            name,
+            count,
+            time.as_nanos() as f64 / count as f64,
+            time.as_secs_f64() / total.as_secs_f64() * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+fn create_interpreter(rom_data: &[u8], decode_cache: bool) -> Result<Interpreter, String> {
+    let mut interpreter = Interpreter::builder().decode_cache(decode_cache).build()?;
+
+    interpreter.load_program(rom_data)?;
+
+    Ok(interpreter)
+}
+
+fn measure_throughput(
+    rom_data: &[u8],
+    duration: Duration,
+    decode_cache: bool,
+) -> Result<(u64, Duration), String> {
+    let mut interpreter = create_interpreter(rom_data, decode_cache)?;
+
+    let instructions_per_frame = interpreter.instructions_per_frame as u64;
+
+    let start = Instant::now();
+
+    loop {
+        for _ in 0..CLOCK_CHECK_INTERVAL {
+            interpreter.try_execute_cycle()?;
+
+            if interpreter.cycles() % instructions_per_frame == 0 {
+                interpreter.tick_timers();
+            }
+        }
+
+        let elapsed = start.elapsed();
+
+        if elapsed >= duration {
+            return Ok((interpreter.cycles(), elapsed));
+        }
+    }
+}
+
+// Runs whole frames, since the JIT only compiles blocks that fit in the
+// instructions left in a frame.
+#[cfg(feature = "jit")]
+fn measure_jit_throughput(rom_data: &[u8], duration: Duration) -> Result<(u64, Duration), String> {
+    let mut interpreter = create_interpreter(rom_data, false)?;
+
+    let mut jit = chip8_vm::chip8::jit::Jit::new();
+
+    let instructions_per_frame = interpreter.instructions_per_frame as u64;
+
+    let frames_per_clock_check = (CLOCK_CHECK_INTERVAL / instructions_per_frame).max(1);
+
+    let start = Instant::now();
+
+    let mut instructions = 0;
+
+    loop {
+        for _ in 0..frames_per_clock_check {
+            jit.run_frame(&mut interpreter)?;
+
+            instructions += instructions_per_frame;
+        }
+
+        let elapsed = start.elapsed();
+
+        if elapsed >= duration {
+            return Ok((instructions, elapsed));
+        }
+    }
+}
+
+fn measure_breakdown(
+    rom_data: &[u8],
+    duration: Duration,
+    decode_cache: bool,
+) -> Result<HashMap<&'static str, (u64, Duration)>, String> {
+    let mut interpreter = create_interpreter(rom_data, decode_cache)?;
+
+    let instructions_per_frame = interpreter.instructions_per_frame as u64;
+
+    let mut breakdown: HashMap<&'static str, (u64, Duration)> = HashMap::new();
+
+    let start = Instant::now();
+
+    while start.elapsed() < duration {
+        let pc = interpreter.program_counter as usize;
+
+        let opcode = (interpreter.memory[pc] as u16) << 8 | interpreter.memory[pc + 1] as u16;
+
+        let instruction_start = Instant::now();
+
+        interpreter.try_execute_cycle()?;
+
+        let time = instruction_start.elapsed();
+
+        let entry = breakdown.entry(opcode_name(opcode)).or_default();
+
+        entry.0 += 1;
+        entry.1 += time;
+
+        if interpreter.cycles() % instructions_per_frame == 0 {
+            interpreter.tick_timers();
+        }
+    }
+
+    Ok(breakdown)
+}
+
+fn opcode_name(opcode: u16) -> &'static str {
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => "00E0",
+            0x00EE => "00EE",
+            _ => "0NNN",
+        },
+        0x1000 => "1NNN",
+        0x2000 => "2NNN",
+        0x3000 => "3XNN",
+        0x4000 => "4XNN",
+        0x5000 => "5XY0",
+        0x6000 => "6XNN",
+        0x7000 => "7XNN",
+        0x8000 => match opcode & 0x000F {
+            0x0 => "8XY0",
+            0x1 => "8XY1",
+            0x2 => "8XY2",
+            0x3 => "8XY3",
+            0x4 => "8XY4",
+            0x5 => "8XY5",
+            0x6 => "8XY6",
+            0x7 => "8XY7",
+            0xE => "8XYE",
+            _ => "8XY?",
+        },
+        0x9000 => "9XY0",
+        0xA000 => "ANNN",
+        0xB000 => "BNNN",
+        0xC000 => "CXNN",
+        0xD000 => "DXYN",
+        0xE000 => match opcode & 0x00FF {
+            0x9E => "EX9E",
+            0xA1 => "EXA1",
+            _ => "EX??",
+        },
+        _ => match opcode & 0x00FF {
+            0x07 => "FX07",
+            0x0A => "FX0A",
+            0x15 => "FX15",
+            0x18 => "FX18",
+            0x1E => "FX1E",
+            0x29 => "FX29",
+            0x33 => "FX33",
+            0x55 => "FX55",
+            0x65 => "FX65",
+            _ => "FX??",
+        },
+    }
+}
@@ -0,0 +1,347 @@
+use std::env;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use chip8_vm::chip8::{Instruction, Interpreter, Observer, Platform};
+
+const DEFAULT_CYCLES: u64 = 100_000;
+
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "sc8", "xo8"];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    UnsupportedOpcode,
+    Crashed,
+    Halted,
+    Running,
+}
+
+impl Status {
+    const ALL: [Status; 4] = [
+        Status::UnsupportedOpcode,
+        Status::Crashed,
+        Status::Halted,
+        Status::Running,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Status::UnsupportedOpcode => "unsupported-opcode",
+            Status::Crashed => "crashed",
+            Status::Halted => "halted",
+            Status::Running => "running",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Status::UnsupportedOpcode => "#f4c7a1",
+            Status::Crashed => "#f4a1a1",
+            Status::Halted => "#d8e8f8",
+            Status::Running => "#c4ecc4",
+        }
+    }
+}
+
+struct Outcome {
+    rom: String,
+    status: Status,
+    cycles: u64,
+    detail: String,
+}
+
+// Tells the run that the program jumped to itself, the usual way CHIP-8
+// programs end.
+struct HaltObserver(Arc<AtomicBool>);
+
+impl Observer for HaltObserver {
+    fn on_halt(&mut self, error: Option<&str>) {
+        if error.is_none() {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let mut directory = None;
+    let mut cycles = DEFAULT_CYCLES;
+    let mut platform = Platform::default();
+    let mut csv_path = None;
+    let mut html_path = None;
+
+    let mut arguments = args.iter().skip(1);
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--cycles" => {
+                cycles = arguments
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&value| value > 0)
+                    .ok_or("--cycles needs a positive number")?;
+            }
+            "--platform" => {
+                platform = arguments
+                    .next()
+                    .ok_or("--platform needs a platform name")?
+                    .parse()?;
+            }
+            "--csv" => csv_path = Some(arguments.next().ok_or("--csv needs a path")?.clone()),
+            "--html" => html_path = Some(arguments.next().ok_or("--html needs a path")?.clone()),
+            flag if flag.starts_with('-') => usage(&args[0]),
+            path => directory = Some(PathBuf::from(path)),
+        }
+    }
+
+    let directory = directory.unwrap_or_else(|| usage(&args[0]));
+
+    let mut roms = Vec::new();
+
+    find_roms(&directory, &mut roms)?;
+
+    roms.sort();
+
+    let outcomes: Vec<Outcome> = roms
+        .iter()
+        .map(|path| {
+            let name = path
+                .strip_prefix(&directory)
+                .unwrap_or(path)
+                .display()
+                .to_string();
+
+            run_rom(name, path, platform, cycles)
+        })
+        .collect();
+
+    for outcome in &outcomes {
+        println!(
+            "{:<16}{:<40}{}",
+            outcome.status.name(),
+            outcome.rom,
+            outcome.detail
+        );
+    }
+
+    println!();
+    println!("{}", summary(&outcomes));
+
+    if let Some(path) = csv_path {
+        fs::write(&path, csv_report(&outcomes))
+            .map_err(|error| format!("Failed to write {}: {}", path, error))?;
+    }
+
+    if let Some(path) = html_path {
+        let title = format!("Compatibility: {} for {} cycles", platform.name(), cycles);
+
+        fs::write(&path, html_report(&title, &outcomes))
+            .map_err(|error| format!("Failed to write {}: {}", path, error))?;
+    }
+
+    Ok(())
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} <rom_directory> [--cycles <n>] [--platform <name>] [--csv <path>] [--html <path>]",
+        program
+    );
+
+    std::process::exit(1);
+}
+
+// Every ROM in the directory and the ones below it.
+fn find_roms(directory: &Path, roms: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(directory)
+        .map_err(|error| format!("Failed to read {}: {}", directory.display(), error))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            find_roms(&path, roms)?;
+        } else if path.extension().is_some_and(|found| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|extension| found.eq_ignore_ascii_case(extension))
+        }) {
+            roms.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+// Runs the ROM like a headless run, ticking the timers every frame, until
+// the cycles run out or it stops.
+fn run_rom(rom: String, path: &Path, platform: Platform, cycles: u64) -> Outcome {
+    let outcome = |status, cycles, detail: String| Outcome {
+        rom: rom.clone(),
+        status,
+        cycles,
+        detail,
+    };
+
+    let loaded = fs::read(path)
+        .map_err(|error| error.to_string())
+        .and_then(|rom_data| {
+            let mut interpreter = Interpreter::builder().platform(platform).build()?;
+
+            interpreter.load_program(&rom_data)?;
+
+            Ok(interpreter)
+        });
+
+    let mut interpreter = match loaded {
+        Ok(interpreter) => interpreter,
+        Err(error) => return outcome(Status::Crashed, 0, error),
+    };
+
+    let halted = Arc::new(AtomicBool::new(false));
+
+    interpreter.set_observer(Some(Box::new(HaltObserver(Arc::clone(&halted)))));
+
+    let instructions_per_frame = interpreter.instructions_per_frame as u64;
+
+    for cycle in 0..cycles {
+        if let Err(error) = interpreter.try_execute_cycle() {
+            let status = if unsupported_opcode(&interpreter) {
+                Status::UnsupportedOpcode
+            } else {
+                Status::Crashed
+            };
+
+            return outcome(status, cycle, error);
+        }
+
+        if interpreter.exited() {
+            return outcome(Status::Halted, cycle + 1, "Exited with 00FD".to_string());
+        }
+
+        if halted.load(Ordering::Relaxed) {
+            let detail = format!("Stopped at {:03X}", interpreter.program_counter);
+
+            return outcome(Status::Halted, cycle + 1, detail);
+        }
+
+        if (cycle + 1) % instructions_per_frame == 0 {
+            interpreter.tick_timers();
+        }
+    }
+
+    let pc = interpreter.program_counter as usize;
+
+    // Programs that stop to ask for a key are running, but it helps to know.
+    let detail = match interpreter.memory.get(pc..pc + 2) {
+        Some(&[high, low]) if high & 0xF0 == 0xF0 && low == 0x0A => "Waiting for a key".to_string(),
+        _ => String::new(),
+    };
+
+    outcome(Status::Running, cycles, detail)
+}
+
+// Whether the instruction the program stopped on isn't one the interpreter
+// knows, as opposed to one that failed as it ran.
+fn unsupported_opcode(interpreter: &Interpreter) -> bool {
+    let pc = interpreter.program_counter as usize;
+
+    match interpreter.memory.get(pc..pc + 2) {
+        Some(&[high, low]) => Instruction::decode(u16::from_be_bytes([high, low])).is_none(),
+        _ => false,
+    }
+}
+
+fn summary(outcomes: &[Outcome]) -> String {
+    let counts: Vec<String> = Status::ALL
+        .iter()
+        .map(|&status| {
+            let count = outcomes
+                .iter()
+                .filter(|outcome| outcome.status == status)
+                .count();
+
+            format!("{} {}", count, status.name())
+        })
+        .collect();
+
+    format!("{} ROMs: {}", outcomes.len(), counts.join(", "))
+}
+
+fn csv_report(outcomes: &[Outcome]) -> String {
+    let mut report = "rom,status,cycles,detail\n".to_string();
+
+    for outcome in outcomes {
+        let _ = writeln!(
+            report,
+            "{},{},{},{}",
+            csv_field(&outcome.rom),
+            outcome.status.name(),
+            outcome.cycles,
+            csv_field(&outcome.detail)
+        );
+    }
+
+    report
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn html_report(title: &str, outcomes: &[Outcome]) -> String {
+    let mut report = String::new();
+
+    let _ = writeln!(report, "<!DOCTYPE html>");
+    let _ = writeln!(report, "<html>");
+    let _ = writeln!(report, "<head>");
+    let _ = writeln!(report, "<meta charset=\"utf-8\">");
+    let _ = writeln!(report, "<title>{}</title>", html_escape(title));
+    let _ = writeln!(
+        report,
+        "<style>body {{ font-family: sans-serif; }} table {{ border-collapse: collapse; }} td, th {{ padding: 2px 8px; text-align: left; }}</style>"
+    );
+    let _ = writeln!(report, "</head>");
+    let _ = writeln!(report, "<body>");
+    let _ = writeln!(report, "<h1>{}</h1>", html_escape(title));
+    let _ = writeln!(report, "<p>{}</p>", html_escape(&summary(outcomes)));
+    let _ = writeln!(report, "<table>");
+    let _ = writeln!(
+        report,
+        "<tr><th>ROM</th><th>Status</th><th>Cycles</th><th>Detail</th></tr>"
+    );
+
+    for outcome in outcomes {
+        let _ = writeln!(
+            report,
+            "<tr style=\"background: {}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            outcome.status.color(),
+            html_escape(&outcome.rom),
+            outcome.status.name(),
+            outcome.cycles,
+            html_escape(&outcome.detail)
+        );
+    }
+
+    let _ = writeln!(report, "</table>");
+    let _ = writeln!(report, "</body>");
+    let _ = writeln!(report, "</html>");
+
+    report
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
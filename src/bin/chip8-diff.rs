@@ -0,0 +1,124 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::ops::Range;
+
+use chip8_vm::chip8::disassembler::{disassemble, Line, Symbols, Syntax};
+use chip8_vm::chip8::patch::diff;
+use chip8_vm::chip8::MemoryMap;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let mut paths = Vec::new();
+    let mut syntax = Syntax::default();
+
+    let mut arguments = args.iter().skip(1);
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--syntax" => {
+                syntax = arguments
+                    .next()
+                    .ok_or("--syntax needs one of: classic, octo")?
+                    .parse()?;
+            }
+            flag if flag.starts_with("--") => usage(&args[0]),
+            path => paths.push(path.to_string()),
+        }
+    }
+
+    let (old_path, new_path) = match paths.as_slice() {
+        [old_path, new_path] => (old_path, new_path),
+        _ => usage(&args[0]),
+    };
+
+    let old_rom = fs::read(old_path)?;
+    let new_rom = fs::read(new_path)?;
+
+    let origin = MemoryMap::default().program_address;
+
+    let ranges = diff(&old_rom, &new_rom);
+
+    if ranges.is_empty() {
+        println!("ROMs are identical");
+
+        return Ok(());
+    }
+
+    let old_lines =
+        disassemble(&old_rom, origin, &[]).map_err(|error| format!("{}: {}", old_path, error))?;
+    let new_lines =
+        disassemble(&new_rom, origin, &[]).map_err(|error| format!("{}: {}", new_path, error))?;
+
+    println!("--- {}", old_path);
+    println!("+++ {}", new_path);
+
+    let changed: usize = ranges.iter().map(|range| range.len()).sum();
+
+    for range in &ranges {
+        println!(
+            "@@ {:03X}-{:03X} ({}) @@",
+            origin as usize + range.start,
+            origin as usize + range.end - 1,
+            plural(range.len(), "byte")
+        );
+
+        print_lines('-', &old_lines, range, origin, syntax);
+        print_lines('+', &new_lines, range, origin, syntax);
+    }
+
+    println!(
+        "{} changed in {}",
+        plural(changed, "byte"),
+        plural(ranges.len(), "range")
+    );
+
+    // Like diff, exit with 1 when the files differ.
+    std::process::exit(1);
+}
+
+// Prints every line of a listing that overlaps the changed bytes, so partly
+// changed instructions are shown whole.
+fn print_lines(prefix: char, lines: &[Line], range: &Range<usize>, origin: u16, syntax: Syntax) {
+    let start = origin as usize + range.start;
+    let end = origin as usize + range.end;
+
+    for line in lines {
+        let address = line.address as usize;
+
+        if address + line.bytes.len() <= start || address >= end {
+            continue;
+        }
+
+        let hex: String = line
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+
+        println!(
+            "{} {:03X}: {:<4}  {}",
+            prefix,
+            address,
+            hex,
+            syntax.line(line, &Symbols::default())
+        );
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}s", count, noun),
+    }
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} <path_to_old_rom> <path_to_new_rom> [--syntax <classic|octo>]",
+        program
+    );
+
+    std::process::exit(2);
+}
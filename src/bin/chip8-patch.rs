@@ -0,0 +1,57 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chip8_vm::chip8::patch;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let mut paths = Vec::new();
+    let mut output_path = None;
+
+    let mut arguments = args.iter().skip(1);
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "-o" => output_path = Some(arguments.next().ok_or("-o needs a path")?.clone()),
+            flag if flag.starts_with('-') => usage(&args[0]),
+            path => paths.push(path.to_string()),
+        }
+    }
+
+    let (rom_path, patch_path) = match paths.as_slice() {
+        [rom_path, patch_path] => (Path::new(rom_path), Path::new(patch_path)),
+        _ => usage(&args[0]),
+    };
+
+    // Without -o, game.ch8 is patched into game.patched.ch8.
+    let output_path = output_path.map(PathBuf::from).unwrap_or_else(|| {
+        let extension = rom_path
+            .extension()
+            .and_then(|extension| extension.to_str());
+
+        rom_path.with_extension(format!("patched.{}", extension.unwrap_or("ch8")))
+    });
+
+    let mut rom = fs::read(rom_path)?;
+
+    patch::apply(&mut rom, &fs::read(patch_path)?)
+        .map_err(|error| format!("{}: {}", patch_path.display(), error))?;
+
+    fs::write(&output_path, &rom)?;
+
+    println!("Wrote {} bytes to {}", rom.len(), output_path.display());
+
+    Ok(())
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} <path_to_rom> <path_to_patch> [-o <path_to_output>]",
+        program
+    );
+
+    std::process::exit(1);
+}
@@ -0,0 +1,258 @@
+use std::env;
+use std::error::Error;
+use std::io::BufReader;
+use std::net::TcpStream;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::thread;
+
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::{Window, WindowId};
+
+use softbuffer::{Context, Surface};
+
+use chip8_vm::chip8::stream::read_magic;
+use chip8_vm::chip8::StreamMessage;
+
+// Watches and plays a ROM running elsewhere with `chip8_vm --stream`.
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let address = match args.as_slice() {
+        [_, address] if !address.starts_with("--") => address.clone(),
+        _ => usage(&args[0]),
+    };
+
+    let stream = TcpStream::connect(&address)
+        .map_err(|error| format!("Failed to connect to {}: {}", address, error))?;
+
+    let _ = stream.set_nodelay(true);
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    read_magic(&mut reader).map_err(|error| format!("{}: {}", address, error))?;
+
+    let event_loop = EventLoop::<ViewerEvent>::with_user_event().build()?;
+
+    let event_loop_proxy = event_loop.create_proxy();
+
+    thread::spawn(move || loop {
+        let event = match StreamMessage::read_from(&mut reader) {
+            Ok(message) => ViewerEvent::Message(message),
+            Err(error) => ViewerEvent::Disconnected(error.to_string()),
+        };
+
+        let disconnected = matches!(event, ViewerEvent::Disconnected(_));
+
+        if event_loop_proxy.send_event(event).is_err() || disconnected {
+            break;
+        }
+    });
+
+    let mut viewer = Viewer {
+        address,
+        stream,
+        window: None,
+        surface: None,
+        frame: None,
+    };
+
+    event_loop.run_app(&mut viewer)?;
+
+    Ok(())
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!("Usage: {} <host:port>", program);
+
+    std::process::exit(1);
+}
+
+enum ViewerEvent {
+    Message(StreamMessage),
+    Disconnected(String),
+}
+
+struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+struct Viewer {
+    address: String,
+    // Key presses go back to the emulator on this.
+    stream: TcpStream,
+    window: Option<Arc<Window>>,
+    surface: Option<Surface<Arc<Window>, Arc<Window>>>,
+    frame: Option<Frame>,
+}
+
+impl Viewer {
+    fn draw(&mut self) -> Result<(), Box<dyn Error>> {
+        let (window, surface, frame) = match (&self.window, &mut self.surface, &self.frame) {
+            (Some(window), Some(surface), Some(frame)) => (window, surface, frame),
+            _ => return Ok(()),
+        };
+
+        let size = window.inner_size();
+
+        let (width, height) = match (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+            (Some(width), Some(height)) => (width, height),
+            _ => return Ok(()),
+        };
+
+        surface.resize(width, height)?;
+
+        let mut buffer = surface.buffer_mut()?;
+
+        buffer.fill(0xFF000000);
+
+        let buffer_width = size.width as usize;
+
+        let scale = (buffer_width / frame.width).min(size.height as usize / frame.height);
+        let left = (buffer_width - frame.width * scale) / 2;
+        let top = (size.height as usize - frame.height * scale) / 2;
+
+        let row_bytes = frame.width / 8;
+
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                if (frame.pixels[y * row_bytes + x / 8] >> (7 - x % 8)) & 0x1 == 0 {
+                    continue;
+                }
+
+                for draw_y in top + y * scale..top + (y + 1) * scale {
+                    let row_start = draw_y * buffer_width + left + x * scale;
+
+                    buffer[row_start..row_start + scale].fill(0xFFFFFFFF);
+                }
+            }
+        }
+
+        buffer.present()?;
+
+        Ok(())
+    }
+
+    fn send_key(&mut self, key: u8, pressed: bool) {
+        let message = StreamMessage::Key { key, pressed };
+
+        if let Err(error) = message.write_to(&mut self.stream) {
+            eprintln!("Failed to send a key press: {}", error);
+        }
+    }
+}
+
+impl ApplicationHandler<ViewerEvent> for Viewer {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+
+        let attributes = Window::default_attributes()
+            .with_title(format!("chip8_vm: {}", self.address))
+            .with_inner_size(LogicalSize::new(640, 320));
+
+        let window = Arc::new(
+            event_loop
+                .create_window(attributes)
+                .expect("failed to create the viewer window"),
+        );
+
+        let context = Context::new(Arc::clone(&window)).expect("failed to create a context");
+
+        let surface =
+            Surface::new(&context, Arc::clone(&window)).expect("failed to create a surface");
+
+        self.window = Some(window);
+        self.surface = Some(surface);
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: ViewerEvent) {
+        match event {
+            ViewerEvent::Message(StreamMessage::Frame {
+                width,
+                height,
+                pixels,
+            }) => {
+                self.frame = Some(Frame {
+                    width: width as usize,
+                    height: height as usize,
+                    pixels,
+                });
+
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+            // The emulator never sends keys.
+            ViewerEvent::Message(StreamMessage::Key { .. }) => {}
+            ViewerEvent::Disconnected(error) => {
+                eprintln!("Lost the connection to {}: {}", self.address, error);
+
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::RedrawRequested => {
+                if let Err(error) = self.draw() {
+                    eprintln!("Failed to draw: {}", error);
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key,
+                        state,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if physical_key == PhysicalKey::Code(KeyCode::Escape) {
+                    event_loop.exit();
+                } else if let Some(key) = keypad_key(physical_key) {
+                    self.send_key(key, state == ElementState::Pressed);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// The same keyboard layout as the emulator window.
+fn keypad_key(key: PhysicalKey) -> Option<u8> {
+    let code = match key {
+        PhysicalKey::Code(code) => code,
+        PhysicalKey::Unidentified(_) => return None,
+    };
+
+    match code {
+        KeyCode::Digit1 => Some(0x1),
+        KeyCode::Digit2 => Some(0x2),
+        KeyCode::Digit3 => Some(0x3),
+        KeyCode::Digit4 => Some(0xC),
+        KeyCode::KeyQ => Some(0x4),
+        KeyCode::KeyW => Some(0x5),
+        KeyCode::KeyE => Some(0x6),
+        KeyCode::KeyR => Some(0xD),
+        KeyCode::KeyA => Some(0x7),
+        KeyCode::KeyS => Some(0x8),
+        KeyCode::KeyD => Some(0x9),
+        KeyCode::KeyF => Some(0xE),
+        KeyCode::KeyZ => Some(0xA),
+        KeyCode::KeyX => Some(0x0),
+        KeyCode::KeyC => Some(0xB),
+        KeyCode::KeyV => Some(0xF),
+        _ => None,
+    }
+}
@@ -0,0 +1,68 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+
+use chip8_vm::chip8::quirk_test::{detect_quirks, QuirkTarget};
+use chip8_vm::chip8::Platform;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let mut rom_path = None;
+    let mut target = QuirkTarget::default();
+    let mut platform = Platform::default();
+
+    let mut arguments = args.iter().skip(1);
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--target" => {
+                target = arguments
+                    .next()
+                    .ok_or("--target needs one of: chip8, schip, xochip")?
+                    .parse()?;
+            }
+            "--platform" => {
+                platform = arguments
+                    .next()
+                    .ok_or("--platform needs a platform name")?
+                    .parse()?;
+            }
+            flag if flag.starts_with('-') => usage(&args[0]),
+            path => rom_path = Some(path.to_string()),
+        }
+    }
+
+    let rom_path = rom_path.unwrap_or_else(|| usage(&args[0]));
+
+    let rom = fs::read(&rom_path)?;
+
+    let quirks = detect_quirks(&rom, platform, target)?.to_list();
+
+    for setting in quirks.split(',') {
+        let (name, state) = match setting.strip_prefix("no-") {
+            Some(name) => (name, "off"),
+            None => (setting, "on"),
+        };
+
+        println!("{:<18}{}", name, state);
+    }
+
+    println!();
+    println!(
+        "Run with: --platform {} --quirks {}",
+        platform.name(),
+        quirks
+    );
+
+    Ok(())
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} <path_to_quirks_test_rom> [--target <chip8|schip|xochip>] [--platform <name>]",
+        program
+    );
+
+    std::process::exit(1);
+}
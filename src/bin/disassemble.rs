@@ -1,99 +1,407 @@
-use std::{env, fs};
-use std::io;
+use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
 
-fn main() -> io::Result<()> {
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use chip8_vm::chip8::assembler::assemble;
+use chip8_vm::chip8::disassembler::{
+    disassemble, write_call_graph, write_cross_references, write_listing, Line, Symbols, Syntax,
+};
+use chip8_vm::chip8::{parse_address, MemoryMap};
+
+// How long to wait after a change for the writer to finish; assemblers and
+// editors often write a file in several steps.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+}
+
+struct Settings {
+    rom_path: String,
+    output_path: Option<String>,
+    symbols_path: Option<String>,
+    callgraph_path: Option<String>,
+    data: Vec<RangeInclusive<u16>>,
+    syntax: Syntax,
+    format: Format,
+    verify: bool,
+    xref: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 2 {
-        eprintln!("Usage: {} <path_to_rom>", args[0]);
-    }
+    let mut rom_path = None;
+    let mut output_path = None;
+    let mut symbols_path = None;
+    let mut callgraph_path = None;
+    let mut data = Vec::new();
+    let mut syntax = Syntax::default();
+    let mut format = Format::Text;
+    let mut verify = false;
+    let mut xref = false;
+    let mut watch = false;
 
-    let rom_path = &args[1];
-
-    println!("Rom file: {}", rom_path);
-
-    let rom_data: Vec<u8> = fs::read(rom_path).unwrap();
-
-    for (index, opcode) in rom_data.chunks(2).enumerate() {
-        match opcode {
-            &[high_byte, low_byte] => {
-                print!("{:#012x}: {:02x}{:02x}", 0x200 + (2 * index), high_byte, low_byte);
-
-                let nnn = ((high_byte & 0x0F) as u16) << 8 | (low_byte as u16);
-                let x = high_byte & 0x0F;
-                let y = low_byte >> 4;
-                let kk = low_byte;
-                let n = low_byte & 0x0F;
-
-                let description = match(
-                    (high_byte >> 4) & 0xF,
-                    high_byte & 0xF,
-                    (low_byte >> 4) & 0xF,
-                    low_byte & 0xF
-                ) {
-                    (0x0, 0x0, 0x0, 0x0) => {
-                        String::from("NOP")
-                    }
-
-                    (0x0, 0x0, 0xE, 0x0) => {
-                        String::from("CLS")
-                    }
-                    
-                    (0x0, 0x0, 0xE, 0xE) => {
-                        String::from("RET")
-                    }
-
-                    (0x0, _, _, _) => {
-                        format!("SYS {:#012X}", nnn)
-                    }
-
-                    (0x1, _, _, _) => {
-                        format!("JP {:#012X}", nnn)
-                    }
-
-                    (0x2, _, _, _) => {
-                        format!("CALL {:012X}", nnn)
-                    }
-
-                    (0x3, _, _, _) => {
-                        format!("SE V{:01x}, {:#02X}", x, kk)
-                    }
-
-                    (0x6, _, _, _) => {
-                        format!("LD V{:01x}, {:#02X}", x, kk)
-                    }
-
-                    (0x7, _, _, _) => {
-                        format!("ADD V{:01x}, {}", x, kk)
-                    }
-
-                    (0xA, _, _, _) => {
-                        format!("LD I, {:#03X}", nnn)
-                    }
-
-                    (0xD, _, _, _) => {
-                        format!("DRW V{:01x}, V{:01x}, {:#01X}", x, y, n)
-                    }
-
-                    _ => {
-                        // eprintln!("Unsupported opcode: {:02x}{:02x}", high_byte, low_byte);
-
-                        String::new()
-                    }
-                };
+    let mut arguments = args.iter().skip(1);
 
-                print!(" ; {}", description);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--syntax" => {
+                syntax = arguments
+                    .next()
+                    .ok_or("--syntax needs one of: classic, octo")?
+                    .parse()?;
+            }
+            "-o" => output_path = Some(arguments.next().ok_or("-o needs a path")?.clone()),
+            "--symbols" => {
+                symbols_path = Some(arguments.next().ok_or("--symbols needs a path")?.clone())
+            }
+            "--callgraph" => {
+                callgraph_path = Some(arguments.next().ok_or("--callgraph needs a path")?.clone())
+            }
+            "--data" => {
+                let range = arguments
+                    .next()
+                    .ok_or("--data needs a range like 0x300-0x31F")?;
 
-                println!();
+                data.push(parse_range(range)?);
             }
+            "--format" => {
+                format = match arguments.next().map(String::as_str) {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    _ => return Err("--format needs one of: text, json".into()),
+                };
+            }
+            "--verify" => verify = true,
+            "--xref" => xref = true,
+            "--watch" => watch = true,
+            flag if flag.starts_with("--") => usage(&args[0]),
+            path => rom_path = Some(path.to_string()),
+        }
+    }
+
+    let settings = Settings {
+        rom_path: rom_path.unwrap_or_else(|| usage(&args[0])),
+        output_path,
+        symbols_path,
+        callgraph_path,
+        data,
+        syntax,
+        format,
+        verify,
+        xref,
+    };
+
+    if watch {
+        if settings.rom_path == "-" {
+            return Err("--watch needs a ROM file, not stdin".into());
+        }
+
+        return watch_rom(&settings);
+    }
+
+    run(&settings)
+}
+
+fn run(settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let Settings {
+        rom_path,
+        output_path,
+        symbols_path,
+        callgraph_path,
+        data,
+        syntax,
+        format,
+        verify,
+        xref,
+    } = settings;
+
+    let (syntax, format) = (*syntax, *format);
+
+    // "-" reads the ROM from stdin, so the disassembler fits in a pipeline.
+    let rom_data: Vec<u8> = match rom_path.as_str() {
+        "-" => {
+            let mut rom_data = Vec::new();
+
+            io::stdin().read_to_end(&mut rom_data)?;
+
+            rom_data
+        }
+        path => fs::read(path)?,
+    };
+
+    let lines = disassemble(&rom_data, MemoryMap::default().program_address, data)?;
+
+    let mut symbols = match &symbols_path {
+        Some(path) => Symbols::parse(&fs::read_to_string(path)?)
+            .map_err(|error| format!("{}: {}", path, error))?,
+        None => Symbols::default(),
+    };
+
+    // Labels can only go at the start of a line of the listing, which for
+    // data is every byte.
+    let starts: HashSet<u16> = lines
+        .iter()
+        .flat_map(|line| {
+            let length = if line.is_data() { line.bytes.len() } else { 1 };
+
+            line.addresses().take(length)
+        })
+        .collect();
+
+    symbols.retain(|address| starts.contains(&address));
+
+    if let Some(path) = callgraph_path {
+        let mut graph = BufWriter::new(File::create(path)?);
+
+        write_call_graph(&mut graph, &lines, &symbols)?;
+
+        graph.flush()?;
+    }
+
+    if *verify {
+        return verify_round_trip(&rom_data, &lines, &symbols, syntax);
+    }
+
+    let mut output: Box<dyn Write> = match &output_path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
 
-            _ => {
-                panic!("Opcodes without two bytes not supported!");
+    match format {
+        Format::Text => {
+            let rom_name = if rom_path == "-" { "stdin" } else { rom_path };
+
+            write_text(&mut output, rom_name, &lines, &symbols, syntax)?;
+
+            if *xref {
+                writeln!(output)?;
+
+                write_cross_references(&mut output, &lines, &symbols, syntax)?;
             }
         }
+        Format::Json => write_json(&mut output, &lines, &symbols)?,
     }
 
-    println!();
+    output.flush()?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Runs again every time the ROM or symbols file is written, until
+// interrupted. Errors, such as a ROM caught half-written, are reported and
+// the next write tried again.
+fn watch_rom(settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let (sender, receiver) = channel();
+
+    let mut watcher = notify::recommended_watcher(sender)?;
+
+    let mut watched = Vec::new();
+
+    // The directories are watched rather than the files, since a file that's
+    // replaced by renaming a new one over it would end a watch on it.
+    for path in [Some(&settings.rom_path), settings.symbols_path.as_ref()]
+        .into_iter()
+        .flatten()
+    {
+        let path = Path::new(path).canonicalize()?;
+
+        if let Some(directory) = path.parent() {
+            watcher.watch(directory, RecursiveMode::NonRecursive)?;
+        }
+
+        watched.push(path);
+    }
+
+    // A listing on the terminal replaces the last one rather than scrolling.
+    let clear_screen = settings.output_path.is_none() && io::stdout().is_terminal();
+
+    loop {
+        if clear_screen {
+            print!("\x1B[2J\x1B[H");
+        }
+
+        if let Err(error) = run(settings) {
+            eprintln!("{}: {}", settings.rom_path, error);
+        }
+
+        eprintln!("Watching {} for changes", settings.rom_path);
+
+        wait_for_change(&receiver, &watched)?;
+    }
+}
+
+fn wait_for_change(
+    receiver: &Receiver<notify::Result<Event>>,
+    watched: &[PathBuf],
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        let event = receiver.recv()??;
+
+        let written = match event.kind {
+            EventKind::Modify(ModifyKind::Metadata(_)) => false,
+            EventKind::Create(_) | EventKind::Modify(_) => true,
+            _ => false,
+        };
+
+        if written && event.paths.iter().any(|path| watched.contains(path)) {
+            thread::sleep(SETTLE_TIME);
+
+            while receiver.try_recv().is_ok() {}
+
+            return Ok(());
+        }
+    }
+}
+
+fn write_text(
+    output: &mut impl Write,
+    rom_name: &str,
+    lines: &[Line],
+    symbols: &Symbols,
+    syntax: Syntax,
+) -> io::Result<()> {
+    writeln!(output, "{} Rom file: {}", syntax.comment(), rom_name)?;
+
+    write_listing(output, lines, symbols, syntax)
+}
+
+// Reassembles the listing and compares it with the ROM byte by byte, so the
+// listing can be trusted as a starting point for patches.
+fn verify_round_trip(
+    rom_data: &[u8],
+    lines: &[Line],
+    symbols: &Symbols,
+    syntax: Syntax,
+) -> Result<(), Box<dyn Error>> {
+    let mut listing = Vec::new();
+
+    write_listing(&mut listing, lines, symbols, syntax)?;
+
+    let reassembled = assemble(&String::from_utf8(listing)?, syntax)
+        .map_err(|error| format!("Reassembling the listing failed: {}", error))?;
+
+    let origin = MemoryMap::default().program_address as usize;
+
+    let byte = |bytes: &[u8], index: usize| match bytes.get(index) {
+        Some(byte) => format!("{:02X}", byte),
+        None => "--".to_string(),
+    };
+
+    let mut differences = 0;
+
+    for index in 0..rom_data.len().max(reassembled.len()) {
+        if rom_data.get(index) != reassembled.get(index) {
+            println!(
+                "{:03X}: {} != {}",
+                origin + index,
+                byte(rom_data, index),
+                byte(&reassembled, index)
+            );
+
+            differences += 1;
+        }
+    }
+
+    if differences > 0 {
+        return Err(format!(
+            "{} of {} bytes differ after reassembling the {} listing",
+            differences,
+            rom_data.len(),
+            syntax.name()
+        )
+        .into());
+    }
+
+    println!(
+        "Round trip OK: the {} listing reassembles to the same {} bytes",
+        syntax.name(),
+        rom_data.len()
+    );
+
+    Ok(())
+}
+
+// One record per line. The mnemonic and operands are always in the classic
+// syntax, which splits into the two cleanly.
+fn write_json(output: &mut impl Write, lines: &[Line], symbols: &Symbols) -> io::Result<()> {
+    writeln!(output, "[")?;
+
+    for (index, line) in lines.iter().enumerate() {
+        let statement = Syntax::Classic.line(line, symbols);
+
+        let (mnemonic, operands) = statement.split_once(' ').unwrap_or((&statement, ""));
+
+        let operands: Vec<String> = operands
+            .split(", ")
+            .filter(|operand| !operand.is_empty())
+            .map(json_string)
+            .collect();
+
+        writeln!(
+            output,
+            "  {{\"address\": {}, \"opcode\": {}, \"mnemonic\": {}, \"operands\": [{}], \"is_data\": {}}}{}",
+            line.address,
+            json_string(&hex(&line.bytes)),
+            json_string(mnemonic),
+            operands.join(", "),
+            line.is_data(),
+            if index + 1 < lines.len() { "," } else { "" }
+        )?;
+    }
+
+    writeln!(output, "]")
+}
+
+// An inclusive range of addresses, e.g. 0x300-0x31F.
+fn parse_range(range: &str) -> Result<RangeInclusive<u16>, String> {
+    range
+        .split_once('-')
+        .and_then(|(start, end)| Some(parse_address(start)?..=parse_address(end)?))
+        .ok_or_else(|| format!("Invalid address range: {}", range))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for character in text.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            character if character.is_control() => {
+                quoted.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => quoted.push(character),
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} <path_to_rom|-> [-o <path_to_listing>] [--symbols <path>] [--data <start-end>]... [--syntax <classic|octo>] [--format <text|json>] [--xref] [--callgraph <path>] [--verify] [--watch]",
+        program
+    );
+
+    std::process::exit(1);
+}
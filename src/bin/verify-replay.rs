@@ -0,0 +1,30 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+
+use chip8_vm::chip8::Replay;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() != 3 {
+        eprintln!("Usage: {} <path_to_rom> <path_to_replay>", args[0]);
+
+        std::process::exit(1);
+    }
+
+    let rom = fs::read(&args[1])?;
+
+    let replay = Replay::parse(&fs::read_to_string(&args[2])?)
+        .map_err(|error| format!("{}: {}", args[2], error))?;
+
+    replay.verify(&rom)?;
+
+    println!(
+        "Verified {} frames, ending in state {:016x}",
+        replay.frames(),
+        replay.final_hash
+    );
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+// Addresses as every tool accepts them: hex with a 0x or 0X prefix, as they
+// are usually written and as listings print them, or plain decimal.
+pub fn parse_address(text: &str) -> Option<u16> {
+    let text = text.trim();
+
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::parse_address;
+
+    #[test]
+    fn test_parse_address_reads_hex_with_either_prefix_or_decimal() {
+        assert_eq!(parse_address("0x1F"), Some(0x1F));
+        assert_eq!(parse_address("0X1F"), Some(0x1F));
+        assert_eq!(parse_address(" 512 "), Some(512));
+        assert_eq!(parse_address("1F"), None, "Hex should need a prefix!");
+        assert_eq!(parse_address("0x10000"), None);
+    }
+}
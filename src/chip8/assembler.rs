@@ -0,0 +1,1596 @@
+use std::collections::HashMap;
+
+use crate::chip8::disassembler::Syntax;
+use crate::chip8::{parse_address, DebugInfo, Instruction, MemoryMap};
+
+// Assembles a program written in either syntax the disassembler produces into
+// ROM bytes, to be loaded at the usual program address.
+pub fn assemble(source: &str, syntax: Syntax) -> Result<Vec<u8>, String> {
+    assemble_with_debug_info(source, syntax).map(|(rom, _)| rom)
+}
+
+// Like assemble, also noting the source line of every instruction and the
+// address of every label.
+pub fn assemble_with_debug_info(
+    source: &str,
+    syntax: Syntax,
+) -> Result<(Vec<u8>, DebugInfo), String> {
+    assemble_with_images(source, syntax, |path| {
+        Err(format!(
+            "can't load '{}': images are only read when assembling a file",
+            path
+        ))
+    })
+}
+
+// Like assemble_with_debug_info, getting the images that IMAGE and :image
+// name from `load_image`.
+pub fn assemble_with_images(
+    source: &str,
+    syntax: Syntax,
+    load_image: impl Fn(&str) -> Result<Image, String> + 'static,
+) -> Result<(Vec<u8>, DebugInfo), String> {
+    let mut assembler = Assembler::new(Box::new(load_image));
+
+    match syntax {
+        Syntax::Classic => assembler.classic(source)?,
+        Syntax::Octo => OctoParser::new(&mut assembler, source).parse()?,
+    }
+
+    assembler.finish()
+}
+
+// A 1-bit image, such as a PNG drawn in an image editor, with its pixels row
+// by row.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<bool>,
+}
+
+impl Image {
+    // The rows of the sprite in a region of the image, each a byte for every
+    // 8 pixels across, leftmost pixel in the highest bit.
+    pub fn sprite(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<Vec<u8>, String> {
+        if width == 0 || height == 0 || x + width > self.width || y + height > self.height {
+            return Err(format!(
+                "region {}x{} at {},{} isn't inside the {}x{} image",
+                width, height, x, y, self.width, self.height
+            ));
+        }
+
+        let mut rows = Vec::new();
+
+        for row in y..y + height {
+            let pixels = &self.pixels[row * self.width + x..][..width];
+
+            for chunk in pixels.chunks(8) {
+                let byte = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |byte, (bit, &set)| byte | (set as u8) << (7 - bit));
+
+                rows.push(byte);
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+type ImageLoader = Box<dyn Fn(&str) -> Result<Image, String>>;
+
+// How deeply classic macros may use other macros, and how many times Octo
+// macros may be expanded in all; either only runs out when a macro uses
+// itself.
+const MAX_MACRO_DEPTH: usize = 64;
+const MAX_MACRO_EXPANSIONS: usize = 4096;
+
+// An address that can only be filled in once the whole program has been
+// read, because its expression uses a label defined further down.
+struct Fixup {
+    offset: usize,
+    expression: String,
+    line: usize,
+}
+
+struct Assembler {
+    origin: u16,
+    output: Vec<u8>,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, i64>,
+    fixups: Vec<Fixup>,
+    load_image: ImageLoader,
+    // The line being assembled, or 0 for code the assembler adds itself.
+    line: usize,
+    debug_info: DebugInfo,
+}
+
+impl Assembler {
+    fn new(load_image: ImageLoader) -> Self {
+        Self {
+            origin: MemoryMap::default().program_address,
+            output: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            fixups: Vec::new(),
+            load_image,
+            line: 0,
+            debug_info: DebugInfo::default(),
+        }
+    }
+
+    fn here(&self) -> Result<u16, String> {
+        u16::try_from(self.origin as usize + self.output.len())
+            .map_err(|_| "the program runs past address 0xFFFF".to_string())
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> Result<(), String> {
+        let here = self.here()?;
+
+        if self.line > 0 {
+            self.debug_info.add_line(here, self.line);
+        }
+
+        self.output
+            .extend_from_slice(&instruction.encode().to_be_bytes());
+
+        Ok(())
+    }
+
+    fn define_label(&mut self, name: &str, line: usize) -> Result<(), String> {
+        let here = self
+            .here()
+            .map_err(|error| format!("Line {}: {}", line, error))?;
+
+        if self.labels.insert(name.to_string(), here).is_some() {
+            return Err(format!(
+                "Line {}: label '{}' is already defined",
+                line, name
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Evaluates an operand with the constants and labels defined so far. A
+    // name on its own is looked up first, since Octo names may contain
+    // characters that are operators in expressions.
+    fn resolve<'t>(&self, text: &'t str) -> Result<Evaluation<'t>, String> {
+        let lookup = |name: &str| {
+            self.constants
+                .get(name)
+                .copied()
+                .or_else(|| self.labels.get(name).map(|&address| address as i64))
+        };
+
+        match lookup(text) {
+            Some(value) => Ok(Evaluation::Value(value)),
+            None => evaluate(text, &lookup),
+        }
+    }
+
+    // Values can't wait for labels further down, unlike addresses.
+    fn value(&self, text: &str) -> Result<i64, String> {
+        match self.resolve(text)? {
+            Evaluation::Value(value) => Ok(value),
+            Evaluation::Undefined(name) => Err(format!(
+                "'{}' must be defined before it's used as a value",
+                name
+            )),
+        }
+    }
+
+    // Resolves an address operand of the instruction about to be emitted.
+    // Addresses using labels that aren't defined yet resolve to 0 for now and
+    // are patched in by finish().
+    fn address(&mut self, text: &str, line: usize) -> Result<u16, String> {
+        match self
+            .resolve(text)
+            .map_err(|error| format!("Line {}: {}", line, error))?
+        {
+            Evaluation::Value(address) => check_address(address, line),
+            Evaluation::Undefined(_) => {
+                self.fixups.push(Fixup {
+                    offset: self.output.len(),
+                    expression: text.to_string(),
+                    line,
+                });
+
+                Ok(0)
+            }
+        }
+    }
+
+    // Emits a region of an image as sprite data, or all of it without one.
+    fn image(&mut self, path: &str, region: Option<[i64; 4]>) -> Result<(), String> {
+        let image = (self.load_image)(path)?;
+
+        let region = region.unwrap_or([0, 0, image.width as i64, image.height as i64]);
+
+        let [x, y, width, height] =
+            region.map(|value| usize::try_from(value).unwrap_or(usize::MAX));
+
+        let rows = image
+            .sprite(x, y, width, height)
+            .map_err(|error| format!("{}: {}", path, error))?;
+
+        self.output.extend(rows);
+
+        Ok(())
+    }
+
+    // Points the address of an already emitted instruction somewhere else.
+    fn patch(&mut self, offset: usize, address: u16) {
+        self.output[offset] = self.output[offset] & 0xF0 | (address >> 8) as u8;
+        self.output[offset + 1] = address as u8;
+    }
+
+    fn finish(mut self) -> Result<(Vec<u8>, DebugInfo), String> {
+        for fixup in std::mem::take(&mut self.fixups) {
+            let address = match self
+                .resolve(&fixup.expression)
+                .map_err(|error| format!("Line {}: {}", fixup.line, error))?
+            {
+                Evaluation::Value(address) => address,
+                Evaluation::Undefined(name) => {
+                    return Err(format!("Line {}: undefined label '{}'", fixup.line, name))
+                }
+            };
+
+            self.patch(fixup.offset, check_address(address, fixup.line)?);
+        }
+
+        for (name, &address) in &self.labels {
+            self.debug_info.add_label(name, address);
+        }
+
+        Ok((self.output, self.debug_info))
+    }
+
+    fn classic(&mut self, source: &str) -> Result<(), String> {
+        let mut macros = HashMap::new();
+
+        let mut lines = source.lines().enumerate();
+
+        while let Some((index, line)) = lines.next() {
+            let line_number = index + 1;
+
+            self.line = line_number;
+
+            let code = line.split(';').next().unwrap_or("").trim();
+
+            let (keyword, definition) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+
+            // MACRO name a, b ... ENDM, with the parameters replaced by the
+            // arguments wherever they appear as whole words in the body.
+            if keyword.eq_ignore_ascii_case("MACRO") {
+                let (name, parameters) = definition
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((definition.trim(), ""));
+
+                if name.is_empty() {
+                    return Err(format!("Line {}: MACRO needs a name", line_number));
+                }
+
+                let mut body = Vec::new();
+
+                loop {
+                    let (_, line) = lines.next().ok_or_else(|| {
+                        format!("Line {}: MACRO without a matching ENDM", line_number)
+                    })?;
+
+                    let code = line.split(';').next().unwrap_or("").trim();
+
+                    if code.eq_ignore_ascii_case("ENDM") {
+                        break;
+                    }
+
+                    body.push(code.to_string());
+                }
+
+                let parameters = split_operands(parameters).map(str::to_string).collect();
+
+                macros.insert(name.to_uppercase(), ClassicMacro { parameters, body });
+
+                continue;
+            }
+
+            self.classic_line(code, line_number, &macros, 0)?;
+        }
+
+        Ok(())
+    }
+
+    fn classic_line(
+        &mut self,
+        code: &str,
+        line_number: usize,
+        macros: &HashMap<String, ClassicMacro>,
+        depth: usize,
+    ) -> Result<(), String> {
+        let mut code = code;
+
+        if let Some((label, rest)) = code.split_once(':') {
+            self.define_label(label.trim(), line_number)?;
+
+            code = rest.trim();
+        }
+
+        if code.is_empty() {
+            return Ok(());
+        }
+
+        let (mnemonic, operands) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+
+        // name EQU expression
+        if let Some((keyword, value)) = operands.trim().split_once(char::is_whitespace) {
+            if keyword.eq_ignore_ascii_case("EQU") {
+                let value = self
+                    .value(value.trim())
+                    .map_err(|error| format!("Line {}: {}", line_number, error))?;
+
+                self.constants.insert(mnemonic.to_string(), value);
+
+                return Ok(());
+            }
+        }
+
+        if let Some(definition) = macros.get(&mnemonic.to_uppercase()) {
+            if depth == MAX_MACRO_DEPTH {
+                return Err(format!(
+                    "Line {}: macros nest too deeply; does '{}' use itself?",
+                    line_number, mnemonic
+                ));
+            }
+
+            let arguments: Vec<&str> = split_operands(operands).collect();
+
+            if arguments.len() != definition.parameters.len() {
+                return Err(format!(
+                    "Line {}: macro '{}' takes {} arguments, got {}",
+                    line_number,
+                    mnemonic,
+                    definition.parameters.len(),
+                    arguments.len()
+                ));
+            }
+
+            for line in &definition.body {
+                let line = substitute(line, &definition.parameters, &arguments);
+
+                self.classic_line(&line, line_number, macros, depth + 1)?;
+            }
+
+            return Ok(());
+        }
+
+        let operands: Vec<Operand> = split_operands(operands).map(Operand::parse).collect();
+
+        self.classic_statement(&mnemonic.to_uppercase(), &operands, line_number)
+            .map_err(|error| format!("Line {}: {}", line_number, error))
+    }
+
+    fn classic_statement(
+        &mut self,
+        mnemonic: &str,
+        operands: &[Operand],
+        line: usize,
+    ) -> Result<(), String> {
+        use Operand::*;
+
+        let instruction = match (mnemonic, operands) {
+            ("DB", values) => {
+                for value in values {
+                    let byte = byte(self.value(value.text()?)?)?;
+
+                    self.output.push(byte);
+                }
+
+                return Ok(());
+            }
+            ("DW", values) => {
+                for value in values {
+                    let word = word(self.value(value.text()?)?)?;
+
+                    self.output.extend_from_slice(&word.to_be_bytes());
+                }
+
+                return Ok(());
+            }
+            ("IMAGE", [Value(path)]) => return self.image(unquote(path), None),
+            ("IMAGE", [Value(path), Value(x), Value(y), Value(width), Value(height)]) => {
+                let region = [
+                    self.value(x)?,
+                    self.value(y)?,
+                    self.value(width)?,
+                    self.value(height)?,
+                ];
+
+                return self.image(unquote(path), Some(region));
+            }
+            ("CLS", []) => Instruction::ClearScreen,
+            ("AUDIO", []) => Instruction::LoadAudioPattern,
+            ("RET", []) => Instruction::Return,
+            ("EXIT", []) => Instruction::Exit,
+            ("SYS", [Value(target)]) => Instruction::System {
+                address: self.address(target, line)?,
+            },
+            ("JP", [Value(target)]) => Instruction::Jump {
+                address: self.address(target, line)?,
+            },
+            ("JP", [Register(0), Value(target)]) => Instruction::JumpOffset {
+                address: self.address(target, line)?,
+            },
+            ("CALL", [Value(target)]) => Instruction::Call {
+                address: self.address(target, line)?,
+            },
+            ("SE", [Register(x), Register(y)]) => {
+                Instruction::SkipIfRegistersEqual { x: *x, y: *y }
+            }
+            ("SE", [Register(x), Value(value)]) => Instruction::SkipIfEqual {
+                x: *x,
+                value: byte(self.value(value)?)?,
+            },
+            ("SNE", [Register(x), Register(y)]) => {
+                Instruction::SkipIfRegistersNotEqual { x: *x, y: *y }
+            }
+            ("SNE", [Register(x), Value(value)]) => Instruction::SkipIfNotEqual {
+                x: *x,
+                value: byte(self.value(value)?)?,
+            },
+            ("LD", [Register(x), Register(y)]) => Instruction::Move { x: *x, y: *y },
+            ("LD", [Register(x), Value(value)]) => Instruction::Load {
+                x: *x,
+                value: byte(self.value(value)?)?,
+            },
+            ("LD", [Index, Value(target)]) => Instruction::LoadIndex {
+                address: self.address(target, line)?,
+            },
+            ("LD", [Register(x), DelayTimer]) => Instruction::LoadDelayTimer { x: *x },
+            ("LD", [Register(x), Key]) => Instruction::WaitForKey { x: *x },
+            ("LD", [DelayTimer, Register(x)]) => Instruction::SetDelayTimer { x: *x },
+            ("LD", [SoundTimer, Register(x)]) => Instruction::SetSoundTimer { x: *x },
+            ("LD", [Font, Register(x)]) => Instruction::LoadFont { x: *x },
+            ("LD", [Bcd, Register(x)]) => Instruction::StoreBcd { x: *x },
+            ("LD", [Pitch, Register(x)]) => Instruction::SetPitch { x: *x },
+            ("LD", [IndexMemory, Register(x)]) => Instruction::StoreRegisters { x: *x },
+            ("LD", [Register(x), IndexMemory]) => Instruction::LoadRegisters { x: *x },
+            ("LD", [Rpl, Register(x)]) => Instruction::StoreFlags { x: *x },
+            ("LD", [Register(x), Rpl]) => Instruction::LoadFlags { x: *x },
+            ("ADD", [Register(x), Register(y)]) => Instruction::AddRegisters { x: *x, y: *y },
+            ("ADD", [Register(x), Value(value)]) => Instruction::Add {
+                x: *x,
+                value: byte(self.value(value)?)?,
+            },
+            ("ADD", [Index, Register(x)]) => Instruction::AddIndex { x: *x },
+            ("OR", [Register(x), Register(y)]) => Instruction::Or { x: *x, y: *y },
+            ("AND", [Register(x), Register(y)]) => Instruction::And { x: *x, y: *y },
+            ("XOR", [Register(x), Register(y)]) => Instruction::Xor { x: *x, y: *y },
+            ("SUB", [Register(x), Register(y)]) => Instruction::Subtract { x: *x, y: *y },
+            ("SUBN", [Register(x), Register(y)]) => Instruction::SubtractReversed { x: *x, y: *y },
+            ("SHR", [Register(x)]) => Instruction::ShiftRight { x: *x, y: *x },
+            ("SHR", [Register(x), Register(y)]) => Instruction::ShiftRight { x: *x, y: *y },
+            ("SHL", [Register(x)]) => Instruction::ShiftLeft { x: *x, y: *x },
+            ("SHL", [Register(x), Register(y)]) => Instruction::ShiftLeft { x: *x, y: *y },
+            ("RND", [Register(x), Value(mask)]) => Instruction::Random {
+                x: *x,
+                mask: byte(self.value(mask)?)?,
+            },
+            ("DRW", [Register(x), Register(y), Value(rows)]) => Instruction::Draw {
+                x: *x,
+                y: *y,
+                rows: nibble(self.value(rows)?)?,
+            },
+            ("SKP", [Register(x)]) => Instruction::SkipIfKeyPressed { x: *x },
+            ("SKNP", [Register(x)]) => Instruction::SkipIfKeyNotPressed { x: *x },
+            _ => return Err(format!("can't assemble '{}' with these operands", mnemonic)),
+        };
+
+        self.emit(instruction)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum Operand<'a> {
+    Register(usize),
+    Index,
+    IndexMemory,
+    DelayTimer,
+    SoundTimer,
+    Key,
+    Font,
+    Bcd,
+    Pitch,
+    Rpl,
+    Value(&'a str),
+}
+
+impl<'a> Operand<'a> {
+    fn parse(text: &'a str) -> Self {
+        match text.to_uppercase().as_str() {
+            "I" => Operand::Index,
+            "[I]" => Operand::IndexMemory,
+            "DT" => Operand::DelayTimer,
+            "ST" => Operand::SoundTimer,
+            "K" => Operand::Key,
+            "F" => Operand::Font,
+            "B" => Operand::Bcd,
+            "PITCH" => Operand::Pitch,
+            "R" => Operand::Rpl,
+            _ => match register(text) {
+                Some(x) => Operand::Register(x),
+                None => Operand::Value(text),
+            },
+        }
+    }
+
+    fn text(&self) -> Result<&'a str, String> {
+        match self {
+            Operand::Value(text) => Ok(text),
+            operand => Err(format!("expected a number, got {:?}", operand)),
+        }
+    }
+}
+
+struct ClassicMacro {
+    parameters: Vec<String>,
+    body: Vec<String>,
+}
+
+// Replaces every whole-word parameter in a line of a macro's body with its
+// argument.
+fn substitute(line: &str, parameters: &[String], arguments: &[&str]) -> String {
+    line.split_inclusive(|character: char| !is_name_character(character))
+        .map(|piece| {
+            let end = piece
+                .find(|character: char| !is_name_character(character))
+                .unwrap_or(piece.len());
+
+            let (word, rest) = piece.split_at(end);
+
+            match parameters.iter().position(|parameter| parameter == word) {
+                Some(index) => format!("{}{}", arguments[index], rest),
+                None => piece.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn unquote(path: &str) -> &str {
+    path.strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+        .unwrap_or(path)
+}
+
+fn split_operands(operands: &str) -> impl Iterator<Item = &str> {
+    operands
+        .split(',')
+        .map(str::trim)
+        .filter(|operand| !operand.is_empty())
+}
+
+// Octo sources are a free-form stream of whitespace separated tokens; line
+// breaks only matter for ending comments.
+struct OctoParser<'a> {
+    assembler: &'a mut Assembler,
+    tokens: Vec<(usize, &'a str)>,
+    position: usize,
+    aliases: HashMap<&'a str, usize>,
+    macros: HashMap<&'a str, OctoMacro<'a>>,
+    expansions: usize,
+    // Offsets of the jumps that skip over open if ... begin blocks.
+    blocks: Vec<usize>,
+    // Start of each open loop and the offsets of its while exits.
+    loops: Vec<(u16, Vec<usize>)>,
+    // Octo programs start at main: a jump to it is reserved up front and
+    // dropped again if main turns out to be the very first label.
+    jump_to_main: bool,
+}
+
+// Expanding a macro puts its body, with each parameter token replaced by the
+// matching argument, in place of the call in the token stream.
+#[derive(Clone)]
+struct OctoMacro<'a> {
+    parameters: Vec<&'a str>,
+    body: Vec<&'a str>,
+}
+
+#[derive(Clone, Copy)]
+enum Condition {
+    Equal(usize, Comparand),
+    NotEqual(usize, Comparand),
+    Key(usize),
+    NotKey(usize),
+}
+
+#[derive(Clone, Copy)]
+enum Comparand {
+    Register(usize),
+    Value(u8),
+}
+
+impl<'a> OctoParser<'a> {
+    fn new(assembler: &'a mut Assembler, source: &'a str) -> Self {
+        let tokens = source
+            .lines()
+            .enumerate()
+            .flat_map(|(index, line)| {
+                let code = line.split('#').next().unwrap_or("");
+
+                code.split_whitespace().map(move |token| (index + 1, token))
+            })
+            .collect();
+
+        Self {
+            assembler,
+            tokens,
+            position: 0,
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            expansions: 0,
+            blocks: Vec::new(),
+            loops: Vec::new(),
+            jump_to_main: true,
+        }
+    }
+
+    fn parse(mut self) -> Result<(), String> {
+        self.assembler.emit(Instruction::Jump { address: 0 })?;
+
+        while self.position < self.tokens.len() {
+            let line = self.line();
+
+            self.assembler.line = line;
+
+            self.statement()
+                .map_err(|error| format!("Line {}: {}", line, error))?;
+        }
+
+        if !self.blocks.is_empty() {
+            return Err("'begin' without a matching 'end'".to_string());
+        }
+
+        if !self.loops.is_empty() {
+            return Err("'loop' without a matching 'again'".to_string());
+        }
+
+        if self.jump_to_main {
+            let main = *self
+                .assembler
+                .labels
+                .get("main")
+                .ok_or("Octo programs need a ': main' label")?;
+
+            self.assembler.patch(0, main);
+        }
+
+        Ok(())
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map_or(0, |&(line, _)| line)
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|&(_, token)| token)
+            .ok_or("unexpected end of file")?;
+
+        self.position += 1;
+
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("expected '{}', got '{}'", expected, token)),
+        }
+    }
+
+    fn register(&mut self) -> Result<usize, String> {
+        let token = self.next()?;
+
+        self.register_named(token)
+            .ok_or_else(|| format!("expected a register, got '{}'", token))
+    }
+
+    fn register_named(&self, token: &str) -> Option<usize> {
+        register(token).or_else(|| self.aliases.get(token).copied())
+    }
+
+    fn value(&mut self) -> Result<i64, String> {
+        let token = self.next()?;
+
+        self.assembler.value(token)
+    }
+
+    // Address operands may also be labels, which are resolved at the end.
+    fn address(&mut self) -> Result<u16, String> {
+        let line = self.line();
+        let token = self.next()?;
+
+        self.assembler.address(token, line)
+    }
+
+    // The tokens up to the `}` matching an opening `{`.
+    fn block(&mut self) -> Result<Vec<&'a str>, String> {
+        self.expect("{")?;
+
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            match self.next()? {
+                "}" if depth == 0 => return Ok(tokens),
+                token => {
+                    match token {
+                        "{" => depth += 1,
+                        "}" => depth -= 1,
+                        _ => {}
+                    }
+
+                    tokens.push(token);
+                }
+            }
+        }
+    }
+
+    fn expand(&mut self, definition: OctoMacro<'a>, name: &str, line: usize) -> Result<(), String> {
+        self.expansions += 1;
+
+        if self.expansions > MAX_MACRO_EXPANSIONS {
+            return Err(format!(
+                "macros expand too many times; does '{}' use itself?",
+                name
+            ));
+        }
+
+        let mut arguments = Vec::new();
+
+        for _ in &definition.parameters {
+            arguments.push(self.next()?);
+        }
+
+        // The expansion is attributed to the line of the call.
+        let expansion: Vec<(usize, &'a str)> = definition
+            .body
+            .iter()
+            .map(|&token| {
+                let index = definition
+                    .parameters
+                    .iter()
+                    .position(|&parameter| parameter == token);
+
+                (line, index.map_or(token, |index| arguments[index]))
+            })
+            .collect();
+
+        self.tokens.splice(self.position..self.position, expansion);
+
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let line = self.line();
+        let token = self.next()?;
+
+        if let Some(definition) = self.macros.get(token).cloned() {
+            return self.expand(definition, token, line);
+        }
+
+        let instruction = match token {
+            ":" => {
+                let name = self.next()?;
+
+                // Nothing but the reserved jump so far, so main is where the
+                // program starts anyway.
+                if name == "main"
+                    && self.jump_to_main
+                    && self.assembler.output.len() == 2
+                    && self.assembler.labels.is_empty()
+                {
+                    self.assembler.output.clear();
+                    self.jump_to_main = false;
+                }
+
+                return self.assembler.define_label(name, line);
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.value()?;
+
+                self.assembler.constants.insert(name.to_string(), value);
+
+                return Ok(());
+            }
+            ":calc" => {
+                let name = self.next()?;
+                let expression = self.block()?.join(" ");
+
+                let value = self.assembler.value(&expression)?;
+
+                self.assembler.constants.insert(name.to_string(), value);
+
+                return Ok(());
+            }
+            ":macro" => {
+                let name = self.next()?;
+
+                let mut parameters = Vec::new();
+
+                while self.tokens.get(self.position).map(|&(_, token)| token) != Some("{") {
+                    parameters.push(self.next()?);
+                }
+
+                let body = self.block()?;
+
+                self.macros.insert(name, OctoMacro { parameters, body });
+
+                return Ok(());
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let x = self.register()?;
+
+                self.aliases.insert(name, x);
+
+                return Ok(());
+            }
+            // :image path, optionally followed by { x y width height }
+            ":image" => {
+                let path = unquote(self.next()?);
+
+                let region = match self.tokens.get(self.position) {
+                    Some(&(_, "{")) => {
+                        let values = self
+                            .block()?
+                            .into_iter()
+                            .map(|token| self.assembler.value(token))
+                            .collect::<Result<Vec<_>, _>>()?;
+
+                        let region: [i64; 4] = values
+                            .try_into()
+                            .map_err(|_| "an :image region is { x y width height }")?;
+
+                        Some(region)
+                    }
+                    _ => None,
+                };
+
+                return self.assembler.image(path, region);
+            }
+            ":byte" => {
+                let value = self.value()?;
+
+                self.assembler.output.push(byte(value)?);
+
+                return Ok(());
+            }
+            ":call" => Instruction::Call {
+                address: self.address()?,
+            },
+            "clear" => Instruction::ClearScreen,
+            "return" | ";" => Instruction::Return,
+            "exit" => Instruction::Exit,
+            "jump" => Instruction::Jump {
+                address: self.address()?,
+            },
+            "jump0" => Instruction::JumpOffset {
+                address: self.address()?,
+            },
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let rows = nibble(self.value()?)?;
+
+                Instruction::Draw { x, y, rows }
+            }
+            "bcd" => Instruction::StoreBcd {
+                x: self.register()?,
+            },
+            "save" => Instruction::StoreRegisters {
+                x: self.register()?,
+            },
+            "load" => Instruction::LoadRegisters {
+                x: self.register()?,
+            },
+            "saveflags" => Instruction::StoreFlags {
+                x: self.register()?,
+            },
+            "loadflags" => Instruction::LoadFlags {
+                x: self.register()?,
+            },
+            "audio" => Instruction::LoadAudioPattern,
+            "pitch" => {
+                self.expect(":=")?;
+
+                Instruction::SetPitch {
+                    x: self.register()?,
+                }
+            }
+            "delay" => {
+                self.expect(":=")?;
+
+                Instruction::SetDelayTimer {
+                    x: self.register()?,
+                }
+            }
+            "buzzer" => {
+                self.expect(":=")?;
+
+                Instruction::SetSoundTimer {
+                    x: self.register()?,
+                }
+            }
+            "i" => match self.next()? {
+                ":=" => match self.tokens.get(self.position) {
+                    Some(&(_, "hex")) => {
+                        self.position += 1;
+
+                        Instruction::LoadFont {
+                            x: self.register()?,
+                        }
+                    }
+                    _ => Instruction::LoadIndex {
+                        address: self.address()?,
+                    },
+                },
+                "+=" => Instruction::AddIndex {
+                    x: self.register()?,
+                },
+                operator => return Err(format!("unsupported operator 'i {}'", operator)),
+            },
+            "if" => {
+                let condition = self.condition()?;
+
+                match self.next()? {
+                    "then" => skip(condition, false),
+                    "begin" => {
+                        self.assembler.emit(skip(condition, true))?;
+
+                        self.blocks.push(self.assembler.output.len());
+
+                        Instruction::Jump { address: 0 }
+                    }
+                    token => return Err(format!("expected 'then' or 'begin', got '{}'", token)),
+                }
+            }
+            "else" => {
+                let begin = self.blocks.pop().ok_or("'else' without 'begin'")?;
+
+                self.blocks.push(self.assembler.output.len());
+                self.assembler.emit(Instruction::Jump { address: 0 })?;
+
+                let here = self.assembler.here()?;
+                self.assembler.patch(begin, here);
+
+                return Ok(());
+            }
+            "end" => {
+                let begin = self.blocks.pop().ok_or("'end' without 'begin'")?;
+
+                let here = self.assembler.here()?;
+                self.assembler.patch(begin, here);
+
+                return Ok(());
+            }
+            "loop" => {
+                self.loops.push((self.assembler.here()?, Vec::new()));
+
+                return Ok(());
+            }
+            "while" => {
+                let condition = self.condition()?;
+
+                self.assembler.emit(skip(condition, true))?;
+
+                let offset = self.assembler.output.len();
+
+                self.loops
+                    .last_mut()
+                    .ok_or("'while' outside of a loop")?
+                    .1
+                    .push(offset);
+
+                Instruction::Jump { address: 0 }
+            }
+            "again" => {
+                let (start, exits) = self.loops.pop().ok_or("'again' without 'loop'")?;
+
+                self.assembler.emit(Instruction::Jump { address: start })?;
+
+                let here = self.assembler.here()?;
+
+                for exit in exits {
+                    self.assembler.patch(exit, here);
+                }
+
+                return Ok(());
+            }
+            token => {
+                if let Some(x) = self.register_named(token) {
+                    self.register_statement(x)?
+                } else if let Some(value) = parse_number(token) {
+                    self.assembler.output.push(byte(value)?);
+
+                    return Ok(());
+                } else if token.starts_with(':') {
+                    return Err(format!("unsupported directive '{}'", token));
+                } else {
+                    // A bare name calls the subroutine with that label.
+                    Instruction::Call {
+                        address: self.assembler.address(token, line)?,
+                    }
+                }
+            }
+        };
+
+        self.assembler.emit(instruction)?;
+
+        Ok(())
+    }
+
+    fn register_statement(&mut self, x: usize) -> Result<Instruction, String> {
+        let operator = self.next()?;
+
+        let source = self.tokens.get(self.position).map(|&(_, token)| token);
+        let y = source.and_then(|token| self.register_named(token));
+
+        if let Some(y) = y {
+            self.position += 1;
+
+            return match operator {
+                ":=" => Ok(Instruction::Move { x, y }),
+                "|=" => Ok(Instruction::Or { x, y }),
+                "&=" => Ok(Instruction::And { x, y }),
+                "^=" => Ok(Instruction::Xor { x, y }),
+                "+=" => Ok(Instruction::AddRegisters { x, y }),
+                "-=" => Ok(Instruction::Subtract { x, y }),
+                "=-" => Ok(Instruction::SubtractReversed { x, y }),
+                ">>=" => Ok(Instruction::ShiftRight { x, y }),
+                "<<=" => Ok(Instruction::ShiftLeft { x, y }),
+                _ => Err(format!(
+                    "unsupported operator '{}' between registers",
+                    operator
+                )),
+            };
+        }
+
+        match (operator, source) {
+            (":=", Some("delay")) => {
+                self.position += 1;
+
+                Ok(Instruction::LoadDelayTimer { x })
+            }
+            (":=", Some("key")) => {
+                self.position += 1;
+
+                Ok(Instruction::WaitForKey { x })
+            }
+            (":=", Some("random")) => {
+                self.position += 1;
+
+                Ok(Instruction::Random {
+                    x,
+                    mask: byte(self.value()?)?,
+                })
+            }
+            (":=", _) => Ok(Instruction::Load {
+                x,
+                value: byte(self.value()?)?,
+            }),
+            ("+=", _) => Ok(Instruction::Add {
+                x,
+                value: byte(self.value()?)?,
+            }),
+            ("-=", _) => Ok(Instruction::Add {
+                x,
+                value: byte(-self.value()?)?,
+            }),
+            _ => Err(format!(
+                "unsupported operator '{}' with a constant",
+                operator
+            )),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let x = self.register()?;
+
+        let operator = self.next()?;
+
+        let comparand = |parser: &mut Self| -> Result<Comparand, String> {
+            let token = parser.tokens.get(parser.position).map(|&(_, token)| token);
+
+            match token.and_then(|token| parser.register_named(token)) {
+                Some(y) => {
+                    parser.position += 1;
+
+                    Ok(Comparand::Register(y))
+                }
+                None => Ok(Comparand::Value(byte(parser.value()?)?)),
+            }
+        };
+
+        match operator {
+            "==" => Ok(Condition::Equal(x, comparand(self)?)),
+            "!=" => Ok(Condition::NotEqual(x, comparand(self)?)),
+            "key" => Ok(Condition::Key(x)),
+            "-key" => Ok(Condition::NotKey(x)),
+            _ => Err(format!("unsupported comparison '{}'", operator)),
+        }
+    }
+}
+
+// The instruction that skips the next one when the condition is `when`.
+fn skip(condition: Condition, when: bool) -> Instruction {
+    let condition = if when {
+        condition
+    } else {
+        match condition {
+            Condition::Equal(x, comparand) => Condition::NotEqual(x, comparand),
+            Condition::NotEqual(x, comparand) => Condition::Equal(x, comparand),
+            Condition::Key(x) => Condition::NotKey(x),
+            Condition::NotKey(x) => Condition::Key(x),
+        }
+    };
+
+    match condition {
+        Condition::Equal(x, Comparand::Value(value)) => Instruction::SkipIfEqual { x, value },
+        Condition::Equal(x, Comparand::Register(y)) => Instruction::SkipIfRegistersEqual { x, y },
+        Condition::NotEqual(x, Comparand::Value(value)) => Instruction::SkipIfNotEqual { x, value },
+        Condition::NotEqual(x, Comparand::Register(y)) => {
+            Instruction::SkipIfRegistersNotEqual { x, y }
+        }
+        Condition::Key(x) => Instruction::SkipIfKeyPressed { x },
+        Condition::NotKey(x) => Instruction::SkipIfKeyNotPressed { x },
+    }
+}
+
+// What an expression comes to, or the first name in it that isn't defined
+// (yet).
+enum Evaluation<'t> {
+    Value(i64),
+    Undefined(&'t str),
+}
+
+// Evaluates numbers and names combined with + - * / % and parentheses, e.g.
+// `sprites + 5 * 2`. Arithmetic wraps rather than overflowing; whether the
+// result fits is up to the operand.
+fn evaluate<'t>(
+    text: &'t str,
+    lookup: &dyn Fn(&str) -> Option<i64>,
+) -> Result<Evaluation<'t>, String> {
+    let mut tokens = Vec::new();
+    let mut characters = text.char_indices().peekable();
+
+    while let Some((start, character)) = characters.next() {
+        if character.is_whitespace() {
+            continue;
+        }
+
+        if is_name_character(character) {
+            let mut end = start + character.len_utf8();
+
+            while let Some(&(index, character)) = characters.peek() {
+                if !is_name_character(character) {
+                    break;
+                }
+
+                end = index + character.len_utf8();
+                characters.next();
+            }
+
+            tokens.push(&text[start..end]);
+        } else if "+-*/%()".contains(character) {
+            tokens.push(&text[start..start + 1]);
+        } else {
+            return Err(format!("unexpected '{}' in '{}'", character, text));
+        }
+    }
+
+    let mut expression = Expression {
+        text,
+        tokens,
+        position: 0,
+        lookup,
+        undefined: None,
+    };
+
+    let value = expression.sum()?;
+
+    if expression.position < expression.tokens.len() {
+        return Err(expression.invalid());
+    }
+
+    Ok(match expression.undefined {
+        Some(name) => Evaluation::Undefined(name),
+        None => Evaluation::Value(value),
+    })
+}
+
+// A recursive descent parser over the tokens of an expression. Undefined
+// names count as 0 so the rest can still be checked.
+struct Expression<'t, 'l> {
+    text: &'t str,
+    tokens: Vec<&'t str>,
+    position: usize,
+    lookup: &'l dyn Fn(&str) -> Option<i64>,
+    undefined: Option<&'t str>,
+}
+
+impl<'t> Expression<'t, '_> {
+    fn invalid(&self) -> String {
+        format!("invalid expression '{}'", self.text)
+    }
+
+    fn peek(&self) -> Option<&'t str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn sum(&mut self) -> Result<i64, String> {
+        let mut value = self.product()?;
+
+        while let Some(operator @ ("+" | "-")) = self.peek() {
+            self.position += 1;
+
+            let operand = self.product()?;
+
+            value = match operator {
+                "+" => value.wrapping_add(operand),
+                _ => value.wrapping_sub(operand),
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<i64, String> {
+        let mut value = self.factor()?;
+
+        while let Some(operator @ ("*" | "/" | "%")) = self.peek() {
+            self.position += 1;
+
+            let operand = self.factor()?;
+
+            value = match (operator, operand) {
+                ("*", _) => value.wrapping_mul(operand),
+                (_, 0) if self.undefined.is_some() => 0,
+                (_, 0) => return Err(format!("division by zero in '{}'", self.text)),
+                ("/", _) => value.wrapping_div(operand),
+                _ => value.wrapping_rem(operand),
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn factor(&mut self) -> Result<i64, String> {
+        let token = self.peek().ok_or_else(|| self.invalid())?;
+
+        self.position += 1;
+
+        match token {
+            "-" => Ok(self.factor()?.wrapping_neg()),
+            "(" => {
+                let value = self.sum()?;
+
+                match self.peek() {
+                    Some(")") => {
+                        self.position += 1;
+
+                        Ok(value)
+                    }
+                    _ => Err(self.invalid()),
+                }
+            }
+            _ if token.starts_with(|character: char| character.is_ascii_digit()) => {
+                parse_number(token).ok_or_else(|| self.invalid())
+            }
+            _ if token.starts_with(is_name_character) => match (self.lookup)(token) {
+                Some(value) => Ok(value),
+                None => {
+                    self.undefined.get_or_insert(token);
+
+                    Ok(0)
+                }
+            },
+            _ => Err(self.invalid()),
+        }
+    }
+}
+
+fn is_name_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+fn register(token: &str) -> Option<usize> {
+    let digit = token.strip_prefix(['v', 'V'])?;
+
+    match digit.len() {
+        1 => usize::from_str_radix(digit, 16).ok(),
+        _ => None,
+    }
+}
+
+fn parse_number(token: &str) -> Option<i64> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token),
+    };
+
+    let value = match digits.strip_prefix("0b").or(digits.strip_prefix("0B")) {
+        Some(binary) => i64::from_str_radix(binary, 2).ok()?,
+        None => parse_address(digits)? as i64,
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+fn check_address(value: i64, line: usize) -> Result<u16, String> {
+    match value {
+        0..=0xFFF => Ok(value as u16),
+        _ => Err(format!(
+            "Line {}: address {:#X} is out of range",
+            line, value
+        )),
+    }
+}
+
+// Bytes may be written signed, so -1 is 0xFF.
+fn byte(value: i64) -> Result<u8, String> {
+    match value {
+        -0x80..=0xFF => Ok(value as u8),
+        _ => Err(format!("{} doesn't fit in a byte", value)),
+    }
+}
+
+fn word(value: i64) -> Result<u16, String> {
+    match value {
+        -0x8000..=0xFFFF => Ok(value as u16),
+        _ => Err(format!("{} doesn't fit in a word", value)),
+    }
+}
+
+fn nibble(value: i64) -> Result<u8, String> {
+    match value {
+        0..=0xF => Ok(value as u8),
+        _ => Err(format!("{} doesn't fit in a nibble", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::assembler::{assemble, assemble_with_images, Image};
+    use crate::chip8::disassembler::Syntax;
+    use crate::chip8::Instruction;
+
+    #[test]
+    fn test_classic_source_resolves_labels() {
+        let source = "
+            start:  LD V0, 0x05   ; counter
+                    CALL draw
+                    JP start
+            draw:   DRW V0, V1, 5
+                    RET
+                    DB 0xF0, 0x90
+        ";
+
+        assert_eq!(
+            assemble(source, Syntax::Classic).unwrap(),
+            [0x60, 0x05, 0x22, 0x06, 0x12, 0x00, 0xD0, 0x15, 0x00, 0xEE, 0xF0, 0x90]
+        );
+
+        assert!(
+            assemble("JP nowhere", Syntax::Classic).is_err(),
+            "Undefined labels should be an error!"
+        );
+    }
+
+    #[test]
+    fn test_code_past_the_end_of_the_address_space_is_an_error() {
+        let source = "DB 0\n".repeat(0x10000 - 0x200) + "CLS";
+
+        assert!(
+            assemble(&source, Syntax::Classic).is_err(),
+            "An instruction past 0xFFFF should be an error!"
+        );
+    }
+
+    #[test]
+    fn test_octo_source_assembles_statements_and_blocks() {
+        let source = "
+            :const SPEED 3
+            : main
+                v0 := SPEED    # comment
+                loop
+                    v0 += 1
+                    while v0 != 10
+                    if v1 == v2 begin draw else v1 := -1 end
+                again
+            : draw
+                sprite v0 v1 5
+                return
+        ";
+
+        assert_eq!(
+            assemble(source, Syntax::Octo).unwrap(),
+            [
+                0x60, 0x03, // 200: v0 := 3
+                0x70, 0x01, // 202: v0 += 1
+                0x40, 0x0A, // 204: stay in the loop while v0 != 10
+                0x12, 0x14, // 206: otherwise leave it
+                0x51, 0x20, // 208: enter the block if v1 == v2
+                0x12, 0x10, // 20A: otherwise jump to else
+                0x22, 0x14, // 20C: draw
+                0x12, 0x12, // 20E: jump to end
+                0x61, 0xFF, // 210: v1 := -1
+                0x12, 0x02, // 212: again
+                0xD0, 0x15, // 214: sprite v0 v1 5
+                0x00, 0xEE, // 216: return
+            ]
+        );
+    }
+
+    #[test]
+    fn test_octo_program_jumps_to_main_when_it_is_not_first() {
+        assert_eq!(
+            assemble(": helper return : main helper", Syntax::Octo).unwrap(),
+            [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]
+        );
+    }
+
+    #[test]
+    fn test_classic_macros_constants_and_expressions() {
+        let source = "
+            ROWS EQU 2 + 3
+            MACRO show x, y
+                    DRW x, y, ROWS
+            ENDM
+
+            start:  LD I, sprites + ROWS * 2   ; a label further down
+                    show V0, V1
+                    JP start + 2
+            sprites:
+                    DB ROWS - 1, (ROWS + 1) / 2, -ROWS % 3
+        ";
+
+        assert_eq!(
+            assemble(source, Syntax::Classic).unwrap(),
+            [0xA2, 0x10, 0xD0, 0x15, 0x12, 0x02, 0x04, 0x03, 0xFE]
+        );
+
+        assert!(
+            assemble("LD V0, later\nlater: RET", Syntax::Classic).is_err(),
+            "Values should only use labels defined above them!"
+        );
+
+        assert!(
+            assemble("MACRO loop\nloop\nENDM\nloop", Syntax::Classic).is_err(),
+            "A macro using itself should be an error!"
+        );
+
+        assert!(
+            assemble("LD V0, 1 / (2 - 2)", Syntax::Classic).is_err(),
+            "Dividing by zero should be an error!"
+        );
+    }
+
+    #[test]
+    fn test_octo_macros_and_calc() {
+        let source = "
+            :macro move-to register value {
+                register := value
+            }
+            :calc SPRITE_ROWS { 1 + 2 * 2 }
+            : main
+                move-to v3 SPRITE_ROWS
+                i := sprite+1
+                jump done
+            : sprite
+                0xF0 0x90
+            : done
+                return
+        ";
+
+        assert_eq!(
+            assemble(source, Syntax::Octo).unwrap(),
+            [0x63, 0x05, 0xA2, 0x07, 0x12, 0x08, 0xF0, 0x90, 0x00, 0xEE]
+        );
+
+        assert!(
+            assemble(
+                ":macro again-and-again { again-and-again } : main again-and-again",
+                Syntax::Octo
+            )
+            .is_err(),
+            "A macro using itself should be an error!"
+        );
+    }
+
+    #[test]
+    fn test_images_assemble_to_sprite_rows() {
+        // A 10x2 image with a column two pixels wide at either side.
+        let load_image = |path: &str| {
+            assert_eq!(path, "ship.png", "The path should be unquoted!");
+
+            let row = "##......##";
+
+            Ok(Image {
+                width: 10,
+                height: 2,
+                pixels: row.repeat(2).chars().map(|pixel| pixel == '#').collect(),
+            })
+        };
+
+        let (rom, _) = assemble_with_images(
+            "IMAGE \"ship.png\"\nIMAGE ship.png, 8, 1, 2, 1",
+            Syntax::Classic,
+            load_image,
+        )
+        .unwrap();
+
+        assert_eq!(rom, [0xC0, 0xC0, 0xC0, 0xC0, 0xC0]);
+
+        let (rom, _) = assemble_with_images(
+            ": main :image ship.png { 1 0 8 1 }",
+            Syntax::Octo,
+            load_image,
+        )
+        .unwrap();
+
+        assert_eq!(rom, [0x81]);
+
+        assert!(
+            assemble_with_images("IMAGE ship.png, 4, 0, 8, 1", Syntax::Classic, load_image)
+                .is_err(),
+            "A region outside the image should be an error!"
+        );
+
+        assert!(
+            assemble("IMAGE ship.png", Syntax::Classic).is_err(),
+            "Images can't be loaded without a loader!"
+        );
+    }
+
+    #[test]
+    fn test_disassembled_instructions_assemble_to_the_same_opcode() {
+        for opcode in 0..=u16::MAX {
+            if Instruction::decode(opcode).is_none() {
+                continue;
+            }
+
+            for syntax in [Syntax::Classic, Syntax::Octo] {
+                let mut source = syntax.opcode(opcode);
+
+                if syntax == Syntax::Octo {
+                    source = format!(": main {}", source);
+                }
+
+                assert_eq!(
+                    assemble(&source, syntax),
+                    Ok(opcode.to_be_bytes().to_vec()),
+                    "'{}' should assemble back to {:04X}!",
+                    source,
+                    opcode
+                );
+            }
+        }
+    }
+}
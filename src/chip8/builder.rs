@@ -0,0 +1,455 @@
+use std::collections::BTreeSet;
+
+use crate::chip8::font::{DEFAULT_FONT, FONT_SIZE};
+use crate::chip8::tone::DEFAULT_PITCH;
+use crate::chip8::{
+    Cheat, Coverage, Interpreter, MemoryMap, Platform, Quirks, Region, Replay, Timing,
+};
+
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+#[derive(Clone, Debug)]
+pub struct InterpreterBuilder {
+    platform: Platform,
+    quirks: Quirks,
+    seed: u64,
+    instructions_per_frame: u32,
+    max_instructions_per_frame: Option<u32>,
+    speed_heuristics: bool,
+    timing: Timing,
+    memory_map: MemoryMap,
+    stack_depth: Option<usize>,
+    font: [u8; FONT_SIZE],
+    decode_cache: bool,
+    coverage: bool,
+    record_replay: bool,
+    strict_system_calls: bool,
+    wrap_memory: bool,
+    cheats: Vec<Cheat>,
+}
+
+impl Default for InterpreterBuilder {
+    fn default() -> Self {
+        Self {
+            platform: Platform::default(),
+            quirks: Quirks::default(),
+            seed: 0,
+            instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+            max_instructions_per_frame: None,
+            speed_heuristics: false,
+            timing: Timing::default(),
+            memory_map: MemoryMap::default(),
+            stack_depth: Some(Platform::default().stack_depth()),
+            font: DEFAULT_FONT,
+            decode_cache: false,
+            coverage: false,
+            record_replay: false,
+            strict_system_calls: false,
+            wrap_memory: false,
+            cheats: Vec::new(),
+        }
+    }
+}
+
+impl InterpreterBuilder {
+    // Selects the display resolution and resets quirks, memory map and stack
+    // depth to the platform's preset, so call this before overriding them.
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self.quirks = platform.quirks();
+        self.memory_map = platform.memory_map();
+        self.stack_depth = Some(platform.stack_depth());
+
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+
+        self
+    }
+
+    pub fn instructions_per_frame(mut self, instructions_per_frame: u32) -> Self {
+        self.instructions_per_frame = instructions_per_frame;
+
+        self
+    }
+
+    // Caps the instructions any one frame runs, however the speed was set and
+    // under either timing, so a ROM set far too fast can't hold up its host.
+    pub fn max_instructions_per_frame(mut self, max: u32) -> Self {
+        self.max_instructions_per_frame = Some(max);
+
+        self
+    }
+
+    // Lets load_program pick the instructions per frame to suit the ROM,
+    // replacing the set value; see speed::suggest_instructions_per_frame.
+    pub fn speed_heuristics(mut self, enabled: bool) -> Self {
+        self.speed_heuristics = enabled;
+
+        self
+    }
+
+    // With Timing::CosmacVip the instructions per frame setting is ignored.
+    pub fn timing(mut self, timing: Timing) -> Self {
+        self.timing = timing;
+
+        self
+    }
+
+    pub fn memory_map(mut self, memory_map: MemoryMap) -> Self {
+        self.memory_map = memory_map;
+
+        self
+    }
+
+    // How many calls can nest before 2NNN fails with a stack overflow; None
+    // lets recursion go as deep as it likes.
+    pub fn stack_depth(mut self, stack_depth: Option<usize>) -> Self {
+        self.stack_depth = stack_depth;
+
+        self
+    }
+
+    pub fn font(mut self, font: [u8; FONT_SIZE]) -> Self {
+        self.font = font;
+
+        self
+    }
+
+    // Caches decoded instructions by address. Writes made by the program are
+    // tracked, but anything poking `memory` directly must call
+    // Interpreter::invalidate_decode_cache afterwards.
+    pub fn decode_cache(mut self, enabled: bool) -> Self {
+        self.decode_cache = enabled;
+
+        self
+    }
+
+    // Records which addresses run as code and which are read as data, for
+    // Interpreter::coverage.
+    pub fn coverage(mut self, enabled: bool) -> Self {
+        self.coverage = enabled;
+
+        self
+    }
+
+    // Records the keys held in every frame, for Interpreter::replay.
+    pub fn record_replay(mut self, enabled: bool) -> Self {
+        self.record_replay = enabled;
+
+        self
+    }
+
+    // Makes 0NNN calls to machine code an error rather than skipping them
+    // and listing them in Interpreter::system_calls.
+    pub fn strict_system_calls(mut self, strict: bool) -> Self {
+        self.strict_system_calls = strict;
+
+        self
+    }
+
+    // Gives every address a ROM can reach a meaning, so that no program can
+    // halt the interpreter by running off the end of memory. Reads and writes
+    // through I wrap around to the start of memory a byte at a time, as does
+    // the program counter, and I itself wraps at 32 bits. MegaChip samples,
+    // which play straight out of memory, are cut short at its end instead.
+    //
+    // Together with try_step this suits hosts that run untrusted ROMs, such
+    // as servers and plugins; the fuzzing feature exercises it.
+    pub fn wrap_memory(mut self, enabled: bool) -> Self {
+        self.wrap_memory = enabled;
+
+        self
+    }
+
+    // Applied from the first frame on, so they take effect after the program
+    // has been loaded.
+    pub fn cheats(mut self, cheats: Vec<Cheat>) -> Self {
+        self.cheats = cheats;
+
+        self
+    }
+
+    pub fn build(self) -> Result<Interpreter, String> {
+        if self.instructions_per_frame == 0 {
+            return Err("Instructions per frame must be greater than zero".to_string());
+        }
+
+        if self.max_instructions_per_frame == Some(0) {
+            return Err("Max instructions per frame must be greater than zero".to_string());
+        }
+
+        if self.stack_depth == Some(0) {
+            return Err("Stack depth must be greater than zero".to_string());
+        }
+
+        let memory_size = self.platform.memory_size();
+
+        let (display_width, display_height) = self.platform.resolution();
+
+        let display_size = self.platform.display_size();
+
+        if self.memory_map.program_address as usize >= memory_size {
+            return Err(format!(
+                "Program address {:#05X} is outside of memory",
+                self.memory_map.program_address
+            ));
+        }
+
+        let font_address = self.memory_map.font_address as usize;
+        let display_address = self.memory_map.display_address as usize;
+
+        if font_address + FONT_SIZE > memory_size {
+            return Err(format!(
+                "Font at {:#05X} does not fit in memory",
+                font_address
+            ));
+        }
+
+        if display_address + display_size > memory_size {
+            return Err(format!(
+                "Display buffer at {:#05X} does not fit in memory",
+                display_address
+            ));
+        }
+
+        let font_range = font_address..font_address + FONT_SIZE;
+        let display_range = display_address..display_address + display_size;
+
+        if font_range.start < display_range.end && display_range.start < font_range.end {
+            return Err("Font and display buffer overlap".to_string());
+        }
+
+        let program_address = self.memory_map.program_address as usize;
+
+        if font_range.contains(&program_address) || display_range.contains(&program_address) {
+            return Err(format!(
+                "Program address {:#05X} is inside the font or display buffer",
+                program_address
+            ));
+        }
+
+        let mut memory = vec![0; memory_size].into_boxed_slice();
+
+        memory[font_range].copy_from_slice(&self.font);
+
+        Ok(Interpreter {
+            memory,
+            registers: [0; 16],
+            index_register: 0x00,
+            program_counter: self.memory_map.program_address,
+            delay_timer: 0,
+            sound_timer: 0,
+            stack: Vec::new(),
+            stack_depth: self.stack_depth,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            rpl_flags: [0; Interpreter::RPL_FLAGS],
+            keypad: [false; 16],
+            key_wait: None,
+            exited: false,
+            strict_system_calls: self.strict_system_calls,
+            wrap_memory: self.wrap_memory,
+            system_calls: Vec::new(),
+            platform: self.platform,
+            display_width,
+            display_height,
+            quirks: self.quirks,
+            memory_map: self.memory_map,
+            instructions_per_frame: self.instructions_per_frame,
+            max_instructions_per_frame: self.max_instructions_per_frame,
+            speed_heuristics: self.speed_heuristics,
+            timing: self.timing,
+            cycle_budget: 0,
+            // The program counter can't reach past 64K, however much memory
+            // there is.
+            decode_cache: self
+                .decode_cache
+                .then(|| vec![None; memory_size.min(0x10000)].into_boxed_slice()),
+            coverage: self.coverage.then(|| Box::new(Coverage::new(memory_size))),
+            replay: self.record_replay.then(|| {
+                Box::new(Replay::new(
+                    self.platform,
+                    self.quirks,
+                    self.seed,
+                    self.instructions_per_frame,
+                    self.timing,
+                ))
+            }),
+            megachip: (self.platform == Platform::MegaChip).then(Box::default),
+            dirty: Some(Region {
+                x: 0,
+                y: 0,
+                width: display_width,
+                height: display_height,
+            }),
+            cheats: self.cheats,
+            breakpoints: BTreeSet::new(),
+            stopped_at: None,
+            written: None,
+            pc_history: [0; Interpreter::PC_HISTORY],
+            cycles: 0,
+            frames: 0,
+            yielded: false,
+            busy_frames: 0,
+            random_state: self.seed,
+            observer: None,
+            peripherals: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Interpreter, MemoryMap, Platform, Quirks};
+
+    #[test]
+    fn test_builder_defaults_match_new() {
+        let built = Interpreter::builder().build().unwrap();
+        let new = Interpreter::new();
+
+        assert_eq!(built.memory, new.memory, "Memory should match!");
+
+        assert_eq!(
+            built.program_counter, new.program_counter,
+            "Program counter should match!"
+        );
+    }
+
+    #[test]
+    fn test_builder_applies_options() {
+        let quirks = Quirks {
+            vf_reset: true,
+            ..Quirks::default()
+        };
+
+        let interpreter = Interpreter::builder()
+            .quirks(quirks)
+            .instructions_per_frame(20)
+            .build()
+            .unwrap();
+
+        assert_eq!(interpreter.quirks, quirks, "Quirks should be applied!");
+
+        assert_eq!(
+            interpreter.instructions_per_frame, 20,
+            "Instructions per frame should be applied!"
+        );
+    }
+
+    #[test]
+    fn test_builder_places_font_at_configured_address() {
+        let font = [0xAA; 80];
+
+        let interpreter = Interpreter::builder()
+            .memory_map(MemoryMap {
+                font_address: 0x050,
+                ..MemoryMap::default()
+            })
+            .font(font)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            interpreter.memory[0x050..0x050 + 80],
+            font,
+            "Font should be loaded at 0x050!"
+        );
+    }
+
+    #[test]
+    fn test_builder_applies_platform_resolution() {
+        let interpreter = Interpreter::builder()
+            .platform(Platform::HiresChip8)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            (interpreter.display_width, interpreter.display_height),
+            (64, 64),
+            "Hi-res platform should have a 64x64 display!"
+        );
+
+        assert_eq!(
+            interpreter.display().len(),
+            512,
+            "Hi-res display should span 512 bytes!"
+        );
+    }
+
+    #[test]
+    fn test_builder_starts_at_program_address() {
+        let interpreter = Interpreter::builder()
+            .memory_map(MemoryMap {
+                program_address: 0x600,
+                ..MemoryMap::default()
+            })
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            interpreter.program_counter, 0x600,
+            "Program counter should start at 0x600!"
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_program_address_outside_memory() {
+        let result = Interpreter::builder()
+            .memory_map(MemoryMap {
+                program_address: 0x1000,
+                ..MemoryMap::default()
+            })
+            .build();
+
+        assert!(result.is_err(), "Program address 0x1000 should not fit!");
+    }
+
+    #[test]
+    fn test_builder_rejects_display_outside_memory() {
+        let result = Interpreter::builder()
+            .memory_map(MemoryMap {
+                display_address: 0xFF0,
+                ..MemoryMap::default()
+            })
+            .build();
+
+        assert!(result.is_err(), "Display at 0xFF0 should not fit!");
+    }
+
+    #[test]
+    fn test_builder_rejects_overlapping_font_and_display() {
+        let result = Interpreter::builder()
+            .memory_map(MemoryMap {
+                font_address: 0xF00,
+                display_address: 0xF00,
+                ..MemoryMap::default()
+            })
+            .build();
+
+        assert!(result.is_err(), "Overlapping regions should be rejected!");
+    }
+
+    #[test]
+    fn test_builder_rejects_program_inside_display() {
+        let result = Interpreter::builder()
+            .memory_map(MemoryMap {
+                display_address: 0x180,
+                ..MemoryMap::default()
+            })
+            .build();
+
+        assert!(
+            result.is_err(),
+            "Programs shouldn't load over the display buffer!"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+use crate::chip8::parse_address;
+
+// A value written to memory when the program starts, or before every frame if
+// frozen, e.g. to keep a lives counter from going down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub address: u32,
+    pub value: u8,
+    pub freeze: bool,
+}
+
+impl Cheat {
+    // Reads a cheat file with one "address = value" per line, optionally
+    // followed by "freeze". '#' and ';' start comments.
+    pub fn parse_file(text: &str) -> Result<Vec<Cheat>, String> {
+        let mut cheats = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split(['#', ';']).next().unwrap_or("").trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let (spec, freeze) = match line.strip_suffix("freeze") {
+                Some(spec) => (spec, true),
+                None => (line, false),
+            };
+
+            let mut cheat: Cheat = spec
+                .parse()
+                .map_err(|error| format!("Line {}: {}", index + 1, error))?;
+
+            cheat.freeze = freeze;
+
+            cheats.push(cheat);
+        }
+
+        Ok(cheats)
+    }
+}
+
+// Parses "address=value". Both are decimal unless written in hex with a 0x
+// prefix, as addresses usually are.
+impl FromStr for Cheat {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (address, value) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("Expected address=value, got: {}", spec.trim()))?;
+
+        let (address, value) = (address.trim(), value.trim());
+
+        let address = parse_address(address)
+            .filter(|&address| address <= 0xFFF)
+            .map(u32::from)
+            .ok_or_else(|| format!("Invalid cheat address: {}", address))?;
+
+        let value = parse_address(value)
+            .and_then(|value| u8::try_from(value).ok())
+            .ok_or_else(|| format!("Invalid cheat value: {}", value))?;
+
+        Ok(Cheat {
+            address,
+            value,
+            freeze: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::cheat::Cheat;
+
+    #[test]
+    fn test_cheat_file_reads_pokes_and_freezes() {
+        let cheats = Cheat::parse_file("# Lives\n0x2A4 = 3 freeze\n677=0x10\n").unwrap();
+
+        assert_eq!(
+            cheats,
+            [
+                Cheat {
+                    address: 0x2A4,
+                    value: 3,
+                    freeze: true
+                },
+                Cheat {
+                    address: 0x2A5,
+                    value: 0x10,
+                    freeze: false
+                }
+            ]
+        );
+
+        assert!(Cheat::parse_file("0x2A4 = 256").is_err());
+        assert!(Cheat::parse_file("0x1000 = 1").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use crate::chip8::{Platform, Quirks};
+
+// What a saved file holds. Each kind has its own magic and numbers its
+// versions separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    State,
+    Replay,
+}
+
+impl Kind {
+    fn magic(self) -> &'static str {
+        match self {
+            Kind::State => "chip8-state",
+            Kind::Replay => "chip8-replay",
+        }
+    }
+
+    // The version written now. Loaders read every version up to it.
+    pub fn version(self) -> u32 {
+        match self {
+            Kind::State => 1,
+            Kind::Replay => 2,
+        }
+    }
+
+    // Version 1 replays kept the platform and quirks among their other
+    // settings.
+    fn has_header(self, version: u32) -> bool {
+        !(self == Kind::Replay && version < 2)
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::State => "save state",
+            Kind::Replay => "replay",
+        })
+    }
+}
+
+// The wrapping for files the emulator saves, so a loader can tell what it's
+// been given, which release wrote it and what it needs to be loaded. A line
+// with the magic and version, then the platform and quirks, then a blank
+// line and the payload:
+//
+//   chip8-state 1
+//   platform chip48
+//   quirks no-vf-reset,no-index-overflow,no-memory-increment,shift,jump
+//
+//   <payload>
+//
+// Payloads are laid out differently from version to version; the version
+// says how to read one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Container {
+    pub kind: Kind,
+    pub version: u32,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub payload: Vec<u8>,
+}
+
+impl Container {
+    pub fn new(kind: Kind, platform: Platform, quirks: Quirks, payload: Vec<u8>) -> Self {
+        Self {
+            kind,
+            version: kind.version(),
+            platform,
+            quirks,
+            payload,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!(
+            "{} {}\nplatform {}\nquirks {}\n\n",
+            self.kind.magic(),
+            self.version,
+            self.platform.name(),
+            self.quirks.to_list()
+        )
+        .into_bytes();
+
+        bytes.extend_from_slice(&self.payload);
+
+        bytes
+    }
+
+    // Files from newer releases are refused rather than misread. Versions
+    // from before the header existed come back with the default platform and
+    // quirks, and everything after the first line as the payload.
+    pub fn parse(bytes: &[u8], kind: Kind) -> Result<Self, String> {
+        let (first_line, mut rest) = split_line(bytes);
+
+        let version = first_line
+            .and_then(|line| line.strip_prefix(kind.magic()))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .filter(|&version| version > 0)
+            .ok_or(format!("Not a {} file", kind))?;
+
+        if version > kind.version() {
+            return Err(format!(
+                "The {} was saved by a newer release (version {}, this one reads up to {})",
+                kind,
+                version,
+                kind.version()
+            ));
+        }
+
+        let mut platform = Platform::default();
+        let mut quirks = None;
+
+        if kind.has_header(version) {
+            loop {
+                let (line, next) = split_line(rest);
+
+                rest = next;
+
+                let line = line.ok_or(format!("The {}'s header is cut short", kind))?;
+
+                match line.split_once(' ') {
+                    _ if line.is_empty() => break,
+                    Some(("platform", name)) => platform = name.parse()?,
+                    Some(("quirks", names)) => quirks = Some(names),
+                    _ => return Err(format!("Invalid {} header line '{}'", kind, line)),
+                }
+            }
+        }
+
+        // The quirks are read once the platform they start from is known.
+        let mut platform_quirks = platform.quirks();
+
+        if let Some(names) = quirks {
+            platform_quirks.apply(names)?;
+        }
+
+        Ok(Self {
+            kind,
+            version,
+            platform,
+            quirks: platform_quirks,
+            payload: rest.to_vec(),
+        })
+    }
+}
+
+// The first line as text, if there is a whole one, and the bytes after it.
+fn split_line(bytes: &[u8]) -> (Option<&str>, &[u8]) {
+    match bytes.iter().position(|&byte| byte == b'\n') {
+        Some(end) => (
+            std::str::from_utf8(&bytes[..end]).ok().map(str::trim_end),
+            &bytes[end + 1..],
+        ),
+        None => (None, bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::container::{Container, Kind};
+    use crate::chip8::Platform;
+
+    #[test]
+    fn test_container_round_trips_and_refuses_newer_versions() {
+        let container = Container::new(
+            Kind::State,
+            Platform::Chip48,
+            Platform::Chip48.quirks(),
+            vec![0, b'\n', 0xFF],
+        );
+
+        let bytes = container.to_bytes();
+
+        assert_eq!(Container::parse(&bytes, Kind::State), Ok(container));
+
+        assert!(
+            Container::parse(&bytes, Kind::Replay).is_err(),
+            "A save state should not load as a replay!"
+        );
+
+        let newer = String::from_utf8_lossy(&bytes).replacen("chip8-state 1", "chip8-state 9", 1);
+
+        assert!(Container::parse(newer.as_bytes(), Kind::State)
+            .unwrap_err()
+            .contains("newer release"));
+    }
+}
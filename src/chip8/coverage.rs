@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crate::chip8::disassembler::{disassemble, write_annotated_listing, Symbols, Syntax};
+
+const EXECUTED: u8 = 0b01;
+const READ: u8 = 0b10;
+
+// Which addresses a program ran as instructions and which it only read as
+// data (sprites, FX65 loads and audio patterns), for finding dead code and
+// untested branches. Kept by interpreters built with coverage enabled.
+#[derive(Clone, Debug)]
+pub struct Coverage {
+    flags: Box<[u8]>,
+    // The ROM as loaded, so the listing isn't thrown off by self-modifying
+    // code.
+    origin: u16,
+    rom: Vec<u8>,
+}
+
+impl Coverage {
+    pub(crate) fn new(memory_size: usize) -> Self {
+        Self {
+            flags: vec![0; memory_size].into_boxed_slice(),
+            origin: 0,
+            rom: Vec::new(),
+        }
+    }
+
+    pub fn executed(&self, address: u16) -> bool {
+        self.flag(address) & EXECUTED != 0
+    }
+
+    pub fn read(&self, address: u16) -> bool {
+        self.flag(address) & READ != 0
+    }
+
+    fn flag(&self, address: u16) -> u8 {
+        self.flags.get(address as usize).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn set_rom(&mut self, origin: u16, rom: &[u8]) {
+        self.origin = origin;
+        self.rom = rom.to_vec();
+    }
+
+    pub(crate) fn mark_executed(&mut self, address: usize) {
+        self.mark(address..address + 2, EXECUTED);
+    }
+
+    pub(crate) fn mark_read(&mut self, range: Range<usize>) {
+        self.mark(range, READ);
+    }
+
+    fn mark(&mut self, range: Range<usize>, flag: u8) {
+        let end = range.end.min(self.flags.len());
+
+        for value in &mut self.flags[range.start.min(end)..end] {
+            *value |= flag;
+        }
+    }
+
+    // The ROM's disassembly with every instruction marked as run or never run
+    // and every byte of data as read, run (code reached only by a computed
+    // jump) or unused, after a summary. It still assembles.
+    pub fn write_listing(&self, output: &mut impl Write, syntax: Syntax) -> io::Result<()> {
+        let lines = disassemble(&self.rom, self.origin, &[])
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+
+        let (code, data): (Vec<_>, Vec<_>) = lines.iter().partition(|line| !line.is_data());
+
+        let ran = code
+            .iter()
+            .filter(|line| self.executed(line.address))
+            .count();
+
+        let data_addresses = data.iter().flat_map(|line| line.addresses());
+
+        let (mut read, mut unused) = (0, 0);
+
+        for address in data_addresses {
+            if self.read(address) {
+                read += 1;
+            } else if !self.executed(address) {
+                unused += 1;
+            }
+        }
+
+        let comment = syntax.comment();
+
+        writeln!(
+            output,
+            "{} Coverage: {} of {} instructions ran",
+            comment,
+            ran,
+            code.len()
+        )?;
+        writeln!(
+            output,
+            "{} Data bytes read: {}, never used: {}",
+            comment, read, unused
+        )?;
+
+        let code: HashSet<u16> = code.iter().map(|line| line.address).collect();
+
+        write_annotated_listing(output, &lines, &Symbols::default(), syntax, |address| {
+            let is_code = code.contains(&address);
+
+            Some(match (self.executed(address), self.read(address)) {
+                (true, _) => "ran",
+                (false, _) if is_code => "never ran",
+                (false, true) => "read",
+                (false, false) => "unused",
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::disassembler::Syntax;
+    use crate::chip8::Interpreter;
+
+    #[test]
+    fn test_coverage_separates_code_run_from_data_read() {
+        let mut interpreter = Interpreter::builder().coverage(true).build().unwrap();
+
+        // LD I, 20A; DRW V0, V0, 1; SE V0, 0; JP 200 (always skipped);
+        // JP 208; a sprite.
+        let rom = [
+            0xA2, 0x0A, 0xD0, 0x01, 0x30, 0x00, 0x12, 0x00, 0x12, 0x08, 0xF0,
+        ];
+
+        interpreter.load_program(&rom).unwrap();
+
+        for _ in 0..4 {
+            interpreter.execute_cycle();
+        }
+
+        let coverage = interpreter.coverage().unwrap();
+
+        assert!(coverage.executed(0x202));
+        assert!(!coverage.executed(0x206), "JP 200 should never run!");
+        assert!(coverage.read(0x20A), "The sprite should have been read!");
+
+        let mut listing = Vec::new();
+
+        coverage
+            .write_listing(&mut listing, Syntax::Classic)
+            .unwrap();
+
+        let listing = String::from_utf8(listing).unwrap();
+
+        assert!(listing.starts_with("; Coverage: 4 of 5 instructions ran\n"));
+        assert!(listing.contains("206: 1200  never ran\n"));
+        assert!(listing.contains("20A: F0    ####....  read\n"));
+    }
+}
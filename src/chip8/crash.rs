@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use crate::chip8::disassembler::Syntax;
+use crate::chip8::state::stack;
+use crate::chip8::Interpreter;
+
+const ROW_SIZE: usize = 16;
+
+// Only the memory the program counter can reach is dumped; a MegaChip's 16 MiB
+// would bury the rest of the report.
+const DUMP_SIZE: usize = 0x10000;
+
+// A plain-text report of everything needed to reproduce a failure: the error,
+// registers, the instructions leading up to it and a hex dump of memory.
+pub fn crash_report(interpreter: &Interpreter, error: &str) -> String {
+    let mut report = String::new();
+
+    // Writing to a String can't fail.
+    let _ = write_report(&mut report, interpreter, error);
+
+    report
+}
+
+fn write_report(report: &mut String, interpreter: &Interpreter, error: &str) -> std::fmt::Result {
+    writeln!(report, "Error: {}", error)?;
+    writeln!(report, "Platform: {}", interpreter.platform.name())?;
+    writeln!(report)?;
+
+    writeln!(report, "PC: 0x{:03X}", interpreter.program_counter)?;
+    writeln!(report, "I:  0x{:03X}", interpreter.index_register)?;
+    writeln!(report, "DT: {}", interpreter.delay_timer)?;
+    writeln!(report, "ST: {}", interpreter.sound_timer)?;
+    writeln!(report, "Stack: {}", stack(&interpreter.stack))?;
+
+    for (register, value) in interpreter.registers.iter().enumerate() {
+        writeln!(report, "V{:X}: 0x{:02X}", register, value)?;
+    }
+
+    writeln!(report)?;
+    writeln!(report, "Last instructions:")?;
+
+    for address in interpreter.pc_history() {
+        let address = address as usize;
+
+        let opcode = match interpreter.memory.get(address..address + 2) {
+            Some(&[high, low]) => u16::from_be_bytes([high, low]),
+            _ => continue,
+        };
+
+        writeln!(
+            report,
+            "  {:03X}: {:04X}  {}",
+            address,
+            opcode,
+            Syntax::Classic.opcode(opcode)
+        )?;
+    }
+
+    writeln!(report)?;
+    writeln!(report, "Memory:")?;
+
+    let dumped = interpreter.memory.len().min(DUMP_SIZE);
+
+    for (row, bytes) in interpreter.memory[..dumped].chunks(ROW_SIZE).enumerate() {
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+
+        writeln!(report, "  {:03X}: {}", row * ROW_SIZE, hex.join(" "))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::crash::crash_report;
+    use crate::chip8::Interpreter;
+
+    #[test]
+    fn test_crash_report_shows_instructions_leading_to_error() {
+        let mut interpreter = Interpreter::new();
+
+        // LD V0, 0x05; then an unsupported opcode.
+        interpreter.load_program(&[0x60, 0x05, 0xE0, 0xFF]).unwrap();
+
+        interpreter.try_execute_cycle().unwrap();
+
+        let error = interpreter.try_execute_cycle().unwrap_err();
+
+        let report = crash_report(&interpreter, &error);
+
+        assert!(report.starts_with("Error: Unsupported opcode E0FF at 202\n"));
+        assert!(report.contains("V0: 0x05\n"));
+        assert!(
+            report.contains("Last instructions:\n  200: 6005  LD V0, 0x05\n  202: E0FF"),
+            "The failing instruction should end the history!"
+        );
+        assert!(report.contains("  200: 60 05 E0 FF 00"));
+    }
+}
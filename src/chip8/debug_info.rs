@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::chip8::parse_address;
+
+// Where each instruction of an assembled ROM came from, so a debugger can
+// show source lines and take breakpoints by label or line. The assembler
+// writes it next to the ROM as text, one entry per line:
+//
+//   source game.asm
+//   label 0x2A4 draw_score
+//   line 0x200 12
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+    // The source file, relative to the debug info's directory unless it's
+    // absolute.
+    pub source: Option<String>,
+    // The line each instruction starts on, by address.
+    lines: BTreeMap<u16, usize>,
+    labels: BTreeMap<String, u16>,
+}
+
+impl DebugInfo {
+    // rom.ch8 keeps its debug info in rom.dbg.
+    pub fn path_for(rom_path: &Path) -> PathBuf {
+        rom_path.with_extension("dbg")
+    }
+
+    pub fn source_path(&self, info_path: &Path) -> Option<PathBuf> {
+        let source = Path::new(self.source.as_ref()?);
+
+        Some(match info_path.parent() {
+            Some(directory) => directory.join(source),
+            None => source.to_path_buf(),
+        })
+    }
+
+    pub(crate) fn add_line(&mut self, address: u16, line: usize) {
+        self.lines.insert(address, line);
+    }
+
+    pub(crate) fn add_label(&mut self, name: &str, address: u16) {
+        self.labels.insert(name.to_string(), address);
+    }
+
+    pub fn line_at(&self, address: u16) -> Option<usize> {
+        self.lines.get(&address).copied()
+    }
+
+    // The first instruction assembled from the line, or from the next line
+    // that has one, as with breakpoints on a blank line or a comment.
+    pub fn address_of_line(&self, line: usize) -> Option<u16> {
+        self.lines
+            .iter()
+            .filter(|&(_, &source_line)| source_line >= line)
+            .min_by_key(|&(&address, &source_line)| (source_line, address))
+            .map(|(&address, _)| address)
+    }
+
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    pub fn label_at(&self, address: u16) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|&(_, &label_address)| label_address == address)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut info = DebugInfo::default();
+
+        for (index, line) in text.lines().enumerate() {
+            // Paths may have spaces in them.
+            if let Some(path) = line.strip_prefix("source ") {
+                info.source = Some(path.to_string());
+
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            let parsed = match fields.as_slice() {
+                [] => Some(()),
+                ["label", at, name] => parse_address(at).map(|at| info.add_label(name, at)),
+                ["line", at, source_line] => parse_address(at)
+                    .zip(source_line.parse().ok())
+                    .map(|(at, source_line)| info.add_line(at, source_line)),
+                _ => None,
+            };
+
+            if parsed.is_none() {
+                return Err(format!("Line {}: invalid debug info '{}'", index + 1, line));
+            }
+        }
+
+        Ok(info)
+    }
+}
+
+impl fmt::Display for DebugInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(source) = &self.source {
+            writeln!(f, "source {}", source)?;
+        }
+
+        for (name, address) in &self.labels {
+            writeln!(f, "label 0x{:03X} {}", address, name)?;
+        }
+
+        for (address, line) in &self.lines {
+            writeln!(f, "line 0x{:03X} {}", address, line)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::assembler::assemble_with_debug_info;
+    use crate::chip8::debug_info::DebugInfo;
+    use crate::chip8::disassembler::Syntax;
+
+    #[test]
+    fn test_debug_info_maps_lines_and_labels() {
+        let source = "\
+; Counts up forever.
+start:
+    LD V0, 0x00
+
+loop: ADD V0, 0x01
+    JP loop
+";
+
+        let (rom, info) = assemble_with_debug_info(source, Syntax::Classic).unwrap();
+
+        assert_eq!(rom.len(), 6);
+
+        assert_eq!(info.line_at(0x200), Some(3));
+        assert_eq!(info.line_at(0x204), Some(6));
+        assert_eq!(info.label("loop"), Some(0x202));
+        assert_eq!(info.label_at(0x200), Some("start"));
+        assert_eq!(
+            info.address_of_line(4),
+            Some(0x202),
+            "A blank line should break at the next instruction!"
+        );
+        assert_eq!(info.address_of_line(7), None);
+
+        assert_eq!(
+            DebugInfo::parse(&info.to_string()),
+            Ok(info),
+            "Debug info should survive being written out!"
+        );
+    }
+}
@@ -0,0 +1,789 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::chip8::{parse_address, Instruction};
+
+// The assembly dialects the disassembler can write.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    // Cowgod's mnemonics: "LD V0, 0x05", "DRW V0, V1, 5".
+    #[default]
+    Classic,
+
+    // Octo statements: "v0 := 5", "sprite v0 v1 5". Octo has no way to
+    // spell 0NNN, so those are written as raw bytes.
+    Octo,
+}
+
+impl Syntax {
+    pub const NAMES: [&'static str; 2] = ["classic", "octo"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Syntax::Classic => "classic",
+            Syntax::Octo => "octo",
+        }
+    }
+
+    pub fn comment(self) -> &'static str {
+        match self {
+            Syntax::Classic => ";",
+            Syntax::Octo => "#",
+        }
+    }
+
+    // Disassembles an opcode, falling back to a data directive for words that
+    // aren't instructions.
+    pub fn opcode(self, opcode: u16) -> String {
+        match Instruction::decode(opcode) {
+            Some(instruction) => self.instruction(instruction, &Symbols::default()),
+            None => self.data(&opcode.to_be_bytes()),
+        }
+    }
+
+    pub fn line(self, line: &Line, symbols: &Symbols) -> String {
+        match line.instruction {
+            Some(instruction) => self.instruction(instruction, symbols),
+            None => self.data(&line.bytes),
+        }
+    }
+
+    pub fn instruction(self, instruction: Instruction, symbols: &Symbols) -> String {
+        match self {
+            Syntax::Classic => classic(instruction, symbols),
+            Syntax::Octo => octo(instruction, symbols),
+        }
+    }
+
+    pub fn label(self, name: &str) -> String {
+        match self {
+            Syntax::Classic => format!("{}:", name),
+            Syntax::Octo => format!(": {}", name),
+        }
+    }
+
+    pub fn data(self, bytes: &[u8]) -> String {
+        let bytes: Vec<String> = bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+
+        match self {
+            Syntax::Classic => format!("DB {}", bytes.join(", ")),
+            Syntax::Octo => bytes.join(" "),
+        }
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "classic" => Ok(Syntax::Classic),
+            "octo" => Ok(Syntax::Octo),
+            _ => Err(format!(
+                "Unknown syntax '{}', expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+// Names for addresses, read from a symbol file with one "0x2A4 = draw_score"
+// per line. Names are used in address operands and as labels.
+#[derive(Clone, Debug, Default)]
+pub struct Symbols {
+    names: HashMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut names = HashMap::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split(['#', ';']).next().unwrap_or("").trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let (address, name) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Line {}: expected address = name", index + 1))?;
+
+            let (address, name) = (address.trim(), name.trim());
+
+            let address = parse_address(address)
+                .filter(|&address| address <= 0xFFF)
+                .ok_or_else(|| format!("Line {}: invalid address '{}'", index + 1, address))?;
+
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+            if !valid {
+                return Err(format!("Line {}: invalid name '{}'", index + 1, name));
+            }
+
+            names.insert(address, name.to_string());
+        }
+
+        Ok(Self { names })
+    }
+
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    // Drops the names of addresses that won't get a label, so operands only
+    // refer to labels that exist.
+    pub fn retain(&mut self, mut keep: impl FnMut(u16) -> bool) {
+        self.names.retain(|&address, _| keep(address));
+    }
+
+    fn address(&self, address: u16) -> String {
+        match self.name(address) {
+            Some(name) => name.to_string(),
+            None => format!("0x{:03X}", address),
+        }
+    }
+}
+
+// One line of a listing: either an instruction or bytes that are never run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub instruction: Option<Instruction>,
+}
+
+impl Line {
+    pub fn is_data(&self) -> bool {
+        self.instruction.is_none()
+    }
+
+    // The address of each of the line's bytes.
+    pub fn addresses(&self) -> impl Iterator<Item = u16> {
+        let start = self.address as usize;
+
+        (start..start + self.bytes.len()).map(|address| address as u16)
+    }
+}
+
+// How an instruction leads to another address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reference {
+    Call,
+    // 1NNN, or BNNN to the start of its jump table.
+    Jump,
+}
+
+// Splits a ROM loaded at `origin` into instructions and data. Only what can
+// be reached by following the program from its first instruction counts as
+// code, so code that is only reached through a computed jump (BNNN) shows up
+// as data. The `data` ranges are never treated as code. A ROM that runs past
+// 0xFFFF can't be listed.
+pub fn disassemble(
+    rom: &[u8],
+    origin: u16,
+    data: &[RangeInclusive<u16>],
+) -> Result<Vec<Line>, String> {
+    if origin as usize + rom.len() > 0x10000 {
+        return Err(format!(
+            "a {}-byte ROM loaded at {:#X} runs past address 0xFFFF",
+            rom.len(),
+            origin
+        ));
+    }
+
+    let code = find_code(rom, origin, data);
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+
+    while offset < rom.len() {
+        let is_code = code.contains(&offset);
+
+        // Data is listed a word at a time, stopping short of the next
+        // instruction.
+        let length = if is_code || (offset + 1 < rom.len() && !code.contains(&(offset + 1))) {
+            2
+        } else {
+            1
+        };
+
+        let bytes = rom[offset..offset + length].to_vec();
+
+        let instruction = if is_code {
+            Instruction::decode(u16::from_be_bytes([bytes[0], bytes[1]]))
+        } else {
+            None
+        };
+
+        lines.push(Line {
+            address: origin + offset as u16,
+            bytes,
+            instruction,
+        });
+
+        offset += length;
+    }
+
+    Ok(lines)
+}
+
+// Writes a listing the assembler can read back, with each line's address and
+// bytes in a trailing comment.
+pub fn write_listing(
+    output: &mut impl Write,
+    lines: &[Line],
+    symbols: &Symbols,
+    syntax: Syntax,
+) -> io::Result<()> {
+    write_annotated_listing(output, lines, symbols, syntax, |_| None)
+}
+
+// Like write_listing, with a note for an address (an instruction or a byte of
+// data) added to the end of its comment, e.g. coverage.
+pub fn write_annotated_listing(
+    output: &mut impl Write,
+    lines: &[Line],
+    symbols: &Symbols,
+    syntax: Syntax,
+    annotate: impl Fn(u16) -> Option<&'static str>,
+) -> io::Result<()> {
+    let comment = syntax.comment();
+
+    let note = |address: u16| match annotate(address) {
+        Some(note) => format!("  {}", note),
+        None => String::new(),
+    };
+
+    // Octo starts running at the main label.
+    if syntax == Syntax::Octo {
+        writeln!(output, ": main")?;
+    }
+
+    let origin = lines.first().map_or(0, |line| line.address);
+
+    let label = |output: &mut dyn Write, address: u16| match symbols.name(address) {
+        // The Octo listing already starts at main.
+        Some("main") if syntax == Syntax::Octo && address == origin => Ok(()),
+        Some(name) => writeln!(output, "{}", syntax.label(name)),
+        None => Ok(()),
+    };
+
+    for line in lines {
+        if line.is_data() {
+            // One byte per line so sprites show up in the comments.
+            for (address, &byte) in (line.address..).zip(&line.bytes) {
+                label(output, address)?;
+
+                writeln!(
+                    output,
+                    "{:<24}{} {:03X}: {:02X}    {}{}",
+                    syntax.data(&[byte]),
+                    comment,
+                    address,
+                    byte,
+                    bitmap(byte),
+                    note(address)
+                )?;
+            }
+
+            continue;
+        }
+
+        label(output, line.address)?;
+
+        writeln!(
+            output,
+            "{:<24}{} {:03X}: {}{}",
+            syntax.line(line, symbols),
+            comment,
+            line.address,
+            line.bytes
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<String>(),
+            note(line.address)
+        )?;
+    }
+
+    Ok(())
+}
+
+// Every address the listing's calls and jumps lead to, in order, with the
+// addresses of the instructions that lead there.
+pub fn cross_references(lines: &[Line]) -> BTreeMap<u16, Vec<(u16, Reference)>> {
+    let mut references: BTreeMap<u16, Vec<(u16, Reference)>> = BTreeMap::new();
+
+    for line in lines {
+        let (target, reference) = match line.instruction {
+            Some(Instruction::Call { address }) => (address, Reference::Call),
+            Some(Instruction::Jump { address } | Instruction::JumpOffset { address }) => {
+                (address, Reference::Jump)
+            }
+            _ => continue,
+        };
+
+        references
+            .entry(target)
+            .or_default()
+            .push((line.address, reference));
+    }
+
+    references
+}
+
+// Writes the cross-references as comments, one target per line, so they can
+// follow a listing without changing what it assembles to:
+//
+//   ; draw_score (0x2A4): called from 0x210, 0x250
+//   ; 0x300: jumped to from 0x2FE; called from 0x220
+pub fn write_cross_references(
+    output: &mut impl Write,
+    lines: &[Line],
+    symbols: &Symbols,
+    syntax: Syntax,
+) -> io::Result<()> {
+    let comment = syntax.comment();
+
+    writeln!(output, "{} Cross-references", comment)?;
+
+    for (target, references) in cross_references(lines) {
+        let target = match symbols.name(target) {
+            Some(name) => format!("{} (0x{:03X})", name, target),
+            None => format!("0x{:03X}", target),
+        };
+
+        let sources = |kind: Reference| {
+            references
+                .iter()
+                .filter(|&&(_, reference)| reference == kind)
+                .map(|(source, _)| format!("0x{:03X}", source))
+                .collect::<Vec<_>>()
+        };
+
+        let groups: Vec<String> = [(Reference::Call, "called"), (Reference::Jump, "jumped to")]
+            .into_iter()
+            .map(|(kind, verb)| (verb, sources(kind)))
+            .filter(|(_, sources)| !sources.is_empty())
+            .map(|(verb, sources)| format!("{} from {}", verb, sources.join(", ")))
+            .collect();
+
+        writeln!(output, "{} {}: {}", comment, target, groups.join("; "))?;
+    }
+
+    Ok(())
+}
+
+// Writes the program's structure as a Graphviz graph. Each node is the start
+// of the program or an address something calls or jumps to, and stands for
+// the code from there up to the next node; calls are solid edges and jumps
+// dashed.
+pub fn write_call_graph(
+    output: &mut impl Write,
+    lines: &[Line],
+    symbols: &Symbols,
+) -> io::Result<()> {
+    let references = cross_references(lines);
+
+    let mut nodes: BTreeSet<u16> = references.keys().copied().collect();
+
+    let origin = lines.first().map_or(0, |line| line.address);
+
+    nodes.insert(origin);
+
+    let mut edges = BTreeSet::new();
+
+    for (&target, sources) in &references {
+        for &(source, reference) in sources {
+            let from = nodes.range(..=source).next_back().unwrap_or(&origin);
+
+            edges.insert((*from, target, reference));
+        }
+    }
+
+    writeln!(output, "digraph program {{")?;
+    writeln!(output, "    node [shape=box, fontname=monospace];")?;
+
+    for &node in &nodes {
+        let label = match symbols.name(node) {
+            Some(name) => format!("{}\\n0x{:03X}", name, node),
+            None => format!("0x{:03X}", node),
+        };
+
+        writeln!(output, "    \"0x{:03X}\" [label=\"{}\"];", node, label)?;
+    }
+
+    for (from, to, reference) in edges {
+        let style = match reference {
+            Reference::Call => "",
+            Reference::Jump => " [style=dashed]",
+        };
+
+        writeln!(
+            output,
+            "    \"0x{:03X}\" -> \"0x{:03X}\"{};",
+            from, to, style
+        )?;
+    }
+
+    writeln!(output, "}}")
+}
+
+// Draws the bits of a byte, which makes sprites easy to spot in data.
+pub fn bitmap(byte: u8) -> String {
+    (0..8)
+        .rev()
+        .map(|bit| if byte >> bit & 1 == 1 { '#' } else { '.' })
+        .collect()
+}
+
+// Offsets of every instruction reachable from the start of the ROM.
+fn find_code(rom: &[u8], origin: u16, data: &[RangeInclusive<u16>]) -> HashSet<usize> {
+    let mut code = HashSet::new();
+    let mut pending = vec![origin];
+
+    while let Some(address) = pending.pop() {
+        let offset = match address.checked_sub(origin) {
+            Some(offset) => offset as usize,
+            None => continue,
+        };
+
+        if offset + 1 >= rom.len() || code.contains(&offset) {
+            continue;
+        }
+
+        if data
+            .iter()
+            .any(|range| range.contains(&address) || range.contains(&(address + 1)))
+        {
+            continue;
+        }
+
+        let opcode = u16::from_be_bytes([rom[offset], rom[offset + 1]]);
+
+        let instruction = match Instruction::decode(opcode) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+
+        code.insert(offset);
+
+        // Wraps around rather than overflowing after the last instruction of
+        // a ROM that fills the address space.
+        let next = address.wrapping_add(2);
+
+        match instruction {
+            Instruction::Jump { address } => pending.push(address),
+            Instruction::Call { address } => pending.extend([address, next]),
+            Instruction::Return | Instruction::Exit | Instruction::JumpOffset { .. } => {}
+            Instruction::SkipIfEqual { .. }
+            | Instruction::SkipIfNotEqual { .. }
+            | Instruction::SkipIfRegistersEqual { .. }
+            | Instruction::SkipIfRegistersNotEqual { .. }
+            | Instruction::SkipIfKeyPressed { .. }
+            | Instruction::SkipIfKeyNotPressed { .. } => {
+                pending.extend([next, next.wrapping_add(2)])
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    code
+}
+
+fn classic(instruction: Instruction, symbols: &Symbols) -> String {
+    match instruction {
+        Instruction::ClearScreen => "CLS".to_string(),
+        Instruction::Return => "RET".to_string(),
+        Instruction::Exit => "EXIT".to_string(),
+        Instruction::System { address } => format!("SYS {}", symbols.address(address)),
+        Instruction::Jump { address } => format!("JP {}", symbols.address(address)),
+        Instruction::Call { address } => format!("CALL {}", symbols.address(address)),
+        Instruction::SkipIfEqual { x, value } => format!("SE V{:X}, 0x{:02X}", x, value),
+        Instruction::SkipIfNotEqual { x, value } => format!("SNE V{:X}, 0x{:02X}", x, value),
+        Instruction::SkipIfRegistersEqual { x, y } => format!("SE V{:X}, V{:X}", x, y),
+        Instruction::Load { x, value } => format!("LD V{:X}, 0x{:02X}", x, value),
+        Instruction::Add { x, value } => format!("ADD V{:X}, 0x{:02X}", x, value),
+        Instruction::Move { x, y } => format!("LD V{:X}, V{:X}", x, y),
+        Instruction::Or { x, y } => format!("OR V{:X}, V{:X}", x, y),
+        Instruction::And { x, y } => format!("AND V{:X}, V{:X}", x, y),
+        Instruction::Xor { x, y } => format!("XOR V{:X}, V{:X}", x, y),
+        Instruction::AddRegisters { x, y } => format!("ADD V{:X}, V{:X}", x, y),
+        Instruction::Subtract { x, y } => format!("SUB V{:X}, V{:X}", x, y),
+        Instruction::ShiftRight { x, y } => format!("SHR V{:X}, V{:X}", x, y),
+        Instruction::SubtractReversed { x, y } => format!("SUBN V{:X}, V{:X}", x, y),
+        Instruction::ShiftLeft { x, y } => format!("SHL V{:X}, V{:X}", x, y),
+        Instruction::SkipIfRegistersNotEqual { x, y } => format!("SNE V{:X}, V{:X}", x, y),
+        Instruction::LoadIndex { address } => format!("LD I, {}", symbols.address(address)),
+        Instruction::JumpOffset { address } => format!("JP V0, {}", symbols.address(address)),
+        Instruction::Random { x, mask } => format!("RND V{:X}, 0x{:02X}", x, mask),
+        Instruction::Draw { x, y, rows } => format!("DRW V{:X}, V{:X}, {}", x, y, rows),
+        Instruction::SkipIfKeyPressed { x } => format!("SKP V{:X}", x),
+        Instruction::SkipIfKeyNotPressed { x } => format!("SKNP V{:X}", x),
+        Instruction::LoadAudioPattern => "AUDIO".to_string(),
+        Instruction::LoadDelayTimer { x } => format!("LD V{:X}, DT", x),
+        Instruction::WaitForKey { x } => format!("LD V{:X}, K", x),
+        Instruction::SetDelayTimer { x } => format!("LD DT, V{:X}", x),
+        Instruction::SetSoundTimer { x } => format!("LD ST, V{:X}", x),
+        Instruction::AddIndex { x } => format!("ADD I, V{:X}", x),
+        Instruction::LoadFont { x } => format!("LD F, V{:X}", x),
+        Instruction::StoreBcd { x } => format!("LD B, V{:X}", x),
+        Instruction::SetPitch { x } => format!("LD PITCH, V{:X}", x),
+        Instruction::StoreRegisters { x } => format!("LD [I], V{:X}", x),
+        Instruction::LoadRegisters { x } => format!("LD V{:X}, [I]", x),
+        Instruction::StoreFlags { x } => format!("LD R, V{:X}", x),
+        Instruction::LoadFlags { x } => format!("LD V{:X}, R", x),
+    }
+}
+
+// Octo spells skips as conditionals on the *following* statement, so the
+// condition is the opposite of the one that causes the skip.
+fn octo(instruction: Instruction, symbols: &Symbols) -> String {
+    match instruction {
+        Instruction::ClearScreen => "clear".to_string(),
+        Instruction::Return => "return".to_string(),
+        Instruction::Exit => "exit".to_string(),
+        Instruction::System { .. } => Syntax::Octo.data(&instruction.encode().to_be_bytes()),
+        Instruction::Jump { address } => format!("jump {}", symbols.address(address)),
+        Instruction::Call { address } => format!(":call {}", symbols.address(address)),
+        Instruction::SkipIfEqual { x, value } => format!("if v{:x} != {} then", x, value),
+        Instruction::SkipIfNotEqual { x, value } => format!("if v{:x} == {} then", x, value),
+        Instruction::SkipIfRegistersEqual { x, y } => format!("if v{:x} != v{:x} then", x, y),
+        Instruction::Load { x, value } => format!("v{:x} := {}", x, value),
+        Instruction::Add { x, value } => format!("v{:x} += {}", x, value),
+        Instruction::Move { x, y } => format!("v{:x} := v{:x}", x, y),
+        Instruction::Or { x, y } => format!("v{:x} |= v{:x}", x, y),
+        Instruction::And { x, y } => format!("v{:x} &= v{:x}", x, y),
+        Instruction::Xor { x, y } => format!("v{:x} ^= v{:x}", x, y),
+        Instruction::AddRegisters { x, y } => format!("v{:x} += v{:x}", x, y),
+        Instruction::Subtract { x, y } => format!("v{:x} -= v{:x}", x, y),
+        Instruction::ShiftRight { x, y } => format!("v{:x} >>= v{:x}", x, y),
+        Instruction::SubtractReversed { x, y } => format!("v{:x} =- v{:x}", x, y),
+        Instruction::ShiftLeft { x, y } => format!("v{:x} <<= v{:x}", x, y),
+        Instruction::SkipIfRegistersNotEqual { x, y } => format!("if v{:x} == v{:x} then", x, y),
+        Instruction::LoadIndex { address } => format!("i := {}", symbols.address(address)),
+        Instruction::JumpOffset { address } => format!("jump0 {}", symbols.address(address)),
+        Instruction::Random { x, mask } => format!("v{:x} := random 0x{:02X}", x, mask),
+        Instruction::Draw { x, y, rows } => format!("sprite v{:x} v{:x} {}", x, y, rows),
+        Instruction::SkipIfKeyPressed { x } => format!("if v{:x} -key then", x),
+        Instruction::SkipIfKeyNotPressed { x } => format!("if v{:x} key then", x),
+        Instruction::LoadAudioPattern => "audio".to_string(),
+        Instruction::LoadDelayTimer { x } => format!("v{:x} := delay", x),
+        Instruction::WaitForKey { x } => format!("v{:x} := key", x),
+        Instruction::SetDelayTimer { x } => format!("delay := v{:x}", x),
+        Instruction::SetSoundTimer { x } => format!("buzzer := v{:x}", x),
+        Instruction::AddIndex { x } => format!("i += v{:x}", x),
+        Instruction::LoadFont { x } => format!("i := hex v{:x}", x),
+        Instruction::StoreBcd { x } => format!("bcd v{:x}", x),
+        Instruction::SetPitch { x } => format!("pitch := v{:x}", x),
+        Instruction::StoreRegisters { x } => format!("save v{:x}", x),
+        Instruction::LoadRegisters { x } => format!("load v{:x}", x),
+        Instruction::StoreFlags { x } => format!("saveflags v{:x}", x),
+        Instruction::LoadFlags { x } => format!("loadflags v{:x}", x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::assembler::assemble;
+    use crate::chip8::disassembler::{
+        bitmap, disassemble, write_call_graph, write_cross_references, write_listing, Symbols,
+        Syntax,
+    };
+    use crate::chip8::Instruction;
+
+    #[test]
+    fn test_classic_syntax_uses_cowgod_mnemonics() {
+        assert_eq!(Syntax::Classic.opcode(0x6005), "LD V0, 0x05");
+        assert_eq!(Syntax::Classic.opcode(0xD015), "DRW V0, V1, 5");
+        assert_eq!(Syntax::Classic.opcode(0xFA55), "LD [I], VA");
+        assert_eq!(
+            Syntax::Classic.opcode(0x5121),
+            "DB 0x51, 0x21",
+            "Words that aren't instructions should be written as data!"
+        );
+    }
+
+    #[test]
+    fn test_octo_syntax_uses_octo_statements() {
+        assert_eq!(Syntax::Octo.opcode(0x6005), "v0 := 5");
+        assert_eq!(Syntax::Octo.opcode(0xF029), "i := hex v0");
+        assert_eq!(Syntax::Octo.opcode(0xD015), "sprite v0 v1 5");
+        assert_eq!(
+            Syntax::Octo.opcode(0x3A10),
+            "if va != 16 then",
+            "3XNN should skip when the register equals the value!"
+        );
+        assert_eq!(Syntax::Octo.opcode(0x0123), "0x01 0x23");
+    }
+
+    #[test]
+    fn test_disassemble_treats_unreachable_bytes_as_data() {
+        // 200: JP 0x206, 202-205: sprite data, 206: CLS, 208: JP 0x208
+        let rom = [0x12, 0x06, 0xF0, 0x90, 0xF0, 0x12, 0x00, 0xE0, 0x12, 0x08];
+
+        let lines = disassemble(&rom, 0x200, &[]).unwrap();
+
+        let data: Vec<(u16, bool)> = lines
+            .iter()
+            .map(|line| (line.address, line.is_data()))
+            .collect();
+
+        assert_eq!(
+            data,
+            [
+                (0x200, false),
+                (0x202, true),
+                (0x204, true),
+                (0x206, false),
+                (0x208, false)
+            ],
+            "Bytes the program never reaches should be data!"
+        );
+
+        assert_eq!(lines[2].bytes, [0xF0, 0x12]);
+    }
+
+    #[test]
+    fn test_symbols_name_address_operands() {
+        let symbols = Symbols::parse("# Known routines\n0x2A4 = draw_score\n").unwrap();
+
+        let call = Instruction::Call { address: 0x2A4 };
+
+        assert_eq!(
+            Syntax::Classic.instruction(call, &symbols),
+            "CALL draw_score"
+        );
+        assert_eq!(Syntax::Octo.instruction(call, &symbols), ":call draw_score");
+        assert_eq!(
+            Syntax::Classic.instruction(Instruction::Jump { address: 0x2A6 }, &symbols),
+            "JP 0x2A6"
+        );
+
+        assert!(Symbols::parse("0x2A4 draw_score").is_err());
+        assert!(Symbols::parse("0x2A4 = 2fast").is_err());
+    }
+
+    #[test]
+    fn test_data_ranges_are_never_code() {
+        // 200: CLS, 202: JP 0x200
+        let rom = [0x00, 0xE0, 0x12, 0x00];
+
+        let lines = disassemble(&rom, 0x200, &[0x202..=0x202]).unwrap();
+
+        assert!(!lines[0].is_data());
+        assert!(
+            lines[1].is_data(),
+            "An instruction overlapping a data range should be data!"
+        );
+    }
+
+    #[test]
+    fn test_cross_references_list_callers_and_jump_sources() {
+        // 200: CALL 0x20A, 202: CALL 0x20A, 204: SE V0, 0x00, 206: JP 0x200,
+        // 208: JP 0x20A, 20A: RET
+        let rom = [
+            0x22, 0x0A, 0x22, 0x0A, 0x30, 0x00, 0x12, 0x00, 0x12, 0x0A, 0x00, 0xEE,
+        ];
+
+        let lines = disassemble(&rom, 0x200, &[]).unwrap();
+
+        let symbols = Symbols::parse("0x20A = draw").unwrap();
+
+        let mut output = Vec::new();
+
+        write_cross_references(&mut output, &lines, &symbols, Syntax::Classic).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "; Cross-references\n\
+             ; 0x200: jumped to from 0x206\n\
+             ; draw (0x20A): called from 0x200, 0x202; jumped to from 0x208\n"
+        );
+    }
+
+    #[test]
+    fn test_call_graph_joins_the_code_containing_each_call_or_jump() {
+        // 200: CALL 0x206, 202: JP 0x200, 204: data, 206: CLS, 208: JP 0x206
+        let rom = [0x22, 0x06, 0x12, 0x00, 0x00, 0x00, 0x00, 0xE0, 0x12, 0x06];
+
+        let lines = disassemble(&rom, 0x200, &[]).unwrap();
+
+        let symbols = Symbols::parse("0x206 = clear").unwrap();
+
+        let mut output = Vec::new();
+
+        write_call_graph(&mut output, &lines, &symbols).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "digraph program {\n\
+             \x20   node [shape=box, fontname=monospace];\n\
+             \x20   \"0x200\" [label=\"0x200\"];\n\
+             \x20   \"0x206\" [label=\"clear\\n0x206\"];\n\
+             \x20   \"0x200\" -> \"0x200\" [style=dashed];\n\
+             \x20   \"0x200\" -> \"0x206\";\n\
+             \x20   \"0x206\" -> \"0x206\" [style=dashed];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_bitmap_draws_bits_from_the_left() {
+        assert_eq!(bitmap(0xF1), "####...#");
+    }
+
+    #[test]
+    fn test_listings_reassemble_to_the_same_rom() {
+        // Code, a sprite, an invalid opcode and an odd trailing byte.
+        let rom = [
+            0xA2, 0x08, 0x22, 0x0A, 0x12, 0x04, 0x00, 0xEE, 0xF0, 0x90, 0xD0, 0x15, 0x00, 0xEE,
+            0x5A, 0xB1, 0x07,
+        ];
+
+        let lines = disassemble(&rom, 0x200, &[]).unwrap();
+
+        let symbols = Symbols::parse("0x208 = sprite\n0x20A = draw").unwrap();
+
+        for syntax in [Syntax::Classic, Syntax::Octo] {
+            let mut listing = Vec::new();
+
+            write_listing(&mut listing, &lines, &symbols, syntax).unwrap();
+
+            let listing = String::from_utf8(listing).unwrap();
+
+            assert_eq!(
+                assemble(&listing, syntax).unwrap(),
+                rom,
+                "The {} listing should reassemble to the same ROM:\n{}",
+                syntax.name(),
+                listing
+            );
+        }
+    }
+
+    #[test]
+    fn test_disassemble_lists_up_to_0xffff_and_rejects_larger_roms() {
+        let rom = vec![0x12; 0x10000 - 0x200];
+
+        let lines = disassemble(&rom, 0x200, &[]).unwrap();
+
+        assert_eq!(
+            lines.last().map(|line| line.address),
+            Some(0xFFFE),
+            "A ROM filling the address space should be listed to the end!"
+        );
+
+        let mut rom = rom;
+        rom.push(0x00);
+
+        assert!(
+            disassemble(&rom, 0x200, &[]).is_err(),
+            "A ROM running past 0xFFFF should be rejected!"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::str::FromStr;
+
+use crate::chip8::Frame;
+
+// Text and image formats for dumping the display, for debugging without a
+// window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+    // One line per row, '#' for lit pixels and '.' for unlit ones.
+    #[default]
+    Ascii,
+
+    // Binary portable bitmap (P4), which happens to share the display's
+    // packed one-bit-per-pixel layout.
+    Pbm,
+
+    // Scalable vector graphics, a rectangle per run of lit pixels in a row on
+    // a black background, in the display's colors.
+    Svg,
+}
+
+impl DumpFormat {
+    pub const NAMES: [&'static str; 3] = ["ascii", "pbm", "svg"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DumpFormat::Ascii => "ascii",
+            DumpFormat::Pbm => "pbm",
+            DumpFormat::Svg => "svg",
+        }
+    }
+
+    pub fn dump(self, frame: Frame<'_>) -> Vec<u8> {
+        match self {
+            DumpFormat::Ascii => dump_ascii(frame),
+            DumpFormat::Pbm => dump_pbm(frame),
+            DumpFormat::Svg => dump_svg(frame),
+        }
+    }
+}
+
+impl FromStr for DumpFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "ascii" => Ok(DumpFormat::Ascii),
+            "pbm" => Ok(DumpFormat::Pbm),
+            "svg" => Ok(DumpFormat::Svg),
+            _ => Err(format!(
+                "Unknown dump format '{}', expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+fn dump_ascii(frame: Frame<'_>) -> Vec<u8> {
+    let row_bytes = frame.width / 8;
+
+    let mut output = Vec::with_capacity((frame.width + 1) * frame.height);
+
+    for row in frame.pixels.chunks(row_bytes).take(frame.height) {
+        for x in 0..frame.width {
+            let lit = (row[x / 8] >> (7 - x % 8)) & 0x1 == 1;
+
+            output.push(if lit { b'#' } else { b'.' });
+        }
+
+        output.push(b'\n');
+    }
+
+    output
+}
+
+fn dump_pbm(frame: Frame<'_>) -> Vec<u8> {
+    let mut output = format!("P4\n{} {}\n", frame.width, frame.height).into_bytes();
+
+    output.extend_from_slice(frame.pixels);
+
+    output
+}
+
+// Drawn 10 units to a pixel so it opens at a sensible size; it scales to any.
+const SVG_PIXEL_SIZE: usize = 10;
+
+fn dump_svg(frame: Frame<'_>) -> Vec<u8> {
+    let row_bytes = frame.width / 8;
+
+    let color = |x: usize, y: usize| {
+        let lit = (frame.pixels[y * row_bytes + x / 8] >> (7 - x % 8)) & 0x1 == 1;
+
+        match frame.colors {
+            _ if !lit => None,
+            Some(colors) => Some(colors[y * frame.width + x] & 0xFFFFFF),
+            None => Some(0xFFFFFF),
+        }
+    };
+
+    let mut output = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {0} {1}\" width=\"{2}\" height=\"{3}\" shape-rendering=\"crispEdges\">\n\
+         <rect width=\"{0}\" height=\"{1}\" fill=\"#000000\"/>\n",
+        frame.width,
+        frame.height,
+        frame.width * SVG_PIXEL_SIZE,
+        frame.height * SVG_PIXEL_SIZE
+    );
+
+    for y in 0..frame.height {
+        let mut x = 0;
+
+        while x < frame.width {
+            let start = x;
+
+            let run_color = color(x, y);
+
+            while x < frame.width && color(x, y) == run_color {
+                x += 1;
+            }
+
+            if let Some(run_color) = run_color {
+                output += &format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"#{:06x}\"/>\n",
+                    start,
+                    y,
+                    x - start,
+                    run_color
+                );
+            }
+        }
+    }
+
+    output += "</svg>\n";
+
+    output.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{DumpFormat, Frame};
+
+    const PIXELS: [u8; 4] = [0x80, 0x01, 0x00, 0xFF];
+
+    fn frame() -> Frame<'static> {
+        Frame {
+            pixels: &PIXELS,
+            width: 16,
+            height: 2,
+            dirty: None,
+            colors: None,
+        }
+    }
+
+    #[test]
+    fn test_ascii_dump_draws_one_line_per_row() {
+        let dump = String::from_utf8(DumpFormat::Ascii.dump(frame())).unwrap();
+
+        assert_eq!(dump, "#..............#\n........########\n");
+    }
+
+    #[test]
+    fn test_pbm_dump_has_header_and_packed_pixels() {
+        let dump = DumpFormat::Pbm.dump(frame());
+
+        assert_eq!(&dump[..9], b"P4\n16 2\n\x80");
+        assert_eq!(&dump[8..], &PIXELS);
+    }
+
+    #[test]
+    fn test_svg_dump_draws_runs_of_lit_pixels() {
+        let dump = String::from_utf8(DumpFormat::Svg.dump(frame())).unwrap();
+
+        assert!(dump.contains("viewBox=\"0 0 16 2\""));
+        assert!(dump.contains("<rect x=\"0\" y=\"0\" width=\"1\" height=\"1\" fill=\"#ffffff\"/>"));
+        assert!(
+            dump.contains("<rect x=\"8\" y=\"1\" width=\"8\" height=\"1\" fill=\"#ffffff\"/>"),
+            "A run of lit pixels should be one rectangle!"
+        );
+        assert_eq!(dump.matches("<rect").count(), 4);
+
+        let colors: Vec<u32> = (0..32).map(|index| 0xFF000000 | index).collect();
+
+        let dump = DumpFormat::Svg.dump(Frame {
+            colors: Some(&colors),
+            ..frame()
+        });
+
+        assert!(
+            String::from_utf8(dump)
+                .unwrap()
+                .contains("fill=\"#00001f\""),
+            "Pixels should keep their colors!"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+pub const FONT_SIZE: usize = 16 * 5;
+
+pub const DEFAULT_FONT: [u8; FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // Binary '0'
+    0x20, 0x60, 0x20, 0x20, 0x70, // Binary '1'
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // Binary '2'
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // Binary '3'
+    0x90, 0x90, 0xF0, 0x10, 0x10, // Binary '4'
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // Binary '5'
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // Binary '6'
+    0xF0, 0x10, 0x20, 0x40, 0x40, // Binary '7'
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // Binary '8'
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // Binary '9'
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // Binary 'A'
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // Binary 'B'
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // Binary 'C'
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // Binary 'D'
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // Binary 'E'
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // Binary 'F'
+];
+
+// The digits in the COSMAC VIP's interpreter ROM, with its narrower 1 and
+// distinctive 4, 7, B and D.
+pub const VIP_FONT: [u8; FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // '0'
+    0x60, 0x20, 0x20, 0x20, 0x70, // '1'
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // '2'
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // '3'
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // '4'
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // '5'
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // '6'
+    0xF0, 0x10, 0x10, 0x10, 0x10, // '7'
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // '8'
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // '9'
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // 'A'
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // 'B'
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // 'C'
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // 'D'
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // 'E'
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // 'F'
+];
+
+// The DREAM 6800's three pixel wide digits.
+pub const DREAM_6800_FONT: [u8; FONT_SIZE] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // '0'
+    0x40, 0x40, 0x40, 0x40, 0x40, // '1'
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // '2'
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // '3'
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // '4'
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // '5'
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // '6'
+    0xE0, 0x20, 0x20, 0x20, 0x20, // '7'
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // '8'
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // '9'
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // 'A'
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // 'B'
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // 'C'
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // 'D'
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // 'E'
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // 'F'
+];
+
+pub const FONT_NAMES: [&str; 3] = ["default", "vip", "dream6800"];
+
+pub fn named_font(name: &str) -> Option<[u8; FONT_SIZE]> {
+    match name {
+        "default" => Some(DEFAULT_FONT),
+        "vip" => Some(VIP_FONT),
+        "dream6800" => Some(DREAM_6800_FONT),
+        _ => None,
+    }
+}
+
+// A font file holds the 16 digits back to back, five rows of one byte each,
+// the same as in memory.
+pub fn parse_font(data: &[u8]) -> Result<[u8; FONT_SIZE], String> {
+    data.try_into().map_err(|_| {
+        format!(
+            "A font should be {} bytes, five for each digit, not {}",
+            FONT_SIZE,
+            data.len()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::font::{named_font, parse_font, FONT_NAMES, FONT_SIZE};
+
+    #[test]
+    fn test_fonts_by_name_and_from_files() {
+        for name in FONT_NAMES {
+            assert!(named_font(name).is_some(), "{} should be built in!", name);
+        }
+
+        assert_eq!(
+            named_font("vip").unwrap()[5..10],
+            [0x60, 0x20, 0x20, 0x20, 0x70]
+        );
+
+        assert_eq!(parse_font(&[0xAA; FONT_SIZE]), Ok([0xAA; FONT_SIZE]));
+        assert!(
+            parse_font(&[0xAA; 64]).is_err(),
+            "A short file shouldn't load!"
+        );
+    }
+}
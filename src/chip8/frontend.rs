@@ -0,0 +1,219 @@
+use crate::chip8::Interpreter;
+
+// Extension points for frontends (winit, terminal, wasm, embedded). The core
+// only ever talks to these traits, never to a windowing or audio library.
+
+// One bit per pixel, most significant bit leftmost, rows packed back to back.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame<'a> {
+    pub pixels: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    // The part of the display that changed since the previous frame was
+    // presented, or None if nothing did.
+    pub dirty: Option<Region>,
+    // ARGB colors, one per pixel, for displays with more than two (MegaChip).
+    // `pixels` then marks the pixels that aren't black.
+    pub colors: Option<&'a [u32]>,
+}
+
+// A rectangle of display pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn union(self, other: Region) -> Region {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+
+        Region {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        }
+    }
+}
+
+pub trait DisplayDriver {
+    fn present(&mut self, frame: Frame<'_>);
+}
+
+pub trait InputSource {
+    // Returns the pressed state of keys 0x0 through 0xF.
+    fn poll_keys(&mut self) -> [bool; 16];
+}
+
+// start_tone/stop_tone may be called every frame, so implementations should
+// ignore calls that don't change the current state.
+pub trait AudioSink {
+    fn start_tone(&mut self);
+
+    fn stop_tone(&mut self);
+
+    // Called every frame once an XO-CHIP program has loaded an audio pattern,
+    // which then plays in place of the tone. Sinks that can't play patterns
+    // keep the tone.
+    fn set_pattern(&mut self, _pattern: [u8; 16], _pitch: u8) {}
+
+    // MegaChip digitised sound: 8-bit unsigned samples at `rate` Hz, played
+    // alongside the tone until the end, or until stopped if `looped`. Sinks
+    // that can't play samples ignore them.
+    fn play_sample(&mut self, _samples: &[u8], _rate: u32, _looped: bool) {}
+
+    fn stop_sample(&mut self) {}
+}
+
+pub struct NoInput;
+
+impl InputSource for NoInput {
+    fn poll_keys(&mut self) -> [bool; 16] {
+        [false; 16]
+    }
+}
+
+pub struct NoAudio;
+
+impl AudioSink for NoAudio {
+    fn start_tone(&mut self) {}
+
+    fn stop_tone(&mut self) {}
+}
+
+impl Interpreter {
+    pub fn run_frame_with(
+        &mut self,
+        display: &mut dyn DisplayDriver,
+        input: &mut dyn InputSource,
+        audio: &mut dyn AudioSink,
+    ) -> Result<(), String> {
+        self.keypad = input.poll_keys();
+
+        self.try_run_frame()?;
+
+        if let Some(pattern) = self.audio_pattern {
+            audio.set_pattern(pattern, self.pitch);
+        }
+
+        match self.take_sample_change() {
+            Some(Some(sample)) => {
+                let samples = &self.memory[sample.address..sample.address + sample.length];
+
+                audio.play_sample(samples, sample.rate, sample.looped);
+            }
+            Some(None) => audio.stop_sample(),
+            None => {}
+        }
+
+        if self.sound_timer > 0 {
+            audio.start_tone();
+        } else {
+            audio.stop_tone();
+        }
+
+        display.present(self.frame());
+
+        self.dirty = None;
+
+        Ok(())
+    }
+
+    pub fn frame(&self) -> Frame<'_> {
+        if let Some(frame) = self.megachip_frame() {
+            return frame;
+        }
+
+        Frame {
+            pixels: self.display(),
+            width: self.display_width,
+            height: self.display_height,
+            dirty: self.dirty,
+            colors: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AudioSink, DisplayDriver, Frame, InputSource};
+    use crate::chip8::Interpreter;
+
+    struct RecordingDisplay {
+        frames: Vec<Vec<u8>>,
+    }
+
+    impl DisplayDriver for RecordingDisplay {
+        fn present(&mut self, frame: Frame<'_>) {
+            self.frames.push(frame.pixels.to_vec());
+        }
+    }
+
+    struct FixedInput {
+        keys: [bool; 16],
+    }
+
+    impl InputSource for FixedInput {
+        fn poll_keys(&mut self) -> [bool; 16] {
+            self.keys
+        }
+    }
+
+    struct RecordingAudio {
+        tone: bool,
+    }
+
+    impl AudioSink for RecordingAudio {
+        fn start_tone(&mut self) {
+            self.tone = true;
+        }
+
+        fn stop_tone(&mut self) {
+            self.tone = false;
+        }
+    }
+
+    #[test]
+    fn test_run_frame_with_calls_out_to_frontend() {
+        // LD V0, 0x05; SKP V0; JP 0x202; LD ST, V0; JP 0x208
+        let program: Vec<u8> = [0x6005u16, 0xE09E, 0x1202, 0xF018, 0x1208]
+            .iter()
+            .flat_map(|opcode| opcode.to_be_bytes())
+            .collect();
+
+        let mut interpreter = Interpreter::builder()
+            .instructions_per_frame(4)
+            .build()
+            .unwrap();
+
+        interpreter.load_program(&program).unwrap();
+
+        let mut display = RecordingDisplay { frames: Vec::new() };
+
+        let mut input = FixedInput { keys: [false; 16] };
+
+        input.keys[0x5] = true;
+
+        let mut audio = RecordingAudio { tone: false };
+
+        interpreter
+            .run_frame_with(&mut display, &mut input, &mut audio)
+            .unwrap();
+
+        assert_eq!(display.frames.len(), 1, "One frame should be presented!");
+
+        assert_eq!(
+            interpreter.program_counter, 0x208,
+            "Key 5 should have skipped the busy loop!"
+        );
+
+        assert!(audio.tone, "Tone should be playing!");
+    }
+}
@@ -0,0 +1,94 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::chip8::{Interpreter, Platform, Quirks, Timing};
+
+// A machine in any state fuzz input describes: one of the platform presets
+// with arbitrary quirks, timing and memory wrapping, and arbitrary memory from
+// the program address up, registers, timers and keys. The program counter and
+// I start somewhere in memory, where most of the interesting bugs are; programs
+// can still move them past the end.
+impl<'a> Arbitrary<'a> for Interpreter {
+    fn arbitrary(input: &mut Unstructured<'a>) -> Result<Self> {
+        let mut interpreter = Interpreter::builder()
+            .platform(Platform::arbitrary(input)?)
+            .quirks(Quirks::arbitrary(input)?)
+            .timing(Timing::arbitrary(input)?)
+            .instructions_per_frame(input.int_in_range(1..=1000)?)
+            .decode_cache(input.arbitrary()?)
+            .wrap_memory(input.arbitrary()?)
+            .seed(input.arbitrary()?)
+            .build()
+            .expect("Platform presets should be valid");
+
+        let program_address = interpreter.memory_map.program_address as usize;
+
+        input.fill_buffer(&mut interpreter.memory[program_address..])?;
+
+        interpreter.invalidate_decode_cache();
+
+        interpreter.registers = input.arbitrary()?;
+        interpreter.index_register = input.int_in_range(0..=0xFFF)?;
+        interpreter.program_counter = input.int_in_range(0..=0xFFF)?;
+        interpreter.delay_timer = input.arbitrary()?;
+        interpreter.sound_timer = input.arbitrary()?;
+        interpreter.keypad = input.arbitrary()?;
+
+        Ok(interpreter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use crate::chip8::{Instruction, Interpreter};
+
+    // With wrapped memory every address is defined, so the only ways left to
+    // fail are the program's own: an opcode that doesn't exist, a call too
+    // deep or a return with nothing to return to, or too many RPL flags.
+    fn program_error(interpreter: &Interpreter) -> bool {
+        match Instruction::decode(interpreter.current_opcode()) {
+            None => true,
+            Some(Instruction::Call { .. }) => interpreter
+                .stack_depth
+                .is_some_and(|limit| interpreter.stack.len() >= limit),
+            Some(Instruction::Return) => interpreter.stack.is_empty(),
+            Some(Instruction::StoreFlags { x } | Instruction::LoadFlags { x }) => {
+                x >= interpreter.rpl_flags.len()
+            }
+            Some(_) => false,
+        }
+    }
+
+    #[test]
+    fn test_try_step_survives_arbitrary_machines() {
+        let mut state: u64 = 0x9E3779B97F4A7C15;
+
+        let mut data = vec![0; 8192];
+
+        for _ in 0..1000 {
+            for byte in &mut data {
+                // xorshift64, so every run fuzzes the same machines.
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+
+                *byte = state as u8;
+            }
+
+            let mut interpreter = Interpreter::arbitrary(&mut Unstructured::new(&data)).unwrap();
+
+            for _ in 0..500 {
+                if let Err(error) = interpreter.try_step() {
+                    assert!(
+                        !interpreter.wrap_memory || program_error(&interpreter),
+                        "Wrapped memory should only fail on the program's own errors: {}!",
+                        error
+                    );
+
+                    break;
+                }
+            }
+        }
+    }
+}
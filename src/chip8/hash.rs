@@ -0,0 +1,19 @@
+// FNV-1a, for hashes that are recorded or used as file names and so have to
+// stay the same across platforms and releases.
+pub fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xCBF29CE484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001B3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::fnv1a;
+
+    #[test]
+    fn test_fnv1a_matches_the_reference_values() {
+        assert_eq!(fnv1a([]), 0xCBF29CE484222325);
+        assert_eq!(fnv1a(*b"a"), 0xAF63DC4C8601EC8C);
+        assert_eq!(fnv1a(*b"foobar"), 0x85944171F73967E8);
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use crate::chip8::{InputSource, Interpreter};
+
+impl Interpreter {
+    // Holds down a keypad key, 0x0 to 0xF, until release_key.
+    pub fn press_key(&mut self, key: u8) {
+        self.keypad[key as usize] = true;
+    }
+
+    pub fn release_key(&mut self, key: u8) {
+        self.keypad[key as usize] = false;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyChange {
+    Press(u8),
+    Release(u8),
+}
+
+// Key presses and releases scheduled by frame, for bots and tests that play
+// a game without a frontend. The same queue against the same interpreter
+// settings always plays out the same way, since keys only change between
+// frames, never partway through one.
+//
+// It's an InputSource, so it can drive run_frame_with, or it can run frames
+// itself with run_frame.
+#[derive(Clone, Debug, Default)]
+pub struct InputQueue {
+    // The frame the next poll is for, counting from 0.
+    frame: u64,
+    // In the order they were scheduled, which is the order they apply in
+    // when several fall on the same frame.
+    changes: VecDeque<(u64, KeyChange)>,
+    keys: [bool; 16],
+}
+
+impl InputQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The frame the next poll or run_frame is for.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // Whether any scheduled change hasn't happened yet.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    // Presses the key from the next frame on.
+    pub fn press_key(&mut self, key: u8) {
+        self.press_at(self.frame, key);
+    }
+
+    // Releases the key from the next frame on.
+    pub fn release_key(&mut self, key: u8) {
+        self.release_at(self.frame, key);
+    }
+
+    pub fn press_at(&mut self, frame: u64, key: u8) {
+        self.schedule(frame, KeyChange::Press(key));
+    }
+
+    pub fn release_at(&mut self, frame: u64, key: u8) {
+        self.schedule(frame, KeyChange::Release(key));
+    }
+
+    // Holds the key for `frames` frames from the next one. FX0A needs a key
+    // held for at least a frame and then let go.
+    pub fn tap(&mut self, key: u8, frames: u64) {
+        self.press_at(self.frame, key);
+        self.release_at(self.frame + frames, key);
+    }
+
+    fn schedule(&mut self, frame: u64, change: KeyChange) {
+        let key = match change {
+            KeyChange::Press(key) | KeyChange::Release(key) => key,
+        };
+
+        assert!(key < 16, "Keypad keys run from 0x0 to 0xF, not {:#X}", key);
+
+        // Changes for frames already run happen on the next one.
+        let frame = frame.max(self.frame);
+
+        let index = self
+            .changes
+            .iter()
+            .position(|&(scheduled, _)| scheduled > frame)
+            .unwrap_or(self.changes.len());
+
+        self.changes.insert(index, (frame, change));
+    }
+
+    // Sets the keypad for the next frame and runs it.
+    pub fn run_frame(&mut self, interpreter: &mut Interpreter) -> Result<(), String> {
+        interpreter.keypad = self.poll_keys();
+
+        interpreter.try_run_frame()
+    }
+}
+
+impl InputSource for InputQueue {
+    fn poll_keys(&mut self) -> [bool; 16] {
+        while let Some(&(frame, change)) = self.changes.front() {
+            if frame > self.frame {
+                break;
+            }
+
+            match change {
+                KeyChange::Press(key) => self.keys[key as usize] = true,
+                KeyChange::Release(key) => self.keys[key as usize] = false,
+            }
+
+            self.changes.pop_front();
+        }
+
+        self.frame += 1;
+
+        self.keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::input::InputQueue;
+    use crate::chip8::{InputSource, Interpreter};
+
+    #[test]
+    fn test_queued_keys_change_between_frames() {
+        let mut queue = InputQueue::new();
+
+        queue.press_at(2, 0x5);
+        queue.tap(0xA, 1);
+        queue.release_at(4, 0x5);
+
+        let held: Vec<Vec<usize>> = (0..5)
+            .map(|_| {
+                let keys = queue.poll_keys();
+
+                (0..16).filter(|&key| keys[key]).collect()
+            })
+            .collect();
+
+        assert_eq!(held, [vec![0xA], vec![], vec![0x5], vec![0x5], vec![]]);
+        assert!(queue.is_empty());
+
+        // LD V0, K; JP 0x202
+        let rom = [0xF0, 0x0A, 0x12, 0x02];
+
+        let mut interpreter = Interpreter::builder().build().unwrap();
+
+        interpreter.load_program(&rom).unwrap();
+
+        let mut queue = InputQueue::new();
+
+        queue.press_at(3, 0x7);
+        queue.release_at(5, 0x7);
+
+        for _ in 0..5 {
+            queue.run_frame(&mut interpreter).unwrap();
+        }
+
+        assert_eq!(
+            interpreter.program_counter, 0x200,
+            "FX0A should still be waiting for the key to be released!"
+        );
+
+        queue.run_frame(&mut interpreter).unwrap();
+
+        assert_eq!(interpreter.registers[0], 0x7);
+    }
+}
@@ -0,0 +1,216 @@
+// A decoded CHIP-8 opcode. X and Y are register indices, NN is a byte, NNN an
+// address and N a nibble, as in the usual opcode notation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    ClearScreen,                                    // 00E0
+    Return,                                         // 00EE
+    Exit,                                           // 00FD (SCHIP)
+    System { address: u16 },                        // 0NNN
+    Jump { address: u16 },                          // 1NNN
+    Call { address: u16 },                          // 2NNN
+    SkipIfEqual { x: usize, value: u8 },            // 3XNN
+    SkipIfNotEqual { x: usize, value: u8 },         // 4XNN
+    SkipIfRegistersEqual { x: usize, y: usize },    // 5XY0
+    Load { x: usize, value: u8 },                   // 6XNN
+    Add { x: usize, value: u8 },                    // 7XNN
+    Move { x: usize, y: usize },                    // 8XY0
+    Or { x: usize, y: usize },                      // 8XY1
+    And { x: usize, y: usize },                     // 8XY2
+    Xor { x: usize, y: usize },                     // 8XY3
+    AddRegisters { x: usize, y: usize },            // 8XY4
+    Subtract { x: usize, y: usize },                // 8XY5
+    ShiftRight { x: usize, y: usize },              // 8XY6
+    SubtractReversed { x: usize, y: usize },        // 8XY7
+    ShiftLeft { x: usize, y: usize },               // 8XYE
+    SkipIfRegistersNotEqual { x: usize, y: usize }, // 9XY0
+    LoadIndex { address: u16 },                     // ANNN
+    JumpOffset { address: u16 },                    // BNNN
+    Random { x: usize, mask: u8 },                  // CXNN
+    Draw { x: usize, y: usize, rows: u8 },          // DXYN
+    SkipIfKeyPressed { x: usize },                  // EX9E
+    SkipIfKeyNotPressed { x: usize },               // EXA1
+    LoadAudioPattern,                               // F002 (XO-CHIP)
+    LoadDelayTimer { x: usize },                    // FX07
+    WaitForKey { x: usize },                        // FX0A
+    SetDelayTimer { x: usize },                     // FX15
+    SetSoundTimer { x: usize },                     // FX18
+    AddIndex { x: usize },                          // FX1E
+    LoadFont { x: usize },                          // FX29
+    StoreBcd { x: usize },                          // FX33
+    SetPitch { x: usize },                          // FX3A (XO-CHIP)
+    StoreRegisters { x: usize },                    // FX55
+    LoadRegisters { x: usize },                     // FX65
+    StoreFlags { x: usize },                        // FX75 (SCHIP)
+    LoadFlags { x: usize },                         // FX85 (SCHIP)
+}
+
+impl Instruction {
+    // Returns None for opcodes that don't correspond to any instruction.
+    #[inline]
+    pub fn decode(opcode: u16) -> Option<Self> {
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        let y = ((opcode & 0x00F0) >> 4) as usize;
+        let n = (opcode & 0x000F) as u8;
+        let nn = (opcode & 0x00FF) as u8;
+        let nnn = opcode & 0x0FFF;
+
+        let instruction = match opcode >> 12 {
+            0x0 => match opcode {
+                0x00E0 => Instruction::ClearScreen,
+                0x00EE => Instruction::Return,
+                0x00FD => Instruction::Exit,
+                _ => Instruction::System { address: nnn },
+            },
+            0x1 => Instruction::Jump { address: nnn },
+            0x2 => Instruction::Call { address: nnn },
+            0x3 => Instruction::SkipIfEqual { x, value: nn },
+            0x4 => Instruction::SkipIfNotEqual { x, value: nn },
+            0x5 if n == 0 => Instruction::SkipIfRegistersEqual { x, y },
+            0x6 => Instruction::Load { x, value: nn },
+            0x7 => Instruction::Add { x, value: nn },
+            0x8 => match n {
+                0x0 => Instruction::Move { x, y },
+                0x1 => Instruction::Or { x, y },
+                0x2 => Instruction::And { x, y },
+                0x3 => Instruction::Xor { x, y },
+                0x4 => Instruction::AddRegisters { x, y },
+                0x5 => Instruction::Subtract { x, y },
+                0x6 => Instruction::ShiftRight { x, y },
+                0x7 => Instruction::SubtractReversed { x, y },
+                0xE => Instruction::ShiftLeft { x, y },
+                _ => return None,
+            },
+            0x9 if n == 0 => Instruction::SkipIfRegistersNotEqual { x, y },
+            0xA => Instruction::LoadIndex { address: nnn },
+            0xB => Instruction::JumpOffset { address: nnn },
+            0xC => Instruction::Random { x, mask: nn },
+            0xD => Instruction::Draw { x, y, rows: n },
+            0xE => match nn {
+                0x9E => Instruction::SkipIfKeyPressed { x },
+                0xA1 => Instruction::SkipIfKeyNotPressed { x },
+                _ => return None,
+            },
+            0xF => match nn {
+                0x02 if x == 0 => Instruction::LoadAudioPattern,
+                0x07 => Instruction::LoadDelayTimer { x },
+                0x0A => Instruction::WaitForKey { x },
+                0x15 => Instruction::SetDelayTimer { x },
+                0x18 => Instruction::SetSoundTimer { x },
+                0x1E => Instruction::AddIndex { x },
+                0x29 => Instruction::LoadFont { x },
+                0x33 => Instruction::StoreBcd { x },
+                0x3A => Instruction::SetPitch { x },
+                0x55 => Instruction::StoreRegisters { x },
+                0x65 => Instruction::LoadRegisters { x },
+                0x75 => Instruction::StoreFlags { x },
+                0x85 => Instruction::LoadFlags { x },
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+
+    pub fn encode(self) -> u16 {
+        let xy = |high: u16, x: usize, y: usize, low: u16| {
+            high << 12 | (x as u16) << 8 | (y as u16) << 4 | low
+        };
+        let xnn = |high: u16, x: usize, value: u8| high << 12 | (x as u16) << 8 | value as u16;
+        let fx = |x: usize, low: u16| 0xF000 | (x as u16) << 8 | low;
+
+        match self {
+            Instruction::ClearScreen => 0x00E0,
+            Instruction::Return => 0x00EE,
+            Instruction::Exit => 0x00FD,
+            Instruction::System { address } => address & 0x0FFF,
+            Instruction::Jump { address } => 0x1000 | address & 0x0FFF,
+            Instruction::Call { address } => 0x2000 | address & 0x0FFF,
+            Instruction::SkipIfEqual { x, value } => xnn(0x3, x, value),
+            Instruction::SkipIfNotEqual { x, value } => xnn(0x4, x, value),
+            Instruction::SkipIfRegistersEqual { x, y } => xy(0x5, x, y, 0x0),
+            Instruction::Load { x, value } => xnn(0x6, x, value),
+            Instruction::Add { x, value } => xnn(0x7, x, value),
+            Instruction::Move { x, y } => xy(0x8, x, y, 0x0),
+            Instruction::Or { x, y } => xy(0x8, x, y, 0x1),
+            Instruction::And { x, y } => xy(0x8, x, y, 0x2),
+            Instruction::Xor { x, y } => xy(0x8, x, y, 0x3),
+            Instruction::AddRegisters { x, y } => xy(0x8, x, y, 0x4),
+            Instruction::Subtract { x, y } => xy(0x8, x, y, 0x5),
+            Instruction::ShiftRight { x, y } => xy(0x8, x, y, 0x6),
+            Instruction::SubtractReversed { x, y } => xy(0x8, x, y, 0x7),
+            Instruction::ShiftLeft { x, y } => xy(0x8, x, y, 0xE),
+            Instruction::SkipIfRegistersNotEqual { x, y } => xy(0x9, x, y, 0x0),
+            Instruction::LoadIndex { address } => 0xA000 | address & 0x0FFF,
+            Instruction::JumpOffset { address } => 0xB000 | address & 0x0FFF,
+            Instruction::Random { x, mask } => xnn(0xC, x, mask),
+            Instruction::Draw { x, y, rows } => xy(0xD, x, y, rows as u16 & 0xF),
+            Instruction::SkipIfKeyPressed { x } => xnn(0xE, x, 0x9E),
+            Instruction::SkipIfKeyNotPressed { x } => xnn(0xE, x, 0xA1),
+            Instruction::LoadAudioPattern => 0xF002,
+            Instruction::LoadDelayTimer { x } => fx(x, 0x07),
+            Instruction::WaitForKey { x } => fx(x, 0x0A),
+            Instruction::SetDelayTimer { x } => fx(x, 0x15),
+            Instruction::SetSoundTimer { x } => fx(x, 0x18),
+            Instruction::AddIndex { x } => fx(x, 0x1E),
+            Instruction::LoadFont { x } => fx(x, 0x29),
+            Instruction::StoreBcd { x } => fx(x, 0x33),
+            Instruction::SetPitch { x } => fx(x, 0x3A),
+            Instruction::StoreRegisters { x } => fx(x, 0x55),
+            Instruction::LoadRegisters { x } => fx(x, 0x65),
+            Instruction::StoreFlags { x } => fx(x, 0x75),
+            Instruction::LoadFlags { x } => fx(x, 0x85),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Instruction;
+
+    #[test]
+    fn test_decode_extracts_operands() {
+        assert_eq!(
+            Instruction::decode(0xD12F),
+            Some(Instruction::Draw {
+                x: 1,
+                y: 2,
+                rows: 0xF
+            })
+        );
+
+        assert_eq!(
+            Instruction::decode(0x7A42),
+            Some(Instruction::Add {
+                x: 0xA,
+                value: 0x42
+            })
+        );
+
+        assert_eq!(
+            Instruction::decode(0x5121),
+            None,
+            "5XY1 is not an instruction!"
+        );
+        assert_eq!(
+            Instruction::decode(0xE0FF),
+            None,
+            "EXFF is not an instruction!"
+        );
+    }
+
+    #[test]
+    fn test_every_valid_opcode_round_trips() {
+        for opcode in 0..=u16::MAX {
+            if let Some(instruction) = Instruction::decode(opcode) {
+                assert_eq!(
+                    instruction.encode(),
+                    opcode,
+                    "{:?} should encode back to {:04X}!",
+                    instruction,
+                    opcode
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use crate::chip8::{Instruction, Interpreter, Timing};
+
+// Longest run of straight-line instructions compiled into a single block.
+const MAX_BLOCK_LENGTH: usize = 64;
+
+type Operation = Box<dyn Fn(&mut Interpreter)>;
+
+// An instruction that can only come last in a block, as it may skip the next
+// instruction, write memory or fail. Returns how far to move the program
+// counter past it.
+type Exit = Box<dyn Fn(&mut Interpreter) -> Result<u16, String>>;
+
+// A run of instructions that can't branch, fail or write memory, compiled into
+// closures with their operands already decoded, and perhaps an exit.
+struct Block {
+    // Every byte that was looked at while compiling, including the opcode that
+    // ended the block. If any of them change, the block is recompiled.
+    source: Vec<u8>,
+    operations: Vec<Operation>,
+    exit: Option<Exit>,
+}
+
+impl Block {
+    fn len(&self) -> usize {
+        self.operations.len() + self.exit.is_some() as usize
+    }
+}
+
+// Experimental backend that runs straight-line code as precompiled blocks,
+// ending at a skip or BCD store, and hands everything else (jumps, draws,
+// other memory writes) to the interpreter.
+pub struct Jit {
+    // Indexed by start address.
+    blocks: Vec<Option<Block>>,
+}
+
+impl Default for Jit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Jit {
+    pub fn new() -> Self {
+        Self {
+            blocks: (0..Interpreter::MEMORY_SIZE).map(|_| None).collect(),
+        }
+    }
+
+    // Behaves like Interpreter::try_run_frame. Only Timing::Fixed is compiled;
+    // cycle-counted timing, and stopping at breakpoints, is left to the
+    // interpreter.
+    pub fn run_frame(&mut self, interpreter: &mut Interpreter) -> Result<(), String> {
+        if interpreter.timing != Timing::Fixed
+            || !interpreter.breakpoints.is_empty()
+            || interpreter.stopped_at.is_some()
+        {
+            return interpreter.try_run_frame();
+        }
+
+        interpreter.begin_frame();
+
+        let mut remaining = interpreter.max_frame_instructions() as usize;
+
+        while remaining > 0 {
+            remaining -= self.run_block(interpreter, remaining)?;
+        }
+
+        interpreter.tick_timers();
+
+        Ok(())
+    }
+
+    // Runs the block at the program counter, or a single interpreted
+    // instruction if there is no block or it's longer than `limit`. Returns the
+    // number of instructions executed.
+    pub fn run_block(
+        &mut self,
+        interpreter: &mut Interpreter,
+        limit: usize,
+    ) -> Result<usize, String> {
+        let pc = interpreter.program_counter;
+
+        let block = match self.block_at(interpreter, pc) {
+            Some(block) if block.len() > 0 && block.len() <= limit => block,
+            _ => {
+                interpreter.try_execute_cycle()?;
+
+                return Ok(1);
+            }
+        };
+
+        for operation in &block.operations {
+            operation(interpreter);
+        }
+
+        interpreter.program_counter = pc + 2 * block.operations.len() as u16;
+
+        if let Some(exit) = &block.exit {
+            let step = exit(interpreter)?;
+
+            interpreter.program_counter = interpreter.program_counter.wrapping_add(step);
+        }
+
+        Ok(block.len())
+    }
+
+    fn block_at(&mut self, interpreter: &Interpreter, pc: u16) -> Option<&Block> {
+        let start = pc as usize;
+
+        let slot = self.blocks.get_mut(start)?;
+
+        // Self-modifying code shows up as a block whose source bytes no longer
+        // match memory.
+        let stale = match slot {
+            Some(block) => {
+                interpreter.memory.get(start..start + block.source.len()) != Some(&block.source)
+            }
+            None => true,
+        };
+
+        if stale {
+            *slot = Some(compile(&interpreter.memory, start));
+        }
+
+        slot.as_ref()
+    }
+}
+
+fn compile(memory: &[u8], start: usize) -> Block {
+    let mut operations = Vec::new();
+
+    let mut exit = None;
+
+    let mut address = start;
+
+    while operations.len() < MAX_BLOCK_LENGTH && address + 1 < memory.len() {
+        let opcode = u16::from_be_bytes([memory[address], memory[address + 1]]);
+
+        address += 2;
+
+        let instruction = match Instruction::decode(opcode) {
+            Some(instruction) => instruction,
+            None => break,
+        };
+
+        match compile_instruction(instruction) {
+            Some(operation) => operations.push(operation),
+            None => {
+                exit = compile_exit(instruction);
+
+                break;
+            }
+        }
+    }
+
+    let source = memory
+        .get(start..address)
+        .map(|bytes| bytes.to_vec())
+        .unwrap_or_default();
+
+    Block {
+        source,
+        operations,
+        exit,
+    }
+}
+
+// Mirrors the matching arms of Interpreter::try_execute_cycle, minus the
+// program counter update.
+fn compile_instruction(instruction: Instruction) -> Option<Operation> {
+    let operation: Operation = match instruction {
+        Instruction::Load { x, value } => Box::new(move |i| i.registers[x] = value),
+
+        Instruction::Add { x, value } => {
+            Box::new(move |i| i.registers[x] = i.registers[x].wrapping_add(value))
+        }
+
+        Instruction::Move { x, y } => Box::new(move |i| i.registers[x] = i.registers[y]),
+
+        Instruction::Or { x, y } => Box::new(move |i| {
+            i.registers[x] |= i.registers[y];
+            i.reset_vf_after_logic_op();
+        }),
+
+        Instruction::And { x, y } => Box::new(move |i| {
+            i.registers[x] &= i.registers[y];
+            i.reset_vf_after_logic_op();
+        }),
+
+        Instruction::Xor { x, y } => Box::new(move |i| {
+            i.registers[x] ^= i.registers[y];
+            i.reset_vf_after_logic_op();
+        }),
+
+        Instruction::AddRegisters { x, y } => Box::new(move |i| {
+            let (result, carry) = i.registers[x].overflowing_add(i.registers[y]);
+
+            i.registers[x] = result;
+            i.registers[0xF] = carry as u8;
+        }),
+
+        Instruction::Subtract { x, y } => Box::new(move |i| {
+            let (result, borrow) = i.registers[x].overflowing_sub(i.registers[y]);
+
+            i.registers[x] = result;
+            i.registers[0xF] = !borrow as u8;
+        }),
+
+        Instruction::ShiftRight { x, y } => Box::new(move |i| {
+            let value = i.shift_source(x, y);
+
+            i.registers[x] = value >> 1;
+            i.registers[0xF] = value & 0x01;
+        }),
+
+        Instruction::SubtractReversed { x, y } => Box::new(move |i| {
+            let (result, borrow) = i.registers[y].overflowing_sub(i.registers[x]);
+
+            i.registers[x] = result;
+            i.registers[0xF] = !borrow as u8;
+        }),
+
+        Instruction::ShiftLeft { x, y } => Box::new(move |i| {
+            let value = i.shift_source(x, y);
+
+            i.registers[x] = value << 1;
+            i.registers[0xF] = value >> 7;
+        }),
+
+        Instruction::LoadIndex { address } => Box::new(move |i| i.index_register = address as u32),
+
+        Instruction::Random { x, mask } => {
+            Box::new(move |i| i.registers[x] = i.next_random_byte() & mask)
+        }
+
+        Instruction::LoadDelayTimer { x } => Box::new(move |i| {
+            i.registers[x] = i.delay_timer;
+            i.yielded = true;
+        }),
+
+        Instruction::SetDelayTimer { x } => Box::new(move |i| i.delay_timer = i.registers[x]),
+
+        Instruction::SetSoundTimer { x } => Box::new(move |i| i.set_sound_timer(i.registers[x])),
+
+        Instruction::AddIndex { x } => Box::new(move |i| {
+            let address = i.index_register.wrapping_add(i.registers[x] as u32);
+
+            if i.quirks.index_overflow {
+                i.registers[0xF] = (address > 0x0FFF) as u8;
+            }
+
+            i.index_register = address;
+        }),
+
+        Instruction::LoadFont { x } => Box::new(move |i| {
+            let digit = (i.registers[x] & 0x0F) as u16;
+
+            i.index_register = (i.memory_map.font_address + digit * 5) as u32;
+        }),
+
+        _ => return None,
+    };
+
+    Some(operation)
+}
+
+// Mirrors the matching arms of Interpreter::try_execute_cycle, for the
+// instructions that may end a block.
+fn compile_exit(instruction: Instruction) -> Option<Exit> {
+    // Skipping moves past the next instruction as well.
+    let skip = |condition: bool| if condition { 4 } else { 2 };
+
+    let exit: Exit = match instruction {
+        Instruction::SkipIfEqual { x, value } => {
+            Box::new(move |i| Ok(skip(i.registers[x] == value)))
+        }
+
+        Instruction::SkipIfNotEqual { x, value } => {
+            Box::new(move |i| Ok(skip(i.registers[x] != value)))
+        }
+
+        Instruction::SkipIfRegistersEqual { x, y } => {
+            Box::new(move |i| Ok(skip(i.registers[x] == i.registers[y])))
+        }
+
+        Instruction::SkipIfRegistersNotEqual { x, y } => {
+            Box::new(move |i| Ok(skip(i.registers[x] != i.registers[y])))
+        }
+
+        Instruction::StoreBcd { x } => Box::new(move |i| {
+            i.store_bcd(x, i.program_counter as usize)?;
+
+            Ok(2)
+        }),
+
+        _ => return None,
+    };
+
+    Some(exit)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::jit::Jit;
+    use crate::chip8::{Interpreter, Quirks};
+
+    fn setup(opcodes: &[u16], quirks: Quirks) -> Interpreter {
+        let mut interpreter = Interpreter::builder()
+            .quirks(quirks)
+            .seed(7)
+            .build()
+            .unwrap();
+
+        let program: Vec<u8> = opcodes
+            .iter()
+            .flat_map(|opcode| opcode.to_be_bytes())
+            .collect();
+
+        interpreter.load_program(&program).unwrap();
+
+        interpreter
+    }
+
+    #[test]
+    fn test_jit_matches_interpreter() {
+        // Exercises every compiled instruction, then draws, skips, stores BCD
+        // and loops.
+        let program = [
+            0x6A05, 0x6B03, 0x7A01, 0x8AB0, 0x8AB1, 0x8AB2, 0x8AB3, 0x8AB4, 0x8AB5, 0x8AB6, 0x8AB7,
+            0x8ABE, 0xA300, 0xC1FF, 0xF215, 0xF218, 0xF307, 0xF11E, 0xF229, 0xD125, 0x9AB0, 0x7B01,
+            0x3B07, 0x6A00, 0xA310, 0xFA33, 0x1202,
+        ];
+
+        let all_quirks = Quirks {
+            vf_reset: true,
+            index_overflow: true,
+            memory_increment: true,
+            shift: true,
+            jump: true,
+        };
+
+        for quirks in [Quirks::default(), all_quirks] {
+            let mut interpreted = setup(&program, quirks);
+            let mut compiled = setup(&program, quirks);
+
+            let mut jit = Jit::new();
+
+            for _ in 0..20 {
+                interpreted.run_frame();
+
+                jit.run_frame(&mut compiled).unwrap();
+            }
+
+            assert_eq!(
+                compiled.registers, interpreted.registers,
+                "Registers should match!"
+            );
+            assert_eq!(
+                compiled.index_register, interpreted.index_register,
+                "Index register should match!"
+            );
+            assert_eq!(
+                compiled.program_counter, interpreted.program_counter,
+                "Program counter should match!"
+            );
+            assert_eq!(
+                compiled.display_hash(),
+                interpreted.display_hash(),
+                "Display should match!"
+            );
+            assert_eq!(compiled.memory, interpreted.memory, "Memory should match!");
+        }
+    }
+
+    #[test]
+    fn test_jit_recompiles_self_modifying_code() {
+        // LD VA, 0x01; LD V0, 0x6A; LD V1, 0x02; LD I, 0x200; LD [I], V1; JP 0x200
+        let mut interpreter = setup(
+            &[0x6A01, 0x606A, 0x6102, 0xA200, 0xF155, 0x1200],
+            Quirks::default(),
+        );
+
+        let mut jit = Jit::new();
+
+        let mut executed = 0;
+
+        while executed < 7 {
+            executed += jit.run_block(&mut interpreter, 7 - executed).unwrap();
+        }
+
+        assert_eq!(
+            interpreter.registers[0xA], 0x02,
+            "Register VA should contain 0x02 from the rewritten instruction!"
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use crate::chip8::{Frame, Interpreter, Region};
+
+// LDHI loads I with 24 bits, so a MegaChip machine addresses 16 MiB.
+pub const MEGACHIP_MEMORY_SIZE: usize = 0x100_0000;
+
+pub const MEGACHIP_WIDTH: usize = 256;
+pub const MEGACHIP_HEIGHT: usize = 192;
+
+const PIXELS: usize = MEGACHIP_WIDTH * MEGACHIP_HEIGHT;
+
+const BLACK: u32 = 0xFF000000;
+
+// 060N's header: a 16-bit sample rate, a 24-bit length and a spare byte.
+const SAMPLE_HEADER: usize = 6;
+
+// How 080N mixes sprite pixels with what's already been drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    // The sprite at 25%, 50% or 75% opacity.
+    Quarter,
+    Half,
+    ThreeQuarters,
+    Add,
+    Multiply,
+}
+
+impl BlendMode {
+    fn from_nibble(n: u16) -> Option<Self> {
+        match n {
+            0 => Some(BlendMode::Normal),
+            1 => Some(BlendMode::Quarter),
+            2 => Some(BlendMode::Half),
+            3 => Some(BlendMode::ThreeQuarters),
+            4 => Some(BlendMode::Add),
+            5 => Some(BlendMode::Multiply),
+            _ => None,
+        }
+    }
+
+    fn blend(self, source: u32, destination: u32) -> u32 {
+        let channels = |mix: &dyn Fn(u32, u32) -> u32| {
+            [16, 8, 0].iter().fold(BLACK, |color, shift| {
+                let mixed = mix(source >> shift & 0xFF, destination >> shift & 0xFF);
+
+                color | mixed.min(0xFF) << shift
+            })
+        };
+
+        match self {
+            BlendMode::Normal => source | BLACK,
+            BlendMode::Quarter => channels(&|s, d| (s + 3 * d) / 4),
+            BlendMode::Half => channels(&|s, d| (s + d) / 2),
+            BlendMode::ThreeQuarters => channels(&|s, d| (3 * s + d) / 4),
+            BlendMode::Add => channels(&|s, d| s + d),
+            BlendMode::Multiply => channels(&|s, d| s * d / 0xFF),
+        }
+    }
+}
+
+// A digitised sound started by 060N, as 8-bit unsigned samples in memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub address: usize,
+    pub length: usize,
+    pub rate: u32,
+    pub looped: bool,
+}
+
+// The MegaChip-8 extensions: a 256x192 screen of palette colors drawn with
+// sprites of one byte per pixel, double buffered so that 00E0 shows what was
+// drawn since the last one. They're active between 0011 and 0010; the rest of
+// the time the machine is an ordinary CHIP-8.
+#[derive(Clone, Debug)]
+pub struct MegaChip {
+    pub enabled: bool,
+    // ARGB colors set by 02NN. Index 0 is transparent in sprites.
+    pub palette: [u32; 256],
+    // 03NN and 04NN, where 0 stands for 256.
+    pub sprite_width: u8,
+    pub sprite_height: u8,
+    pub blend_mode: BlendMode,
+    // Drawing over a pixel of this palette index sets VF.
+    pub collision_index: u8,
+    // Scales the brightness of the whole screen, for fades.
+    pub alpha: u8,
+    pub sample: Option<Sample>,
+    // Set when the sound starts or stops, until the frontend has been told.
+    sample_changed: bool,
+    // The screen being drawn, as palette indices for collisions and as
+    // blended colors.
+    indices: Box<[u8]>,
+    colors: Box<[u32]>,
+    // What 00E0 last showed, and a copy packed one bit per pixel like the
+    // CHIP-8 display for frontends that only show two colors.
+    screen: Box<[u32]>,
+    mask: Box<[u8]>,
+}
+
+impl Default for MegaChip {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            palette: [BLACK; 256],
+            sprite_width: 0,
+            sprite_height: 0,
+            blend_mode: BlendMode::Normal,
+            collision_index: 0,
+            alpha: 0xFF,
+            sample: None,
+            sample_changed: false,
+            indices: vec![0; PIXELS].into_boxed_slice(),
+            colors: vec![BLACK; PIXELS].into_boxed_slice(),
+            screen: vec![BLACK; PIXELS].into_boxed_slice(),
+            mask: vec![0; PIXELS / 8].into_boxed_slice(),
+        }
+    }
+}
+
+impl MegaChip {
+    // The screen as of the last 00E0.
+    pub fn screen(&self) -> &[u32] {
+        &self.screen
+    }
+
+    fn frame(&self, dirty: Option<Region>) -> Frame<'_> {
+        Frame {
+            pixels: &self.mask,
+            width: MEGACHIP_WIDTH,
+            height: MEGACHIP_HEIGHT,
+            dirty,
+            colors: Some(&self.screen),
+        }
+    }
+
+    fn present(&mut self) {
+        let alpha = self.alpha as u32;
+
+        for (pixel, (screen, &color)) in self.screen.iter_mut().zip(&*self.colors).enumerate() {
+            *screen = BlendMode::Multiply.blend(color, alpha * 0x010101);
+
+            let bit = 0x80 >> (pixel % 8);
+
+            if *screen & 0xFFFFFF != 0 {
+                self.mask[pixel / 8] |= bit;
+            } else {
+                self.mask[pixel / 8] &= !bit;
+            }
+        }
+
+        self.indices.fill(0);
+        self.colors.fill(BLACK);
+    }
+
+    // Moves what's been drawn so far by whole pixels, filling in with blank.
+    fn scroll(&mut self, right: isize, down: isize) {
+        let (width, height) = (MEGACHIP_WIDTH as isize, MEGACHIP_HEIGHT as isize);
+
+        let indices = self.indices.clone();
+        let colors = self.colors.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = (y * width + x) as usize;
+
+                let (from_x, from_y) = (x - right, y - down);
+
+                if (0..width).contains(&from_x) && (0..height).contains(&from_y) {
+                    let from = (from_y * width + from_x) as usize;
+
+                    self.indices[pixel] = indices[from];
+                    self.colors[pixel] = colors[from];
+                } else {
+                    self.indices[pixel] = 0;
+                    self.colors[pixel] = BLACK;
+                }
+            }
+        }
+    }
+}
+
+impl Interpreter {
+    pub fn megachip(&self) -> Option<&MegaChip> {
+        self.megachip.as_deref()
+    }
+
+    pub(crate) fn megachip_enabled(&self) -> bool {
+        self.megachip
+            .as_ref()
+            .is_some_and(|megachip| megachip.enabled)
+    }
+
+    pub(crate) fn megachip_frame(&self) -> Option<Frame<'_>> {
+        let megachip = self
+            .megachip
+            .as_deref()
+            .filter(|megachip| megachip.enabled)?;
+
+        Some(megachip.frame(self.dirty))
+    }
+
+    // Runs the MegaChip opcodes that share 0NNN with machine code calls,
+    // returning false for anything else so it's treated as SYS.
+    pub(crate) fn run_megachip(&mut self, address: u16, pc: usize) -> Result<bool, String> {
+        let mut megachip = match self.megachip.take() {
+            Some(megachip) => megachip,
+            None => return Ok(false),
+        };
+
+        let result = self.run_megachip_opcode(&mut megachip, address, pc);
+
+        self.megachip = Some(megachip);
+
+        result
+    }
+
+    fn run_megachip_opcode(
+        &mut self,
+        megachip: &mut MegaChip,
+        address: u16,
+        pc: usize,
+    ) -> Result<bool, String> {
+        let nn = (address & 0xFF) as u8;
+        let n = address & 0xF;
+
+        match address >> 8 {
+            0x0 => match address {
+                0x010 | 0x011 => {
+                    megachip.enabled = address == 0x011;
+
+                    let region = if megachip.enabled {
+                        Region {
+                            x: 0,
+                            y: 0,
+                            width: MEGACHIP_WIDTH,
+                            height: MEGACHIP_HEIGHT,
+                        }
+                    } else {
+                        self.full_display()
+                    };
+
+                    // The old region is in the other mode's coordinates.
+                    self.dirty = None;
+
+                    self.mark_dirty(region);
+                }
+                0x0B0..=0x0BF if megachip.enabled => megachip.scroll(0, -(n as isize)),
+                0x0C0..=0x0CF if megachip.enabled => megachip.scroll(0, n as isize),
+                0x0FB if megachip.enabled => megachip.scroll(4, 0),
+                0x0FC if megachip.enabled => megachip.scroll(-4, 0),
+                _ => return Ok(false),
+            },
+
+            // LDHI: the low 16 bits of I come from the following word.
+            0x1 => {
+                let low = match self.memory.get(pc + 2..pc + 4) {
+                    Some(&[high, low]) => u16::from_be_bytes([high, low]),
+                    _ if self.wrap_memory => self.opcode_at(pc + 2),
+                    _ => return Err(format!("LDHI cut off by the end of memory at {:03X}", pc)),
+                };
+
+                self.index_register = (nn as u32) << 16 | low as u32;
+
+                self.program_counter = self.program_counter.wrapping_add(4);
+
+                return Ok(true);
+            }
+
+            0x2 => {
+                self.check_memory_access(nn as usize * 4, "Palette", pc)?;
+
+                for color in 0..nn as usize {
+                    let bytes: [u8; 4] = std::array::from_fn(|byte| {
+                        self.memory[self.indexed_address(color * 4 + byte)]
+                    });
+
+                    megachip.palette[color + 1] = u32::from_be_bytes(bytes);
+                }
+            }
+
+            0x3 => megachip.sprite_width = nn,
+            0x4 => megachip.sprite_height = nn,
+            0x5 => megachip.alpha = nn,
+
+            0x6 if address & 0xF0 == 0 => {
+                self.check_memory_access(SAMPLE_HEADER, "Sample", pc)?;
+
+                let header: [u8; SAMPLE_HEADER] =
+                    std::array::from_fn(|byte| self.memory[self.indexed_address(byte)]);
+
+                let rate = u16::from_be_bytes([header[0], header[1]]) as u32;
+                let length = u32::from_be_bytes([0, header[2], header[3], header[4]]) as usize;
+
+                // Samples play straight out of memory, so they can't wrap
+                // around its end like other reads through I. Under
+                // wrap_memory they're cut short there instead.
+                let address = self.indexed_address(SAMPLE_HEADER);
+                let available = self.memory.len() - address;
+
+                if length > available && !self.wrap_memory {
+                    return Err(format!("Sample out of bounds at {:03X}", pc));
+                }
+
+                megachip.sample = Some(Sample {
+                    address,
+                    length: length.min(available),
+                    rate,
+                    looped: n == 0,
+                });
+
+                megachip.sample_changed = true;
+            }
+
+            0x7 if address == 0x700 => {
+                megachip.sample = None;
+                megachip.sample_changed = true;
+            }
+
+            0x8 if address & 0xF0 == 0 => match BlendMode::from_nibble(n) {
+                Some(blend_mode) => megachip.blend_mode = blend_mode,
+                None => return Ok(false),
+            },
+
+            0x9 => megachip.collision_index = nn,
+
+            _ => return Ok(false),
+        }
+
+        self.step_to_next_instruction();
+
+        Ok(true)
+    }
+
+    // 00E0 shows the finished screen and starts a blank one.
+    pub(crate) fn present_megachip(&mut self) {
+        if let Some(megachip) = &mut self.megachip {
+            megachip.present();
+        }
+
+        self.mark_dirty(Region {
+            x: 0,
+            y: 0,
+            width: MEGACHIP_WIDTH,
+            height: MEGACHIP_HEIGHT,
+        });
+    }
+
+    // DXYN draws a sprite of the 03NN by 04NN size set beforehand, N unused,
+    // clipped at the edges. VF is set if it covers the collision color.
+    pub(crate) fn draw_megachip_sprite(
+        &mut self,
+        x: usize,
+        y: usize,
+        pc: usize,
+    ) -> Result<(), String> {
+        let mut megachip = match self.megachip.take() {
+            Some(megachip) => megachip,
+            None => return Ok(()),
+        };
+
+        let width = match megachip.sprite_width {
+            0 => 256,
+            width => width as usize,
+        };
+        let height = match megachip.sprite_height {
+            0 => 256,
+            height => height as usize,
+        };
+
+        if let Err(error) = self.check_memory_access(width * height, "Sprite", pc) {
+            self.megachip = Some(megachip);
+
+            return Err(error);
+        }
+
+        let (left, top) = (self.registers[x] as usize, self.registers[y] as usize);
+
+        let mut collided = false;
+
+        for row in 0..height.min(MEGACHIP_HEIGHT.saturating_sub(top)) {
+            for column in 0..width.min(MEGACHIP_WIDTH - left) {
+                let index = self.memory[self.indexed_address(row * width + column)];
+
+                if index == 0 {
+                    continue;
+                }
+
+                let pixel = (top + row) * MEGACHIP_WIDTH + left + column;
+
+                collided |= megachip.indices[pixel] == megachip.collision_index;
+
+                megachip.indices[pixel] = index;
+                megachip.colors[pixel] = megachip
+                    .blend_mode
+                    .blend(megachip.palette[index as usize], megachip.colors[pixel]);
+            }
+        }
+
+        self.registers[0xF] = collided as u8;
+
+        self.megachip = Some(megachip);
+
+        Ok(())
+    }
+
+    // The sound to start (Some) or stop (None) if it changed since the last
+    // call.
+    pub(crate) fn take_sample_change(&mut self) -> Option<Option<Sample>> {
+        let megachip = self.megachip.as_deref_mut()?;
+
+        if !megachip.sample_changed {
+            return None;
+        }
+
+        megachip.sample_changed = false;
+
+        Some(megachip.sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::megachip::{BlendMode, Sample, MEGACHIP_WIDTH};
+    use crate::chip8::{Interpreter, Platform};
+
+    #[test]
+    fn test_megachip_sprite_appears_after_clear() {
+        let mut interpreter = Interpreter::builder()
+            .platform(Platform::MegaChip)
+            .build()
+            .unwrap();
+
+        // MEGAON; LDHI I, 0x010000; LDPAL 1; SPRW 2; SPRH 1; LD V0, 3;
+        // LD V1, 4; ADD I, V1; DRW V0, V0, 0; CLS
+        let mut rom = vec![
+            0x00, 0x11, 0x01, 0x01, 0x00, 0x00, 0x02, 0x01, 0x03, 0x02, 0x04, 0x01, 0x60, 0x03,
+            0x61, 0x04, 0xF1, 0x1E, 0xD0, 0x00, 0x00, 0xE0,
+        ];
+
+        rom.resize(0x10000 - 0x200, 0);
+
+        // One palette color, then a sprite of it and a transparent pixel.
+        rom.extend_from_slice(&[0xFF, 0x12, 0x34, 0x56, 0x01, 0x00]);
+
+        interpreter.load_program(&rom).unwrap();
+
+        for _ in 0..9 {
+            interpreter.execute_cycle();
+        }
+
+        assert_eq!(
+            interpreter.index_register, 0x10004,
+            "LDHI should load 24 bits!"
+        );
+
+        let screen = interpreter.megachip().unwrap().screen();
+
+        assert!(
+            screen.iter().all(|&color| color == 0xFF000000),
+            "Nothing should show before 00E0!"
+        );
+
+        interpreter.execute_cycle();
+
+        let frame = interpreter.frame();
+
+        let colors = frame.colors.unwrap();
+
+        assert_eq!((frame.width, frame.height), (256, 192));
+        assert_eq!(colors[3 * MEGACHIP_WIDTH + 3], 0xFF123456);
+        assert_eq!(
+            colors[3 * MEGACHIP_WIDTH + 4],
+            0xFF000000,
+            "Index 0 should be transparent!"
+        );
+    }
+
+    #[test]
+    fn test_megachip_sample_is_cut_short_at_the_end_of_memory_with_wrap_memory() {
+        let mut interpreter = Interpreter::builder()
+            .platform(Platform::MegaChip)
+            .wrap_memory(true)
+            .build()
+            .unwrap();
+
+        // DIGISND 1
+        interpreter.load_program(&[0x06, 0x01]).unwrap();
+
+        let end = interpreter.memory.len();
+
+        // 8000 Hz, 16 bytes long, with only 2 of them left in memory.
+        interpreter.memory[end - 8..end - 2].copy_from_slice(&[0x1F, 0x40, 0x00, 0x00, 0x10, 0x00]);
+        interpreter.index_register = (end - 8) as u32;
+
+        interpreter.execute_cycle();
+
+        assert_eq!(
+            interpreter.megachip().unwrap().sample,
+            Some(Sample {
+                address: end - 2,
+                length: 2,
+                rate: 8000,
+                looped: false,
+            }),
+            "A sample past the end of memory should stop there!"
+        );
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let (source, destination) = (0xFF804020, 0xFF402080);
+
+        assert_eq!(BlendMode::Half.blend(source, destination), 0xFF603050);
+        assert_eq!(BlendMode::Add.blend(source, destination), 0xFFC060A0);
+        assert_eq!(
+            BlendMode::Multiply.blend(0xFFFF8000, destination),
+            0xFF401000
+        );
+    }
+}
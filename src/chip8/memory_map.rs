@@ -0,0 +1,86 @@
+use crate::chip8::parse_address;
+
+// Where the interpreter keeps the font and the display buffer, and where
+// programs go. On the COSMAC VIP both lived in RAM the program could reach,
+// and some programs read or write them directly, so they are addresses
+// rather than separate buffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryMap {
+    pub font_address: u16,
+    pub display_address: u16,
+
+    // Where programs are loaded and execution starts. Almost always 0x200,
+    // but ETI-660 programs expect 0x600.
+    pub program_address: u16,
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self {
+            font_address: 0x000,
+            display_address: 0xF00,
+            program_address: 0x200,
+        }
+    }
+}
+
+impl MemoryMap {
+    pub const NAMES: [&'static str; 3] = ["font", "display", "program"];
+
+    // Applies a comma separated list of addresses, e.g.
+    // "font=0x050,display=0xE00". Addresses are decimal unless written in hex
+    // with a 0x prefix.
+    pub fn apply(&mut self, settings: &str) -> Result<(), String> {
+        for setting in settings
+            .split(',')
+            .map(str::trim)
+            .filter(|setting| !setting.is_empty())
+        {
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected name=address, got '{}'", setting))?;
+
+            let address = match name.trim() {
+                "font" => &mut self.font_address,
+                "display" => &mut self.display_address,
+                "program" => &mut self.program_address,
+                name => {
+                    return Err(format!(
+                        "Unknown memory region '{}', expected one of: {}",
+                        name,
+                        Self::NAMES.join(", ")
+                    ))
+                }
+            };
+
+            *address = parse_address(value.trim())
+                .ok_or_else(|| format!("Invalid address for {}: {}", name.trim(), value))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::MemoryMap;
+
+    #[test]
+    fn test_apply_moves_named_regions() {
+        let mut memory_map = MemoryMap::default();
+
+        memory_map.apply("font=0x050, display=3584").unwrap();
+
+        assert_eq!(
+            memory_map,
+            MemoryMap {
+                font_address: 0x050,
+                display_address: 0xE00,
+                program_address: 0x200,
+            }
+        );
+
+        assert!(memory_map.apply("stack=0xEA0").is_err());
+        assert!(memory_map.apply("font").is_err());
+    }
+}
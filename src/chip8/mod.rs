@@ -1,3 +1,69 @@
+pub mod address;
+pub mod assembler;
+pub mod builder;
+pub mod cheat;
+pub mod container;
+pub mod coverage;
+pub mod crash;
+pub mod debug_info;
+pub mod disassembler;
+pub mod dump;
+pub mod font;
+pub mod frontend;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+pub mod hash;
+pub mod input;
+pub mod instruction;
 pub mod interpreter;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod megachip;
+pub mod memory_map;
+pub mod observer;
+pub mod patch;
+pub mod peripheral;
+pub mod platform;
+pub mod pure;
+pub mod quirk_test;
+pub mod quirks;
+pub mod ram_search;
+pub mod replay;
+pub mod save_state;
+pub mod self_test;
+pub mod snapshot;
+pub mod speed;
+pub mod state;
+pub mod statistics;
+pub mod step;
+pub mod stream;
+pub mod timing;
+pub mod tone;
+pub mod trace;
+pub mod until;
 
+pub use address::parse_address;
+pub use builder::InterpreterBuilder;
+pub use cheat::Cheat;
+pub use container::Container;
+pub use coverage::Coverage;
+pub use debug_info::DebugInfo;
+pub use dump::DumpFormat;
+pub use frontend::{AudioSink, DisplayDriver, Frame, InputSource, Region};
+pub use hash::fnv1a;
+pub use input::InputQueue;
+pub use instruction::Instruction;
 pub use interpreter::Interpreter;
+pub use memory_map::MemoryMap;
+pub use observer::Observer;
+pub use peripheral::Peripheral;
+pub use platform::Platform;
+pub use quirks::Quirks;
+pub use replay::Replay;
+pub use save_state::SaveState;
+pub use snapshot::StateHandle;
+pub use state::State;
+pub use statistics::Statistics;
+pub use step::Step;
+pub use stream::StreamMessage;
+pub use timing::Timing;
@@ -0,0 +1,26 @@
+use crate::chip8::Region;
+
+// Hooks the interpreter calls as significant events happen, so embedders can
+// build frontends and instrumentation without polling the whole state every
+// cycle. Every method does nothing by default.
+pub trait Observer {
+    // Part of the display changed, by drawing or by a write to display memory.
+    fn on_draw(&mut self, _region: Region) {}
+
+    // The buzzer started or stopped, whether set by FX18 or run out.
+    fn on_sound(&mut self, _playing: bool) {}
+
+    // FX0A started waiting for a key to go into VX.
+    fn on_key_wait(&mut self, _x: usize) {}
+
+    // The program stopped: jumped to itself, the usual way CHIP-8 programs
+    // end, or hit an error, which is passed along.
+    fn on_halt(&mut self, _error: Option<&str>) {}
+
+    // 0NNN called a machine code routine, which was skipped. Each address
+    // is only reported the first time.
+    fn on_system_call(&mut self, _address: u16) {}
+
+    // The program ran SCHIP's 00FD to say it has finished.
+    fn on_exit(&mut self) {}
+}
@@ -0,0 +1,197 @@
+use std::ops::Range;
+
+use crate::chip8::{parse_address, MemoryMap};
+
+// Applies either kind of patch, telling them apart by the IPS header.
+pub fn apply(rom: &mut Vec<u8>, patch: &[u8]) -> Result<(), String> {
+    if patch.starts_with(IPS_HEADER) {
+        apply_ips(rom, patch)
+    } else {
+        let text = std::str::from_utf8(patch).map_err(|_| "Patch is neither IPS nor text")?;
+
+        apply_hex(rom, text)
+    }
+}
+
+// A text patch has one "address = bytes" per line, e.g. "0x2A4 = 12 34".
+// Addresses are CHIP-8 addresses, as in disassembly listings, written like
+// any other address; the bytes are always hex. Patches past the end of the
+// ROM grow it. '#' and ';' start comments.
+pub fn apply_hex(rom: &mut Vec<u8>, text: &str) -> Result<(), String> {
+    let origin = MemoryMap::default().program_address as usize;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+
+        let line = line.split(['#', ';']).next().unwrap_or("").trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let (address, bytes) = line
+            .split_once('=')
+            .ok_or_else(|| format!("Line {}: expected address = bytes", line_number))?;
+
+        let address = parse_address(address)
+            .map(usize::from)
+            .filter(|&address| (origin..=0xFFF).contains(&address))
+            .ok_or_else(|| format!("Line {}: invalid address '{}'", line_number, address.trim()))?;
+
+        let bytes = bytes
+            .split_whitespace()
+            .map(|byte| {
+                parse_hex(byte)
+                    .and_then(|byte| u8::try_from(byte).ok())
+                    .ok_or_else(|| format!("Line {}: invalid byte '{}'", line_number, byte))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+
+        write(rom, address - origin, &bytes);
+    }
+
+    Ok(())
+}
+
+const IPS_HEADER: &[u8] = b"PATCH";
+const IPS_FOOTER: &[u8] = b"EOF";
+
+// IPS patches address the ROM file itself, so offset 0 is the first byte of
+// the program.
+pub fn apply_ips(rom: &mut Vec<u8>, patch: &[u8]) -> Result<(), String> {
+    let mut data = patch
+        .strip_prefix(IPS_HEADER)
+        .ok_or("IPS patch doesn't start with PATCH")?;
+
+    let mut take = |length: usize| -> Result<&[u8], String> {
+        if data.len() < length {
+            return Err("IPS patch ends in the middle of a record".to_string());
+        }
+
+        let (head, tail) = data.split_at(length);
+        data = tail;
+
+        Ok(head)
+    };
+
+    loop {
+        let offset = take(3)?;
+
+        if offset == IPS_FOOTER {
+            break;
+        }
+
+        let offset = big_endian(offset);
+        let size = big_endian(take(2)?);
+
+        // A zero size marks a run of one repeated byte.
+        if size == 0 {
+            let count = big_endian(take(2)?);
+            let value = take(1)?[0];
+
+            write(rom, offset, &vec![value; count]);
+        } else {
+            let bytes = take(size)?.to_vec();
+
+            write(rom, offset, &bytes);
+        }
+    }
+
+    // Some patches end with the size to truncate the result to.
+    if let Ok(length) = take(3) {
+        rom.truncate(big_endian(length));
+    }
+
+    Ok(())
+}
+
+// The byte ranges where two ROMs differ, including bytes only one of them has.
+pub fn diff(old: &[u8], new: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for offset in 0..old.len().max(new.len()) {
+        if old.get(offset) == new.get(offset) {
+            continue;
+        }
+
+        match ranges.last_mut() {
+            Some(range) if range.end == offset => range.end += 1,
+            _ => ranges.push(offset..offset + 1),
+        }
+    }
+
+    ranges
+}
+
+fn big_endian(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| value << 8 | byte as usize)
+}
+
+fn write(rom: &mut Vec<u8>, offset: usize, bytes: &[u8]) {
+    if rom.len() < offset + bytes.len() {
+        rom.resize(offset + bytes.len(), 0);
+    }
+
+    rom[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix("0x")
+        .or(text.strip_prefix("0X"))
+        .unwrap_or(text);
+
+    usize::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::patch::{apply, apply_hex, diff};
+
+    #[test]
+    fn test_hex_patch_writes_at_chip8_addresses() {
+        let mut rom = vec![0x00, 0xE0, 0x12, 0x00];
+
+        apply_hex(&mut rom, "# infinite lives\n0x202 = 12 02\n517 = FF").unwrap();
+
+        assert_eq!(rom, [0x00, 0xE0, 0x12, 0x02, 0x00, 0xFF]);
+
+        assert!(
+            apply_hex(&mut rom, "0x100 = 00").is_err(),
+            "Addresses below the program should be rejected!"
+        );
+    }
+
+    #[test]
+    fn test_ips_patch_applies_records_and_runs() {
+        let mut rom = vec![0; 4];
+
+        let mut patch = b"PATCH".to_vec();
+        // Two bytes at offset 1.
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAB, 0xCD]);
+        // Three 0xEE bytes at offset 4.
+        patch.extend_from_slice(&[0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0xEE]);
+        patch.extend_from_slice(b"EOF");
+
+        apply(&mut rom, &patch).unwrap();
+
+        assert_eq!(rom, [0x00, 0xAB, 0xCD, 0x00, 0xEE, 0xEE, 0xEE]);
+
+        assert!(
+            apply(&mut rom, b"PATCH\x00\x00").is_err(),
+            "Truncated patches should be rejected!"
+        );
+    }
+
+    #[test]
+    fn test_diff_groups_adjacent_changes() {
+        let old = [0x00, 0xE0, 0x12, 0x00, 0xAA];
+        let new = [0x00, 0xE1, 0x13, 0x00, 0xAB, 0xFF];
+
+        assert_eq!(diff(&old, &new), [1..3, 4..6]);
+        assert!(diff(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,29 @@
+use std::ops::Range;
+
+// Hardware an embedder adds to the machine, such as a serial port, a clock
+// or extra storage, for teaching or experiments without changing the
+// interpreter. A peripheral can take over a range of addresses, which FX55
+// and FX65 then write to and read from instead of RAM, and answer 0NNN
+// machine code calls. Every method does nothing by default.
+pub trait Peripheral {
+    // The addresses mapped to the peripheral. Sprites, audio patterns and
+    // instructions are still read from RAM underneath.
+    fn addresses(&self) -> Range<u16> {
+        0..0
+    }
+
+    // FX65 read a mapped address.
+    fn read(&mut self, _address: u16) -> u8 {
+        0
+    }
+
+    // FX55 wrote a mapped address.
+    fn write(&mut self, _address: u16, _value: u8) {}
+
+    // 0NNN called `address`. Returns whether the peripheral handled the call,
+    // passing results back in the registers; unhandled calls go on to the
+    // next peripheral, and are skipped if none takes them.
+    fn system_call(&mut self, _address: u16, _registers: &mut [u8; 16]) -> bool {
+        false
+    }
+}
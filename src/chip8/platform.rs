@@ -0,0 +1,141 @@
+use std::str::FromStr;
+
+use crate::chip8::megachip::MEGACHIP_MEMORY_SIZE;
+use crate::chip8::{Interpreter, MemoryMap, Quirks};
+
+// Named presets bundling the display resolution, memory layout and quirks of a
+// historical CHIP-8 implementation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum Platform {
+    #[default]
+    Chip8,
+
+    // The two-page hi-res variant for the COSMAC VIP: a 64x64 display, and
+    // programs that start with 1260 which the interpreter redirects to 0x2C0.
+    HiresChip8,
+
+    // The HP-48 calculator port: shifts VX in place, treats BNNN as BXNN and
+    // leaves I unchanged after FX55/FX65.
+    Chip48,
+
+    // Revival Studios' MegaChip-8: CHIP-48 with 16 MiB of memory and a 256x192
+    // color mode, switched on by 0011. The resolution is the CHIP-8 one it
+    // starts in.
+    MegaChip,
+}
+
+impl Platform {
+    pub const NAMES: [&'static str; 4] = ["chip8", "hires-chip8", "chip48", "megachip"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::HiresChip8 => "hires-chip8",
+            Platform::Chip48 => "chip48",
+            Platform::MegaChip => "megachip",
+        }
+    }
+
+    pub fn resolution(self) -> (usize, usize) {
+        match self {
+            Platform::Chip8 | Platform::Chip48 | Platform::MegaChip => (64, 32),
+            Platform::HiresChip8 => (64, 64),
+        }
+    }
+
+    pub fn display_size(self) -> usize {
+        let (width, height) = self.resolution();
+
+        width * height / 8
+    }
+
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::MegaChip => MEGACHIP_MEMORY_SIZE,
+            _ => Interpreter::MEMORY_SIZE,
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 | Platform::HiresChip8 => Quirks::default(),
+            Platform::Chip48 | Platform::MegaChip => Quirks {
+                vf_reset: false,
+                index_overflow: false,
+                memory_increment: false,
+                shift: true,
+                jump: true,
+            },
+        }
+    }
+
+    // How many subroutine calls can nest: the VIP interpreter kept 12 return
+    // addresses below the display buffer and the HP-48 port 16.
+    pub fn stack_depth(self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::HiresChip8 => 12,
+            Platform::Chip48 | Platform::MegaChip => 16,
+        }
+    }
+
+    pub fn memory_map(self) -> MemoryMap {
+        match self {
+            Platform::Chip8 | Platform::Chip48 | Platform::MegaChip => MemoryMap::default(),
+            Platform::HiresChip8 => MemoryMap {
+                display_address: 0xE00,
+                ..MemoryMap::default()
+            },
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "chip8" => Ok(Platform::Chip8),
+            "hires-chip8" => Ok(Platform::HiresChip8),
+            "chip48" => Ok(Platform::Chip48),
+            "megachip" => Ok(Platform::MegaChip),
+            _ => Err(format!(
+                "Unknown platform '{}', expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Platform;
+
+    #[test]
+    fn test_platform_names_round_trip() {
+        for name in Platform::NAMES {
+            let platform: Platform = name.parse().unwrap();
+
+            assert_eq!(platform.name(), name, "Platform name should round trip!");
+        }
+    }
+
+    #[test]
+    fn test_chip48_preset_quirks() {
+        let quirks = Platform::Chip48.quirks();
+
+        assert!(quirks.shift, "CHIP-48 should shift in place!");
+        assert!(quirks.jump, "CHIP-48 should use BXNN!");
+        assert!(!quirks.memory_increment, "CHIP-48 should not increment I!");
+    }
+
+    #[test]
+    fn test_hires_display_fits_in_memory() {
+        let platform = Platform::HiresChip8;
+
+        let display_end = platform.memory_map().display_address as usize + platform.display_size();
+
+        assert!(display_end <= 4096, "Hi-res display should fit in memory!");
+    }
+}
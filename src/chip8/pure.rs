@@ -0,0 +1,148 @@
+use crate::chip8::{InterpreterBuilder, Region, State, Step};
+
+// Everything an instruction reads besides State, so a step can be computed
+// from plain values with no threads, clocks or generator hidden away.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Inputs {
+    pub keys: [bool; 16],
+    // What CXNN draws from, like a seed; Effects hands back the next value.
+    pub random_state: u64,
+    // The key an FX0A in progress saw pressed, from the previous step.
+    pub key_wait: Option<u8>,
+}
+
+// What a step did besides changing State.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Effects {
+    pub step: Step,
+    pub random_state: u64,
+    pub key_wait: Option<u8>,
+    // The part of the display drawn to or cleared.
+    pub drawn: Option<Region>,
+}
+
+// Runs the instruction at the state's program counter on a fresh interpreter
+// built from `settings`, leaving `state` untouched. Timers don't tick; that
+// happens between frames. Only State is carried over, so XO-CHIP audio and
+// the RPL flags start out as the builder leaves them.
+pub fn step(
+    settings: &InterpreterBuilder,
+    state: &State,
+    inputs: &Inputs,
+) -> Result<(State, Effects), String> {
+    let mut interpreter = settings.clone().seed(inputs.random_state).build()?;
+
+    if state.memory.len() != interpreter.memory.len() {
+        return Err(format!(
+            "State has {} bytes of memory instead of {}",
+            state.memory.len(),
+            interpreter.memory.len()
+        ));
+    }
+
+    interpreter.memory.copy_from_slice(&state.memory);
+    interpreter.registers = state.registers;
+    interpreter.index_register = state.index_register;
+    interpreter.program_counter = state.program_counter;
+    interpreter.delay_timer = state.delay_timer;
+    interpreter.sound_timer = state.sound_timer;
+    interpreter.stack = state.stack.clone();
+    interpreter.keypad = inputs.keys;
+    interpreter.key_wait = inputs.key_wait.map(|key| key as usize & 0xF);
+    interpreter.dirty = None;
+
+    let step = interpreter.try_step()?;
+
+    let effects = Effects {
+        step,
+        random_state: interpreter.random_state,
+        key_wait: interpreter.key_wait.map(|key| key as u8),
+        drawn: interpreter.dirty,
+    };
+
+    Ok((interpreter.state(), effects))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::pure::{step, Inputs};
+    use crate::chip8::{Instruction, Interpreter};
+
+    // Instructions documented to change VF: as a flag, or as VX with X = F.
+    fn may_write_vf(instruction: Instruction) -> bool {
+        match instruction {
+            Instruction::Load { x, .. }
+            | Instruction::Add { x, .. }
+            | Instruction::Move { x, .. }
+            | Instruction::Random { x, .. }
+            | Instruction::LoadDelayTimer { x }
+            | Instruction::WaitForKey { x }
+            | Instruction::LoadRegisters { x }
+            | Instruction::LoadFlags { x } => x == 0xF,
+            Instruction::Or { .. }
+            | Instruction::And { .. }
+            | Instruction::Xor { .. }
+            | Instruction::AddRegisters { .. }
+            | Instruction::Subtract { .. }
+            | Instruction::ShiftRight { .. }
+            | Instruction::SubtractReversed { .. }
+            | Instruction::ShiftLeft { .. }
+            | Instruction::Draw { .. }
+            | Instruction::AddIndex { .. } => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_vf_only_changes_when_documented() {
+        let settings = Interpreter::builder();
+
+        let mut state = Interpreter::new().state();
+
+        let mut random: u64 = 0x2545F4914F6CDD1D;
+
+        let mut next = || {
+            random ^= random << 13;
+            random ^= random >> 7;
+            random ^= random << 17;
+
+            random
+        };
+
+        for _ in 0..5000 {
+            let opcode = next() as u16;
+
+            state.memory[0x200..0x202].copy_from_slice(&opcode.to_be_bytes());
+            for register in &mut state.registers {
+                *register = next() as u8;
+            }
+
+            state.index_register = next() as u32 & 0xFFF;
+            state.program_counter = 0x200;
+
+            let inputs = Inputs {
+                keys: [next() & 1 == 1; 16],
+                random_state: next(),
+                key_wait: None,
+            };
+
+            let result = step(&settings, &state, &inputs);
+
+            assert_eq!(
+                step(&settings, &state, &inputs),
+                result,
+                "The same state and inputs should always give the same step!"
+            );
+
+            if let Ok((after, effects)) = result {
+                if after.registers[0xF] != state.registers[0xF] {
+                    assert!(
+                        may_write_vf(effects.step.instruction),
+                        "{:04X} shouldn't change VF!",
+                        opcode
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::str::FromStr;
+
+use crate::chip8::{Interpreter, Platform, Quirks};
+
+// The quirks test in Timendus' CHIP-8 test suite skips its menu when the
+// target to check against is already in memory here.
+const TARGET_ADDRESS: usize = 0x1FF;
+
+// Plenty for every test to finish; the ROM jumps to itself when it's done.
+const MAX_FRAMES: usize = 600;
+
+// The quirks the test checks, besides display wait and clipping, which
+// aren't configurable here.
+const TESTED_QUIRKS: [&str; 4] = ["vf-reset", "memory-increment", "shift", "jump"];
+
+// The original COSMAC VIP interpreter, which the test expects of its CHIP-8
+// target.
+const VIP_QUIRKS: Quirks = Quirks {
+    vf_reset: true,
+    index_overflow: false,
+    memory_increment: true,
+    shift: false,
+    jump: false,
+};
+
+// The systems the quirks test knows the expected behavior of, numbered as in
+// its menu.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuirkTarget {
+    #[default]
+    Chip8 = 1,
+    SuperChip = 2,
+    XoChip = 3,
+}
+
+impl QuirkTarget {
+    pub const NAMES: [&'static str; 3] = ["chip8", "schip", "xochip"];
+}
+
+impl FromStr for QuirkTarget {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "chip8" => Ok(QuirkTarget::Chip8),
+            "schip" => Ok(QuirkTarget::SuperChip),
+            "xochip" => Ok(QuirkTarget::XoChip),
+            _ => Err(format!(
+                "Unknown target '{}', expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+// Works out the quirks a program written for the target expects by running
+// the quirks test headlessly and reading its verdicts off the display.
+//
+// Rather than knowing what the test's marks look like, it learns them: run
+// against the CHIP-8 target with the VIP's quirks every verdict passes, and
+// flipping one quirk changes only that quirk's part of the screen. Against
+// the real target, a quirk keeps the VIP's setting if its part of the screen
+// still matches the all-passing run.
+//
+// Quirks the test doesn't check keep the platform's setting.
+pub fn detect_quirks(
+    rom: &[u8],
+    platform: Platform,
+    target: QuirkTarget,
+) -> Result<Quirks, String> {
+    let passing = run_quirk_test(rom, platform, QuirkTarget::Chip8, VIP_QUIRKS)?;
+
+    let screen = run_quirk_test(rom, platform, target, VIP_QUIRKS)?;
+
+    let mut quirks = platform.quirks();
+
+    for name in TESTED_QUIRKS {
+        let mut flipped = VIP_QUIRKS;
+
+        let vip_setting = *flag(&mut flipped, name);
+
+        *flag(&mut flipped, name) = !vip_setting;
+
+        let failing = run_quirk_test(rom, platform, QuirkTarget::Chip8, flipped)?;
+
+        // Where the test reports on the quirk.
+        let verdict: Vec<usize> = (0..passing.len())
+            .filter(|&index| passing[index] != failing[index])
+            .collect();
+
+        if verdict.is_empty() {
+            return Err(format!(
+                "The ROM doesn't report on the {} quirk, is it the quirks test?",
+                name
+            ));
+        }
+
+        let passed = verdict.iter().all(|&index| screen[index] == passing[index]);
+
+        *flag(&mut quirks, name) = vip_setting == passed;
+    }
+
+    Ok(quirks)
+}
+
+fn flag<'a>(quirks: &'a mut Quirks, name: &str) -> &'a mut bool {
+    quirks
+        .flag_mut(name)
+        .expect("Tested quirks should be known quirks")
+}
+
+// The display once the test has finished, or after MAX_FRAMES.
+fn run_quirk_test(
+    rom: &[u8],
+    platform: Platform,
+    target: QuirkTarget,
+    quirks: Quirks,
+) -> Result<Vec<u8>, String> {
+    let mut interpreter = Interpreter::builder()
+        .platform(platform)
+        .quirks(quirks)
+        .build()?;
+
+    interpreter.load_program(rom)?;
+
+    interpreter.memory[TARGET_ADDRESS] = target as u8;
+
+    for _ in 0..MAX_FRAMES {
+        interpreter.try_run_frame()?;
+
+        if interpreter.current_opcode() == 0x1000 | interpreter.program_counter {
+            break;
+        }
+    }
+
+    Ok(interpreter.display().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::assembler::assemble;
+    use crate::chip8::disassembler::Syntax;
+    use crate::chip8::quirk_test::{detect_quirks, QuirkTarget};
+    use crate::chip8::{Platform, Quirks};
+
+    // A cut-down quirks test for the CHIP-8 and SCHIP targets. Each quirk gets
+    // a row with a verdict (the digit 1 or 0) and the behavior seen (1 for on).
+    const QUIRKS_TEST: &str = "
+    LD V7, 0x00
+
+    ; vf-reset: OR clears VF.
+    LD VF, 0x05
+    OR V0, V0
+    LD V5, 0x01
+    SE VF, 0x00
+    LD V5, 0x00
+    LD V6, 0x00
+    CALL report
+
+    ; memory-increment: the second load reads the next byte.
+    LD I, scratch
+    LD V0, [I]
+    LD V0, [I]
+    LD V5, V0
+    LD V6, 0x01
+    CALL report
+
+    ; shift: VX is shifted rather than VY.
+    LD V1, 0x04
+    LD V2, 0x02
+    SHR V1, V2
+    LD V5, 0x00
+    SNE V1, 0x02
+    LD V5, 0x01
+    LD V6, 0x02
+    CALL report
+
+    ; jump: BNNN adds V2 instead of V0, as the target is in 0x2xx.
+    LD V0, 0x00
+    LD V2, 0x02
+    JP V0, jump_target
+jump_target:
+    JP jump_off
+    LD V5, 0x01
+    JP jump_done
+jump_off:
+    LD V5, 0x00
+jump_done:
+    LD V6, 0x03
+    CALL report
+
+done:
+    JP done
+
+; Draws the verdict on quirk V6, seen to be V5, at row V7.
+report:
+    LD I, 0x1FF
+    LD V0, [I]
+    ADD V0, 0xFF
+    ADD V0, V0
+    ADD V0, V0
+    ADD V0, V6
+    LD I, expected
+    ADD I, V0
+    LD V0, [I]
+    LD V1, 0x01
+    SE V0, V5
+    LD V1, 0x00
+    LD F, V1
+    LD V2, 0x00
+    DRW V2, V7, 5
+    LD F, V5
+    LD V2, 0x08
+    DRW V2, V7, 5
+    ADD V7, 0x06
+    RET
+
+scratch:
+    DB 0x00, 0x01
+
+; vf-reset, memory-increment, shift and jump for each target.
+expected:
+    DB 0x01, 0x01, 0x00, 0x00
+    DB 0x00, 0x00, 0x01, 0x01
+";
+
+    #[test]
+    fn test_detect_quirks_reads_the_verdicts() {
+        let rom = assemble(QUIRKS_TEST, Syntax::Classic).unwrap();
+
+        let chip8 = detect_quirks(&rom, Platform::Chip8, QuirkTarget::Chip8).unwrap();
+
+        assert_eq!(
+            chip8,
+            Quirks {
+                vf_reset: true,
+                index_overflow: false,
+                memory_increment: true,
+                shift: false,
+                jump: false,
+            },
+            "The CHIP-8 target should get the VIP's quirks!"
+        );
+
+        let schip = detect_quirks(&rom, Platform::Chip8, QuirkTarget::SuperChip).unwrap();
+
+        assert_eq!(
+            schip,
+            Platform::Chip48.quirks(),
+            "The SCHIP target should get the HP-48's quirks!"
+        );
+    }
+
+    #[test]
+    fn test_detect_quirks_rejects_other_roms() {
+        let rom = assemble("done: JP done", Syntax::Classic).unwrap();
+
+        assert!(detect_quirks(&rom, Platform::Chip8, QuirkTarget::Chip8).is_err());
+    }
+}
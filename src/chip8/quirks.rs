@@ -0,0 +1,129 @@
+// Behaviors that differ between CHIP-8 implementations. The defaults match
+// what this interpreter has always done.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub struct Quirks {
+    // 8XY1, 8XY2 and 8XY3 reset VF to zero, as on the COSMAC VIP.
+    pub vf_reset: bool,
+
+    // FX1E sets VF to 1 when I + VX overflows past 0x0FFF, and to 0 otherwise.
+    // Spacefight 2091! depends on this Amiga interpreter behavior.
+    pub index_overflow: bool,
+
+    // FX55 and FX65 leave I pointing past the last register stored or loaded,
+    // as on the COSMAC VIP. SCHIP leaves I unchanged.
+    pub memory_increment: bool,
+
+    // 8XY6 and 8XYE shift VX in place instead of shifting VY into VX.
+    pub shift: bool,
+
+    // BNNN is treated as BXNN, jumping to XNN + VX instead of NNN + V0.
+    pub jump: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            vf_reset: false,
+            index_overflow: false,
+            memory_increment: true,
+            shift: false,
+            jump: false,
+        }
+    }
+}
+
+impl Quirks {
+    pub const NAMES: [&'static str; 5] = [
+        "vf-reset",
+        "index-overflow",
+        "memory-increment",
+        "shift",
+        "jump",
+    ];
+
+    // Applies a comma separated list of quirk names, e.g.
+    // "vf-reset,no-memory-increment". A "no-" prefix turns a quirk off.
+    pub fn apply(&mut self, names: &str) -> Result<(), String> {
+        for name in names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let (name, enabled) = match name.strip_prefix("no-") {
+                Some(name) => (name, false),
+                None => (name, true),
+            };
+
+            let quirk = self.flag_mut(name).ok_or_else(|| {
+                format!(
+                    "Unknown quirk '{}', expected one of: {}",
+                    name,
+                    Self::NAMES.join(", ")
+                )
+            })?;
+
+            *quirk = enabled;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "vf-reset" => Some(&mut self.vf_reset),
+            "index-overflow" => Some(&mut self.index_overflow),
+            "memory-increment" => Some(&mut self.memory_increment),
+            "shift" => Some(&mut self.shift),
+            "jump" => Some(&mut self.jump),
+            _ => None,
+        }
+    }
+
+    // Whether the named quirk is on; unknown names never are.
+    pub fn is_enabled(mut self, name: &str) -> bool {
+        self.flag_mut(name).is_some_and(|quirk| *quirk)
+    }
+
+    // The --quirks list that sets every quirk to this combination, e.g.
+    // "vf-reset,no-index-overflow,memory-increment,no-shift,no-jump".
+    pub fn to_list(self) -> String {
+        Self::NAMES
+            .iter()
+            .map(|&name| {
+                if self.is_enabled(name) {
+                    name.to_string()
+                } else {
+                    format!("no-{}", name)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Quirks;
+
+    #[test]
+    fn test_apply_enables_and_disables_quirks() {
+        let mut quirks = Quirks::default();
+
+        quirks.apply("vf-reset, no-memory-increment").unwrap();
+
+        assert!(quirks.vf_reset, "vf-reset should be enabled!");
+
+        assert!(
+            !quirks.memory_increment,
+            "memory-increment should be disabled!"
+        );
+    }
+
+    #[test]
+    fn test_apply_rejects_unknown_quirk() {
+        let mut quirks = Quirks::default();
+
+        assert!(quirks.apply("warp-speed").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use std::str::FromStr;
+
+use crate::chip8::parse_address;
+
+// How a byte has to relate to its value in the previous snapshot to stay a
+// candidate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    Equal(u8),
+}
+
+impl Comparison {
+    fn matches(self, previous: u8, current: u8) -> bool {
+        match self {
+            Comparison::Changed => current != previous,
+            Comparison::Unchanged => current == previous,
+            Comparison::Increased => current > previous,
+            Comparison::Decreased => current < previous,
+            Comparison::Equal(value) => current == value,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut words = text.split_whitespace();
+
+        let comparison = match words.next() {
+            Some("changed") => Comparison::Changed,
+            Some("unchanged") => Comparison::Unchanged,
+            Some("increased") => Comparison::Increased,
+            Some("decreased") => Comparison::Decreased,
+            Some("equals") => {
+                let value = words.next().ok_or("equals needs a value")?;
+
+                let parsed = parse_address(value).and_then(|value| u8::try_from(value).ok());
+
+                Comparison::Equal(parsed.ok_or_else(|| format!("Invalid value: {}", value))?)
+            }
+            _ => {
+                return Err(format!(
+                    "Unknown comparison '{}', expected one of: changed, unchanged, increased, decreased, equals <value>",
+                    text.trim()
+                ))
+            }
+        };
+
+        match words.next() {
+            Some(extra) => Err(format!("Unexpected '{}' after comparison", extra)),
+            None => Ok(comparison),
+        }
+    }
+}
+
+// Narrows memory down to the addresses of a game variable, like the cheat
+// search in other emulators: take a snapshot, play until the variable
+// changes, filter, and repeat until few candidates are left.
+#[derive(Clone, Debug)]
+pub struct RamSearch {
+    previous: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl RamSearch {
+    pub fn new(memory: &[u8]) -> Self {
+        Self {
+            previous: memory.to_vec(),
+            candidates: (0..memory.len() as u16).collect(),
+        }
+    }
+
+    // Keeps the candidates whose byte compares as asked with the last
+    // snapshot, then takes a new snapshot for the next filter.
+    pub fn filter(&mut self, memory: &[u8], comparison: Comparison) {
+        let previous = &self.previous;
+
+        self.candidates.retain(|&address| {
+            let address = address as usize;
+
+            comparison.matches(previous[address], memory[address])
+        });
+
+        self.previous = memory.to_vec();
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+
+    // The value each candidate had in the last snapshot.
+    pub fn value(&self, address: u16) -> u8 {
+        self.previous[address as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::ram_search::{Comparison, RamSearch};
+
+    #[test]
+    fn test_ram_search_narrows_down_to_changing_byte() {
+        let mut memory = [0u8; 16];
+
+        memory[3] = 3;
+        memory[9] = 7;
+
+        let mut search = RamSearch::new(&memory);
+
+        // Lose a life; something else happens to change as well.
+        memory[3] = 2;
+        memory[9] = 8;
+
+        search.filter(&memory, Comparison::Changed);
+
+        assert_eq!(search.candidates(), [3, 9]);
+
+        memory[9] = 9;
+
+        search.filter(&memory, Comparison::Unchanged);
+
+        assert_eq!(
+            search.candidates(),
+            [3],
+            "Bytes that changed again should be filtered out!"
+        );
+
+        memory[3] = 1;
+
+        search.filter(&memory, Comparison::Decreased);
+        search.filter(&memory, Comparison::Equal(1));
+
+        assert_eq!(search.candidates(), [3]);
+        assert_eq!(search.value(3), 1);
+    }
+
+    #[test]
+    fn test_comparisons_parse_from_console_commands() {
+        assert_eq!("increased".parse(), Ok(Comparison::Increased));
+        assert_eq!("equals 0x10".parse(), Ok(Comparison::Equal(0x10)));
+        assert_eq!("equals 3".parse(), Ok(Comparison::Equal(3)));
+
+        assert!("equals 256".parse::<Comparison>().is_err());
+        assert!("bigger".parse::<Comparison>().is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use std::fmt;
+use std::fmt::Write;
+
+use crate::chip8::container::{Container, Kind};
+use crate::chip8::{fnv1a, Interpreter, Platform, Quirks, Timing};
+
+// The keys held in every frame of a run, along with everything else the run
+// depends on, so it can be played back exactly and checked: a header with
+// the ROM's hash and the settings, and a trailer with the number of frames
+// and a hash of the final state. Kept by interpreters built with replay
+// recording enabled.
+//
+// It's saved as text in a Container, the keys as runs of frames with the same
+// keys held, each a bitmask with key 0 in the lowest bit:
+//
+//   chip8-replay 2
+//   platform chip8
+//   quirks no-vf-reset,no-index-overflow,memory-increment,no-shift,no-jump
+//
+//   rom 8c3a6e2f0b1d4e57
+//   seed 1234
+//   ipf 10
+//   timing fixed
+//   rpl-flags 0000000000000000
+//   keys 0000 120
+//   keys 0020 4
+//   end 124 0f6d2c9a31b5e871
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub rom_hash: u64,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub seed: u64,
+    pub instructions_per_frame: u32,
+    pub timing: Timing,
+    // The HP-48 flags as they were before the first frame.
+    pub rpl_flags: [u8; Interpreter::RPL_FLAGS],
+    frames: Vec<u16>,
+    pub final_hash: u64,
+}
+
+impl Replay {
+    pub(crate) fn new(
+        platform: Platform,
+        quirks: Quirks,
+        seed: u64,
+        instructions_per_frame: u32,
+        timing: Timing,
+    ) -> Self {
+        Self {
+            rom_hash: 0,
+            platform,
+            quirks,
+            seed,
+            instructions_per_frame,
+            timing,
+            rpl_flags: [0; Interpreter::RPL_FLAGS],
+            frames: Vec::new(),
+            final_hash: 0,
+        }
+    }
+
+    pub(crate) fn set_rom(&mut self, rom: &[u8], instructions_per_frame: u32) {
+        self.rom_hash = rom_hash(rom);
+        self.instructions_per_frame = instructions_per_frame;
+    }
+
+    pub(crate) fn record_frame(&mut self, interpreter: &Interpreter) {
+        if self.frames.is_empty() {
+            self.rpl_flags = interpreter.rpl_flags;
+        }
+
+        let keys = (0..16)
+            .filter(|&key| interpreter.keypad[key])
+            .fold(0, |keys, key| keys | 1 << key);
+
+        self.frames.push(keys);
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Plays the replay back on the ROM and checks it ends in the same state.
+    pub fn verify(&self, rom: &[u8]) -> Result<(), String> {
+        if rom_hash(rom) != self.rom_hash {
+            return Err(format!(
+                "The replay is of another ROM (hash {:016x}, not {:016x})",
+                self.rom_hash,
+                rom_hash(rom)
+            ));
+        }
+
+        let mut interpreter = Interpreter::builder()
+            .platform(self.platform)
+            .quirks(self.quirks)
+            .seed(self.seed)
+            .instructions_per_frame(self.instructions_per_frame)
+            .timing(self.timing)
+            .build()?;
+
+        interpreter.load_program(rom)?;
+
+        interpreter.rpl_flags = self.rpl_flags;
+
+        for (frame, &keys) in self.frames.iter().enumerate() {
+            for key in 0..16 {
+                interpreter.keypad[key] = keys >> key & 1 == 1;
+            }
+
+            interpreter
+                .try_run_frame()
+                .map_err(|error| format!("Frame {}: {}", frame + 1, error))?;
+        }
+
+        let final_hash = interpreter.state().hash();
+
+        if final_hash != self.final_hash {
+            return Err(format!(
+                "The final state doesn't match (hash {:016x}, not {:016x})",
+                final_hash, self.final_hash
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let container = Container::parse(text.as_bytes(), Kind::Replay)?;
+
+        let body = String::from_utf8_lossy(&container.payload);
+
+        let mut replay = Replay::new(container.platform, container.quirks, 0, 1, Timing::Fixed);
+
+        let mut end = None;
+
+        for line in body.lines() {
+            let invalid = || format!("Invalid replay line '{}'", line);
+
+            let hex = |value: &str| u64::from_str_radix(value, 16).map_err(|_| invalid());
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields.as_slice() {
+                [] => {}
+                // Version 1 had no header, so these came with the rest.
+                ["platform", name] => replay.platform = name.parse()?,
+                ["quirks", names] => replay.quirks.apply(names)?,
+                ["rom", hash] => replay.rom_hash = hex(hash)?,
+                ["seed", seed] => replay.seed = seed.parse().map_err(|_| invalid())?,
+                ["ipf", count] => {
+                    replay.instructions_per_frame = count.parse().map_err(|_| invalid())?
+                }
+                ["timing", name] => replay.timing = name.parse()?,
+                ["rpl-flags", flags] => {
+                    replay.rpl_flags = hex(flags)?.to_be_bytes();
+                }
+                ["keys", keys, count] => {
+                    let keys = u16::from_str_radix(keys, 16).map_err(|_| invalid())?;
+                    let count: usize = count.parse().map_err(|_| invalid())?;
+
+                    replay.frames.extend(std::iter::repeat_n(keys, count));
+                }
+                ["end", frames, hash] => {
+                    end = Some((frames.parse::<usize>().map_err(|_| invalid())?, hex(hash)?));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        let (frames, final_hash) = end.ok_or("The replay is missing its end line")?;
+
+        if frames != replay.frames.len() {
+            return Err(format!(
+                "The replay should have {} frames but has {}",
+                frames,
+                replay.frames.len()
+            ));
+        }
+
+        replay.final_hash = final_hash;
+
+        Ok(replay)
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut body = String::new();
+
+        writeln!(body, "rom {:016x}", self.rom_hash)?;
+        writeln!(body, "seed {}", self.seed)?;
+        writeln!(body, "ipf {}", self.instructions_per_frame)?;
+        writeln!(body, "timing {}", self.timing.name())?;
+        writeln!(
+            body,
+            "rpl-flags {:016x}",
+            u64::from_be_bytes(self.rpl_flags)
+        )?;
+
+        for run in self.frames.chunk_by(|a, b| a == b) {
+            writeln!(body, "keys {:04x} {}", run[0], run.len())?;
+        }
+
+        writeln!(body, "end {} {:016x}", self.frames.len(), self.final_hash)?;
+
+        let container = Container::new(Kind::Replay, self.platform, self.quirks, body.into_bytes());
+
+        f.write_str(&String::from_utf8_lossy(&container.to_bytes()))
+    }
+}
+
+fn rom_hash(rom: &[u8]) -> u64 {
+    fnv1a(rom.iter().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::replay::Replay;
+    use crate::chip8::{Interpreter, Platform};
+
+    #[test]
+    fn test_replay_round_trips_and_verifies() {
+        // LD V0, K; LD F, V0; DRW V1, V1, 5; RND V2, 0xFF; JP 0x200
+        let rom = [0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0xC2, 0xFF, 0x12, 0x00];
+
+        let mut interpreter = Interpreter::builder()
+            .seed(7)
+            .record_replay(true)
+            .build()
+            .unwrap();
+
+        interpreter.load_program(&rom).unwrap();
+
+        for frame in 0..20 {
+            interpreter.keypad[5] = frame % 4 == 0;
+
+            interpreter.run_frame();
+        }
+
+        let replay = interpreter.replay().unwrap();
+
+        assert_eq!(replay.frames(), 20);
+
+        let replay = Replay::parse(&replay.to_string()).unwrap();
+
+        assert_eq!(replay.verify(&rom), Ok(()));
+
+        let mut tampered = rom;
+
+        tampered[7] = 0x0F;
+
+        assert!(
+            replay.verify(&tampered).is_err(),
+            "A different ROM should be rejected!"
+        );
+
+        let mut cheated = replay.clone();
+
+        cheated.final_hash ^= 1;
+
+        assert!(
+            cheated.verify(&rom).is_err(),
+            "A different final state should be rejected!"
+        );
+    }
+
+    #[test]
+    fn test_version_1_replays_still_load() {
+        let version_1 = "\
+chip8-replay 1
+rom 8c3a6e2f0b1d4e57
+platform chip48
+quirks no-vf-reset,no-index-overflow,no-memory-increment,shift,jump
+seed 1234
+ipf 30
+timing fixed
+rpl-flags 0000000000000000
+keys 0000 2
+keys 0020 1
+end 3 0f6d2c9a31b5e871
+";
+
+        let replay = Replay::parse(version_1).unwrap();
+
+        assert_eq!(replay.platform, Platform::Chip48);
+        assert_eq!(replay.quirks, Platform::Chip48.quirks());
+        assert_eq!(replay.frames(), 3);
+
+        assert_eq!(
+            Replay::parse(&replay.to_string()),
+            Ok(replay),
+            "A version 1 replay should survive being saved as the current version!"
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use crate::chip8::container::{Container, Kind};
+use crate::chip8::{Interpreter, MemoryMap, Platform, Quirks};
+
+// Everything a program needs to carry on from where it was saved: memory
+// (which holds the display), registers, timers, the stack, sound and the
+// random number generator. Settings such as speed and timing are left to the
+// interpreter it's loaded into, apart from the quirks.
+//
+// It's saved in a Container, with a binary payload whose layout depends on
+// the version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveState {
+    pub platform: Platform,
+    pub quirks: Quirks,
+    memory_map: MemoryMap,
+    display_width: usize,
+    display_height: usize,
+    memory: Vec<u8>,
+    registers: [u8; 16],
+    index_register: u32,
+    program_counter: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+    stack: Vec<u16>,
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+    rpl_flags: [u8; Interpreter::RPL_FLAGS],
+    key_wait: Option<usize>,
+    exited: bool,
+    cycle_budget: i64,
+    cycles: u64,
+    random_state: u64,
+}
+
+impl SaveState {
+    pub fn capture(interpreter: &Interpreter) -> Result<Self, String> {
+        // The color mode's screen and palette live outside memory.
+        if interpreter.platform == Platform::MegaChip {
+            return Err("Save states don't support MegaChip programs".to_string());
+        }
+
+        Ok(Self::copy(interpreter))
+    }
+
+    // Everything but MegaChip's color mode, which snapshots copy themselves.
+    pub(crate) fn copy(interpreter: &Interpreter) -> Self {
+        Self {
+            platform: interpreter.platform,
+            quirks: interpreter.quirks,
+            memory_map: interpreter.memory_map,
+            display_width: interpreter.display_width,
+            display_height: interpreter.display_height,
+            memory: interpreter.memory.to_vec(),
+            registers: interpreter.registers,
+            index_register: interpreter.index_register,
+            program_counter: interpreter.program_counter,
+            delay_timer: interpreter.delay_timer,
+            sound_timer: interpreter.sound_timer,
+            stack: interpreter.stack.clone(),
+            audio_pattern: interpreter.audio_pattern,
+            pitch: interpreter.pitch,
+            rpl_flags: interpreter.rpl_flags,
+            key_wait: interpreter.key_wait,
+            exited: interpreter.exited,
+            cycle_budget: interpreter.cycle_budget,
+            cycles: interpreter.cycles,
+            random_state: interpreter.random_state,
+        }
+    }
+
+    // The interpreter has to be for the same platform, which decides how
+    // much memory there is.
+    pub fn restore(&self, interpreter: &mut Interpreter) -> Result<(), String> {
+        if interpreter.platform != self.platform || interpreter.memory.len() != self.memory.len() {
+            return Err(format!(
+                "The state is of a {} program, not {}",
+                self.platform.name(),
+                interpreter.platform.name()
+            ));
+        }
+
+        interpreter.quirks = self.quirks;
+        interpreter.memory_map = self.memory_map;
+        interpreter.display_width = self.display_width;
+        interpreter.display_height = self.display_height;
+        interpreter.memory.copy_from_slice(&self.memory);
+        interpreter.registers = self.registers;
+        interpreter.index_register = self.index_register;
+        interpreter.program_counter = self.program_counter;
+        interpreter.delay_timer = self.delay_timer;
+        interpreter.set_sound_timer(self.sound_timer);
+        interpreter.stack = self.stack.clone();
+        interpreter.audio_pattern = self.audio_pattern;
+        interpreter.pitch = self.pitch;
+        interpreter.rpl_flags = self.rpl_flags;
+        interpreter.key_wait = self.key_wait;
+        interpreter.exited = self.exited;
+        interpreter.cycle_budget = self.cycle_budget;
+        interpreter.cycles = self.cycles;
+        interpreter.random_state = self.random_state;
+        interpreter.stopped_at = None;
+
+        interpreter.invalidate_decode_cache();
+
+        interpreter.mark_dirty(interpreter.full_display());
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+
+        for address in [
+            self.memory_map.font_address,
+            self.memory_map.display_address,
+            self.memory_map.program_address,
+            self.display_width as u16,
+            self.display_height as u16,
+        ] {
+            payload.extend(address.to_be_bytes());
+        }
+
+        payload.extend((self.memory.len() as u32).to_be_bytes());
+        payload.extend(&self.memory);
+        payload.extend(self.registers);
+        payload.extend(self.index_register.to_be_bytes());
+        payload.extend(self.program_counter.to_be_bytes());
+        payload.extend([self.delay_timer, self.sound_timer]);
+        payload.extend((self.stack.len() as u16).to_be_bytes());
+        payload.extend(self.stack.iter().flat_map(|address| address.to_be_bytes()));
+
+        match self.audio_pattern {
+            Some(pattern) => {
+                payload.push(1);
+                payload.extend(pattern);
+            }
+            None => payload.push(0),
+        }
+
+        payload.push(self.pitch);
+        payload.extend(self.rpl_flags);
+        payload.push(self.key_wait.map_or(0xFF, |register| register as u8));
+        payload.push(self.exited as u8);
+        payload.extend(self.cycle_budget.to_be_bytes());
+        payload.extend(self.cycles.to_be_bytes());
+        payload.extend(self.random_state.to_be_bytes());
+
+        Container::new(Kind::State, self.platform, self.quirks, payload).to_bytes()
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let container = Container::parse(bytes, Kind::State)?;
+
+        let mut payload = Payload(&container.payload);
+
+        // Later versions get their own arms, turning what they find into the
+        // current state, so older saves keep loading.
+        match container.version {
+            1 => read_version_1(&mut payload, container.platform, container.quirks),
+            version => Err(format!("Unknown save state version {}", version)),
+        }
+    }
+}
+
+fn read_version_1(
+    payload: &mut Payload<'_>,
+    platform: Platform,
+    quirks: Quirks,
+) -> Result<SaveState, String> {
+    let memory_map = MemoryMap {
+        font_address: payload.u16()?,
+        display_address: payload.u16()?,
+        program_address: payload.u16()?,
+    };
+
+    let display_width = payload.u16()? as usize;
+    let display_height = payload.u16()? as usize;
+
+    let memory_size = payload.u32()? as usize;
+    let memory = payload.take(memory_size)?.to_vec();
+
+    let registers = payload.array()?;
+    let index_register = payload.u32()?;
+    let program_counter = payload.u16()?;
+    let delay_timer = payload.u8()?;
+    let sound_timer = payload.u8()?;
+
+    let stack_size = payload.u16()? as usize;
+    let stack = (0..stack_size)
+        .map(|_| payload.u16())
+        .collect::<Result<_, _>>()?;
+
+    let audio_pattern = match payload.u8()? {
+        0 => None,
+        _ => Some(payload.array()?),
+    };
+
+    let pitch = payload.u8()?;
+    let rpl_flags = payload.array()?;
+
+    let key_wait = match payload.u8()? {
+        0xFF => None,
+        register => Some(register as usize & 0xF),
+    };
+
+    let exited = payload.u8()? != 0;
+    let cycle_budget = i64::from_be_bytes(payload.array()?);
+    let cycles = u64::from_be_bytes(payload.array()?);
+    let random_state = u64::from_be_bytes(payload.array()?);
+
+    Ok(SaveState {
+        platform,
+        quirks,
+        memory_map,
+        display_width,
+        display_height,
+        memory,
+        registers,
+        index_register,
+        program_counter,
+        delay_timer,
+        sound_timer,
+        stack,
+        audio_pattern,
+        pitch,
+        rpl_flags,
+        key_wait,
+        exited,
+        cycle_budget,
+        cycles,
+        random_state,
+    })
+}
+
+// Reads big-endian values off the front of a payload.
+struct Payload<'a>(&'a [u8]);
+
+impl<'a> Payload<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < count {
+            return Err("The save state is cut short".to_string());
+        }
+
+        let (taken, rest) = self.0.split_at(count);
+
+        self.0 = rest;
+
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().expect("Took exactly N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::save_state::SaveState;
+    use crate::chip8::{Interpreter, Platform};
+
+    #[test]
+    fn test_save_state_round_trips_and_restores() {
+        // LD V0, 0x05; CALL 0x206; JP 0x202; RND V1, 0xFF; RET
+        let rom = [0x60, 0x05, 0x22, 0x06, 0x12, 0x02, 0xC1, 0xFF, 0x00, 0xEE];
+
+        let mut interpreter = Interpreter::builder().seed(3).build().unwrap();
+
+        interpreter.load_program(&rom).unwrap();
+
+        for _ in 0..3 {
+            interpreter.execute_cycle();
+        }
+
+        let state = SaveState::capture(&interpreter).unwrap();
+
+        let state = SaveState::parse(&state.to_bytes()).unwrap();
+
+        let mut restored = Interpreter::builder().build().unwrap();
+
+        state.restore(&mut restored).unwrap();
+
+        assert_eq!(restored.state(), interpreter.state());
+
+        for _ in 0..5 {
+            interpreter.execute_cycle();
+            restored.execute_cycle();
+        }
+
+        assert_eq!(
+            restored.state(),
+            interpreter.state(),
+            "A restored state should carry on exactly like the original, random numbers included!"
+        );
+
+        let mut megachip = Interpreter::builder()
+            .platform(Platform::MegaChip)
+            .build()
+            .unwrap();
+
+        assert!(
+            state.restore(&mut megachip).is_err(),
+            "A state should only load on its own platform!"
+        );
+    }
+}
@@ -0,0 +1,544 @@
+use crate::chip8::{Interpreter, InterpreterBuilder};
+
+// One micro-test: a short program run on a fresh interpreter, after `setup`
+// has prepared the machine, and a look at what it left behind. Expectations
+// that depend on a quirk read it from the interpreter, so the suite checks
+// whatever quirks the build is configured with.
+struct Check {
+    group: &'static str,
+    name: &'static str,
+    program: &'static [u16],
+    setup: fn(&mut Interpreter),
+    // Also given the address the program was loaded at.
+    verify: fn(&Interpreter, u16) -> Result<(), String>,
+}
+
+// How the checks for one opcode group went.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupResult {
+    pub group: &'static str,
+    pub passed: usize,
+    // The name of each failed check and what went wrong.
+    pub failures: Vec<(&'static str, String)>,
+}
+
+impl GroupResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// Runs the built-in opcode checks against interpreters built by `builder`,
+// so a build and the platform and quirks it's set up with can be verified
+// without any test ROMs.
+pub fn run_self_test(builder: &InterpreterBuilder) -> Result<Vec<GroupResult>, String> {
+    let mut results: Vec<GroupResult> = Vec::new();
+
+    for check in CHECKS {
+        let outcome = run_check(builder, check)?;
+
+        let result = match results.last_mut() {
+            Some(result) if result.group == check.group => result,
+            _ => {
+                results.push(GroupResult {
+                    group: check.group,
+                    passed: 0,
+                    failures: Vec::new(),
+                });
+
+                results.last_mut().expect("A group was just added")
+            }
+        };
+
+        match outcome {
+            Ok(()) => result.passed += 1,
+            Err(error) => result.failures.push((check.name, error)),
+        }
+    }
+
+    Ok(results)
+}
+
+// Errors building the interpreter are the builder's fault, not the check's.
+fn run_check(builder: &InterpreterBuilder, check: &Check) -> Result<Result<(), String>, String> {
+    let mut interpreter = builder.clone().build()?;
+
+    let program: Vec<u8> = check
+        .program
+        .iter()
+        .flat_map(|opcode| opcode.to_be_bytes())
+        .collect();
+
+    interpreter.load_program(&program)?;
+
+    let start = interpreter.program_counter;
+
+    (check.setup)(&mut interpreter);
+
+    for _ in check.program {
+        if let Err(error) = interpreter.try_execute_cycle() {
+            return Ok(Err(error));
+        }
+    }
+
+    Ok((check.verify)(&interpreter, start))
+}
+
+fn expect<T: PartialEq + std::fmt::Debug>(
+    what: &str,
+    actual: T,
+    expected: T,
+) -> Result<(), String> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!("{} is {:?}, expected {:?}", what, actual, expected))
+    }
+}
+
+fn no_setup(_: &mut Interpreter) {}
+
+// The flag register after 8XY1, 8XY2 and 8XY3, which the VIP clobbered.
+fn logic_flag(interpreter: &Interpreter) -> u8 {
+    if interpreter.quirks.vf_reset {
+        0
+    } else {
+        7
+    }
+}
+
+fn logic_setup(interpreter: &mut Interpreter) {
+    interpreter.registers[0] = 0x0F;
+    interpreter.registers[1] = 0x3C;
+    interpreter.registers[0xF] = 7;
+}
+
+fn shift_setup(interpreter: &mut Interpreter) {
+    interpreter.registers[0] = 0x42;
+    interpreter.registers[1] = 0x81;
+}
+
+fn memory_setup(interpreter: &mut Interpreter) {
+    interpreter.index_register = 0x300;
+    interpreter.registers[..3].copy_from_slice(&[1, 2, 3]);
+}
+
+// I after FX55 and FX65 with X = 2.
+fn index_after_memory_access(interpreter: &Interpreter) -> u32 {
+    if interpreter.quirks.memory_increment {
+        0x303
+    } else {
+        0x300
+    }
+}
+
+const CHECKS: &[Check] = &[
+    Check {
+        group: "00E0",
+        name: "clears the display",
+        program: &[0x00E0],
+        setup: |interpreter| {
+            let display_address = interpreter.memory_map.display_address as usize;
+            let display_size = interpreter.display_size();
+
+            interpreter.memory[display_address..display_address + display_size].fill(0xFF);
+        },
+        verify: |interpreter, _| {
+            expect(
+                "Any lit pixel",
+                interpreter.frame().pixels.iter().any(|&byte| byte != 0),
+                false,
+            )
+        },
+    },
+    Check {
+        group: "00EE",
+        name: "returns from a subroutine",
+        program: &[0x00EE],
+        setup: |interpreter| interpreter.stack.push(0x345),
+        verify: |interpreter, _| {
+            expect("PC", interpreter.program_counter, 0x345)?;
+            expect("The stack", interpreter.stack.as_slice(), &[])
+        },
+    },
+    Check {
+        group: "1NNN",
+        name: "jumps",
+        program: &[0x1ABC],
+        setup: no_setup,
+        verify: |interpreter, _| expect("PC", interpreter.program_counter, 0xABC),
+    },
+    Check {
+        group: "2NNN",
+        name: "calls a subroutine",
+        program: &[0x2ABC],
+        setup: no_setup,
+        verify: |interpreter, start| {
+            expect("PC", interpreter.program_counter, 0xABC)?;
+            expect("The stack", interpreter.stack.as_slice(), &[start + 2])
+        },
+    },
+    Check {
+        group: "3XNN",
+        name: "skips if equal",
+        program: &[0x6005, 0x3005],
+        setup: no_setup,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 6),
+    },
+    Check {
+        group: "3XNN",
+        name: "doesn't skip if not equal",
+        program: &[0x6005, 0x3006],
+        setup: no_setup,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 4),
+    },
+    Check {
+        group: "4XNN",
+        name: "skips if not equal",
+        program: &[0x6005, 0x4006],
+        setup: no_setup,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 6),
+    },
+    Check {
+        group: "4XNN",
+        name: "doesn't skip if equal",
+        program: &[0x6005, 0x4005],
+        setup: no_setup,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 4),
+    },
+    Check {
+        group: "5XY0",
+        name: "skips if registers are equal",
+        program: &[0x6005, 0x6105, 0x5010],
+        setup: no_setup,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 8),
+    },
+    Check {
+        group: "9XY0",
+        name: "skips if registers differ",
+        program: &[0x6005, 0x6106, 0x9010],
+        setup: no_setup,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 8),
+    },
+    Check {
+        group: "9XY0",
+        name: "doesn't skip if registers are equal",
+        program: &[0x6005, 0x6105, 0x9010],
+        setup: no_setup,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 6),
+    },
+    Check {
+        group: "6XNN",
+        name: "loads a register",
+        program: &[0x60AB],
+        setup: no_setup,
+        verify: |interpreter, _| expect("V0", interpreter.registers[0], 0xAB),
+    },
+    Check {
+        group: "7XNN",
+        name: "adds without touching VF",
+        program: &[0x60FF, 0x7002],
+        setup: |interpreter| interpreter.registers[0xF] = 7,
+        verify: |interpreter, _| {
+            expect("V0", interpreter.registers[0], 0x01)?;
+            expect("VF", interpreter.registers[0xF], 7)
+        },
+    },
+    Check {
+        group: "8XYN",
+        name: "8XY0 copies",
+        program: &[0x8010],
+        setup: logic_setup,
+        verify: |interpreter, _| expect("V0", interpreter.registers[0], 0x3C),
+    },
+    Check {
+        group: "8XYN",
+        name: "8XY1 ORs",
+        program: &[0x8011],
+        setup: logic_setup,
+        verify: |interpreter, _| {
+            expect("V0", interpreter.registers[0], 0x3F)?;
+            expect("VF", interpreter.registers[0xF], logic_flag(interpreter))
+        },
+    },
+    Check {
+        group: "8XYN",
+        name: "8XY2 ANDs",
+        program: &[0x8012],
+        setup: logic_setup,
+        verify: |interpreter, _| {
+            expect("V0", interpreter.registers[0], 0x0C)?;
+            expect("VF", interpreter.registers[0xF], logic_flag(interpreter))
+        },
+    },
+    Check {
+        group: "8XYN",
+        name: "8XY3 XORs",
+        program: &[0x8013],
+        setup: logic_setup,
+        verify: |interpreter, _| {
+            expect("V0", interpreter.registers[0], 0x33)?;
+            expect("VF", interpreter.registers[0xF], logic_flag(interpreter))
+        },
+    },
+    Check {
+        group: "8XYN",
+        name: "8XY4 adds with carry",
+        program: &[0x60F0, 0x6120, 0x8014],
+        setup: no_setup,
+        verify: |interpreter, _| {
+            expect("V0", interpreter.registers[0], 0x10)?;
+            expect("VF", interpreter.registers[0xF], 1)
+        },
+    },
+    Check {
+        group: "8XYN",
+        name: "8XY5 subtracts with borrow",
+        program: &[0x6005, 0x6107, 0x8015],
+        setup: no_setup,
+        verify: |interpreter, _| {
+            expect("V0", interpreter.registers[0], 0xFE)?;
+            expect("VF", interpreter.registers[0xF], 0)
+        },
+    },
+    Check {
+        group: "8XYN",
+        name: "8XY6 shifts right",
+        program: &[0x8016],
+        setup: shift_setup,
+        verify: |interpreter, _| {
+            let (value, flag) = if interpreter.quirks.shift {
+                (0x21, 0)
+            } else {
+                (0x40, 1)
+            };
+
+            expect("V0", interpreter.registers[0], value)?;
+            expect("VF", interpreter.registers[0xF], flag)
+        },
+    },
+    Check {
+        group: "8XYN",
+        name: "8XY7 subtracts in reverse",
+        program: &[0x6005, 0x6107, 0x8017],
+        setup: no_setup,
+        verify: |interpreter, _| {
+            expect("V0", interpreter.registers[0], 0x02)?;
+            expect("VF", interpreter.registers[0xF], 1)
+        },
+    },
+    Check {
+        group: "8XYN",
+        name: "8XYE shifts left",
+        program: &[0x801E],
+        setup: shift_setup,
+        verify: |interpreter, _| {
+            let (value, flag) = if interpreter.quirks.shift {
+                (0x84, 0)
+            } else {
+                (0x02, 1)
+            };
+
+            expect("V0", interpreter.registers[0], value)?;
+            expect("VF", interpreter.registers[0xF], flag)
+        },
+    },
+    Check {
+        group: "ANNN",
+        name: "loads I",
+        program: &[0xA123],
+        setup: no_setup,
+        verify: |interpreter, _| expect("I", interpreter.index_register, 0x123),
+    },
+    Check {
+        group: "BNNN",
+        name: "jumps with an offset",
+        program: &[0x6002, 0x6104, 0xB120],
+        setup: no_setup,
+        verify: |interpreter, _| {
+            let address = if interpreter.quirks.jump {
+                0x124
+            } else {
+                0x122
+            };
+
+            expect("PC", interpreter.program_counter, address)
+        },
+    },
+    Check {
+        group: "CXNN",
+        name: "masks the random number",
+        program: &[0xC00F, 0xC100],
+        setup: no_setup,
+        verify: |interpreter, _| {
+            expect("V0's high bits", interpreter.registers[0] & 0xF0, 0)?;
+            expect("V1", interpreter.registers[1], 0)
+        },
+    },
+    Check {
+        group: "DXYN",
+        name: "draws a sprite",
+        program: &[0xD015],
+        setup: |interpreter| {
+            interpreter.index_register = interpreter.memory_map.font_address as u32
+        },
+        verify: |interpreter, _| {
+            expect("The top left byte", interpreter.frame().pixels[0], 0xF0)?;
+            expect("VF", interpreter.registers[0xF], 0)
+        },
+    },
+    Check {
+        group: "DXYN",
+        name: "erases a sprite and reports the collision",
+        program: &[0xD015, 0xD015],
+        setup: |interpreter| {
+            interpreter.index_register = interpreter.memory_map.font_address as u32
+        },
+        verify: |interpreter, _| {
+            expect("The top left byte", interpreter.frame().pixels[0], 0x00)?;
+            expect("VF", interpreter.registers[0xF], 1)
+        },
+    },
+    Check {
+        group: "EXNN",
+        name: "EX9E skips if the key is down",
+        program: &[0x6005, 0xE09E],
+        setup: |interpreter| interpreter.keypad[5] = true,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 6),
+    },
+    Check {
+        group: "EXNN",
+        name: "EXA1 doesn't skip if the key is down",
+        program: &[0x6005, 0xE0A1],
+        setup: |interpreter| interpreter.keypad[5] = true,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start + 4),
+    },
+    Check {
+        group: "FX07 FX15 FX18",
+        name: "sets and reads the timers",
+        program: &[0x6033, 0xF015, 0xF018, 0xF107],
+        setup: no_setup,
+        verify: |interpreter, _| {
+            expect("The sound timer", interpreter.sound_timer, 0x33)?;
+            expect("V1", interpreter.registers[1], 0x33)
+        },
+    },
+    Check {
+        group: "FX0A",
+        name: "waits for a key",
+        program: &[0xF00A],
+        setup: no_setup,
+        verify: |interpreter, start| expect("PC", interpreter.program_counter, start),
+    },
+    Check {
+        group: "FX1E",
+        name: "adds to I",
+        program: &[0x60FF, 0xAF80, 0xF01E],
+        setup: |interpreter| interpreter.registers[0xF] = 7,
+        verify: |interpreter, _| {
+            let flag = if interpreter.quirks.index_overflow {
+                1
+            } else {
+                7
+            };
+
+            expect("I", interpreter.index_register, 0x107F)?;
+            expect("VF", interpreter.registers[0xF], flag)
+        },
+    },
+    Check {
+        group: "FX29",
+        name: "points I at a digit",
+        program: &[0x600A, 0xF029],
+        setup: no_setup,
+        verify: |interpreter, _| {
+            let address = interpreter.memory_map.font_address as u32 + 50;
+
+            expect("I", interpreter.index_register, address)
+        },
+    },
+    Check {
+        group: "FX33",
+        name: "stores decimal digits",
+        program: &[0x60FE, 0xF033],
+        setup: memory_setup,
+        verify: |interpreter, _| {
+            expect("Memory", &interpreter.memory[0x300..0x303], &[2, 5, 4])?;
+            expect("I", interpreter.index_register, 0x300)
+        },
+    },
+    Check {
+        group: "FX55 FX65",
+        name: "FX55 stores registers",
+        program: &[0xF255],
+        setup: memory_setup,
+        verify: |interpreter, _| {
+            expect("Memory", &interpreter.memory[0x300..0x303], &[1, 2, 3])?;
+            expect(
+                "I",
+                interpreter.index_register,
+                index_after_memory_access(interpreter),
+            )
+        },
+    },
+    Check {
+        group: "FX55 FX65",
+        name: "FX65 loads registers",
+        program: &[0xF265],
+        setup: |interpreter| {
+            interpreter.index_register = 0x300;
+            interpreter.memory[0x300..0x303].copy_from_slice(&[4, 5, 6]);
+        },
+        verify: |interpreter, _| {
+            expect("V0 to V2", &interpreter.registers[..3], &[4, 5, 6])?;
+            expect(
+                "I",
+                interpreter.index_register,
+                index_after_memory_access(interpreter),
+            )
+        },
+    },
+    Check {
+        group: "FX75 FX85",
+        name: "saves and restores flags",
+        program: &[0xF275, 0x6000, 0x6100, 0x6200, 0xF285],
+        setup: memory_setup,
+        verify: |interpreter, _| expect("V0 to V2", &interpreter.registers[..3], &[1, 2, 3]),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Interpreter, Platform, Quirks};
+
+    use super::run_self_test;
+
+    #[test]
+    fn test_self_test_passes_on_every_platform() {
+        for name in Platform::NAMES {
+            let platform: Platform = name.parse().unwrap();
+
+            for enabled in [false, true] {
+                let quirks = Quirks {
+                    vf_reset: enabled,
+                    index_overflow: enabled,
+                    memory_increment: enabled,
+                    shift: enabled,
+                    jump: enabled,
+                };
+
+                let builder = Interpreter::builder().platform(platform).quirks(quirks);
+
+                for result in run_self_test(&builder).unwrap() {
+                    assert!(
+                        result.passed(),
+                        "{} should pass on {}: {:?}!",
+                        result.group,
+                        platform.name(),
+                        result.failures
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::chip8::megachip::MegaChip;
+use crate::chip8::{Interpreter, SaveState};
+
+// A copy of an interpreter's state kept in memory, for programs that branch
+// execution many times a second, such as lookahead search or reinforcement
+// learning. It holds the same state a SaveState does, plus MegaChip's color
+// mode, without encoding it or touching a file. Taking or restoring one is
+// O(state size): a copy of memory, 4 KB for most platforms and 64 KB for
+// XO-CHIP, and of MegaChip's screens when there are any.
+//
+// The keypad isn't part of it; callers set the keys for each branch.
+#[derive(Clone, Debug)]
+pub struct StateHandle {
+    state: SaveState,
+    megachip: Option<Box<MegaChip>>,
+}
+
+impl Interpreter {
+    pub fn snapshot(&self) -> StateHandle {
+        StateHandle {
+            state: SaveState::copy(self),
+            megachip: self.megachip.clone(),
+        }
+    }
+
+    // Handles only restore into an interpreter for the same platform, such
+    // as the one they were taken from.
+    pub fn restore(&mut self, handle: &StateHandle) -> Result<(), String> {
+        handle.state.restore(self)?;
+
+        self.megachip.clone_from(&handle.megachip);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Interpreter, Platform};
+
+    #[test]
+    fn test_snapshots_branch_execution() {
+        // RND V0, 0xFF; ADD V1, 0x01; JP 0x200
+        let rom = [0xC0, 0xFF, 0x71, 0x01, 0x12, 0x00];
+
+        let mut interpreter = Interpreter::builder().seed(5).build().unwrap();
+
+        interpreter.load_program(&rom).unwrap();
+
+        interpreter.run_for(30).unwrap();
+
+        let handle = interpreter.snapshot();
+
+        interpreter.run_for(30).unwrap();
+
+        let first = interpreter.state();
+
+        for _ in 0..3 {
+            interpreter.restore(&handle).unwrap();
+
+            interpreter.run_for(30).unwrap();
+
+            assert_eq!(
+                interpreter.state(),
+                first,
+                "Every branch from a snapshot should play out the same, random numbers included!"
+            );
+        }
+
+        let mut megachip = Interpreter::builder()
+            .platform(Platform::MegaChip)
+            .build()
+            .unwrap();
+
+        assert!(megachip.restore(&handle).is_err());
+
+        // Enable MegaChip mode
+        megachip.load_program(&[0x00, 0x11]).unwrap();
+
+        let before = megachip.snapshot();
+
+        megachip.run_for(1).unwrap();
+
+        assert!(megachip.megachip.as_ref().unwrap().enabled);
+
+        megachip.restore(&before).unwrap();
+
+        assert!(
+            !megachip.megachip.as_ref().unwrap().enabled,
+            "MegaChip's mode should be restored too!"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::chip8::builder::DEFAULT_INSTRUCTIONS_PER_FRAME;
+use crate::chip8::disassembler::disassemble;
+use crate::chip8::{Instruction, Platform};
+
+// CHIP-8 programs that time themselves with the delay timer don't mind a
+// quicker pace than the COSMAC VIP's.
+const PACED_INSTRUCTIONS_PER_FRAME: u32 = 15;
+
+// SCHIP programs were written for the faster HP-48.
+const SCHIP_INSTRUCTIONS_PER_FRAME: u32 = 30;
+
+// XO-CHIP programs, mostly written in Octo, expect hundreds.
+const XO_CHIP_INSTRUCTIONS_PER_FRAME: u32 = 200;
+
+// Drawing in MegaChip's color mode takes thousands.
+const MEGACHIP_INSTRUCTIONS_PER_FRAME: u32 = 3000;
+
+// Picks instructions per frame for a ROM loaded at `origin` from the
+// instructions it can reach: the extensions it uses say which machine it was
+// written for, and a CHIP-8 program that never reads the delay timer relies
+// on running at the VIP's speed.
+pub fn suggest_instructions_per_frame(rom: &[u8], origin: u16, platform: Platform) -> u32 {
+    // Jumps can't reach past 0xFFFF, so the rest of a large MegaChip ROM is
+    // only ever data.
+    let reachable = &rom[..rom.len().min(0x10000 - origin as usize)];
+
+    let instructions: Vec<Instruction> = disassemble(reachable, origin, &[])
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|line| line.instruction)
+        .collect();
+
+    let uses = |used: fn(&Instruction) -> bool| instructions.iter().any(used);
+
+    // 0011 switches to the color mode.
+    if platform == Platform::MegaChip
+        && uses(|instruction| matches!(instruction, Instruction::System { address: 0x011 }))
+    {
+        MEGACHIP_INSTRUCTIONS_PER_FRAME
+    } else if uses(|instruction| {
+        matches!(
+            instruction,
+            Instruction::LoadAudioPattern | Instruction::SetPitch { .. }
+        )
+    }) {
+        XO_CHIP_INSTRUCTIONS_PER_FRAME
+    } else if uses(|instruction| {
+        matches!(
+            instruction,
+            Instruction::Exit | Instruction::StoreFlags { .. } | Instruction::LoadFlags { .. }
+        )
+    }) {
+        SCHIP_INSTRUCTIONS_PER_FRAME
+    } else if uses(|instruction| matches!(instruction, Instruction::LoadDelayTimer { .. })) {
+        PACED_INSTRUCTIONS_PER_FRAME
+    } else {
+        DEFAULT_INSTRUCTIONS_PER_FRAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::speed::suggest_instructions_per_frame;
+    use crate::chip8::Platform;
+
+    fn rom(opcodes: &[u16]) -> Vec<u8> {
+        opcodes
+            .iter()
+            .flat_map(|opcode| opcode.to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_speed_follows_the_instructions_used() {
+        // ADD V0, 1; JP 0x200
+        assert_eq!(
+            suggest_instructions_per_frame(&rom(&[0x7001, 0x1200]), 0x200, Platform::Chip8),
+            10
+        );
+
+        // LD V0, DT; SE V0, 0; JP 0x200; JP 0x206
+        let paced = rom(&[0xF007, 0x3000, 0x1200, 0x1206]);
+
+        assert_eq!(
+            suggest_instructions_per_frame(&paced, 0x200, Platform::Chip8),
+            15
+        );
+
+        // LD R, V3; EXIT
+        assert_eq!(
+            suggest_instructions_per_frame(&rom(&[0xF375, 0x00FD]), 0x200, Platform::Chip8),
+            30
+        );
+
+        // JP 0x204; data that looks like F002; JP 0x204
+        let unreachable = rom(&[0x1204, 0xF002, 0x1204]);
+
+        assert_eq!(
+            suggest_instructions_per_frame(&unreachable, 0x200, Platform::Chip8),
+            10,
+            "Data should not count as instructions!"
+        );
+
+        // 0011, the switch to MegaChip's color mode.
+        let color = rom(&[0x0011, 0x1202]);
+
+        assert_eq!(
+            suggest_instructions_per_frame(&color, 0x200, Platform::MegaChip),
+            3000
+        );
+        assert_eq!(
+            suggest_instructions_per_frame(&color, 0x200, Platform::Chip8),
+            10,
+            "Only MegaChip has a color mode!"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+
+use crate::chip8::fnv1a;
+use crate::chip8::patch::diff;
+
+// Memory differences are split into rows of this many bytes, like a hex dump.
+const ROW_SIZE: usize = 16;
+
+// The machine state a program can observe, captured so two points in a run
+// (or two runs) can be compared.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    pub memory: Vec<u8>,
+    pub registers: [u8; 16],
+    pub index_register: u32,
+    pub program_counter: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub stack: Vec<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Difference {
+    Register(usize, u8, u8),
+    IndexRegister(u32, u32),
+    ProgramCounter(u16, u16),
+    DelayTimer(u8, u8),
+    SoundTimer(u8, u8),
+    Stack(Vec<u16>, Vec<u16>),
+    Memory {
+        address: u16,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+}
+
+impl State {
+    // Everything that differs from the other state, registers first.
+    pub fn diff(&self, other: &State) -> Vec<Difference> {
+        let mut differences = Vec::new();
+
+        for (register, (&old, &new)) in self.registers.iter().zip(&other.registers).enumerate() {
+            if old != new {
+                differences.push(Difference::Register(register, old, new));
+            }
+        }
+
+        if self.index_register != other.index_register {
+            differences.push(Difference::IndexRegister(
+                self.index_register,
+                other.index_register,
+            ));
+        }
+
+        if self.program_counter != other.program_counter {
+            differences.push(Difference::ProgramCounter(
+                self.program_counter,
+                other.program_counter,
+            ));
+        }
+
+        if self.delay_timer != other.delay_timer {
+            differences.push(Difference::DelayTimer(self.delay_timer, other.delay_timer));
+        }
+
+        if self.sound_timer != other.sound_timer {
+            differences.push(Difference::SoundTimer(self.sound_timer, other.sound_timer));
+        }
+
+        if self.stack != other.stack {
+            differences.push(Difference::Stack(self.stack.clone(), other.stack.clone()));
+        }
+
+        for range in diff(&self.memory, &other.memory) {
+            for start in range.clone().step_by(ROW_SIZE) {
+                let end = (start + ROW_SIZE).min(range.end);
+
+                differences.push(Difference::Memory {
+                    address: start as u16,
+                    old: self.memory[start..end].to_vec(),
+                    new: other.memory[start..end].to_vec(),
+                });
+            }
+        }
+
+        differences
+    }
+
+    // FNV-1a over everything in the state, stable across platforms and
+    // releases like the display hash, so runs can be checked against each
+    // other.
+    pub fn hash(&self) -> u64 {
+        let bytes = self
+            .memory
+            .iter()
+            .chain(&self.registers)
+            .copied()
+            .chain(self.index_register.to_be_bytes())
+            .chain(self.program_counter.to_be_bytes())
+            .chain([self.delay_timer, self.sound_timer])
+            .chain(self.stack.iter().flat_map(|address| address.to_be_bytes()));
+
+        fnv1a(bytes)
+    }
+}
+
+// Return addresses, outermost first.
+pub(crate) fn stack(addresses: &[u16]) -> String {
+    if addresses.is_empty() {
+        return "empty".to_string();
+    }
+
+    addresses
+        .iter()
+        .map(|address| format!("{:03X}", address))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+
+        match self {
+            Difference::Register(register, old, new) => {
+                write!(formatter, "V{:X}: 0x{:02X} -> 0x{:02X}", register, old, new)
+            }
+            Difference::IndexRegister(old, new) => {
+                write!(formatter, "I: 0x{:03X} -> 0x{:03X}", old, new)
+            }
+            Difference::ProgramCounter(old, new) => {
+                write!(formatter, "PC: 0x{:03X} -> 0x{:03X}", old, new)
+            }
+            Difference::DelayTimer(old, new) => write!(formatter, "DT: {} -> {}", old, new),
+            Difference::SoundTimer(old, new) => write!(formatter, "ST: {} -> {}", old, new),
+            Difference::Stack(old, new) => {
+                write!(formatter, "Stack: {} -> {}", stack(old), stack(new))
+            }
+            Difference::Memory { address, old, new } => {
+                write!(formatter, "0x{:03X}: {} -> {}", address, hex(old), hex(new))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::state::Difference;
+    use crate::chip8::Interpreter;
+
+    #[test]
+    fn test_state_diff_lists_registers_then_memory() {
+        let mut interpreter = Interpreter::new();
+
+        let before = interpreter.state();
+
+        interpreter.registers[3] = 0x10;
+        interpreter.program_counter += 2;
+        interpreter.memory[0x2A4] = 3;
+        interpreter.memory[0x300..0x312].fill(0xFF);
+
+        let differences = before.diff(&interpreter.state());
+
+        assert_eq!(
+            differences[..3],
+            [
+                Difference::Register(3, 0x00, 0x10),
+                Difference::ProgramCounter(0x200, 0x202),
+                Difference::Memory {
+                    address: 0x2A4,
+                    old: vec![0],
+                    new: vec![3]
+                },
+            ]
+        );
+
+        assert_eq!(
+            differences.len(),
+            5,
+            "Long runs of changed memory should be split into rows!"
+        );
+
+        assert_eq!(differences[4].to_string(), "0x310: 00 00 -> FF FF");
+
+        assert!(before.diff(&before).is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use crate::chip8::Interpreter;
+
+// Counters the interpreter keeps from the moment it's built, for overlays,
+// profilers and benchmarks to build on. Loading a save state puts `cycles`
+// back as it was saved; `frames` keeps counting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Statistics {
+    // Instructions run. Blocks run by the JIT aren't counted.
+    pub cycles: u64,
+    // 60 Hz frames run, counted as the timers tick.
+    pub frames: u64,
+}
+
+impl Statistics {
+    // How long the frames run so far take on real hardware, however fast
+    // they actually ran.
+    pub fn uptime(&self) -> Duration {
+        Duration::from_millis(self.frames * 1000 / 60)
+    }
+}
+
+impl Interpreter {
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn statistics(&self) -> Statistics {
+        Statistics {
+            cycles: self.cycles,
+            frames: self.frames,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chip8::Interpreter;
+
+    #[test]
+    fn test_statistics_count_cycles_and_frames() {
+        let mut interpreter = Interpreter::builder()
+            .instructions_per_frame(10)
+            .build()
+            .unwrap();
+
+        // JP 0x200
+        interpreter.load_program(&[0x12, 0x00]).unwrap();
+
+        for _ in 0..120 {
+            interpreter.run_frame();
+        }
+
+        interpreter.execute_cycle();
+
+        let statistics = interpreter.statistics();
+
+        assert_eq!(statistics.cycles, 1201, "Every instruction should count!");
+        assert_eq!(statistics.frames, 120, "Every frame should count!");
+        assert_eq!(
+            statistics.uptime(),
+            Duration::from_secs(2),
+            "Uptime should be in 60 Hz frames!"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use std::ops::Range;
+
+use crate::chip8::Instruction;
+
+// What one instruction did, as returned by Interpreter::step, so debuggers,
+// tracers and profilers can all work from the same record instead of
+// comparing the whole state themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub address: u16,
+    pub instruction: Instruction,
+    // The registers whose value changed, VF included.
+    pub registers_changed: Vec<usize>,
+    pub index_changed: bool,
+    // Spans every byte of memory written, display memory included.
+    pub memory_changed: Option<Range<u32>>,
+    pub display_changed: bool,
+    // Set while FX0A is still waiting for a key to be pressed and released.
+    pub waiting_for_key: bool,
+}
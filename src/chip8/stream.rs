@@ -0,0 +1,160 @@
+use std::io::{self, Read, Write};
+
+// Sent by the emulator when a viewer connects, so a viewer pointed at the
+// wrong port fails straight away instead of drawing garbage.
+pub const STREAM_MAGIC: &[u8; 4] = b"C8S1";
+
+const FRAME_TAG: u8 = b'F';
+const KEY_TAG: u8 = b'K';
+
+// Frames are at most 256x192 pixels (MegaChip, sent in two colors); anything
+// bigger is a corrupt stream.
+const MAX_FRAME_BYTES: usize = 256 * 192 / 8;
+
+// What the emulator and a viewer send each other over --stream. Frames flow
+// to the viewer, packed one bit per pixel like Frame; key presses flow back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StreamMessage {
+    Frame {
+        width: u16,
+        height: u16,
+        pixels: Vec<u8>,
+    },
+    Key {
+        key: u8,
+        pressed: bool,
+    },
+}
+
+impl StreamMessage {
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            StreamMessage::Frame {
+                width,
+                height,
+                pixels,
+            } => {
+                let mut message = Vec::with_capacity(pixels.len() + 7);
+
+                message.push(FRAME_TAG);
+                message.extend_from_slice(&width.to_be_bytes());
+                message.extend_from_slice(&height.to_be_bytes());
+                message.extend_from_slice(&(pixels.len() as u16).to_be_bytes());
+                message.extend_from_slice(pixels);
+
+                writer.write_all(&message)
+            }
+            StreamMessage::Key { key, pressed } => {
+                writer.write_all(&[KEY_TAG, *key, *pressed as u8])
+            }
+        }
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut tag = [0; 1];
+
+        reader.read_exact(&mut tag)?;
+
+        match tag[0] {
+            FRAME_TAG => {
+                let mut header = [0; 6];
+
+                reader.read_exact(&mut header)?;
+
+                let width = u16::from_be_bytes([header[0], header[1]]);
+                let height = u16::from_be_bytes([header[2], header[3]]);
+                let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+
+                if length > MAX_FRAME_BYTES || length * 8 < width as usize * height as usize {
+                    return Err(invalid(format!(
+                        "Bad {}x{} frame of {} bytes",
+                        width, height, length
+                    )));
+                }
+
+                let mut pixels = vec![0; length];
+
+                reader.read_exact(&mut pixels)?;
+
+                Ok(StreamMessage::Frame {
+                    width,
+                    height,
+                    pixels,
+                })
+            }
+            KEY_TAG => {
+                let mut body = [0; 2];
+
+                reader.read_exact(&mut body)?;
+
+                if body[0] > 0xF {
+                    return Err(invalid(format!("Bad key {:X}", body[0])));
+                }
+
+                Ok(StreamMessage::Key {
+                    key: body[0],
+                    pressed: body[1] != 0,
+                })
+            }
+            tag => Err(invalid(format!("Unknown message {:02X}", tag))),
+        }
+    }
+}
+
+// Checks the greeting a viewer expects as the first bytes of the stream.
+pub fn read_magic(reader: &mut impl Read) -> io::Result<()> {
+    let mut magic = [0; 4];
+
+    reader.read_exact(&mut magic)?;
+
+    if &magic != STREAM_MAGIC {
+        return Err(invalid("Not a CHIP-8 display stream".to_string()));
+    }
+
+    Ok(())
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::chip8::stream::{read_magic, StreamMessage, STREAM_MAGIC};
+
+    #[test]
+    fn test_messages_round_trip() {
+        let messages = [
+            StreamMessage::Frame {
+                width: 64,
+                height: 32,
+                pixels: vec![0xAA; 256],
+            },
+            StreamMessage::Key {
+                key: 0xF,
+                pressed: true,
+            },
+        ];
+
+        let mut stream = STREAM_MAGIC.to_vec();
+
+        for message in &messages {
+            message.write_to(&mut stream).unwrap();
+        }
+
+        let mut reader = Cursor::new(stream);
+
+        read_magic(&mut reader).unwrap();
+
+        for message in &messages {
+            assert_eq!(&StreamMessage::read_from(&mut reader).unwrap(), message);
+        }
+
+        assert!(
+            StreamMessage::read_from(&mut Cursor::new([b'F', 0, 64, 0, 32, 0, 1, 0])).is_err(),
+            "A frame too short for its size should be rejected!"
+        );
+    }
+}
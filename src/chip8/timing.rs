@@ -0,0 +1,119 @@
+use std::str::FromStr;
+
+// How much work the interpreter does per 60 Hz frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+pub enum Timing {
+    // A flat budget of instructions_per_frame instructions.
+    #[default]
+    Fixed,
+
+    // Each instruction costs roughly the machine cycles the original COSMAC
+    // VIP interpreter spent on it, and DXYN waits for the next frame.
+    CosmacVip,
+}
+
+// The VIP's 1.7609 MHz clock runs 8 clock pulses per machine cycle, giving
+// about 3668 machine cycles per 60 Hz frame.
+pub const VIP_CYCLES_PER_FRAME: i64 = 3668;
+
+// Fetching and decoding an instruction costs the same for every opcode.
+const VIP_FETCH_CYCLES: i64 = 40;
+
+// A frame of nothing but the cheapest instructions, which cost no more than
+// their fetch.
+pub const VIP_MAX_INSTRUCTIONS_PER_FRAME: u32 =
+    ((VIP_CYCLES_PER_FRAME + VIP_FETCH_CYCLES - 1) / VIP_FETCH_CYCLES) as u32;
+
+impl Timing {
+    pub const NAMES: [&'static str; 2] = ["fixed", "vip"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Timing::Fixed => "fixed",
+            Timing::CosmacVip => "vip",
+        }
+    }
+}
+
+impl FromStr for Timing {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "fixed" => Ok(Timing::Fixed),
+            "vip" => Ok(Timing::CosmacVip),
+            _ => Err(format!(
+                "Unknown timing '{}', expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+// Approximate machine cycles the VIP interpreter spends on an opcode,
+// including fetch and decode.
+pub fn vip_instruction_cycles(opcode: u16) -> i64 {
+    let x = ((opcode & 0x0F00) >> 8) as i64;
+    let n = (opcode & 0x000F) as i64;
+
+    let execute = match opcode & 0xF000 {
+        0x0000 => match opcode {
+            // Clears the 256 byte display buffer a byte at a time.
+            0x00E0 => 24 + 3078,
+            0x00EE => 10,
+            _ => 0,
+        },
+        0x1000 => 12,
+        0x2000 => 26,
+        0x3000 | 0x4000 => 10,
+        0x5000 | 0x9000 => 14,
+        0x6000 => 6,
+        0x7000 => 10,
+        0x8000 if n == 0 => 12,
+        0x8000 => 44,
+        0xA000 => 12,
+        0xB000 => 22,
+        0xC000 => 36,
+        // Each sprite row is shifted into place bit by bit.
+        0xD000 => 26 + n * 68,
+        0xE000 => 14,
+        0xF000 => match opcode & 0x00FF {
+            0x1E | 0x29 => 16,
+            0x33 => 84,
+            0x55 | 0x65 => 14 + 14 * (x + 1),
+            _ => 10,
+        },
+        _ => 0,
+    };
+
+    VIP_FETCH_CYCLES + execute
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::timing::{vip_instruction_cycles, Timing};
+
+    #[test]
+    fn test_timing_names_round_trip() {
+        for name in Timing::NAMES {
+            let timing: Timing = name.parse().unwrap();
+
+            assert_eq!(timing.name(), name, "Timing name should round trip!");
+        }
+    }
+
+    #[test]
+    fn test_vip_drawing_costs_more_than_alu_ops() {
+        assert!(
+            vip_instruction_cycles(0xD015) > vip_instruction_cycles(0x8014),
+            "DXYN should be slower than 8XY4!"
+        );
+
+        assert!(
+            vip_instruction_cycles(0xFF55) > vip_instruction_cycles(0xF055),
+            "Saving more registers should take longer!"
+        );
+    }
+}
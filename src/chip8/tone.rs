@@ -0,0 +1,254 @@
+use std::f32::consts::TAU;
+
+const TONE_VOLUME: f32 = 0.2;
+
+// XO-CHIP plays audio patterns at 4000 bits per second at this pitch, and an
+// octave higher every 48 steps above it.
+pub const DEFAULT_PITCH: u8 = 64;
+
+const PATTERN_BITS: f32 = 128.0;
+
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - DEFAULT_PITCH as f32) / 48.0)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Waveform {
+    // The classic buzzer; bright, but harsh over long sessions.
+    #[default]
+    Square,
+    Triangle,
+    Sine,
+}
+
+// The buzzer's sound. Attack and release fade the tone in and out, which also
+// takes the click out of starting and stopping it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneSettings {
+    // In Hz.
+    pub frequency: f32,
+    pub waveform: Waveform,
+    // In milliseconds.
+    pub attack: f32,
+    pub release: f32,
+}
+
+impl Default for ToneSettings {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            attack: 0.0,
+            release: 0.0,
+        }
+    }
+}
+
+impl ToneSettings {
+    // Parses a comma separated list of name=value pairs, e.g.
+    // "frequency=330,waveform=sine". Settings that aren't listed keep their
+    // defaults.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut settings = Self::default();
+
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected name=value, got: {}", setting))?;
+
+            let (name, value) = (name.trim(), value.trim());
+
+            let number = |range: std::ops::RangeInclusive<f32>| {
+                value
+                    .parse()
+                    .ok()
+                    .filter(|value| range.contains(value))
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid tone {} (expected {}-{}): {}",
+                            name,
+                            range.start(),
+                            range.end(),
+                            value
+                        )
+                    })
+            };
+
+            match name {
+                "frequency" => settings.frequency = number(20.0..=20000.0)?,
+                "attack" => settings.attack = number(0.0..=1000.0)?,
+                "release" => settings.release = number(0.0..=1000.0)?,
+                "waveform" => {
+                    settings.waveform = match value {
+                        "square" => Waveform::Square,
+                        "triangle" => Waveform::Triangle,
+                        "sine" => Waveform::Sine,
+                        _ => {
+                            return Err(format!(
+                                "Unknown waveform: {} (expected square, triangle or sine)",
+                                value
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    let expected = "frequency, waveform, attack or release";
+
+                    return Err(format!(
+                        "Unknown tone setting: {} (expected {})",
+                        name, expected
+                    ));
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+// Generates the buzzer one sample at a time.
+pub struct Tone {
+    settings: ToneSettings,
+    sample_rate: f32,
+    phase: f32,
+    // The envelope, from 0 (silent) to 1.
+    level: f32,
+    // An XO-CHIP pattern replaces the waveform once set. The position is in
+    // bits, so the pattern is resampled to the output rate by stepping
+    // through it at the pitch's rate.
+    pattern: Option<[u8; 16]>,
+    pattern_rate: f32,
+    pattern_position: f32,
+}
+
+impl Tone {
+    pub fn new(settings: ToneSettings, sample_rate: f32) -> Self {
+        Self {
+            settings,
+            sample_rate,
+            phase: 0.0,
+            level: 0.0,
+            pattern: None,
+            pattern_rate: pattern_rate(DEFAULT_PITCH),
+            pattern_position: 0.0,
+        }
+    }
+
+    pub fn set_pattern(&mut self, pattern: [u8; 16], pitch: u8) {
+        self.pattern = Some(pattern);
+        self.pattern_rate = pattern_rate(pitch);
+    }
+
+    pub fn next_sample(&mut self, playing: bool) -> f32 {
+        let (target, milliseconds) = if playing {
+            (1.0, self.settings.attack)
+        } else {
+            (0.0, self.settings.release)
+        };
+
+        let step = 1000.0 / (milliseconds * self.sample_rate);
+
+        self.level = if step.is_finite() && (self.level - target).abs() > step {
+            self.level + step.copysign(target - self.level)
+        } else {
+            target
+        };
+
+        let wave = match self.pattern {
+            Some(pattern) => {
+                let bit = self.pattern_position as usize;
+
+                self.pattern_position =
+                    (self.pattern_position + self.pattern_rate / self.sample_rate) % PATTERN_BITS;
+
+                if pattern[bit / 8] & 0x80 >> (bit % 8) != 0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            None => {
+                self.phase = (self.phase + self.settings.frequency / self.sample_rate) % 1.0;
+
+                match self.settings.waveform {
+                    Waveform::Square if self.phase < 0.5 => 1.0,
+                    Waveform::Square => -1.0,
+                    Waveform::Triangle => 4.0 * (self.phase - 0.5).abs() - 1.0,
+                    Waveform::Sine => (self.phase * TAU).sin(),
+                }
+            }
+        };
+
+        wave * self.level * TONE_VOLUME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::tone::{pattern_rate, Tone, ToneSettings, Waveform, DEFAULT_PITCH};
+
+    #[test]
+    fn test_parse_overrides_listed_settings() {
+        let settings = ToneSettings::parse("frequency=330, waveform=sine").unwrap();
+
+        assert_eq!(settings.frequency, 330.0);
+        assert_eq!(settings.waveform, Waveform::Sine);
+        assert_eq!(settings.attack, ToneSettings::default().attack);
+
+        assert!(ToneSettings::parse("frequency=0").is_err());
+        assert!(ToneSettings::parse("waveform=sawtooth").is_err());
+    }
+
+    #[test]
+    fn test_envelope_ramps_in_and_out() {
+        let settings = ToneSettings {
+            attack: 10.0,
+            release: 10.0,
+            ..ToneSettings::default()
+        };
+
+        // 10 ms is 10 samples at 1 kHz.
+        let mut tone = Tone::new(settings, 1000.0);
+
+        let first = tone.next_sample(true).abs();
+
+        for _ in 0..9 {
+            tone.next_sample(true);
+        }
+
+        let full = tone.next_sample(true).abs();
+
+        assert!(first < full / 5.0, "The tone should fade in!");
+
+        let released = tone.next_sample(false).abs();
+
+        assert!(
+            released > 0.0 && released < full,
+            "The tone should fade out!"
+        );
+
+        for _ in 0..10 {
+            tone.next_sample(false);
+        }
+
+        assert_eq!(tone.next_sample(false), 0.0);
+    }
+
+    #[test]
+    fn test_patterns_play_bits_at_the_pitch_rate() {
+        assert_eq!(pattern_rate(DEFAULT_PITCH), 4000.0);
+        assert_eq!(pattern_rate(DEFAULT_PITCH + 48), 8000.0);
+
+        // At 4000 Hz output and the default pitch, one sample per bit.
+        let mut tone = Tone::new(ToneSettings::default(), 4000.0);
+
+        let mut pattern = [0x00; 16];
+        pattern[0] = 0b1100_0000;
+
+        tone.set_pattern(pattern, DEFAULT_PITCH);
+
+        let samples: Vec<bool> = (0..4).map(|_| tone.next_sample(true) > 0.0).collect();
+
+        assert_eq!(samples, [true, true, false, false]);
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chip8::Interpreter;
+
+const FIELDS: [&str; 21] = [
+    "PC", "OP", "I", "V0", "V1", "V2", "V3", "V4", "V5", "V6", "V7", "V8", "V9", "VA", "VB", "VC",
+    "VD", "VE", "VF", "DT", "ST",
+];
+
+// The state before one instruction, as a line of "NAME=hex" fields:
+//
+//   PC=0200 OP=6005 I=0000 V0=00 ... VF=00 DT=00 ST=00
+//
+// Traces from other emulators only need to be converted to the same fields;
+// any that are missing are not compared, and unknown ones are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceLine {
+    values: [Option<u32>; FIELDS.len()],
+}
+
+impl TraceLine {
+    pub fn capture(interpreter: &Interpreter) -> Self {
+        let pc = interpreter.program_counter as usize;
+
+        let opcode = match interpreter.memory.get(pc..pc + 2) {
+            Some(&[high, low]) => Some(u16::from_be_bytes([high, low]) as u32),
+            _ => None,
+        };
+
+        let mut values = [None; FIELDS.len()];
+
+        values[0] = Some(interpreter.program_counter as u32);
+        values[1] = opcode;
+        values[2] = Some(interpreter.index_register);
+
+        for (register, &value) in interpreter.registers.iter().enumerate() {
+            values[3 + register] = Some(value as u32);
+        }
+
+        values[19] = Some(interpreter.delay_timer as u32);
+        values[20] = Some(interpreter.sound_timer as u32);
+
+        Self { values }
+    }
+
+    // Describes each field of this (expected) line that the actual one
+    // disagrees with.
+    pub fn mismatches(&self, actual: &TraceLine) -> Vec<String> {
+        FIELDS
+            .iter()
+            .zip(self.values.iter().zip(&actual.values))
+            .filter_map(|(name, values)| match values {
+                (Some(expected), Some(actual)) if expected != actual => Some(format!(
+                    "{}: expected {:X}, got {:X}",
+                    name, expected, actual
+                )),
+                (Some(expected), None) => {
+                    Some(format!("{}: expected {:X}, got nothing", name, expected))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Parses a whole trace, skipping blank lines and '#' comments.
+    pub fn parse_log(text: &str) -> Result<Vec<TraceLine>, String> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| {
+                line.parse()
+                    .map_err(|error| format!("Line {}: {}", index + 1, error))
+            })
+            .collect()
+    }
+}
+
+impl FromStr for TraceLine {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut trace_line = TraceLine::default();
+
+        for field in line.split_whitespace() {
+            let (name, value) = match field.split_once(['=', ':']) {
+                Some(pair) => pair,
+                None => continue,
+            };
+
+            let slot = match FIELDS
+                .iter()
+                .position(|known| known.eq_ignore_ascii_case(name))
+            {
+                Some(slot) => slot,
+                None => continue,
+            };
+
+            let digits = value.trim_start_matches("0x");
+
+            trace_line.values[slot] = Some(
+                u32::from_str_radix(digits, 16)
+                    .map_err(|_| format!("Invalid value for {}: {}", name, value))?,
+            );
+        }
+
+        if trace_line.values.iter().all(Option::is_none) {
+            return Err(format!("No known fields in: {}", line.trim()));
+        }
+
+        Ok(trace_line)
+    }
+}
+
+impl fmt::Display for TraceLine {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let mut separator = "";
+
+        for (slot, (name, value)) in FIELDS.iter().zip(&self.values).enumerate() {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+
+            // PC, OP and I are words; registers and timers are bytes.
+            let width = if slot < 3 { 4 } else { 2 };
+
+            write!(
+                formatter,
+                "{}{}={:0width$X}",
+                separator,
+                name,
+                value,
+                width = width
+            )?;
+
+            separator = " ";
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::trace::TraceLine;
+    use crate::chip8::Interpreter;
+
+    #[test]
+    fn test_trace_line_round_trips_and_compares_known_fields() {
+        let mut interpreter = Interpreter::new();
+
+        interpreter.load_program(&[0x60, 0x05]).unwrap();
+
+        let line = TraceLine::capture(&interpreter);
+
+        assert!(line
+            .to_string()
+            .starts_with("PC=0200 OP=6005 I=0000 V0=00 V1=00"));
+        assert_eq!(line.to_string().parse(), Ok(line.clone()));
+
+        let expected: TraceLine = "pc:0x200 op:6005 v0=01 cycle=17".parse().unwrap();
+
+        assert_eq!(
+            expected.mismatches(&line),
+            ["V0: expected 1, got 0"],
+            "Only the fields in the reference should be compared!"
+        );
+
+        assert!(TraceLine::parse_log("# header\n\nPC=200\nPC=zz").is_err());
+    }
+}
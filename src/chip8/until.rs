@@ -0,0 +1,32 @@
+use std::ops::Range;
+
+use crate::chip8::Interpreter;
+
+// Common conditions for Interpreter::run_until. Any closure taking the
+// interpreter works as well; these cover what tests and tools most often
+// wait for.
+
+// The next instruction to run is at the address.
+pub fn pc_reached(address: u16) -> impl FnMut(&Interpreter) -> bool {
+    move |interpreter| interpreter.program_counter == address
+}
+
+// Any byte in the range differs from what it held when the condition was
+// made.
+pub fn memory_changed(
+    interpreter: &Interpreter,
+    range: Range<usize>,
+) -> impl FnMut(&Interpreter) -> bool {
+    let before = interpreter.memory[range.clone()].to_vec();
+
+    move |interpreter| interpreter.memory[range.clone()] != before[..]
+}
+
+// Any pixel differs from when the condition was made. It compares the whole
+// display after every instruction, which is cheap next to running one, but
+// not free on big displays.
+pub fn display_changed(interpreter: &Interpreter) -> impl FnMut(&Interpreter) -> bool {
+    let before = interpreter.display().to_vec();
+
+    move |interpreter| interpreter.display() != before
+}
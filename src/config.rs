@@ -0,0 +1,100 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chip8_vm::chip8::fnv1a;
+
+// Per-user settings live in $XDG_CONFIG_HOME/chip8_vm (or ~/.config/chip8_vm),
+// or %APPDATA%\chip8_vm on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("chip8_vm"))
+}
+
+// The config file holds default command line options, one per line without
+// the leading dashes, such as `speed = 50` or `pixel-grid`. Lines starting
+// with '#' are comments. Options given on the command line win.
+pub fn config_file() -> Option<PathBuf> {
+    Some(config_dir()?.join("config"))
+}
+
+// A file kept for one ROM, or anything else, in a directory of the config
+// dir, named after a hash of its data that stays the same across releases.
+pub fn hashed_file(directory: &str, data: &[u8]) -> Option<PathBuf> {
+    let hash = fnv1a(data.iter().copied());
+
+    Some(config_dir()?.join(directory).join(format!("{:016x}", hash)))
+}
+
+// The file's options as command line arguments; a missing file has none.
+pub fn read_options(path: &Path) -> Result<Vec<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_options(&text).map_err(|error| format!("{}: {}", path.display(), error)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(format!("Failed to read {}: {}", path.display(), error)),
+    }
+}
+
+pub fn parse_options(text: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (line, None),
+        };
+
+        if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+            return Err(format!("Line {}: invalid option '{}'", index + 1, line));
+        }
+
+        args.push(format!("--{}", name));
+        args.extend(value.map(str::to_string));
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_options;
+
+    #[test]
+    fn test_config_lines_become_options() {
+        let text = "\
+# Slower, with a grid
+speed = 50%
+pixel-grid
+
+quirks = shift, jump
+";
+
+        assert_eq!(
+            parse_options(text),
+            Ok(vec![
+                "--speed".to_string(),
+                "50%".to_string(),
+                "--pixel-grid".to_string(),
+                "--quirks".to_string(),
+                "shift, jump".to_string(),
+            ])
+        );
+
+        assert!(
+            parse_options("game.ch8 extra").is_err(),
+            "Only options should be allowed!"
+        );
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::thread;
+
+use tracing::warn;
+use winit::event_loop::EventLoopProxy;
+
+use chip8_vm::chip8::ram_search::{Comparison, RamSearch};
+use chip8_vm::chip8::{parse_address, Cheat, DebugInfo, SaveState, State};
+
+use crate::emulator::{Emulator, UserEvent};
+use crate::rpl;
+
+const HELP: &str = "\
+Commands:
+  search                 Start a RAM search from a snapshot of memory
+  changed, unchanged     Keep the candidates that changed or not since the last filter
+  increased, decreased   Keep the candidates that went up or down since the last filter
+  equals <value>         Keep the candidates holding the value
+  list                   Show the remaining candidates
+  poke <address=value>   Write a value once
+  freeze <address=value> Write a value before every frame
+  unfreeze <address>     Stop writing a frozen value
+  cheats                 Show the active cheats
+  snapshot <name>        Keep a copy of the registers, timers and memory
+  diff <name> [<name>]   Compare a snapshot with another one or the live state
+  save <path>            Write a save state to a file
+  load <path>            Carry on from a save state
+  export-flags <path>    Write the RPL flags for Octo
+  import-flags <path>    Set the RPL flags from Octo's
+  break <location>       Pause before running the instruction at a location
+  delete <location>      Remove a breakpoint
+  breakpoints            Show the breakpoints
+  step                   Run one instruction while paused
+  where                  Show where the program is
+  help                   Show this list
+
+Locations are addresses such as 0x2A4 or, for ROMs assembled with
+--debug-info, labels and source line numbers.";
+
+// Don't flood the terminal while a search still has most of memory left.
+const MAX_LISTED: usize = 32;
+
+// Reads debug commands from stdin and hands them to the event loop, which runs
+// them against the focused window's emulator.
+pub fn spawn_reader(event_loop_proxy: EventLoopProxy<UserEvent>) {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => return,
+            };
+
+            if event_loop_proxy
+                .send_event(UserEvent::ConsoleCommand(line))
+                .is_err()
+            {
+                return;
+            }
+        }
+    });
+}
+
+// A ROM's debug info from the assembler along with the source it was built
+// from, so the console can show source lines and take labels and line numbers
+// for addresses.
+pub struct SourceMap {
+    info: DebugInfo,
+    source_name: String,
+    lines: Vec<String>,
+}
+
+impl SourceMap {
+    // None unless the ROM was assembled with --debug-info.
+    pub fn load(rom_path: &Path) -> Option<Self> {
+        let info_path = DebugInfo::path_for(rom_path);
+
+        let text = fs::read_to_string(&info_path).ok()?;
+
+        let info = match DebugInfo::parse(&text) {
+            Ok(info) => info,
+            Err(error) => {
+                warn!("Ignoring {}: {}", info_path.display(), error);
+
+                return None;
+            }
+        };
+
+        let source_path = info.source_path(&info_path);
+
+        // Line numbers still work without the source, just not the text.
+        let lines = match source_path.as_ref().map(fs::read_to_string) {
+            Some(Ok(source)) => source.lines().map(str::to_string).collect(),
+            Some(Err(error)) => {
+                warn!(
+                    "Couldn't read the source of {}: {}",
+                    rom_path.display(),
+                    error
+                );
+
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+
+        let source_name = source_path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "line".to_string());
+
+        Some(Self {
+            info,
+            source_name,
+            lines,
+        })
+    }
+
+    // e.g. "0x2A4 draw_score, game.asm:42: DRW V0, V1, 5"
+    fn describe(&self, address: u16) -> String {
+        let mut description = format!("0x{:03X}", address);
+
+        if let Some(label) = self.info.label_at(address) {
+            description += &format!(" {}", label);
+        }
+
+        if let Some(line) = self.info.line_at(address) {
+            description += &format!(", {}:{}", self.source_name, line);
+
+            if let Some(text) = self.lines.get(line - 1) {
+                description += &format!(": {}", text.trim());
+            }
+        }
+
+        description
+    }
+}
+
+// An address, or with debug info a label or a line number.
+fn resolve(location: &str, source_map: Option<&SourceMap>) -> Result<u16, String> {
+    // Plain numbers are line numbers here, so only hex is an address.
+    if location.starts_with("0x") || location.starts_with("0X") {
+        return parse_address(location).ok_or_else(|| format!("Invalid address '{}'", location));
+    }
+
+    let info = match source_map {
+        Some(source_map) => &source_map.info,
+        None => {
+            return Err(format!(
+                "'{}' isn't an address, and the ROM has no debug info",
+                location
+            ))
+        }
+    };
+
+    match location.parse::<usize>() {
+        Ok(line) => info
+            .address_of_line(line)
+            .ok_or_else(|| format!("No code on or after line {}", line)),
+        Err(_) => info
+            .label(location)
+            .ok_or_else(|| format!("No label named '{}'", location)),
+    }
+}
+
+fn describe(address: u16, source_map: Option<&SourceMap>) -> String {
+    match source_map {
+        Some(source_map) => source_map.describe(address),
+        None => format!("0x{:03X}", address),
+    }
+}
+
+// Printed when a frame stops at a breakpoint.
+pub fn report_breakpoint(address: u16, source_map: Option<&SourceMap>) {
+    println!(
+        "Breakpoint at {}, press P to continue",
+        describe(address, source_map)
+    );
+}
+
+#[derive(Default)]
+pub struct DebugConsole {
+    search: Option<RamSearch>,
+    snapshots: HashMap<String, State>,
+}
+
+impl DebugConsole {
+    pub fn run(
+        &mut self,
+        line: &str,
+        emulator: &Emulator,
+        source_map: Option<&SourceMap>,
+    ) -> Result<(), String> {
+        let line = line.trim();
+
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            "" => {}
+            "help" => println!("{}", HELP),
+            "search" => {
+                let search = RamSearch::new(&memory(emulator)?);
+
+                println!("{} candidates", search.candidates().len());
+
+                self.search = Some(search);
+            }
+            "changed" | "unchanged" | "increased" | "decreased" | "equals" => {
+                let comparison: Comparison = line.parse()?;
+
+                let memory = memory(emulator)?;
+
+                let search = self
+                    .search
+                    .as_mut()
+                    .ok_or("No search running, start one with 'search'")?;
+
+                search.filter(&memory, comparison);
+
+                println!("{} candidates", search.candidates().len());
+            }
+            "list" => {
+                let search = self
+                    .search
+                    .as_ref()
+                    .ok_or("No search running, start one with 'search'")?;
+
+                let memory = memory(emulator)?;
+
+                for &address in search.candidates().iter().take(MAX_LISTED) {
+                    let value = memory[address as usize];
+
+                    println!("0x{:03X} = {} (0x{:02X})", address, value, value);
+                }
+
+                if search.candidates().len() > MAX_LISTED {
+                    println!("... {} more", search.candidates().len() - MAX_LISTED);
+                }
+            }
+            "poke" | "freeze" => {
+                let mut cheat: Cheat = argument.parse()?;
+
+                cheat.freeze = command == "freeze";
+
+                emulator
+                    .with_interpreter(move |interpreter| {
+                        interpreter.remove_cheats(cheat.address);
+                        interpreter.add_cheat(cheat);
+
+                        // Take effect now, even while paused.
+                        interpreter.apply_cheats();
+                    })
+                    .ok_or("The emulator has stopped")?;
+            }
+            "unfreeze" => {
+                // Reuse the cheat parser for the address.
+                let cheat: Cheat = format!("{}=0", argument).parse()?;
+
+                emulator
+                    .with_interpreter(move |interpreter| interpreter.remove_cheats(cheat.address))
+                    .ok_or("The emulator has stopped")?;
+            }
+            "cheats" => {
+                let cheats = emulator
+                    .with_interpreter(|interpreter| interpreter.cheats().to_vec())
+                    .ok_or("The emulator has stopped")?;
+
+                if cheats.is_empty() {
+                    println!("No active cheats");
+                }
+
+                for cheat in cheats {
+                    println!(
+                        "0x{:03X} = {}{}",
+                        cheat.address,
+                        cheat.value,
+                        if cheat.freeze { " freeze" } else { "" }
+                    );
+                }
+            }
+            "snapshot" => {
+                if argument.is_empty() {
+                    return Err("snapshot needs a name".to_string());
+                }
+
+                let state = emulator
+                    .with_interpreter(|interpreter| interpreter.state())
+                    .ok_or("The emulator has stopped")?;
+
+                self.snapshots.insert(argument.to_string(), state);
+            }
+            "diff" => {
+                let mut names = argument.split_whitespace();
+
+                let old = self.snapshot(names.next().ok_or("diff needs a snapshot name")?)?;
+
+                let live;
+
+                let new = match names.next() {
+                    Some(name) => self.snapshot(name)?,
+                    None => {
+                        live = emulator
+                            .with_interpreter(|interpreter| interpreter.state())
+                            .ok_or("The emulator has stopped")?;
+
+                        &live
+                    }
+                };
+
+                let differences = old.diff(new);
+
+                if differences.is_empty() {
+                    println!("No differences");
+                }
+
+                for difference in differences {
+                    println!("{}", difference);
+                }
+            }
+            "save" => {
+                if argument.is_empty() {
+                    return Err("save needs a path".to_string());
+                }
+
+                let state = emulator
+                    .with_interpreter(|interpreter| SaveState::capture(interpreter))
+                    .ok_or("The emulator has stopped")??;
+
+                fs::write(argument, state.to_bytes()).map_err(|error| error.to_string())?;
+
+                println!("Saved to {}", argument);
+            }
+            "load" => {
+                let bytes = fs::read(argument).map_err(|error| error.to_string())?;
+
+                let state = SaveState::parse(&bytes)?;
+
+                emulator
+                    .with_interpreter(move |interpreter| state.restore(interpreter))
+                    .ok_or("The emulator has stopped")??;
+
+                println!("Loaded {}", argument);
+            }
+            "export-flags" => {
+                let flags = emulator
+                    .with_interpreter(|interpreter| interpreter.rpl_flags)
+                    .ok_or("The emulator has stopped")?;
+
+                fs::write(argument, rpl::to_octo(&flags)).map_err(|error| error.to_string())?;
+
+                println!("Exported to {}", argument);
+            }
+            "import-flags" => {
+                let text = fs::read_to_string(argument).map_err(|error| error.to_string())?;
+
+                let flags = rpl::from_octo(&text)?;
+
+                emulator
+                    .with_interpreter(move |interpreter| interpreter.rpl_flags = flags)
+                    .ok_or("The emulator has stopped")?;
+
+                println!("Imported {}", argument);
+            }
+            "break" | "delete" => {
+                let address = resolve(argument, source_map)?;
+
+                let added = command == "break";
+
+                let changed = emulator
+                    .with_interpreter(move |interpreter| {
+                        if added {
+                            interpreter.add_breakpoint(address);
+
+                            true
+                        } else {
+                            interpreter.remove_breakpoint(address)
+                        }
+                    })
+                    .ok_or("The emulator has stopped")?;
+
+                if !changed {
+                    return Err(format!("No breakpoint at 0x{:03X}", address));
+                }
+
+                let verb = if added { "Breakpoint at" } else { "Deleted" };
+
+                println!("{} {}", verb, describe(address, source_map));
+            }
+            "breakpoints" => {
+                let breakpoints = emulator
+                    .with_interpreter(|interpreter| interpreter.breakpoints().clone())
+                    .ok_or("The emulator has stopped")?;
+
+                if breakpoints.is_empty() {
+                    println!("No breakpoints");
+                }
+
+                for address in breakpoints {
+                    println!("{}", describe(address, source_map));
+                }
+            }
+            "step" => {
+                if !emulator.is_paused() {
+                    return Err("Pause first, with P or a breakpoint".to_string());
+                }
+
+                let address = emulator
+                    .with_interpreter(|interpreter| {
+                        interpreter
+                            .try_execute_cycle()
+                            .map(|_| interpreter.program_counter)
+                    })
+                    .ok_or("The emulator has stopped")??;
+
+                println!("{}", describe(address, source_map));
+            }
+            "where" => {
+                let address = emulator
+                    .with_interpreter(|interpreter| interpreter.program_counter)
+                    .ok_or("The emulator has stopped")?;
+
+                println!("{}", describe(address, source_map));
+            }
+            _ => return Err(format!("Unknown command '{}', try 'help'", command)),
+        }
+
+        Ok(())
+    }
+
+    fn snapshot(&self, name: &str) -> Result<&State, String> {
+        self.snapshots
+            .get(name)
+            .ok_or_else(|| format!("No snapshot named '{}'", name))
+    }
+}
+
+fn memory(emulator: &Emulator) -> Result<Vec<u8>, String> {
+    emulator
+        .with_interpreter(|interpreter| interpreter.memory.to_vec())
+        .ok_or_else(|| "The emulator has stopped".to_string())
+}
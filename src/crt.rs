@@ -0,0 +1,186 @@
+// Optional CRT look applied to the scaled-up display: dark scanlines, a
+// slightly bulging (barrel distorted) picture and darkened corners.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrtSettings {
+    // How much every other row is darkened, 0 to 1.
+    pub scanlines: f32,
+    // Barrel distortion strength; 0 keeps the picture flat.
+    pub curvature: f32,
+    // How much the corners are darkened, 0 to 1.
+    pub vignette: f32,
+}
+
+impl Default for CrtSettings {
+    fn default() -> Self {
+        Self {
+            scanlines: 0.35,
+            curvature: 0.08,
+            vignette: 0.3,
+        }
+    }
+}
+
+impl CrtSettings {
+    // Parses a comma separated list of name=value pairs, e.g.
+    // "scanlines=0.5,curvature=0". Settings that aren't listed keep their
+    // defaults.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut settings = Self::default();
+
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Expected name=value, got: {}", setting))?;
+
+            let value: f32 = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|value: &f32| (0.0..=1.0).contains(value))
+                .ok_or_else(|| format!("Invalid CRT {} (expected 0-1): {}", name, value))?;
+
+            match name.trim() {
+                "scanlines" => settings.scanlines = value,
+                "curvature" => settings.curvature = value,
+                "vignette" => settings.vignette = value,
+                _ => {
+                    return Err(format!(
+                        "Unknown CRT setting: {} (expected scanlines, curvature or vignette)",
+                        name
+                    ))
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+pub struct CrtEffect {
+    settings: CrtSettings,
+    width: usize,
+    height: usize,
+    // For every output pixel, the source pixel it shows (None outside the
+    // curved screen) and its brightness out of 256. Only rebuilt on resize.
+    mapping: Vec<(Option<u32>, u16)>,
+}
+
+impl CrtEffect {
+    pub fn new(settings: CrtSettings) -> Self {
+        Self {
+            settings,
+            width: 0,
+            height: 0,
+            mapping: Vec::new(),
+        }
+    }
+
+    pub fn apply(&mut self, source: &[u32], target: &mut [u32], width: usize, height: usize) {
+        if (width, height) != (self.width, self.height) {
+            self.build_mapping(width, height);
+        }
+
+        for (pixel, &(index, brightness)) in target.iter_mut().zip(&self.mapping) {
+            *pixel = match index {
+                Some(index) => dim(source[index as usize], brightness),
+                None => 0xFF000000,
+            };
+        }
+    }
+
+    fn build_mapping(&mut self, width: usize, height: usize) {
+        let CrtSettings {
+            scanlines,
+            curvature,
+            vignette,
+        } = self.settings;
+
+        self.width = width;
+        self.height = height;
+
+        self.mapping.clear();
+
+        for y in 0..height {
+            for x in 0..width {
+                // Centered coordinates from -1 to 1.
+                let u = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+
+                let distance = u * u + v * v;
+
+                let u = u * (1.0 + curvature * distance);
+                let v = v * (1.0 + curvature * distance);
+
+                if u.abs() > 1.0 || v.abs() > 1.0 {
+                    self.mapping.push((None, 0));
+
+                    continue;
+                }
+
+                let source_x = (((u + 1.0) / 2.0 * width as f32) as usize).min(width - 1);
+                let source_y = (((v + 1.0) / 2.0 * height as f32) as usize).min(height - 1);
+
+                let mut brightness = 1.0 - vignette * distance / 2.0;
+
+                if y % 2 == 1 {
+                    brightness *= 1.0 - scanlines;
+                }
+
+                let brightness = (brightness.clamp(0.0, 1.0) * 256.0) as u16;
+
+                self.mapping
+                    .push((Some((source_y * width + source_x) as u32), brightness));
+            }
+        }
+    }
+}
+
+fn dim(color: u32, brightness: u16) -> u32 {
+    let channel = |shift: u32| ((color >> shift & 0xFF) * brightness as u32 / 256) << shift;
+
+    0xFF000000 | channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrtEffect, CrtSettings};
+
+    #[test]
+    fn test_parse_overrides_listed_settings() {
+        let settings = CrtSettings::parse("scanlines=0.5, curvature=0").unwrap();
+
+        assert_eq!(settings.scanlines, 0.5);
+        assert_eq!(settings.curvature, 0.0);
+        assert_eq!(settings.vignette, CrtSettings::default().vignette);
+
+        assert!(CrtSettings::parse("scanlines=2").is_err());
+        assert!(CrtSettings::parse("bloom=0.5").is_err());
+    }
+
+    #[test]
+    fn test_curvature_blackens_corners_and_keeps_center() {
+        let mut effect = CrtEffect::new(CrtSettings {
+            scanlines: 0.0,
+            curvature: 0.5,
+            vignette: 0.0,
+        });
+
+        let (width, height) = (64, 32);
+
+        let source = vec![0xFFFFFFFF; width * height];
+        let mut target = vec![0; width * height];
+
+        effect.apply(&source, &mut target, width, height);
+
+        assert_eq!(
+            target[0], 0xFF000000,
+            "Corner should be outside the screen!"
+        );
+
+        assert_eq!(
+            target[height / 2 * width + width / 2],
+            0xFFFFFFFF,
+            "Center should be unchanged!"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+// A ball bouncing around "C8", shown when the emulator starts without a ROM
+// and has none to offer from the recent list.
+const DEMO_PROGRAM: [u16; 35] = [
+    0x00E0, // 200: CLS
+    0x6A1B, // 202: LD VA, 27
+    0x6B0D, // 204: LD VB, 13
+    0x600C, // 206: LD V0, 0xC
+    0xF029, // 208: LD F, V0
+    0xDAB5, // 20A: DRW VA, VB, 5
+    0x7A06, // 20C: ADD VA, 6
+    0x6008, // 20E: LD V0, 8
+    0xF029, // 210: LD F, V0
+    0xDAB5, // 212: DRW VA, VB, 5
+    0x6105, // 214: LD V1, 5
+    0x6203, // 216: LD V2, 3
+    0x6301, // 218: LD V3, 1
+    0x6401, // 21A: LD V4, 1
+    0xA244, // 21C: LD I, 0x244
+    0xD122, // 21E: DRW V1, V2, 2
+    0x6502, // 220: LD V5, 2
+    0xF515, // 222: LD DT, V5
+    0xF507, // 224: LD V5, DT
+    0x3500, // 226: SE V5, 0
+    0x1224, // 228: JP 0x224
+    0xD122, // 22A: DRW V1, V2, 2
+    0x8134, // 22C: ADD V1, V3
+    0x8244, // 22E: ADD V2, V4
+    0x4100, // 230: SNE V1, 0
+    0x6301, // 232: LD V3, 1
+    0x413E, // 234: SNE V1, 62
+    0x63FF, // 236: LD V3, -1
+    0x4200, // 238: SNE V2, 0
+    0x6401, // 23A: LD V4, 1
+    0x421E, // 23C: SNE V2, 30
+    0x64FF, // 23E: LD V4, -1
+    0xD122, // 240: DRW V1, V2, 2
+    0x1220, // 242: JP 0x220
+    0xC0C0, // 244: the ball, two rows of two pixels
+];
+
+pub fn rom_data() -> Vec<u8> {
+    DEMO_PROGRAM
+        .iter()
+        .flat_map(|opcode| opcode.to_be_bytes())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chip8_vm::chip8::Interpreter;
+
+    use super::rom_data;
+
+    #[test]
+    fn test_demo_keeps_running() {
+        let mut interpreter = Interpreter::new();
+
+        interpreter.load_program(&rom_data()).unwrap();
+
+        for _ in 0..600 {
+            interpreter.try_run_frame().unwrap();
+        }
+
+        assert!(!interpreter.exited(), "The demo should run forever!");
+        assert!(
+            interpreter.frame().pixels.iter().any(|&byte| byte != 0),
+            "The demo should draw something!"
+        );
+    }
+}
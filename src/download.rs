@@ -0,0 +1,36 @@
+use std::io::Read;
+use std::time::Duration;
+
+// Far more than any CHIP-8 program needs (XO-CHIP tops out at 64 KiB), but
+// small enough that a wrong URL can't fill up memory.
+const MAX_DOWNLOAD_SIZE: u64 = 64 * 1024;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+pub fn download(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url)
+        .timeout(TIMEOUT)
+        .call()
+        .map_err(|error| format!("Failed to download {}: {}", url, error))?;
+
+    let mut rom_data = Vec::new();
+
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_SIZE + 1)
+        .read_to_end(&mut rom_data)
+        .map_err(|error| format!("Failed to download {}: {}", url, error))?;
+
+    if rom_data.len() as u64 > MAX_DOWNLOAD_SIZE {
+        return Err(format!(
+            "Download from {} exceeds the {} byte limit",
+            url, MAX_DOWNLOAD_SIZE
+        ));
+    }
+
+    Ok(rom_data)
+}
@@ -0,0 +1,961 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use winit::event_loop::EventLoopProxy;
+use winit::window::WindowId;
+
+use tracing::{error, info_span, warn, Span};
+
+use chip8_vm::chip8::crash::crash_report;
+use chip8_vm::chip8::frontend::NoAudio;
+use chip8_vm::chip8::tone::ToneSettings;
+use chip8_vm::chip8::{AudioSink, DisplayDriver, Frame, InputSource, Interpreter, Region};
+
+use crate::remote::{RemoteCommand, RemoteReply};
+use crate::rpl::RplFlags;
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+// Ten seconds of frames without drawing, keys or timers. Games waiting for
+// the player always check a key or the delay timer, so only a program that
+// has lost its way, or is working something out at length, gets this far.
+pub const RUNAWAY_FRAMES: u64 = 600;
+
+#[derive(Debug)]
+pub enum UserEvent {
+    RedrawScreen(WindowId),
+    EmulatorError(WindowId, String),
+    // Something worth telling the player that doesn't stop the program.
+    EmulatorWarning(WindowId, String),
+    // The program ran 00FD and the interpreter thread has stopped.
+    EmulatorExited(WindowId),
+    // A frame stopped at a breakpoint and the emulator has paused.
+    Breakpoint(WindowId, u16),
+    // The program has gone RUNAWAY_FRAMES without drawing, keys or timers,
+    // and was at this address.
+    PossibleRunaway(WindowId, u16),
+    EmulatorStatus(WindowId, EmulatorStatus),
+    RomChanged(PathBuf),
+    ConsoleCommand(String),
+    Remote(RemoteCommand, RemoteReply),
+    // A key pressed or released in a chip8-viewer connected over --stream.
+    StreamKey(u8, bool),
+    // Memory read for a sprite viewer window: where the bytes start, the
+    // bytes, and how much memory there is.
+    SpriteMemory(WindowId, usize, Vec<u8>, usize),
+}
+
+// Work run on the interpreter thread between frames, e.g. to inspect memory
+// without stopping emulation.
+type Command = Box<dyn FnOnce(&mut Interpreter) + Send>;
+
+// Automation that runs between frames, such as a --script.
+pub trait FrameScript: Send {
+    // Called after every frame, including fast-forwarded ones.
+    fn after_frame(&mut self, interpreter: &mut Interpreter) -> Result<(), String>;
+
+    // The keys the next frame sees, given the ones the player is holding.
+    fn keys(&self, keys: [bool; 16]) -> [bool; 16];
+}
+
+// A recording of the session in emulated time, such as --record-audio.
+pub trait Recorder: Send {
+    // Called after every frame, including fast-forwarded ones.
+    fn record_frame(&mut self, interpreter: &Interpreter) -> io::Result<()>;
+
+    // What's being recorded, e.g. "audio".
+    fn kind(&self) -> &'static str;
+}
+
+// Measured emulation speed, reported by the interpreter thread about once per
+// second.
+#[derive(Clone, Copy, Debug)]
+pub struct EmulatorStatus {
+    pub frames_per_second: u32,
+    pub instructions_per_second: u32,
+}
+
+pub struct DisplayFrame {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub dirty: Option<Region>,
+    // ARGB per pixel on a MegaChip in its color mode.
+    pub colors: Option<Vec<u32>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EmulatorSettings {
+    pub deterministic: bool,
+    // How many frames to run per 60 Hz tick while fast-forwarding.
+    pub turbo_speed: u32,
+    // Initial speed; below 100 runs in slow motion.
+    pub speed_percent: u32,
+    pub tone: ToneSettings,
+    // Playback volume in percent; recordings are always at full volume.
+    pub volume: u32,
+    // Hold the interpreter before its first instruction until resumed.
+    pub start_paused: bool,
+    // Run the interpreter from the event loop rather than its own thread.
+    pub single_threaded: bool,
+}
+
+// An interpreter running on its own thread, or on the event loop's with
+// --single-threaded, presenting frames to one window.
+pub struct Emulator {
+    pub receiver: Receiver<DisplayFrame>,
+    pub keypad: SharedKeypad,
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    // Set to run a single frame while paused.
+    advance: Arc<AtomicBool>,
+    muted: Arc<AtomicBool>,
+    // Whether the buzzer is sounding, muted or not, as of the last frame.
+    sounding: Arc<AtomicBool>,
+    turbo: Arc<AtomicBool>,
+    speed_percent: Arc<AtomicU32>,
+    // Frames run since the emulator started.
+    frames: Arc<AtomicU64>,
+    commands: Sender<Command>,
+    engine: Engine,
+}
+
+enum Engine {
+    Thread(JoinHandle<Interpreter>),
+    // Ticked by the event loop through run_due.
+    EventLoop(Box<RefCell<EventLoopRunner>>),
+}
+
+struct EventLoopRunner {
+    runner: Runner,
+    audio: Box<dyn AudioSink>,
+    // When the next tick is due, or None once emulation has stopped.
+    next_tick: Option<Instant>,
+}
+
+impl Emulator {
+    pub fn spawn(
+        mut interpreter: Interpreter,
+        window_id: WindowId,
+        event_loop_proxy: EventLoopProxy<UserEvent>,
+        settings: EmulatorSettings,
+        recorders: Vec<Box<dyn Recorder>>,
+        rpl_flags: Option<RplFlags>,
+        script: Option<Box<dyn FrameScript>>,
+    ) -> Self {
+        let (sender, receiver) = channel();
+
+        let keypad = SharedKeypad::default();
+
+        let display = ChannelDisplay {
+            sender,
+            events: Box::new(move |event| event_loop_proxy.send_event(event).is_ok()),
+            window_id,
+            connected: true,
+        };
+
+        let running = Arc::new(AtomicBool::new(true));
+
+        let paused = Arc::new(AtomicBool::new(settings.start_paused));
+
+        let advance = Arc::new(AtomicBool::new(false));
+
+        let muted = Arc::new(AtomicBool::new(false));
+
+        let sounding = Arc::new(AtomicBool::new(false));
+
+        let turbo = Arc::new(AtomicBool::new(false));
+
+        let speed_percent = Arc::new(AtomicU32::new(settings.speed_percent));
+
+        let frames = Arc::new(AtomicU64::new(0));
+
+        let (commands, runner_commands) = channel::<Command>();
+
+        if let Some(flags) = &rpl_flags {
+            flags.restore(&mut interpreter);
+        }
+
+        let mut runner = Runner {
+            interpreter,
+            display,
+            input: keypad.clone(),
+            running: Arc::clone(&running),
+            paused: Arc::clone(&paused),
+            advance: Arc::clone(&advance),
+            muted: Arc::clone(&muted),
+            sounding: Arc::clone(&sounding),
+            turbo: Arc::clone(&turbo),
+            speed_percent: Arc::clone(&speed_percent),
+            frames: Arc::clone(&frames),
+            commands: runner_commands,
+            settings,
+            recorders,
+            rpl_flags,
+            script,
+            span: info_span!("interpreter", window = ?window_id),
+            next_frame: Instant::now(),
+            status_start: Instant::now(),
+            status_frames: 0,
+            system_calls_reported: 0,
+            runaway_reported: false,
+        };
+
+        // The audio sink is made where it's used, as it may not be able to
+        // move between threads.
+        let engine = if settings.single_threaded {
+            Engine::EventLoop(Box::new(RefCell::new(EventLoopRunner {
+                runner,
+                audio: create_audio_sink(settings.tone, settings.volume),
+                next_tick: Some(Instant::now()),
+            })))
+        } else {
+            Engine::Thread(thread::spawn(move || {
+                let mut audio = create_audio_sink(settings.tone, settings.volume);
+
+                while let Some(next_tick) = runner.tick(audio.as_mut()) {
+                    let now = Instant::now();
+
+                    if next_tick > now {
+                        thread::sleep(next_tick - now);
+                    }
+                }
+
+                runner.interpreter
+            }))
+        };
+
+        Self {
+            receiver,
+            keypad,
+            running,
+            paused,
+            advance,
+            muted,
+            sounding,
+            turbo,
+            speed_percent,
+            frames,
+            commands,
+            engine,
+        }
+    }
+
+    // Runs the emulator's tick if one is due, when the event loop drives it,
+    // and returns when the next one is. Emulators on their own thread keep
+    // their own time and always return None.
+    pub fn run_due(&self, now: Instant) -> Option<Instant> {
+        let event_loop_runner = match &self.engine {
+            Engine::EventLoop(event_loop_runner) => event_loop_runner,
+            Engine::Thread(_) => return None,
+        };
+
+        let EventLoopRunner {
+            runner,
+            audio,
+            next_tick,
+        } = &mut *event_loop_runner.borrow_mut();
+
+        if next_tick.is_some_and(|next_tick| next_tick <= now) {
+            *next_tick = runner.tick(audio.as_mut());
+        }
+
+        *next_tick
+    }
+
+    // Runs the function on the interpreter thread before its next frame and
+    // waits for the result. Returns None if the thread has stopped.
+    pub fn with_interpreter<R: Send + 'static>(
+        &self,
+        function: impl FnOnce(&mut Interpreter) -> R + Send + 'static,
+    ) -> Option<R> {
+        // Between ticks on the event loop, the interpreter is free to use
+        // straight away, once the commands sent before have run.
+        if let Engine::EventLoop(event_loop_runner) = &self.engine {
+            let runner = &mut event_loop_runner.borrow_mut().runner;
+
+            runner.run_commands();
+
+            return Some(function(&mut runner.interpreter));
+        }
+
+        let (sender, receiver) = channel();
+
+        let command: Command = Box::new(move |interpreter| {
+            let _ = sender.send(function(interpreter));
+        });
+
+        self.commands.send(command).ok()?;
+
+        receiver.recv().ok()
+    }
+
+    // Like with_interpreter, but returns straight away; the function has to
+    // pass on any result itself. Returns false if the thread has stopped.
+    pub fn send_command(&self, function: impl FnOnce(&mut Interpreter) + Send + 'static) -> bool {
+        self.commands.send(Box::new(function)).is_ok()
+    }
+
+    pub fn speed_percent(&self) -> u32 {
+        self.speed_percent.load(Ordering::Relaxed)
+    }
+
+    pub fn set_speed_percent(&self, speed_percent: u32) {
+        self.speed_percent.store(speed_percent, Ordering::Relaxed);
+    }
+
+    pub fn set_turbo(&self, turbo: bool) {
+        self.turbo.store(turbo, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    // Runs exactly one frame, instructions and timer ticks, if paused. Asking
+    // again before it has run still only runs one.
+    pub fn advance_frame(&self) {
+        self.advance.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    pub fn is_sounding(&self) -> bool {
+        self.sounding.load(Ordering::Relaxed)
+    }
+
+    // Signals the interpreter thread to finish its current frame and waits for
+    // it. The receiver is kept alive until then so the final present succeeds.
+    // Returns the interpreter as the thread left it, even after an error.
+    pub fn stop(self) -> Option<Interpreter> {
+        self.running.store(false, Ordering::Relaxed);
+
+        let thread = match self.engine {
+            Engine::Thread(thread) => thread,
+            Engine::EventLoop(event_loop_runner) => {
+                return Some(event_loop_runner.into_inner().runner.interpreter)
+            }
+        };
+
+        match thread.join() {
+            Ok(interpreter) => Some(interpreter),
+            Err(_) => {
+                error!("Interpreter thread panicked");
+
+                None
+            }
+        }
+    }
+}
+
+// The interpreter and everything it runs with, stepped a tick at a time by the
+// interpreter thread or the event loop.
+struct Runner {
+    interpreter: Interpreter,
+    display: ChannelDisplay,
+    input: SharedKeypad,
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    advance: Arc<AtomicBool>,
+    muted: Arc<AtomicBool>,
+    sounding: Arc<AtomicBool>,
+    turbo: Arc<AtomicBool>,
+    speed_percent: Arc<AtomicU32>,
+    frames: Arc<AtomicU64>,
+    commands: Receiver<Command>,
+    settings: EmulatorSettings,
+    recorders: Vec<Box<dyn Recorder>>,
+    rpl_flags: Option<RplFlags>,
+    script: Option<Box<dyn FrameScript>>,
+    span: Span,
+    next_frame: Instant,
+    status_start: Instant,
+    status_frames: u32,
+    system_calls_reported: usize,
+    // Set once a possible runaway loop has been reported, until the program
+    // shows signs of life again.
+    runaway_reported: bool,
+}
+
+impl Runner {
+    fn run_commands(&mut self) {
+        for command in self.commands.try_iter() {
+            command(&mut self.interpreter);
+        }
+    }
+
+    // Runs a frame, or fast-forwards several, or while paused presents the
+    // frozen one again. Returns when the next tick is due, or None once
+    // emulation has stopped.
+    fn tick(&mut self, audio: &mut dyn AudioSink) -> Option<Instant> {
+        let span = self.span.clone();
+
+        let _span = span.enter();
+
+        if !self.running.load(Ordering::Relaxed) || !self.display.connected {
+            return None;
+        }
+
+        self.run_commands();
+
+        // Slow motion stretches each frame rather than running fewer
+        // instructions, so the program behaves exactly as at full speed.
+        let speed_percent = self.speed_percent.load(Ordering::Relaxed).clamp(1, 100);
+
+        let frame_duration = FRAME_DURATION * 100 / speed_percent;
+
+        if self.status_start.elapsed() >= STATUS_INTERVAL {
+            self.display.report_status(
+                self.status_frames,
+                self.interpreter.instructions_per_frame,
+                self.status_start.elapsed(),
+            );
+
+            self.status_start = Instant::now();
+
+            self.status_frames = 0;
+        }
+
+        let paused = self.paused.load(Ordering::Relaxed);
+
+        let advancing = paused && self.advance.swap(false, Ordering::Relaxed);
+
+        if paused && !advancing {
+            audio.stop_tone();
+
+            self.sounding.store(false, Ordering::Relaxed);
+
+            // Keep presenting the frozen frame so the window can still
+            // redraw overlays while paused.
+            self.display.present(self.interpreter.frame());
+
+            // Resume on the next frame rather than catching up on the time
+            // spent paused.
+            self.next_frame = Instant::now() + FRAME_DURATION;
+
+            return Some(self.next_frame);
+        }
+
+        let frame_limit = self.frame_limit();
+
+        // Fast-forward by running several frames per tick, showing only the
+        // last. Sound would just be noise at this speed, as it would be a
+        // frame at a time.
+        let turbo = !advancing && self.turbo.load(Ordering::Relaxed);
+
+        let frames = if turbo {
+            self.settings.turbo_speed.clamp(1, frame_limit)
+        } else {
+            1
+        };
+
+        let mut frames_run = frames;
+
+        let audio = if turbo || advancing || self.muted.load(Ordering::Relaxed) {
+            audio.stop_tone();
+
+            &mut NoAudio as &mut dyn AudioSink
+        } else {
+            audio
+        };
+
+        let mut stopped = false;
+
+        for frame in 1..=frames {
+            let presented: Option<&mut dyn AudioSink> = if frame == frames {
+                Some(&mut *audio)
+            } else {
+                None
+            };
+
+            if !self.run_frame(presented)? {
+                stopped = true;
+
+                break;
+            }
+        }
+
+        self.next_frame += frame_duration;
+
+        let now = Instant::now();
+
+        if !stopped && self.next_frame <= now {
+            if self.settings.deterministic {
+                // Never skip ahead: running late only slows emulation down,
+                // it doesn't change which frames get executed.
+                self.next_frame = now;
+            } else {
+                // Catch up on frames missed while the thread wasn't
+                // scheduled so emulation speed tracks wall-clock time,
+                // within the tick's limit.
+                while self.next_frame + frame_duration <= now {
+                    if frames_run >= frame_limit {
+                        self.next_frame = now;
+
+                        break;
+                    }
+
+                    frames_run += 1;
+
+                    if !self.run_frame(None)? {
+                        break;
+                    }
+
+                    self.next_frame += frame_duration;
+                }
+            }
+        }
+
+        self.check_for_runaway();
+
+        for &(address, target) in &self.interpreter.system_calls()[self.system_calls_reported..] {
+            self.display
+                .report_warning(system_call_warning(address, target));
+        }
+
+        self.system_calls_reported = self.interpreter.system_calls().len();
+
+        if self.interpreter.exited() {
+            self.display.report_exit();
+
+            return None;
+        }
+
+        self.sounding
+            .store(self.interpreter.sound_timer > 0, Ordering::Relaxed);
+
+        save_rpl_flags(&mut self.rpl_flags, &self.interpreter);
+
+        Some(self.next_frame)
+    }
+
+    // Runs one frame with the player's and the script's keys, then records it
+    // and runs the script. Only a frame given `audio` is presented and heard;
+    // the rest are fast-forwarded or caught up. Returns whether the tick may
+    // run another, or None once emulation has stopped.
+    fn run_frame(&mut self, audio: Option<&mut dyn AudioSink>) -> Option<bool> {
+        let mut input = ScriptedInput {
+            keypad: &mut self.input,
+            script: self.script.as_deref(),
+        };
+
+        let result = match audio {
+            Some(audio) => self
+                .interpreter
+                .run_frame_with(&mut self.display, &mut input, audio),
+            None => {
+                self.interpreter.keypad = input.poll_keys();
+
+                self.interpreter.try_run_frame()
+            }
+        };
+
+        if let Err(error) = result {
+            self.display
+                .report_error(write_crash_dump(&self.interpreter, error));
+
+            return None;
+        }
+
+        // The rest of the frame runs once the player resumes.
+        if let Some(address) = self.interpreter.stopped_at() {
+            self.paused.store(true, Ordering::Relaxed);
+
+            self.display.report_breakpoint(address);
+
+            return Some(false);
+        }
+
+        record_frame(&mut self.recorders, &self.interpreter);
+
+        run_script(&mut self.script, &mut self.interpreter);
+
+        self.status_frames += 1;
+
+        self.frames.fetch_add(1, Ordering::Relaxed);
+
+        Some(!self.interpreter.exited())
+    }
+
+    // How many frames a tick may run under --max-ipf; the interpreter keeps
+    // each frame under it.
+    fn frame_limit(&self) -> u32 {
+        match self.interpreter.max_instructions_per_frame {
+            Some(max) => (max / self.interpreter.max_frame_instructions()).max(1),
+            None => u32::MAX,
+        }
+    }
+
+    fn check_for_runaway(&mut self) {
+        let busy = self.interpreter.busy_frames() >= RUNAWAY_FRAMES;
+
+        if busy && !self.runaway_reported {
+            self.display
+                .report_runaway(self.interpreter.program_counter);
+        }
+
+        self.runaway_reported = busy;
+    }
+}
+
+pub fn system_call_warning(address: u16, target: u16) -> String {
+    format!(
+        "Skipped a call to the machine code routine at {:03X} (first called at {:03X})",
+        target, address
+    )
+}
+
+// Writes a crash report to the temporary directory and returns the error
+// message with the report's path added, for attaching to bug reports.
+pub fn write_crash_dump(interpreter: &Interpreter, error: String) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    let written = create_crash_file(seconds).and_then(|(path, mut file)| {
+        file.write_all(crash_report(interpreter, &error).as_bytes())?;
+
+        Ok(path)
+    });
+
+    match written {
+        Ok(path) => format!("{} (crash dump written to {})", error, path.display()),
+        Err(write_error) => format!("{} (failed to write crash dump: {})", error, write_error),
+    }
+}
+
+// Never replaces an earlier report, e.g. from another window that crashed in
+// the same second; later ones get a number after the time.
+fn create_crash_file(seconds: u64) -> io::Result<(PathBuf, File)> {
+    let mut attempt = 0;
+
+    loop {
+        let name = match attempt {
+            0 => format!("chip8-crash-{}.txt", seconds),
+            _ => format!("chip8-crash-{}-{}.txt", seconds, attempt),
+        };
+
+        let path = std::env::temp_dir().join(name);
+
+        match File::options().write(true).create_new(true).open(&path) {
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            result => return result.map(|file| (path, file)),
+        }
+    }
+}
+
+// Recording stops at the first write error rather than stopping emulation.
+fn record_frame(recorders: &mut Vec<Box<dyn Recorder>>, interpreter: &Interpreter) {
+    recorders.retain_mut(|recorder| match recorder.record_frame(interpreter) {
+        Ok(()) => true,
+        Err(error) => {
+            warn!("Stopped recording {}: {}", recorder.kind(), error);
+
+            false
+        }
+    });
+}
+
+// Like recording, saving the flags stops at the first error.
+fn save_rpl_flags(rpl_flags: &mut Option<RplFlags>, interpreter: &Interpreter) {
+    if let Some(flags) = rpl_flags {
+        if let Err(error) = flags.save(interpreter) {
+            warn!("Stopped saving RPL flags: {}", error);
+
+            *rpl_flags = None;
+        }
+    }
+}
+
+// A failing script is stopped, leaving the game running.
+fn run_script(script: &mut Option<Box<dyn FrameScript>>, interpreter: &mut Interpreter) {
+    if let Some(running) = script {
+        if let Err(error) = running.after_frame(interpreter) {
+            warn!("Stopped the script: {}", error);
+
+            *script = None;
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+pub fn load_script(path: &Path) -> Result<Box<dyn FrameScript>, String> {
+    Ok(Box::new(crate::script::Script::load(path)?))
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn load_script(_path: &Path) -> Result<Box<dyn FrameScript>, String> {
+    Err("Scripts need the scripting feature (cargo build --features scripting)".to_string())
+}
+
+#[cfg(feature = "audio")]
+fn create_audio_sink(tone: ToneSettings, volume: u32) -> Box<dyn AudioSink> {
+    match crate::audio::CpalAudio::new(tone, volume) {
+        Ok(audio) => Box::new(audio),
+        Err(error) => {
+            warn!("Audio disabled: {}", error);
+
+            Box::new(NoAudio)
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+fn create_audio_sink(_tone: ToneSettings, _volume: u32) -> Box<dyn AudioSink> {
+    Box::new(NoAudio)
+}
+
+// Sends an event to the event loop, returning false once it has gone away.
+type EventSender = Box<dyn Fn(UserEvent) -> bool + Send>;
+
+struct ChannelDisplay {
+    sender: Sender<DisplayFrame>,
+    events: EventSender,
+    window_id: WindowId,
+    // Cleared once the window or event loop has gone away, which tells the
+    // thread to stop.
+    connected: bool,
+}
+
+impl ChannelDisplay {
+    fn send_event(&mut self, event: UserEvent) {
+        if !(self.events)(event) {
+            self.connected = false;
+        }
+    }
+
+    fn report_error(&mut self, message: String) {
+        self.send_event(UserEvent::EmulatorError(self.window_id, message));
+    }
+
+    fn report_warning(&mut self, message: String) {
+        self.send_event(UserEvent::EmulatorWarning(self.window_id, message));
+    }
+
+    fn report_breakpoint(&mut self, address: u16) {
+        self.send_event(UserEvent::Breakpoint(self.window_id, address));
+    }
+
+    fn report_runaway(&mut self, address: u16) {
+        self.send_event(UserEvent::PossibleRunaway(self.window_id, address));
+    }
+
+    fn report_exit(&mut self) {
+        self.send_event(UserEvent::EmulatorExited(self.window_id));
+    }
+
+    fn report_status(&mut self, frames: u32, instructions_per_frame: u32, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+
+        let status = EmulatorStatus {
+            frames_per_second: (frames as f64 / seconds).round() as u32,
+            instructions_per_second: (frames as f64 * instructions_per_frame as f64 / seconds)
+                .round() as u32,
+        };
+
+        self.send_event(UserEvent::EmulatorStatus(self.window_id, status));
+    }
+}
+
+impl DisplayDriver for ChannelDisplay {
+    fn present(&mut self, frame: Frame<'_>) {
+        let frame = DisplayFrame {
+            pixels: frame.pixels.to_vec(),
+            width: frame.width as u32,
+            height: frame.height as u32,
+            dirty: frame.dirty,
+            colors: frame.colors.map(<[u32]>::to_vec),
+        };
+
+        if self.sender.send(frame).is_err() {
+            self.connected = false;
+        } else {
+            self.send_event(UserEvent::RedrawScreen(self.window_id));
+        }
+    }
+}
+
+// Keypad state shared between the window (writer) and the interpreter thread
+// (reader), one bit per key.
+#[derive(Clone, Default)]
+pub struct SharedKeypad(Arc<AtomicU16>);
+
+impl SharedKeypad {
+    pub fn set(&self, key: u8, pressed: bool) {
+        let mask = 1 << key;
+
+        if pressed {
+            self.0.fetch_or(mask, Ordering::Relaxed);
+        } else {
+            self.0.fetch_and(!mask, Ordering::Relaxed);
+        }
+    }
+
+    pub fn pressed_keys(&self) -> [bool; 16] {
+        let bits = self.0.load(Ordering::Relaxed);
+
+        std::array::from_fn(|key| bits & (1 << key) != 0)
+    }
+
+    pub fn release_all(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+impl InputSource for SharedKeypad {
+    fn poll_keys(&mut self) -> [bool; 16] {
+        self.pressed_keys()
+    }
+}
+
+// The player's keys plus any a script is holding down.
+struct ScriptedInput<'a> {
+    keypad: &'a mut SharedKeypad,
+    script: Option<&'a dyn FrameScript>,
+}
+
+impl InputSource for ScriptedInput<'_> {
+    fn poll_keys(&mut self) -> [bool; 16] {
+        let keys = self.keypad.poll_keys();
+
+        match self.script {
+            Some(script) => script.keys(keys),
+            None => keys,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use tracing::Span;
+    use winit::window::WindowId;
+
+    use chip8_vm::chip8::frontend::NoAudio;
+    use chip8_vm::chip8::tone::ToneSettings;
+    use chip8_vm::chip8::Interpreter;
+
+    use super::{
+        create_crash_file, ChannelDisplay, EmulatorSettings, FrameScript, Runner, SharedKeypad,
+        FRAME_DURATION,
+    };
+
+    // Counts the frames it has been run after.
+    struct CountingScript(Arc<AtomicU64>);
+
+    impl FrameScript for CountingScript {
+        fn after_frame(&mut self, _interpreter: &mut Interpreter) -> Result<(), String> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+
+            Ok(())
+        }
+
+        fn keys(&self, keys: [bool; 16]) -> [bool; 16] {
+            keys
+        }
+    }
+
+    #[test]
+    fn test_tick_runs_the_script_after_frames_it_catches_up_on() {
+        let mut interpreter = Interpreter::new();
+
+        // JP 0x200
+        interpreter.load_program(&[0x12, 0x00]).unwrap();
+
+        let (sender, _receiver) = channel();
+
+        let (_commands, runner_commands) = channel();
+
+        let script_frames = Arc::new(AtomicU64::new(0));
+
+        let mut runner = Runner {
+            interpreter,
+            display: ChannelDisplay {
+                sender,
+                events: Box::new(|_| true),
+                window_id: WindowId::dummy(),
+                connected: true,
+            },
+            input: SharedKeypad::default(),
+            running: Arc::new(AtomicBool::new(true)),
+            paused: Arc::new(AtomicBool::new(false)),
+            advance: Arc::new(AtomicBool::new(false)),
+            muted: Arc::new(AtomicBool::new(false)),
+            sounding: Arc::new(AtomicBool::new(false)),
+            turbo: Arc::new(AtomicBool::new(false)),
+            speed_percent: Arc::new(AtomicU32::new(100)),
+            frames: Arc::new(AtomicU64::new(0)),
+            commands: runner_commands,
+            settings: EmulatorSettings {
+                deterministic: false,
+                turbo_speed: 1,
+                speed_percent: 100,
+                tone: ToneSettings::default(),
+                volume: 100,
+                start_paused: false,
+                single_threaded: true,
+            },
+            recorders: Vec::new(),
+            rpl_flags: None,
+            script: Some(Box::new(CountingScript(Arc::clone(&script_frames)))),
+            span: Span::none(),
+            // Five frames behind.
+            next_frame: Instant::now() - FRAME_DURATION * 5,
+            status_start: Instant::now(),
+            status_frames: 0,
+            system_calls_reported: 0,
+            runaway_reported: false,
+        };
+
+        runner.tick(&mut NoAudio).unwrap();
+
+        let frames = runner.frames.load(Ordering::Relaxed);
+
+        assert!(frames > 1, "The tick should catch up on missed frames!");
+        assert_eq!(
+            script_frames.load(Ordering::Relaxed),
+            frames,
+            "The script should run after every frame, including caught up ones!"
+        );
+    }
+
+    #[test]
+    fn test_crash_files_in_the_same_second_dont_replace_each_other() {
+        let (first, _) = create_crash_file(0).unwrap();
+        let (second, _) = create_crash_file(0).unwrap();
+
+        fs::remove_file(&first).unwrap();
+        fs::remove_file(&second).unwrap();
+
+        assert_ne!(first, second, "The second crash should get its own file!");
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chip8_vm::chip8::Region;
+
+use crate::emulator::DisplayFrame;
+use crate::palette::Palette;
+
+// WCAG 2.3.1 allows no more than three flashes in any one second.
+const MAX_FLASHES_PER_SECOND: usize = 3;
+
+// A change to at least this much of the display counts as a flash.
+const FLASH_AREA_PERCENT: usize = 25;
+
+// Makes CHIP-8's flicker easier on photosensitive players. Each frame is
+// blended with the one before, so sprites erased and redrawn on alternate
+// frames show steadily at half brightness rather than blinking, and once the
+// display has flashed MAX_FLASHES_PER_SECOND times in the last second,
+// further big changes are held back until the second is up.
+//
+// MegaChip's color mode is shown as it is.
+#[derive(Default)]
+pub struct FlashFilter {
+    // The last frame let through.
+    previous: Vec<u8>,
+    flashes: VecDeque<Instant>,
+}
+
+impl FlashFilter {
+    pub fn apply(&mut self, frame: &mut DisplayFrame, palette: Palette, now: Instant) {
+        if frame.colors.is_some() {
+            return;
+        }
+
+        let size = frame.width as usize * frame.height as usize;
+
+        // Switching resolution starts afresh.
+        if self.previous.len() != frame.pixels.len() {
+            self.previous = frame.pixels.clone();
+        }
+
+        let changed: usize = self
+            .previous
+            .iter()
+            .zip(&frame.pixels)
+            .map(|(old, new)| (old ^ new).count_ones() as usize)
+            .sum();
+
+        self.flashes
+            .retain(|&flash| now.duration_since(flash) < Duration::from_secs(1));
+
+        if changed * 100 >= size * FLASH_AREA_PERCENT {
+            if self.flashes.len() < MAX_FLASHES_PER_SECOND {
+                self.flashes.push_back(now);
+            } else {
+                frame.pixels.clone_from(&self.previous);
+            }
+        }
+
+        let lit = |pixels: &[u8], index: usize| pixels[index / 8] >> (7 - index % 8) & 1 == 1;
+
+        let half = palette.mix(0.5);
+
+        let colors = (0..size)
+            .map(
+                |index| match (lit(&self.previous, index), lit(&frame.pixels, index)) {
+                    (true, true) => palette.foreground,
+                    (false, false) => palette.background,
+                    _ => half,
+                },
+            )
+            .collect();
+
+        frame.colors = Some(colors);
+
+        // Pixels blended with the previous frame change too.
+        frame.dirty = Some(Region {
+            x: 0,
+            y: 0,
+            width: frame.width as usize,
+            height: frame.height as usize,
+        });
+
+        self.previous.clone_from(&frame.pixels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::FlashFilter;
+    use crate::emulator::DisplayFrame;
+    use crate::palette::Palette;
+
+    fn frame(lit: bool) -> DisplayFrame {
+        DisplayFrame {
+            pixels: vec![if lit { 0xFF } else { 0x00 }; 8 * 4],
+            width: 64,
+            height: 4,
+            dirty: None,
+            colors: None,
+        }
+    }
+
+    #[test]
+    fn test_flicker_is_blended_and_flashes_are_capped() {
+        let palette = Palette::default();
+
+        let mut filter = FlashFilter::default();
+
+        let start = Instant::now();
+
+        let mut shown = Vec::new();
+
+        // The whole display flashing on and off every frame for a second.
+        for index in 0..60 {
+            let mut frame = frame(index % 2 == 1);
+
+            filter.apply(
+                &mut frame,
+                palette,
+                start + Duration::from_millis(index * 16),
+            );
+
+            shown.push(frame.colors.unwrap()[0]);
+        }
+
+        assert_eq!(shown[0], palette.background);
+        assert_eq!(
+            shown[1],
+            palette.mix(0.5),
+            "A change should be blended with the previous frame!"
+        );
+
+        let changes = shown.windows(2).filter(|pair| pair[0] != pair[1]).count();
+
+        assert!(
+            changes <= 6,
+            "At most 3 flashes, each changing the picture twice, should get through, not {}!",
+            changes
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use winit::keyboard::{Key, NamedKey};
+
+use crate::key_map::KeyMap;
+use crate::overlay::KEYPAD_LAYOUT;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hotkey {
+    Menu,
+    Help,
+    Pause,
+    SlowMotion,
+    FrameAdvance,
+    PerfOverlay,
+    KeypadOverlay,
+    TimerOverlay,
+    ResetTimer,
+    PixelGrid,
+    Filter,
+    DumpDisplay,
+    Mute,
+    SpriteViewer,
+}
+
+enum Binding {
+    // A letter, with or without Shift.
+    Letter(&'static str),
+    // Exactly this character, e.g. an upper case letter for Shift and the
+    // letter.
+    Character(&'static str),
+    Named(NamedKey),
+}
+
+impl Binding {
+    fn matches(&self, key: &Key) -> bool {
+        match (self, key) {
+            (Binding::Letter(letter), Key::Character(character)) => {
+                character.eq_ignore_ascii_case(letter)
+            }
+            (Binding::Character(expected), Key::Character(character)) => character == expected,
+            (Binding::Named(expected), Key::Named(named)) => named == expected,
+            _ => false,
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Binding::Letter(letter) => letter.to_uppercase(),
+            Binding::Character(character) if character.chars().all(char::is_uppercase) => {
+                format!("Shift+{}", character)
+            }
+            Binding::Character(character) => character.to_string(),
+            Binding::Named(named) => key_label(&Key::Named(*named)),
+        }
+    }
+}
+
+// Every hotkey, the key for it and what it does, in the order the help
+// lists them. The first binding that matches a key wins.
+const HOTKEYS: [(Hotkey, Binding, &str); 14] = [
+    (Hotkey::Menu, Binding::Named(NamedKey::Escape), "Menu"),
+    (Hotkey::Help, Binding::Character("?"), "Keys"),
+    (Hotkey::Pause, Binding::Letter("p"), "Pause"),
+    (Hotkey::FrameAdvance, Binding::Letter("n"), "Frame advance"),
+    // Shift+M, ahead of M on its own for slow motion.
+    (Hotkey::Mute, Binding::Character("M"), "Mute"),
+    (Hotkey::SlowMotion, Binding::Letter("m"), "Slow motion"),
+    (Hotkey::ResetTimer, Binding::Letter("t"), "Reset timer"),
+    (Hotkey::PixelGrid, Binding::Letter("g"), "Pixel grid"),
+    (
+        Hotkey::PerfOverlay,
+        Binding::Named(NamedKey::F3),
+        "Performance",
+    ),
+    (
+        Hotkey::KeypadOverlay,
+        Binding::Named(NamedKey::F4),
+        "Keypad",
+    ),
+    (
+        Hotkey::SpriteViewer,
+        Binding::Named(NamedKey::F6),
+        "Sprites",
+    ),
+    (Hotkey::TimerOverlay, Binding::Named(NamedKey::F7), "Timer"),
+    (Hotkey::Filter, Binding::Named(NamedKey::F8), "Scaling"),
+    (
+        Hotkey::DumpDisplay,
+        Binding::Named(NamedKey::F12),
+        "Dump display",
+    ),
+];
+
+pub fn hotkey(key: &Key) -> Option<Hotkey> {
+    HOTKEYS
+        .iter()
+        .find(|(_, binding, _)| binding.matches(key))
+        .map(|&(hotkey, _, _)| hotkey)
+}
+
+// The help the `?` hotkey shows, as columns of lines: the hotkeys and the
+// fast-forward key, then the keyboard keys for the keypad, laid out as it is.
+pub fn help(keys: &KeyMap, turbo_key: &Key) -> Vec<Vec<String>> {
+    let bindings = HOTKEYS
+        .iter()
+        .map(|(_, binding, description)| (binding.label(), *description))
+        .chain([(key_label(turbo_key), "Fast forward")]);
+
+    let hotkeys = ["Keys".to_string(), String::new()]
+        .into_iter()
+        .chain(bindings.map(|(label, description)| format!("{:<8} {}", label, description)))
+        .collect();
+
+    let keypad = KEYPAD_LAYOUT.iter().map(|row| {
+        let keypad_keys: Vec<String> = row.iter().map(|key| format!("{:X}", key)).collect();
+        let keyboard_keys: Vec<String> = row
+            .iter()
+            .map(|&key| keys.key_name(key).to_uppercase().to_string())
+            .collect();
+
+        format!("{}  =  {}", keypad_keys.join(" "), keyboard_keys.join(" "))
+    });
+
+    let keypad = ["Keypad".to_string(), String::new()]
+        .into_iter()
+        .chain(keypad)
+        .collect();
+
+    vec![hotkeys, keypad]
+}
+
+fn key_label(key: &Key) -> String {
+    match key {
+        Key::Character(character) => character.to_uppercase(),
+        Key::Named(NamedKey::Escape) => "Esc".to_string(),
+        Key::Named(named) => format!("{:?}", named),
+        _ => "?".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::{Key, NamedKey};
+
+    use super::{help, hotkey, Hotkey};
+    use crate::key_map::KeyMap;
+
+    #[test]
+    fn test_keys_pick_their_hotkeys() {
+        let character = |text: &str| Key::Character(text.into());
+
+        assert_eq!(hotkey(&character("p")), Some(Hotkey::Pause));
+        assert_eq!(hotkey(&character("P")), Some(Hotkey::Pause));
+        assert_eq!(hotkey(&character("m")), Some(Hotkey::SlowMotion));
+        assert_eq!(
+            hotkey(&character("M")),
+            Some(Hotkey::Mute),
+            "Shift+M should mute rather than slow down!"
+        );
+        assert_eq!(hotkey(&character("?")), Some(Hotkey::Help));
+        assert_eq!(hotkey(&Key::Named(NamedKey::Escape)), Some(Hotkey::Menu));
+        assert_eq!(hotkey(&character("x")), None);
+    }
+
+    #[test]
+    fn test_help_lists_hotkeys_and_the_keypad_layout() {
+        let columns = help(&KeyMap::default(), &Key::Named(NamedKey::Tab));
+
+        assert!(columns[0].contains(&"Shift+M  Mute".to_string()));
+        assert!(columns[0].contains(&"F8       Scaling".to_string()));
+        assert!(columns[0].contains(&"Tab      Fast forward".to_string()));
+
+        assert_eq!(
+            columns[1][2..],
+            [
+                "1 2 3 C  =  1 2 3 4",
+                "4 5 6 D  =  Q W E R",
+                "7 8 9 E  =  A S D F",
+                "A 0 B F  =  Z X C V",
+            ]
+        );
+
+        let layout = KeyMap::parse("0123456789abcdef").unwrap();
+
+        assert_eq!(
+            help(&layout, &Key::Named(NamedKey::Tab))[1][2],
+            "1 2 3 C  =  1 2 3 C",
+            "The keypad should follow the key layout!"
+        );
+    }
+}
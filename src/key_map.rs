@@ -0,0 +1,174 @@
+use std::fmt;
+
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+// Keyboard keys by the character they carry on a US QWERTY keyboard.
+const KEYBOARD: [(char, KeyCode); 45] = [
+    ('0', KeyCode::Digit0),
+    ('1', KeyCode::Digit1),
+    ('2', KeyCode::Digit2),
+    ('3', KeyCode::Digit3),
+    ('4', KeyCode::Digit4),
+    ('5', KeyCode::Digit5),
+    ('6', KeyCode::Digit6),
+    ('7', KeyCode::Digit7),
+    ('8', KeyCode::Digit8),
+    ('9', KeyCode::Digit9),
+    ('a', KeyCode::KeyA),
+    ('b', KeyCode::KeyB),
+    ('c', KeyCode::KeyC),
+    ('d', KeyCode::KeyD),
+    ('e', KeyCode::KeyE),
+    ('f', KeyCode::KeyF),
+    ('g', KeyCode::KeyG),
+    ('h', KeyCode::KeyH),
+    ('i', KeyCode::KeyI),
+    ('j', KeyCode::KeyJ),
+    ('k', KeyCode::KeyK),
+    ('l', KeyCode::KeyL),
+    ('m', KeyCode::KeyM),
+    ('n', KeyCode::KeyN),
+    ('o', KeyCode::KeyO),
+    ('p', KeyCode::KeyP),
+    ('q', KeyCode::KeyQ),
+    ('r', KeyCode::KeyR),
+    ('s', KeyCode::KeyS),
+    ('t', KeyCode::KeyT),
+    ('u', KeyCode::KeyU),
+    ('v', KeyCode::KeyV),
+    ('w', KeyCode::KeyW),
+    ('x', KeyCode::KeyX),
+    ('y', KeyCode::KeyY),
+    ('z', KeyCode::KeyZ),
+    ('-', KeyCode::Minus),
+    ('=', KeyCode::Equal),
+    ('[', KeyCode::BracketLeft),
+    (']', KeyCode::BracketRight),
+    (';', KeyCode::Semicolon),
+    ('\'', KeyCode::Quote),
+    (',', KeyCode::Comma),
+    ('.', KeyCode::Period),
+    ('/', KeyCode::Slash),
+];
+
+// The conventional layout mapping the COSMAC VIP hex keypad onto the left
+// side of the keyboard, keypad keys 0 to F in order:
+//
+//   1 2 3 C      1 2 3 4
+//   4 5 6 D  ->  Q W E R
+//   7 8 9 E      A S D F
+//   A 0 B F      Z X C V
+const DEFAULT_LAYOUT: &str = "x123qweasdzc4rfv";
+
+// The keyboard key for each keypad key. Keys are matched by physical
+// position, so a layout is the same on AZERTY, QWERTZ or Dvorak as it is on
+// QWERTY, where it's written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyMap {
+    keys: [KeyCode; 16],
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::parse(DEFAULT_LAYOUT).expect("The default layout is valid")
+    }
+}
+
+impl KeyMap {
+    // Sixteen keys, one for each keypad key from 0 to F, such as
+    // "x123qweasdzc4rfv".
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let characters: Vec<char> = value.to_lowercase().chars().collect();
+
+        if characters.len() != 16 {
+            return Err(format!(
+                "Invalid key layout '{}', expected 16 keys for keypad keys 0 to F",
+                value
+            ));
+        }
+
+        let mut keys = [KeyCode::Digit0; 16];
+
+        for (index, &character) in characters.iter().enumerate() {
+            if characters[..index].contains(&character) {
+                return Err(format!("Key '{}' is used twice in the layout", character));
+            }
+
+            keys[index] = KEYBOARD
+                .iter()
+                .find(|(name, _)| *name == character)
+                .map(|&(_, code)| code)
+                .ok_or_else(|| format!("Unknown key '{}' in the layout", character))?;
+        }
+
+        Ok(Self { keys })
+    }
+
+    pub fn keypad_key(&self, key: PhysicalKey) -> Option<u8> {
+        let code = match key {
+            PhysicalKey::Code(code) => code,
+            PhysicalKey::Unidentified(_) => return None,
+        };
+
+        self.keys
+            .iter()
+            .position(|&mapped| mapped == code)
+            .map(|key| key as u8)
+    }
+
+    // The keyboard key for a keypad key, as written in a layout.
+    pub fn key_name(&self, keypad_key: u8) -> char {
+        let (character, _) = KEYBOARD
+            .iter()
+            .find(|&&(_, code)| code == self.keys[keypad_key as usize])
+            .expect("Every mapped key is on the keyboard");
+
+        *character
+    }
+}
+
+impl fmt::Display for KeyMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for key in 0..16 {
+            write!(f, "{}", self.key_name(key))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::{KeyCode, PhysicalKey};
+
+    use super::KeyMap;
+
+    #[test]
+    fn test_key_layouts() {
+        let default = KeyMap::default();
+
+        assert_eq!(
+            default.keypad_key(PhysicalKey::Code(KeyCode::KeyX)),
+            Some(0x0)
+        );
+        assert_eq!(
+            default.keypad_key(PhysicalKey::Code(KeyCode::Digit4)),
+            Some(0xC)
+        );
+        assert_eq!(default.keypad_key(PhysicalKey::Code(KeyCode::KeyG)), None);
+
+        let custom = KeyMap::parse("0123ijkl456789ab").unwrap();
+
+        assert_eq!(
+            custom.keypad_key(PhysicalKey::Code(KeyCode::KeyK)),
+            Some(0x6)
+        );
+        assert_eq!(custom.to_string(), "0123ijkl456789ab");
+
+        assert!(KeyMap::parse("x123").is_err());
+        assert!(
+            KeyMap::parse("xx23qweasdzc4rfv").is_err(),
+            "A key should not be mapped twice!"
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod chip8;
+
+#[cfg(target_arch = "wasm32")]
+pub mod web;
@@ -0,0 +1,28 @@
+use std::io;
+
+use tracing_subscriber::EnvFilter;
+
+// Startup details, warnings and errors, but nothing logged per frame or per
+// instruction.
+const DEFAULT_FILTER: &str = "info";
+
+// Sends log events to stderr, filtered by `--log-level` or else RUST_LOG,
+// both in the env-filter syntax: a level such as `debug`, or levels per
+// module such as `info,chip8_vm::chip8::interpreter=trace`.
+pub fn init(log_level: Option<&str>) -> Result<(), String> {
+    let filter = match log_level {
+        Some(filter) => EnvFilter::try_new(filter)
+            .map_err(|error| format!("Invalid log level {}: {}", filter, error))?,
+        None => {
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER))
+        }
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .without_time()
+        .init();
+
+    Ok(())
+}
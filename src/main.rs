@@ -1,231 +1,2578 @@
-mod chip8;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use winit::application::ApplicationHandler;
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, KeyEvent, MouseButton, Touch, TouchPhase, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
+use winit::keyboard::Key;
+use winit::raw_window_handle::DisplayHandle;
+use winit::raw_window_handle::HasDisplayHandle;
+use winit::window::{Fullscreen, Window, WindowId, WindowLevel};
+
+use softbuffer::{Context, Rect, Surface};
+
+use tracing::{debug, error, info, trace, warn};
+
+use chip8_vm::chip8::disassembler::Syntax;
+use chip8_vm::chip8::self_test::run_self_test;
+use chip8_vm::chip8::trace::TraceLine;
+use chip8_vm::chip8::{DumpFormat, Frame, Interpreter, InterpreterBuilder, Quirks, Region};
+
+use console::{DebugConsole, SourceMap};
+use crt::{CrtEffect, CrtSettings};
+use emulator::{
+    DisplayFrame, Emulator, EmulatorSettings, EmulatorStatus, FrameScript, Recorder, UserEvent,
+};
+use flash::FlashFilter;
+use hotkeys::{hotkey, Hotkey};
+use key_map::KeyMap;
+use menu::{MenuAction, PauseMenu};
+use options::Options;
+use overlay::{
+    draw_sound_indicator, draw_text_columns, Canvas, KeypadOverlay, PerfOverlay, TimerOverlay,
+    Toasts,
+};
+use palette::Palette;
+use recent::RecentRoms;
+use remote::{RemoteCommand, RemoteResponse};
+use rom_prefs::{RomPrefs, RomSettings};
+use rom_source::RomSource;
+use rpl::RplFlags;
+use scaling::ScalingFilter;
+use sprite_viewer::SpriteViewer;
+use stream::StreamServer;
+use touch::{TouchState, TouchZones};
+use video::VideoRecorder;
+use watcher::RomWatcher;
+use wav::WavRecorder;
+use window_layout::WindowLayout;
+
+#[cfg(feature = "audio")]
+mod audio;
+mod config;
+mod console;
+mod crt;
+mod demo;
+mod download;
+mod emulator;
+mod flash;
+mod hotkeys;
+mod key_map;
+mod logging;
+mod menu;
+mod octo_options;
+mod options;
+mod overlay;
+mod palette;
+mod prompt;
+mod recent;
+mod remote;
+mod rom_prefs;
+mod rom_source;
+mod rpl;
+mod scaling;
+#[cfg(feature = "scripting")]
+mod script;
+mod sprite_viewer;
+mod stream;
+mod touch;
+mod video;
+mod watcher;
+mod wav;
+mod window_layout;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let mut options = match parse_options(&args[1..]) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("Usage: {} {}", args[0], options::USAGE);
+
+            std::process::exit(1);
+        }
+    };
+
+    logging::init(options.log_level.as_deref())?;
+
+    if options.self_test {
+        return self_test(&options);
+    }
+
+    let mut recent_roms = RecentRoms::load();
+
+    // Without a ROM on the command line, offer to reopen a recent one, or
+    // show the demo if there are none yet.
+    if options.rom_paths.is_empty() && !recent_roms.entries().is_empty() {
+        let choice = prompt::choose("Recent ROMs:", recent_roms.entries())?;
+
+        options.rom_paths = vec![recent_roms.entries()[choice].clone()];
+    }
+
+    let sources = if options.rom_paths.is_empty() {
+        vec![RomSource::Demo]
+    } else {
+        options
+            .rom_paths
+            .iter()
+            .map(|rom_path| RomSource::parse(rom_path))
+            .collect::<Result<_, _>>()?
+    };
+
+    let mut roms = Vec::new();
+
+    for source in sources {
+        let builder = options.interpreter_builder()?;
+
+        let (interpreter, rpl_flags, prefs) = load_rom(&source, &builder)?;
+
+        roms.push(Rom {
+            source,
+            builder,
+            interpreter,
+            rpl_flags,
+            prefs,
+        });
+    }
+
+    if let Some(path) = &options.compare_trace {
+        let reference = TraceLine::parse_log(&fs::read_to_string(path)?)
+            .map_err(|error| format!("{}: {}", path, error))?;
+
+        return compare_trace(&mut roms[0], &reference);
+    }
+
+    if let Some(cycles) = options.frame_hash_cycles {
+        let show_paths = roms.len() > 1;
+
+        let mut trace_output = match &options.trace_file {
+            Some(path) => Some(BufWriter::new(File::create(path)?)),
+            None => None,
+        };
+
+        let mut coverage_output = match &options.coverage_file {
+            Some(path) => Some(BufWriter::new(File::create(path)?)),
+            None => None,
+        };
+
+        let mut dump_output = match (options.dump_after_run, &options.dump_file) {
+            (false, _) => None,
+            (true, Some(path)) => Some(Box::new(File::create(path)?) as Box<dyn Write>),
+            (true, None) => Some(Box::new(io::stdout()) as Box<dyn Write>),
+        };
+
+        for mut rom in roms {
+            let result = run_headless(&mut rom.interpreter, cycles, |_, interpreter| {
+                if let Some(output) = &mut trace_output {
+                    writeln!(output, "{}", TraceLine::capture(interpreter))
+                        .map_err(|error| format!("Failed to write the trace: {}", error))?;
+                }
+
+                Ok(())
+            });
+
+            let whole_frames = match result {
+                Ok(whole_frames) => whole_frames,
+                Err(error) => {
+                    return Err(format!(
+                        "{}: {}",
+                        rom.source,
+                        emulator::write_crash_dump(&rom.interpreter, error)
+                    )
+                    .into())
+                }
+            };
+
+            for &(address, target) in rom.interpreter.system_calls() {
+                warn!(
+                    "{}: {}",
+                    rom.source,
+                    emulator::system_call_warning(address, target)
+                );
+            }
+
+            let hash = rom.interpreter.display_hash();
+
+            if show_paths {
+                println!("{:016x}  {}", hash, rom.source);
+            } else {
+                println!("{:016x}", hash);
+            }
+
+            if let Some(output) = &mut dump_output {
+                output.write_all(&options.dump_format.dump(rom.interpreter.frame()))?;
+            }
+
+            if let Some(output) = &mut coverage_output {
+                write_coverage(output, &rom.source.to_string(), &rom.interpreter)?;
+            }
+
+            // Like a window closing, each ROM's replay replaces the last.
+            if let (Some(path), Some(replay)) = (&options.replay_file, rom.interpreter.replay()) {
+                if !whole_frames {
+                    warn!(
+                        "{}: the run ended partway through a frame, so its replay won't verify",
+                        rom.source
+                    );
+                }
+
+                fs::write(path, replay.to_string())?;
+            }
+        }
+
+        if let Some(output) = &mut trace_output {
+            output.flush()?;
+        }
+
+        if let Some(output) = &mut coverage_output {
+            output.flush()?;
+        }
+
+        return Ok(());
+    }
+
+    for rom in &roms {
+        info!("Rom file: {}", rom.source);
+
+        if let Some(entry) = rom.source.recent_entry() {
+            recent_roms.add(&entry);
+        }
+    }
+
+    // Report mistakes in the script before any window opens.
+    if let Some(path) = &options.script {
+        emulator::load_script(path)?;
+    }
+
+    if let Err(error) = recent_roms.save() {
+        warn!("Failed to save recent ROMs: {}", error);
+    }
+
+    info!("Debug mode: {}", options.debug);
+
+    if options.debug {
+        println!("Type 'help' for debug console commands");
+    }
+
+    info!("Deterministic mode: {}", options.deterministic);
+    info!("Platform: {}", options.platform.name());
+
+    let event_loop = EventLoop::<UserEvent>::with_user_event().build()?;
+
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    if let Some(address) = &options.remote {
+        remote::spawn_server(address, event_loop.create_proxy())?;
+
+        info!("Remote control: http://{}", address);
+    }
+
+    let stream = match &options.stream {
+        Some(address) => {
+            let server = StreamServer::listen(address, event_loop.create_proxy())?;
+
+            info!("Streaming the display on {}", address);
+
+            Some(server)
+        }
+        None => None,
+    };
+
+    let mut app = Application::new(&event_loop, roms, &options, &args[1..], stream);
+
+    event_loop.run_app(&mut app).map_err(Into::into)
+}
+
+// The options from the config file followed by the command line's, so the
+// command line wins.
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut all_args = match config::config_file() {
+        Some(path) => config::read_options(&path)?,
+        None => Vec::new(),
+    };
+
+    all_args.extend_from_slice(args);
+
+    Options::parse(&all_args)
+}
+
+// Headless mode: run the ROM for a fixed number of cycles, after which the
+// display is hashed (and optionally dumped) so the output can be compared
+// against a known-good value. `before_cycle` sees the state before each
+// instruction, for traces. Returns whether the run ended between frames, as a
+// replay of it needs to.
+fn run_headless(
+    interpreter: &mut Interpreter,
+    cycles: u64,
+    mut before_cycle: impl FnMut(u64, &Interpreter) -> Result<(), String>,
+) -> Result<bool, String> {
+    // Otherwise the cycles would never be used up.
+    if interpreter.max_frame_instructions() == 0 {
+        return Err("Headless runs need at least one instruction per frame".to_string());
+    }
+
+    let mut cycle = 0;
+
+    let mut cut_short = false;
+
+    // Frames run as they would in a window, under the same timing and
+    // limits, with the last cut short once the cycles are used up.
+    while cycle < cycles && !interpreter.exited() {
+        interpreter.try_run_frame_with(|interpreter| {
+            if cycle == cycles || interpreter.exited() {
+                cut_short = !interpreter.exited();
+
+                return Ok(false);
+            }
+
+            before_cycle(cycle, interpreter)?;
+
+            cycle += 1;
+
+            Ok(true)
+        })?;
+    }
+
+    Ok(!cut_short)
+}
+
+// Prints how each opcode group fared, failing if any of them did.
+fn self_test(options: &Options) -> Result<(), Box<dyn Error>> {
+    let builder = options.interpreter_builder()?;
+
+    println!(
+        "Platform: {}, quirks: {}",
+        options.platform.name(),
+        options.quirks()?.to_list()
+    );
+    println!();
+
+    let results = run_self_test(&builder)?;
+
+    for result in &results {
+        if result.passed() {
+            let checks = if result.passed == 1 {
+                "check"
+            } else {
+                "checks"
+            };
+
+            println!("pass  {:<16}{} {}", result.group, result.passed, checks);
+
+            continue;
+        }
+
+        println!("FAIL  {}", result.group);
+
+        for (name, error) in &result.failures {
+            println!("      {}: {}", name, error);
+        }
+    }
+
+    println!();
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+
+    if failed > 0 {
+        return Err(format!("{} of {} opcode groups failed", failed, results.len()).into());
+    }
+
+    println!("All {} opcode groups passed", results.len());
+
+    Ok(())
+}
+
+// How many matching lines to show before a divergence.
+const TRACE_CONTEXT: usize = 5;
+
+// Runs the ROM headlessly against a reference trace, stopping at the first
+// instruction whose state differs.
+fn compare_trace(rom: &mut Rom, reference: &[TraceLine]) -> Result<(), Box<dyn Error>> {
+    let mut recent = VecDeque::with_capacity(TRACE_CONTEXT);
+
+    let mut diverged = false;
+
+    let result = run_headless(
+        &mut rom.interpreter,
+        reference.len() as u64,
+        |cycle, interpreter| {
+            let actual = TraceLine::capture(interpreter);
+
+            let expected = &reference[cycle as usize];
+
+            let mismatches = expected.mismatches(&actual);
+
+            if mismatches.is_empty() {
+                if recent.len() == TRACE_CONTEXT {
+                    recent.pop_front();
+                }
+
+                recent.push_back((cycle, actual));
+
+                return Ok(());
+            }
+
+            diverged = true;
+
+            for (cycle, line) in &recent {
+                println!("  {:>8}  {}", cycle + 1, line);
+            }
+
+            println!("- {:>8}  {}", cycle + 1, expected);
+            println!("+ {:>8}  {}", cycle + 1, actual);
+
+            for mismatch in mismatches {
+                println!("  {}", mismatch);
+            }
+
+            Err(format!("Trace diverged at instruction {}", cycle + 1))
+        },
+    );
+
+    match result {
+        Ok(_) => {
+            println!("Trace matches all {} instructions", reference.len());
+
+            Ok(())
+        }
+        Err(error) if diverged => Err(error.into()),
+        Err(error) => Err(format!(
+            "{}: {}",
+            rom.source,
+            emulator::write_crash_dump(&rom.interpreter, error)
+        )
+        .into()),
+    }
+}
+
+struct Rom {
+    source: RomSource,
+    // Kept so the ROM can be reloaded into an identically configured
+    // interpreter.
+    builder: InterpreterBuilder,
+    interpreter: Interpreter,
+    // Only used in a window; headless runs start with cleared flags and the
+    // settings they're given.
+    rpl_flags: Option<RplFlags>,
+    prefs: Option<RomPrefs>,
+}
+
+fn load_rom(
+    source: &RomSource,
+    builder: &InterpreterBuilder,
+) -> Result<(Interpreter, Option<RplFlags>, Option<RomPrefs>), String> {
+    let rom_data = source.read()?;
+
+    let mut interpreter = builder.clone().build()?;
+
+    interpreter.load_program(&rom_data)?;
+
+    Ok((
+        interpreter,
+        RplFlags::for_rom(&rom_data),
+        RomPrefs::for_rom(&rom_data, octo_options::args_for(source)),
+    ))
+}
+
+// The settings from a ROM's Octo options and those remembered for it, read
+// between the config file's options and the command line's.
+fn rom_settings(
+    prefs: &mut RomPrefs,
+    config_args: &[String],
+    args: &[String],
+) -> Option<RomSettings> {
+    if prefs.args().is_empty() && prefs.project_args().is_empty() {
+        return None;
+    }
+
+    if !prefs.project_args().is_empty() {
+        let defaults = Options::parse(&[config_args, prefs.project_args(), args].concat())
+            .and_then(|options| RomSettings::from_options(&options));
+
+        match defaults {
+            Ok(defaults) => prefs.set_project_defaults(defaults),
+            Err(error) => {
+                warn!("Ignoring the ROM's Octo options: {}", error);
+
+                return None;
+            }
+        }
+    }
+
+    let options = Options::parse(&[config_args, prefs.project_args(), prefs.args(), args].concat())
+        .and_then(|options| RomSettings::from_options(&options));
+
+    match options {
+        Ok(settings) => Some(settings),
+        Err(error) => {
+            warn!("Ignoring the ROM's saved settings: {}", error);
+
+            None
+        }
+    }
+}
+
+// The options' interpreter builder with a ROM's own quirks and instructions
+// per frame on top, for resets and reloads to keep them.
+fn rom_builder(builder: &InterpreterBuilder, settings: Option<RomSettings>) -> InterpreterBuilder {
+    let mut builder = builder.clone();
+
+    if let Some(settings) = settings {
+        builder = builder.quirks(settings.quirks);
+
+        if let Some(instructions_per_frame) = settings.instructions_per_frame {
+            builder = builder.instructions_per_frame(instructions_per_frame);
+        }
+    }
+
+    builder
+}
+
+// Only ROMs assembled from a local file have debug info next to them.
+fn load_source_map(source: &RomSource) -> Option<SourceMap> {
+    match source {
+        RomSource::File(path) => SourceMap::load(path),
+        _ => None,
+    }
+}
+
+fn write_coverage(
+    output: &mut impl Write,
+    rom_name: &str,
+    interpreter: &Interpreter,
+) -> io::Result<()> {
+    let coverage = match interpreter.coverage() {
+        Some(coverage) => coverage,
+        None => return Ok(()),
+    };
+
+    let syntax = Syntax::default();
+
+    writeln!(output, "{} Rom file: {}", syntax.comment(), rom_name)?;
+
+    coverage.write_listing(output, syntax)
+}
+
+// Every window runs its own copy of the script, started afresh on reload.
+fn start_script(path: Option<&Path>) -> Option<Box<dyn FrameScript>> {
+    match emulator::load_script(path?) {
+        Ok(script) => Some(script),
+        Err(error) => {
+            warn!("Not running the script: {}", error);
+
+            None
+        }
+    }
+}
+
+fn same_key(pressed: &Key, configured: &Key) -> bool {
+    match (pressed, configured) {
+        (Key::Character(pressed), Key::Character(configured)) => {
+            pressed.to_lowercase() == configured.as_str()
+        }
+        _ => pressed == configured,
+    }
+}
+
+// Slow-motion steps cycled through by the hotkey, after which it returns to
+// full speed.
+const SLOW_MOTION_SPEEDS: [u32; 3] = [50, 25, 10];
+
+struct WindowState {
+    surface: Surface<DisplayHandle<'static>, Arc<Window>>,
+    window: Arc<Window>,
+    rom_name: String,
+    rom_source: RomSource,
+    // Canonical path of the ROM, when it is being watched for changes.
+    watched_path: Option<PathBuf>,
+    builder: InterpreterBuilder,
+    status: Option<EmulatorStatus>,
+    error: Option<String>,
+    exited: bool,
+    perf_overlay: PerfOverlay,
+    keypad_overlay: KeypadOverlay,
+    timer_overlay: TimerOverlay,
+    // Open while Escape's menu is up, taking every key.
+    menu: Option<PauseMenu>,
+    // The key bindings, in columns, while `?` shows them.
+    help: Option<Vec<Vec<String>>>,
+    cursor_position: PhysicalPosition<f64>,
+    // Keypad key held down with the mouse, released with the button.
+    clicked_key: Option<u8>,
+    touches: TouchState,
+    toasts: Toasts,
+    // Set when the pause came from losing focus rather than the user, so
+    // regaining focus only resumes what it paused.
+    focus_paused: bool,
+    // The newest frame from the emulator, kept so the window can be redrawn
+    // at any time.
+    frame: Option<DisplayFrame>,
+    // Set when the next draw has to repaint everything, e.g. because the last
+    // one had an overlay on it.
+    redraw_all: bool,
+    pixel_grid: bool,
+    filter: ScalingFilter,
+    sound_indicator: bool,
+    palette: Palette,
+    flash_filter: Option<FlashFilter>,
+    keys: KeyMap,
+    // Where the settings used with the ROM are remembered.
+    prefs: Option<RomPrefs>,
+    dump_format: DumpFormat,
+    dump_file: Option<String>,
+    // The window's last known scale factor, to tell how big a pixel was in
+    // logical units when it changes.
+    scale_factor: f64,
+    snap_resize: bool,
+    // Set while waiting for the resize that answers a snap.
+    snap_requested: bool,
+    crt: Option<CrtEffect>,
+    // The undistorted picture the CRT effect samples from.
+    crt_source: Vec<u32>,
+    // For ROMs assembled with --debug-info, read again on reload.
+    source_map: Option<SourceMap>,
+    emulator: Emulator,
+}
+
+impl WindowState {
+    fn new(
+        app: &Application,
+        window: Window,
+        rom_source: RomSource,
+        builder: InterpreterBuilder,
+        emulator: Emulator,
+    ) -> Result<Self, Box<dyn Error>> {
+        let rom_name = rom_source.name();
+
+        let source_map = load_source_map(&rom_source);
+
+        let window = Arc::new(window);
+
+        let surface = Surface::new(app.context.as_ref().unwrap(), Arc::clone(&window))?;
+
+        // TODO: add a cursor?
+
+        // TODO: Do I need to handle IME?
+
+        let size = window.inner_size();
+
+        let scale_factor = window.scale_factor();
+
+        let mut state = Self {
+            surface,
+            window,
+            rom_name,
+            rom_source,
+            watched_path: None,
+            builder,
+            status: None,
+            error: None,
+            exited: false,
+            perf_overlay: PerfOverlay::default(),
+            keypad_overlay: KeypadOverlay::default(),
+            timer_overlay: TimerOverlay::new(app.timer),
+            menu: None,
+            help: None,
+            cursor_position: PhysicalPosition::default(),
+            clicked_key: None,
+            touches: TouchState::default(),
+            toasts: Toasts::default(),
+            focus_paused: false,
+            frame: None,
+            redraw_all: false,
+            pixel_grid: app.pixel_grid,
+            filter: app.filter,
+            sound_indicator: app.sound_indicator,
+            palette: app.palette,
+            flash_filter: app.reduce_flashing.then(FlashFilter::default),
+            keys: app.keys,
+            prefs: None,
+            dump_format: app.dump_format,
+            dump_file: app.dump_file.clone(),
+            scale_factor,
+            snap_resize: app.snap_resize,
+            snap_requested: false,
+            crt: app.crt.map(CrtEffect::new),
+            crt_source: Vec::new(),
+            source_map,
+            emulator,
+        };
+
+        state.update_title();
+
+        state.resize(size);
+
+        Ok(state)
+    }
+
+    // e.g. "Chip8 — pong.ch8 — 60fps / 600ips — paused"
+    fn update_title(&self) {
+        let mut title = format!("Chip8 — {}", self.rom_name);
+
+        if let Some(error) = &self.error {
+            title += &format!(" — Error: {}", error);
+        } else if self.exited {
+            title += " — exited";
+        } else {
+            if let Some(status) = self.status {
+                title += &format!(
+                    " — {}fps / {}ips",
+                    status.frames_per_second, status.instructions_per_second
+                );
+            }
+
+            if self.emulator.speed_percent() != 100 {
+                title += &format!(" — {}% speed", self.emulator.speed_percent());
+            }
+
+            if self.emulator.is_paused() {
+                title += " — paused";
+            }
+
+            if self.emulator.is_muted() {
+                title += " — muted";
+            }
+        }
+
+        self.window.set_title(&title);
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) {
+        match hotkey {
+            Hotkey::Pause => {
+                let paused = !self.emulator.is_paused();
+
+                self.emulator.set_paused(paused);
+
+                self.toasts.show(if paused { "Paused" } else { "Resumed" });
+
+                self.update_title();
+            }
+
+            Hotkey::Mute => {
+                let muted = !self.emulator.is_muted();
+
+                self.emulator.set_muted(muted);
+
+                self.toasts.show(if muted { "Muted" } else { "Unmuted" });
+
+                self.update_title();
+            }
+
+            Hotkey::SlowMotion => {
+                let current = self.emulator.speed_percent();
+
+                let speed_percent = SLOW_MOTION_SPEEDS
+                    .iter()
+                    .copied()
+                    .find(|&speed| speed < current)
+                    .unwrap_or(100);
+
+                self.emulator.set_speed_percent(speed_percent);
+
+                self.toasts.show(format!("Speed {}%", speed_percent));
+
+                self.update_title();
+            }
+
+            // The first press pauses, so holding the key plays the game a
+            // frame per key repeat.
+            Hotkey::FrameAdvance => {
+                if self.emulator.is_paused() {
+                    self.emulator.advance_frame();
+                } else {
+                    self.emulator.set_paused(true);
+
+                    self.toasts.show("Paused, press N to advance a frame");
+
+                    self.update_title();
+                }
+            }
+
+            Hotkey::PerfOverlay => {
+                self.perf_overlay.visible = !self.perf_overlay.visible;
+            }
+
+            Hotkey::KeypadOverlay => {
+                self.keypad_overlay.visible = !self.keypad_overlay.visible;
+            }
+
+            Hotkey::TimerOverlay => {
+                self.timer_overlay.visible = !self.timer_overlay.visible;
+            }
+
+            Hotkey::ResetTimer => {
+                self.timer_overlay.reset(self.emulator.frame_count());
+
+                self.toasts.show("Timer reset");
+            }
+
+            Hotkey::PixelGrid => {
+                self.pixel_grid = !self.pixel_grid;
+
+                self.redraw_all = true;
+
+                self.window.request_redraw();
+            }
+
+            Hotkey::Filter => {
+                self.filter = self.filter.toggled();
+
+                self.toasts.show(format!("Scaling: {}", self.filter));
+
+                self.redraw_all = true;
+
+                self.window.request_redraw();
+            }
+
+            Hotkey::DumpDisplay => match self.dump_display() {
+                Ok(()) => self.toasts.show("Display dumped"),
+                Err(error) => self.toasts.show(format!("Dump failed: {}", error)),
+            },
+
+            Hotkey::Menu => self.open_menu(),
+
+            // Opening a window and the turbo key's help take the Application.
+            Hotkey::SpriteViewer | Hotkey::Help => {}
+        }
+    }
+
+    fn toggle_help(&mut self, turbo_key: &Key) {
+        self.help = match self.help {
+            Some(_) => None,
+            None => Some(hotkeys::help(&self.keys, turbo_key)),
+        };
+
+        self.redraw_all = true;
+
+        self.window.request_redraw();
+    }
+
+    // Pauses the ROM under the menu.
+    fn open_menu(&mut self) {
+        let quirks = self
+            .emulator
+            .with_interpreter(|interpreter| interpreter.quirks)
+            .unwrap_or_default();
+
+        self.menu = Some(PauseMenu::new(
+            self.palette,
+            quirks,
+            RecentRoms::load().entries().to_vec(),
+            !self.emulator.is_paused(),
+        ));
+
+        self.emulator.set_paused(true);
+
+        self.update_title();
+
+        self.window.request_redraw();
+    }
+
+    // Resumes the ROM if the menu was what paused it.
+    fn close_menu(&mut self) {
+        if let Some(menu) = self.menu.take() {
+            if menu.was_running {
+                self.emulator.set_paused(false);
+            }
+        }
+
+        self.redraw_all = true;
+
+        self.update_title();
+
+        self.window.request_redraw();
+    }
+
+    // Takes on the settings last used with the ROM, apart from the quirks and
+    // instructions per frame, which go to the interpreter before it starts.
+    fn apply_rom_settings(&mut self, settings: RomSettings) {
+        self.emulator.set_speed_percent(settings.speed_percent);
+
+        self.palette = settings.palette;
+        self.keys = settings.keys;
+
+        self.redraw_all = true;
+
+        self.update_title();
+    }
+
+    fn layout(&self) -> WindowLayout {
+        let size = self.window.inner_size();
+
+        WindowLayout {
+            position: self
+                .window
+                .outer_position()
+                .ok()
+                .map(|position| (position.x, position.y)),
+            size: Some((size.width, size.height)),
+            maximized: self.window.is_maximized(),
+            fullscreen: self.window.fullscreen().is_some(),
+            perf_overlay: self.perf_overlay.visible,
+            keypad_overlay: self.keypad_overlay.visible,
+            timer_overlay: self.timer_overlay.visible,
+            pixel_grid: self.pixel_grid,
+        }
+    }
+
+    // The overlays come back as they were left, on top of any the options
+    // turn on.
+    fn apply_layout(&mut self, layout: WindowLayout) {
+        self.perf_overlay.visible |= layout.perf_overlay;
+        self.keypad_overlay.visible |= layout.keypad_overlay;
+        self.timer_overlay.visible |= layout.timer_overlay;
+        self.pixel_grid |= layout.pixel_grid;
+    }
+
+    // Reloads the window when its ROM's file changes, if it has one.
+    fn watch_rom(&mut self, watcher: Option<&mut RomWatcher>) {
+        self.watched_path = None;
+
+        if let (Some(watcher), Some(path)) = (watcher, self.rom_source.local_path()) {
+            match watcher.watch(path) {
+                Ok(path) => self.watched_path = Some(path),
+                Err(error) => warn!("Not watching {}: {}", path.display(), error),
+            }
+        }
+    }
+
+    fn save_prefs(&mut self, quirks: Quirks, defaults: &RomSettings) {
+        let prefs = match &mut self.prefs {
+            Some(prefs) => prefs,
+            None => return,
+        };
+
+        let settings = RomSettings {
+            speed_percent: self.emulator.speed_percent(),
+            palette: self.palette,
+            quirks,
+            keys: self.keys,
+            instructions_per_frame: defaults.instructions_per_frame,
+        };
+
+        if let Err(error) = prefs.save(&settings, defaults) {
+            warn!(
+                "Failed to save the settings for {}: {}",
+                self.rom_name, error
+            );
+        }
+    }
+
+    fn dump_display(&self) -> io::Result<()> {
+        let frame = match &self.frame {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+
+        let dump = self.dump_format.dump(Frame {
+            pixels: &frame.pixels,
+            width: frame.width as usize,
+            height: frame.height as usize,
+            dirty: None,
+            colors: frame.colors.as_deref(),
+        });
+
+        match &self.dump_file {
+            Some(path) => fs::write(path, dump),
+            None => io::stdout().write_all(&dump),
+        }
+    }
+
+    fn mouse_button(&mut self, state: ElementState) {
+        if let Some(key) = self.clicked_key.take() {
+            self.emulator.keypad.set(key, false);
+        }
+
+        if state == ElementState::Pressed {
+            let size = self.window.inner_size();
+
+            self.clicked_key = self.keypad_overlay.key_at(
+                self.cursor_position.x,
+                self.cursor_position.y,
+                size.width as usize,
+                size.height as usize,
+            );
+
+            if let Some(key) = self.clicked_key {
+                self.emulator.keypad.set(key, true);
+            }
+        }
+    }
+
+    // Touches on the keypad overlay press that key, anywhere else the key
+    // mapped to the touched zone of the window.
+    fn touch(&mut self, touch: Touch, zones: &TouchZones) {
+        let size = self.window.inner_size();
+
+        let key = match touch.phase {
+            TouchPhase::Started | TouchPhase::Moved => self
+                .keypad_overlay
+                .key_at(
+                    touch.location.x,
+                    touch.location.y,
+                    size.width as usize,
+                    size.height as usize,
+                )
+                .or_else(|| {
+                    zones.key_at(touch.location.x, touch.location.y, size.width, size.height)
+                }),
+            TouchPhase::Ended | TouchPhase::Cancelled => None,
+        };
+
+        let (released, pressed) = self.touches.update(touch.id, key);
+
+        if let Some(key) = released {
+            self.emulator.keypad.set(key, false);
+        }
+
+        if let Some(key) = pressed {
+            self.emulator.keypad.set(key, true);
+        }
+    }
+
+    fn focus_changed(&mut self, focused: bool, auto_pause: bool) {
+        // Key releases aren't delivered to unfocused windows.
+        if !focused {
+            self.emulator.keypad.release_all();
+
+            self.emulator.set_turbo(false);
+
+            self.clicked_key = None;
+
+            self.touches.clear();
+        }
+
+        if !focused && auto_pause && !self.emulator.is_paused() {
+            self.emulator.set_paused(true);
+
+            self.focus_paused = true;
+        } else if focused && self.focus_paused {
+            self.emulator.set_paused(false);
+
+            self.focus_paused = false;
+        } else {
+            return;
+        }
+
+        self.update_title();
+    }
+
+    // Keeps the display the same logical size when the window moves to a
+    // monitor with a different scale factor, snapped to whole physical pixels.
+    fn rescaled_size(&mut self, scale_factor: f64) -> PhysicalSize<u32> {
+        let (width, height) = self.display_size();
+
+        let layout = Layout::new(self.window.inner_size(), width, height);
+
+        let logical_scale = layout.scale as f64 / self.scale_factor;
+
+        self.scale_factor = scale_factor;
+
+        pixel_perfect_size(width, height, logical_scale * scale_factor)
+    }
+
+    fn display_size(&self) -> (u32, u32) {
+        self.frame.as_ref().map_or(
+            (
+                Interpreter::DISPLAY_WIDTH as u32,
+                Interpreter::DISPLAY_HEIGHT as u32,
+            ),
+            |frame| (frame.width, frame.height),
+        )
+    }
+
+    // With --snap-resize, asks for the nearest size that's a whole multiple
+    // of the display in physical pixels. The resize that answers isn't
+    // snapped again, so a window manager that won't allow the size (a tiled
+    // or maximized window) can't keep the two going back and forth.
+    fn snap(&mut self, size: PhysicalSize<u32>) {
+        if !self.snap_resize || std::mem::take(&mut self.snap_requested) {
+            return;
+        }
+
+        let (width, height) = self.display_size();
+
+        let scale = (size.width as f64 / width as f64).min(size.height as f64 / height as f64);
+
+        let snapped = pixel_perfect_size(width, height, scale);
+
+        if snapped == size {
+            return;
+        }
+
+        match self.window.request_inner_size(snapped) {
+            Some(applied) => self.resize(applied),
+            None => self.snap_requested = true,
+        }
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        let (width, height) = match (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+            (Some(width), Some(height)) => (width, height),
+            _ => return,
+        };
+
+        debug!("Resize {width} {height}");
+
+        self.surface
+            .resize(width, height)
+            .expect("failed to resize inner buffer");
+
+        self.window.request_redraw();
+    }
+
+    // Several frames may have queued up since the last redraw; keep the
+    // newest, marking everything any of them changed as dirty.
+    fn receive_frames(&mut self) {
+        for mut frame in self.emulator.receiver.try_iter() {
+            if let Some(filter) = &mut self.flash_filter {
+                filter.apply(&mut frame, self.palette, Instant::now());
+            }
+
+            if let Some(previous) = &self.frame {
+                frame.dirty = match (previous.dirty, frame.dirty) {
+                    (Some(a), Some(b)) => Some(a.union(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+
+            self.frame = Some(frame);
+        }
+    }
+
+    fn draw(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut frame = match self.frame.take() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+
+        let result = self.draw_frame(&frame);
+
+        if result.is_ok() {
+            frame.dirty = None;
+        }
+
+        self.frame = Some(frame);
+
+        result
+    }
+
+    fn draw_frame(&mut self, frame: &DisplayFrame) -> Result<(), Box<dyn Error>> {
+        let size = self.window.inner_size();
+
+        let layout = Layout::new(size, frame.width, frame.height);
+
+        let mut buffer = self.surface.buffer_mut()?;
+
+        let sounding = self.sound_indicator && self.emulator.is_sounding();
+
+        let overlays = self.perf_overlay.visible
+            || self.keypad_overlay.visible
+            || self.timer_overlay.visible
+            || !self.toasts.is_empty()
+            || self.menu.is_some()
+            || self.help.is_some()
+            || sounding;
+
+        // Only the changed pixels are redrawn when the buffer still holds the
+        // previous frame (age 1). Overlays are blended over the pixels, so a
+        // frame that has or had one is always redrawn in full, as is anything
+        // the CRT effect distorts.
+        // Smooth scaling blends neighbouring pixels, so it always redraws in
+        // full too.
+        let full_redraw = buffer.age() != 1
+            || overlays
+            || self.redraw_all
+            || self.crt.is_some()
+            || self.filter == ScalingFilter::Smooth;
+
+        self.redraw_all = overlays;
+
+        let grid = self.pixel_grid;
+
+        let region = if full_redraw {
+            buffer.fill(self.palette.background);
+
+            Some(Region {
+                x: 0,
+                y: 0,
+                width: frame.width as usize,
+                height: frame.height as usize,
+            })
+        } else {
+            frame.dirty
+        };
+
+        if let Some(crt) = &mut self.crt {
+            self.crt_source.clear();
+            self.crt_source
+                .resize(buffer.len(), self.palette.background);
+
+            if let Some(region) = region {
+                draw_display(
+                    &mut self.crt_source,
+                    size,
+                    frame,
+                    region,
+                    grid,
+                    self.palette,
+                    self.filter,
+                );
+            }
+
+            crt.apply(
+                &self.crt_source,
+                &mut buffer,
+                size.width as usize,
+                size.height as usize,
+            );
+        } else if let Some(region) = region {
+            draw_display(
+                &mut buffer,
+                size,
+                frame,
+                region,
+                grid,
+                self.palette,
+                self.filter,
+            );
+        }
+
+        let now = Instant::now();
+
+        self.perf_overlay.record_frame(now);
+
+        let mut canvas = Canvas::new(&mut buffer, size.width as usize, size.height as usize);
+
+        if sounding {
+            draw_sound_indicator(&mut canvas);
+        }
+
+        if self.perf_overlay.visible {
+            self.perf_overlay.draw(&mut canvas, self.status);
+        }
+
+        if self.keypad_overlay.visible {
+            self.keypad_overlay
+                .draw(&mut canvas, self.emulator.keypad.pressed_keys());
+        }
+
+        if self.timer_overlay.visible {
+            self.timer_overlay
+                .draw(&mut canvas, self.emulator.frame_count(), now);
+        }
+
+        if let Some(help) = &self.help {
+            draw_text_columns(&mut canvas, help);
+        }
+
+        if let Some(menu) = &self.menu {
+            menu.draw(&mut canvas);
+        }
+
+        self.toasts.draw(&mut canvas, now);
+
+        self.window.pre_present_notify();
+
+        if full_redraw {
+            buffer.present()?;
+        } else {
+            let damage: Vec<Rect> = region
+                .and_then(|region| {
+                    Some(Rect {
+                        x: layout.left + region.x as u32 * layout.scale,
+                        y: layout.top + region.y as u32 * layout.scale,
+                        width: NonZeroU32::new(region.width as u32 * layout.scale)?,
+                        height: NonZeroU32::new(region.height as u32 * layout.scale)?,
+                    })
+                })
+                .into_iter()
+                .collect();
+
+            buffer.present_with_damage(&damage)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Where the display sits in the window: scaled by the largest whole number
+// that fits, so every CHIP-8 pixel covers the same square of physical pixels,
+// and centered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Layout {
+    scale: u32,
+    left: u32,
+    top: u32,
+}
+
+impl Layout {
+    fn new(window: PhysicalSize<u32>, display_width: u32, display_height: u32) -> Self {
+        let scale = (window.width / display_width).min(window.height / display_height);
+
+        Self {
+            scale,
+            left: (window.width - display_width * scale) / 2,
+            top: (window.height - display_height * scale) / 2,
+        }
+    }
+}
+
+fn window_level(always_on_top: bool) -> WindowLevel {
+    if always_on_top {
+        WindowLevel::AlwaysOnTop
+    } else {
+        WindowLevel::Normal
+    }
+}
+
+// The window size that shows the display at `scale` physical pixels per pixel,
+// rounded to a whole number.
+fn pixel_perfect_size(display_width: u32, display_height: u32, scale: f64) -> PhysicalSize<u32> {
+    let scale = (scale.round() as u32).max(1);
+
+    PhysicalSize::new(display_width * scale, display_height * scale)
+}
+
+// Pixels have to be at least this big before the grid leaves anything of them
+// to see.
+const MIN_PIXEL_GRID_SCALE: u32 = 4;
+
+const PIXEL_GRID_COLOR: u32 = 0xFF202020;
+
+// The pixel grid needs every pixel scaled to the same whole size, so only
+// shows with nearest scaling.
+fn draw_display(
+    buffer: &mut [u32],
+    size: PhysicalSize<u32>,
+    frame: &DisplayFrame,
+    region: Region,
+    grid: bool,
+    palette: Palette,
+    filter: ScalingFilter,
+) {
+    match filter {
+        ScalingFilter::Nearest => {
+            let layout = Layout::new(size, frame.width, frame.height);
+
+            draw_region(buffer, size.width, frame, region, layout, grid, palette)
+        }
+        ScalingFilter::Smooth => {
+            let (width, height) = (frame.width as usize, frame.height as usize);
+
+            let pixels: Vec<u32> = (0..width * height)
+                .map(|index| pixel_color(frame, index % width, index / width, palette))
+                .collect();
+
+            scaling::draw_smooth(
+                buffer,
+                size.width as usize,
+                size.height as usize,
+                &pixels,
+                width,
+                height,
+            );
+        }
+    }
+}
+
+fn pixel_color(frame: &DisplayFrame, x: usize, y: usize, palette: Palette) -> u32 {
+    let byte = frame.pixels[y * (frame.width as usize / 8) + x / 8];
+
+    if let Some(colors) = &frame.colors {
+        colors[y * frame.width as usize + x] | 0xFF000000
+    } else if (byte >> (7 - x % 8)) & 0x1 == 1 {
+        palette.foreground
+    } else {
+        palette.background
+    }
+}
+
+// Rewrites one region of the display, scaled up, in both colors so it needs no
+// clearing first. With `grid`, the last row and column of every scaled pixel
+// are drawn as a dark gap.
+fn draw_region(
+    buffer: &mut [u32],
+    buffer_width: u32,
+    frame: &DisplayFrame,
+    region: Region,
+    layout: Layout,
+    grid: bool,
+    palette: Palette,
+) {
+    let grid = grid && layout.scale >= MIN_PIXEL_GRID_SCALE;
+
+    let buffer_width = buffer_width as usize;
+    let scale = layout.scale as usize;
+    let (left, top) = (layout.left as usize, layout.top as usize);
+
+    // A window smaller than the display has no room to draw it.
+    if scale == 0 {
+        return;
+    }
+
+    for y in region.y..region.y + region.height {
+        let row_start = (top + y * scale) * buffer_width + left;
+
+        let span = row_start + region.x * scale..row_start + (region.x + region.width) * scale;
+
+        // Draw the first line of this row of pixels...
+        for (x, pixel) in (region.x..).zip(buffer[span.clone()].chunks_exact_mut(scale)) {
+            let color = pixel_color(frame, x, y, palette);
+
+            if grid {
+                pixel[..scale - 1].fill(color);
+                pixel[scale - 1] = PIXEL_GRID_COLOR;
+            } else {
+                pixel.fill(color);
+            }
+        }
+
+        // ...then copy it down, leaving the bottom line for the grid.
+        let lines = if grid { scale - 1 } else { scale };
+
+        for line in 1..lines {
+            buffer.copy_within(span.clone(), span.start + line * buffer_width);
+        }
+
+        if grid {
+            let offset = (scale - 1) * buffer_width;
+
+            buffer[span.start + offset..span.end + offset].fill(PIXEL_GRID_COLOR);
+        }
+    }
+}
+
+struct Application {
+    windows: HashMap<WindowId, WindowState>,
+    context: Option<Context<DisplayHandle<'static>>>,
+    event_loop_proxy: EventLoopProxy<UserEvent>,
+    pending_roms: Vec<Rom>,
+    settings: EmulatorSettings,
+    turbo_key: Key,
+    // As the options last set them, to tell which ones a config change
+    // touches.
+    instructions_per_frame: Option<u32>,
+    quirks: Quirks,
+    // Before any ROM's own settings, for opening other ROMs.
+    builder: InterpreterBuilder,
+    auto_pause: bool,
+    touch_zones: TouchZones,
+    crt: Option<CrtSettings>,
+    pixel_grid: bool,
+    filter: ScalingFilter,
+    timer: bool,
+    // From --scale, before shrinking to fit the monitor.
+    scale: u32,
+    snap_resize: bool,
+    borderless: bool,
+    always_on_top: bool,
+    position: Option<(i32, i32)>,
+    sound_indicator: bool,
+    palette: Palette,
+    reduce_flashing: bool,
+    keys: KeyMap,
+    dump_format: DumpFormat,
+    dump_file: Option<String>,
+    watcher: Option<RomWatcher>,
+    // The config file's canonical path while it's watched, and the command
+    // line, which still wins over the config when it changes.
+    config_file: Option<PathBuf>,
+    config_args: Vec<String>,
+    args: Vec<String>,
+    record_audio: Option<PathBuf>,
+    record_video: Option<PathBuf>,
+    script: Option<PathBuf>,
+    // Written for each window as it closes.
+    coverage_file: Option<String>,
+    replay_file: Option<String>,
+    close_on_exit: bool,
+    // Only with --stream; viewers see and control the most recently focused
+    // window.
+    stream: Option<StreamServer>,
+    // Only with --debug; commands go to the most recently focused window.
+    console: Option<DebugConsole>,
+    focused_window: Option<WindowId>,
+    // Keyed by their own windows, not the ROM windows they show.
+    sprite_viewers: HashMap<WindowId, SpriteViewer>,
+}
+
+impl Application {
+    fn new(
+        event_loop: &EventLoop<UserEvent>,
+        roms: Vec<Rom>,
+        options: &Options,
+        args: &[String],
+        stream: Option<StreamServer>,
+    ) -> Self {
+        let context = Some(
+            Context::new(unsafe {
+                std::mem::transmute::<DisplayHandle<'_>, DisplayHandle<'static>>(
+                    event_loop.display_handle().unwrap(),
+                )
+            })
+            .unwrap(),
+        );
+
+        let mut watcher = match RomWatcher::new(event_loop.create_proxy()) {
+            Ok(watcher) => Some(watcher),
+            Err(error) => {
+                warn!("ROM hot reloading disabled: {}", error);
+
+                None
+            }
+        };
+
+        let config_file = config::config_file()
+            .filter(|path| path.exists())
+            .zip(watcher.as_mut())
+            .and_then(|(path, watcher)| match watcher.watch(&path) {
+                Ok(path) => Some(path),
+                Err(error) => {
+                    warn!("Not watching {} for changes: {}", path.display(), error);
+
+                    None
+                }
+            });
+
+        let console = if options.debug {
+            console::spawn_reader(event_loop.create_proxy());
+
+            Some(DebugConsole::default())
+        } else {
+            None
+        };
+
+        Self {
+            context,
+            windows: Default::default(),
+            event_loop_proxy: event_loop.create_proxy(),
+            pending_roms: roms,
+            settings: EmulatorSettings {
+                deterministic: options.deterministic,
+                turbo_speed: options.turbo_speed,
+                speed_percent: options.speed_percent,
+                tone: options.tone,
+                volume: options.volume,
+                start_paused: options.start_paused,
+                single_threaded: options.single_threaded,
+            },
+            turbo_key: options.turbo_key.clone(),
+            instructions_per_frame: options.instructions_per_frame,
+            quirks: options.quirks().unwrap_or_default(),
+            builder: options.interpreter_builder().unwrap_or_default(),
+            auto_pause: options.auto_pause,
+            touch_zones: options.touch_zones,
+            crt: options.crt,
+            pixel_grid: options.pixel_grid,
+            filter: options.filter,
+            timer: options.timer,
+            scale: options.scale,
+            snap_resize: options.snap_resize,
+            borderless: options.borderless,
+            always_on_top: options.always_on_top,
+            position: options.position,
+            sound_indicator: options.sound_indicator,
+            palette: options.palette,
+            reduce_flashing: options.reduce_flashing,
+            keys: options.keys,
+            dump_format: options.dump_format,
+            dump_file: options.dump_file.clone(),
+            watcher,
+            config_args: config::config_file()
+                .and_then(|path| config::read_options(&path).ok())
+                .unwrap_or_default(),
+            config_file,
+            args: args.to_vec(),
+            record_audio: options.record_audio.clone(),
+            record_video: options.record_video.clone(),
+            script: options.script.clone(),
+            coverage_file: options.coverage_file.clone(),
+            replay_file: options.replay_file.clone(),
+            close_on_exit: options.close_on_exit,
+            stream,
+            console,
+            focused_window: None,
+            sprite_viewers: HashMap::new(),
+        }
+    }
+
+    fn create_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        mut rom: Rom,
+    ) -> Result<WindowId, Box<dyn Error>> {
+        let width = rom.interpreter.display_width as u32;
+        let height = rom.interpreter.display_height as u32;
+
+        // Never open a window bigger than the monitor.
+        let scaling_factor = match event_loop.primary_monitor() {
+            Some(monitor) => {
+                let size = monitor.size().to_logical::<u32>(monitor.scale_factor());
+
+                self.scale
+                    .min(size.width / width)
+                    .min(size.height / height)
+                    .max(1)
+            }
+            None => self.scale,
+        };
+
+        // The first window reopens as it was last left with these monitors,
+        // unless told where to go.
+        let layout = if self.windows.is_empty() && self.position.is_none() {
+            WindowLayout::load(&window_layout::monitors(event_loop))
+        } else {
+            None
+        };
+
+        let mut window_attributes = Window::default_attributes()
+            .with_inner_size(LogicalSize::new(
+                width * scaling_factor,
+                height * scaling_factor,
+            ))
+            .with_transparent(true)
+            .with_decorations(!self.borderless)
+            .with_window_level(window_level(self.always_on_top));
+
+        if let Some((x, y)) = self.position.or(layout.and_then(|layout| layout.position)) {
+            window_attributes = window_attributes.with_position(PhysicalPosition::new(x, y));
+        }
+
+        let restored_size = layout.and_then(|layout| layout.size);
+
+        if let Some((width, height)) = restored_size {
+            window_attributes = window_attributes.with_inner_size(PhysicalSize::new(width, height));
+        }
+
+        if let Some(layout) = layout {
+            window_attributes = window_attributes
+                .with_maximized(layout.maximized)
+                .with_fullscreen(layout.fullscreen.then_some(Fullscreen::Borderless(None)));
+        }
+
+        let window = event_loop.create_window(window_attributes)?;
+
+        // On scaled displays the logical size rarely works out to a whole
+        // number of physical pixels per CHIP-8 pixel, so ask for the nearest
+        // size that does.
+        if restored_size.is_none() {
+            let _ = window.request_inner_size(pixel_perfect_size(
+                width,
+                height,
+                scaling_factor as f64 * window.scale_factor(),
+            ));
+        }
+
+        let mut recorders: Vec<Box<dyn Recorder>> = Vec::new();
+
+        if let Some(path) = &self.record_audio {
+            match WavRecorder::create(path, self.settings.tone) {
+                Ok(recorder) => recorders.push(Box::new(recorder)),
+                Err(error) => warn!("Not recording audio to {}: {}", path.display(), error),
+            }
+        }
+
+        let rom_settings = rom
+            .prefs
+            .as_mut()
+            .and_then(|prefs| rom_settings(prefs, &self.config_args, &self.args));
+
+        if let Some(settings) = rom_settings {
+            rom.interpreter.quirks = settings.quirks;
+
+            if let Some(instructions_per_frame) = settings.instructions_per_frame {
+                rom.interpreter.instructions_per_frame = instructions_per_frame;
+            }
+        }
+
+        let builder = rom_builder(&rom.builder, rom_settings);
+
+        if let Some(path) = &self.record_video {
+            let palette = rom_settings.map_or(self.palette, |settings| settings.palette);
+
+            let frame = rom.interpreter.frame();
+
+            match VideoRecorder::create(
+                path,
+                self.settings.tone,
+                palette,
+                frame.width,
+                frame.height,
+            ) {
+                Ok(recorder) => recorders.push(Box::new(recorder)),
+                Err(error) => warn!("Not recording video to {}: {}", path.display(), error),
+            }
+        }
+
+        let emulator = Emulator::spawn(
+            rom.interpreter,
+            window.id(),
+            self.event_loop_proxy.clone(),
+            self.settings,
+            recorders,
+            rom.rpl_flags,
+            start_script(self.script.as_deref()),
+        );
+
+        let mut window_state = WindowState::new(self, window, rom.source, builder, emulator)?;
+
+        if let Some(settings) = rom_settings {
+            window_state.apply_rom_settings(settings);
+        }
+
+        if let Some(layout) = layout {
+            window_state.apply_layout(layout);
+        }
+
+        window_state.prefs = rom.prefs;
+
+        if self.settings.start_paused {
+            window_state.toasts.show("Paused, press P to start");
+        }
+
+        if matches!(window_state.rom_source, RomSource::Demo) {
+            window_state
+                .toasts
+                .show("No ROM given, pass a ROM path to play one");
+        }
+
+        window_state.watch_rom(self.watcher.as_mut());
+
+        let window_id = window_state.window.id();
+
+        self.windows.insert(window_id, window_state);
+
+        Ok(window_id)
+    }
+
+    // Restarts the window's emulation from a fresh interpreter with the
+    // current contents of the ROM file, keeping the window and pause state.
+    fn reload_rom(&mut self, window_id: WindowId) {
+        let window_state = match self.windows.get_mut(&window_id) {
+            Some(window_state) => window_state,
+            None => return,
+        };
+
+        let (interpreter, rpl_flags, _) =
+            match load_rom(&window_state.rom_source, &window_state.builder) {
+                Ok(rom) => rom,
+                Err(error) => {
+                    error!("Failed to reload {}: {}", window_state.rom_source, error);
+
+                    window_state.toasts.show("Reload failed");
+
+                    return;
+                }
+            };
+
+        window_state.toasts.show("ROM reloaded");
+
+        window_state.source_map = load_source_map(&window_state.rom_source);
+
+        self.restart_emulator(window_id, interpreter, rpl_flags);
+    }
+
+    // Applies the config file after it changes. Settings a running program
+    // can take on change straight away, in every window; the rest wait for
+    // the next ROM load, apart from those only read at startup.
+    fn reload_config(&mut self) {
+        let path = match &self.config_file {
+            Some(path) => path,
+            None => return,
+        };
+
+        let config_args = match config::read_options(path) {
+            // Editors often save in several steps, each reported separately.
+            Ok(config_args) if config_args == self.config_args => return,
+            Ok(config_args) => config_args,
+            Err(error) => return self.config_error(error),
+        };
+
+        let options = match Options::parse(&[config_args.clone(), self.args.clone()].concat()) {
+            Ok(options) => options,
+            Err(error) => return self.config_error(error),
+        };
+
+        let builder = match options.interpreter_builder() {
+            Ok(builder) => builder,
+            Err(error) => return self.config_error(error),
+        };
+
+        // interpreter_builder() has already checked them.
+        let quirks = options.quirks().unwrap_or(self.quirks);
+
+        for window_state in self.windows.values_mut() {
+            // A ROM's own settings still win over the new config.
+            let rom_settings = window_state
+                .prefs
+                .as_mut()
+                .and_then(|prefs| rom_settings(prefs, &config_args, &self.args));
+
+            window_state.builder = rom_builder(&builder, rom_settings);
+
+            if options.speed_percent != self.settings.speed_percent {
+                window_state.emulator.set_speed_percent(
+                    rom_settings.map_or(options.speed_percent, |settings| settings.speed_percent),
+                );
+            }
+
+            if let Some(instructions_per_frame) = options
+                .instructions_per_frame
+                .filter(|_| options.instructions_per_frame != self.instructions_per_frame)
+                .map(|instructions_per_frame| {
+                    rom_settings
+                        .and_then(|settings| settings.instructions_per_frame)
+                        .unwrap_or(instructions_per_frame)
+                })
+            {
+                window_state.emulator.send_command(move |interpreter| {
+                    interpreter.instructions_per_frame = instructions_per_frame
+                });
+            }
+
+            if quirks != self.quirks {
+                let quirks = rom_settings.map_or(quirks, |settings| settings.quirks);
+
+                window_state
+                    .emulator
+                    .send_command(move |interpreter| interpreter.quirks = quirks);
+            }
+
+            if options.pixel_grid != self.pixel_grid {
+                window_state.pixel_grid = options.pixel_grid;
+                window_state.redraw_all = true;
+            }
+
+            if options.filter != self.filter {
+                window_state.filter = options.filter;
+                window_state.redraw_all = true;
+            }
+
+            if options.sound_indicator != self.sound_indicator {
+                window_state.sound_indicator = options.sound_indicator;
+            }
+
+            if options.crt != self.crt {
+                window_state.crt = options.crt.map(CrtEffect::new);
+                window_state.redraw_all = true;
+            }
+
+            if options.palette != self.palette {
+                window_state.palette =
+                    rom_settings.map_or(options.palette, |settings| settings.palette);
+                window_state.redraw_all = true;
+            }
+
+            if options.reduce_flashing != self.reduce_flashing {
+                window_state.flash_filter = options.reduce_flashing.then(FlashFilter::default);
+            }
+
+            if options.keys != self.keys {
+                window_state.keys = rom_settings.map_or(options.keys, |settings| settings.keys);
+            }
+
+            if options.borderless != self.borderless {
+                window_state.window.set_decorations(!options.borderless);
+            }
+
+            if options.always_on_top != self.always_on_top {
+                window_state
+                    .window
+                    .set_window_level(window_level(options.always_on_top));
+            }
+
+            window_state.toasts.show("Config reloaded");
+
+            window_state.update_title();
+
+            window_state.window.request_redraw();
+        }
+
+        self.settings = EmulatorSettings {
+            deterministic: options.deterministic,
+            turbo_speed: options.turbo_speed,
+            speed_percent: options.speed_percent,
+            tone: options.tone,
+            volume: options.volume,
+            start_paused: options.start_paused,
+            // Only read at startup, as the emulators keep running as they
+            // began.
+            single_threaded: self.settings.single_threaded,
+        };
+
+        self.config_args = config_args;
+        self.builder = builder;
+        self.turbo_key = options.turbo_key.clone();
+        self.instructions_per_frame = options.instructions_per_frame;
+        self.quirks = quirks;
+        self.pixel_grid = options.pixel_grid;
+        self.filter = options.filter;
+        self.sound_indicator = options.sound_indicator;
+        self.crt = options.crt;
+        self.palette = options.palette;
+        self.reduce_flashing = options.reduce_flashing;
+        self.keys = options.keys;
+        self.borderless = options.borderless;
+        self.always_on_top = options.always_on_top;
+    }
+
+    // The settings a ROM gets without any remembered for it.
+    fn rom_defaults(&self) -> RomSettings {
+        RomSettings {
+            speed_percent: self.settings.speed_percent,
+            palette: self.palette,
+            quirks: self.quirks,
+            keys: self.keys,
+            instructions_per_frame: self.instructions_per_frame,
+        }
+    }
+
+    fn config_error(&mut self, error: String) {
+        error!("Not applying the changed config: {}", error);
 
-use std::collections::HashMap;
-use std::error::Error;
-use std::fs;
-use std::num::NonZeroU32;
-use std::sync::mpsc::{channel, Receiver};
-use std::sync::Arc;
-use std::{env, thread};
+        for window_state in self.windows.values_mut() {
+            window_state.toasts.show("Config error, see the log");
+        }
+    }
 
-use winit::application::ApplicationHandler;
-use winit::dpi::{LogicalSize, PhysicalSize};
-use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::raw_window_handle::DisplayHandle;
-use winit::raw_window_handle::HasDisplayHandle;
-use winit::window::{Window, WindowId};
+    // Swaps in a freshly loaded interpreter, keeping the window along with its
+    // pause, mute and speed settings.
+    fn restart_emulator(
+        &mut self,
+        window_id: WindowId,
+        interpreter: Interpreter,
+        rpl_flags: Option<RplFlags>,
+    ) {
+        let window_state = match self.windows.get_mut(&window_id) {
+            Some(window_state) => window_state,
+            None => return,
+        };
 
-use softbuffer::{Context, Surface};
+        // Audio and video recordings end with the old emulator rather than
+        // being overwritten by a new one.
+        let emulator = Emulator::spawn(
+            interpreter,
+            window_id,
+            self.event_loop_proxy.clone(),
+            self.settings,
+            Vec::new(),
+            rpl_flags,
+            start_script(self.script.as_deref()),
+        );
 
-use chip8::Interpreter;
+        emulator.set_paused(window_state.emulator.is_paused());
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+        emulator.set_muted(window_state.emulator.is_muted());
 
-    if args.len() < 2 {
-        eprintln!("Usage: {} <path_to_rom> [--debug]", args[0]);
+        emulator.set_speed_percent(window_state.emulator.speed_percent());
 
-        std::process::exit(1);
-    }
+        std::mem::replace(&mut window_state.emulator, emulator).stop();
 
-    let rom_path = &args[1];
-    let debug: bool = args.contains(&"--debug".to_string());
+        // A new run starts from the first frame.
+        window_state.timer_overlay.reset(0);
 
-    println!("Rom file: {}", rom_path);
-    println!("Debug mode: {}", debug);
+        window_state.error = None;
+        window_state.exited = false;
 
-    let rom_data: Vec<u8> = fs::read(rom_path).unwrap();
+        window_state.update_title();
+    }
 
-    let (sender, receiver) = channel();
+    // Stops the window's emulator and writes its coverage and replay, quitting
+    // once the last window has gone.
+    fn close_window(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId) {
+        self.sprite_viewers
+            .retain(|_, viewer| viewer.target != window_id);
 
-    let event_loop = EventLoop::<UserEvent>::with_user_event().build()?;
+        let defaults = self.rom_defaults();
 
-    event_loop.set_control_flow(ControlFlow::Poll);
+        if let Some(mut window_state) = self.windows.remove(&window_id) {
+            if let Some(quirks) = window_state
+                .emulator
+                .with_interpreter(|interpreter| interpreter.quirks)
+            {
+                window_state.save_prefs(quirks, &defaults);
+            }
 
-    let mut app = Application::new(&event_loop, receiver);
+            if let Err(error) = window_state
+                .layout()
+                .save(&window_layout::monitors(event_loop))
+            {
+                warn!("Failed to save the window layout: {}", error);
+            }
 
-    let event_loop_proxy = event_loop.create_proxy();
+            let interpreter = window_state.emulator.stop();
 
-    let _interpreter_thread = thread::spawn(move || {
-        let mut interpreter = Interpreter::new();
+            if let (Some(path), Some(interpreter)) = (&self.coverage_file, &interpreter) {
+                let result = File::create(path).and_then(|mut file| {
+                    write_coverage(&mut file, &window_state.rom_name, interpreter)
+                });
 
-        interpreter.load_program(&rom_data).unwrap();
+                if let Err(error) = result {
+                    error!("Failed to write the coverage to {}: {}", path, error);
+                }
+            }
 
-        loop {
-            interpreter.execute_cycle();
+            let replay = interpreter.as_ref().and_then(Interpreter::replay);
 
-            sender.send(interpreter.memory).unwrap();
+            if let (Some(path), Some(replay)) = (&self.replay_file, replay) {
+                match fs::write(path, replay.to_string()) {
+                    Ok(()) => info!("Wrote a replay of {} frames to {}", replay.frames(), path),
+                    Err(error) => error!("Failed to write the replay to {}: {}", path, error),
+                }
+            }
+        }
 
-            event_loop_proxy.send_event(UserEvent::RedrawScreen).expect("Failed to send event");
+        if self.windows.is_empty() {
+            event_loop.exit();
+        }
+    }
 
-            std::thread::sleep(std::time::Duration::from_millis(1000));
+    // Opens a sprite viewer on the ROM window's memory, starting at the
+    // program, or closes the one already open.
+    fn toggle_sprite_viewer(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        target: WindowId,
+    ) -> Result<(), Box<dyn Error>> {
+        let open = self
+            .sprite_viewers
+            .iter()
+            .find(|(_, viewer)| viewer.target == target)
+            .map(|(&viewer_id, _)| viewer_id);
+
+        if let Some(viewer_id) = open {
+            self.sprite_viewers.remove(&viewer_id);
+
+            return Ok(());
         }
-    });
 
-    event_loop.run_app(&mut app).map_err(Into::into)
-}
+        let window_state = match self.windows.get(&target) {
+            Some(window_state) => window_state,
+            None => return Ok(()),
+        };
 
-#[derive(Debug)]
-enum UserEvent {
-    RedrawScreen,
-}
+        let address = window_state
+            .emulator
+            .with_interpreter(|interpreter| interpreter.memory_map.program_address)
+            .unwrap_or(0x200);
 
-struct WindowState {
-    surface: Surface<DisplayHandle<'static>, Arc<Window>>,
-    window: Arc<Window>,
-}
+        let window_attributes =
+            Window::default_attributes().with_inner_size(LogicalSize::new(640, 400));
 
-impl WindowState {
-    fn new(app: &Application, window: Window) -> Result<Self, Box<dyn Error>> {
-        let window = Arc::new(window);
+        let window = event_loop.create_window(window_attributes)?;
 
-        let surface = Surface::new(app.context.as_ref().unwrap(), Arc::clone(&window))?;
+        let viewer = SpriteViewer::new(
+            self.context.as_ref().unwrap(),
+            window,
+            target,
+            window_state.rom_name.clone(),
+            address as usize,
+        )?;
 
-        // TODO: add a cursor?
+        let viewer_id = viewer.window.id();
 
-        // TODO: Do I need to handle IME?
+        self.sprite_viewers.insert(viewer_id, viewer);
 
-        let size = window.inner_size();
+        self.read_sprite_memory(viewer_id);
 
-        let mut state = Self { surface, window };
+        Ok(())
+    }
 
-        state.resize(size);
+    // Asks the interpreter thread for the viewer's page of memory, which
+    // comes back as a SpriteMemory event before the next frame.
+    fn read_sprite_memory(&self, viewer_id: WindowId) {
+        let viewer = match self.sprite_viewers.get(&viewer_id) {
+            Some(viewer) => viewer,
+            None => return,
+        };
 
-        Ok(state)
+        let window_state = match self.windows.get(&viewer.target) {
+            Some(window_state) => window_state,
+            None => return,
+        };
+
+        let page = viewer.page();
+
+        let event_loop_proxy = self.event_loop_proxy.clone();
+
+        window_state.emulator.send_command(move |interpreter| {
+            let memory_size = interpreter.memory.len();
+
+            let end = page.end.min(memory_size);
+
+            let bytes = interpreter.memory[page.start.min(end)..end].to_vec();
+
+            let event = UserEvent::SpriteMemory(viewer_id, page.start, bytes, memory_size);
+
+            let _ = event_loop_proxy.send_event(event);
+        });
     }
 
-    fn resize(&mut self, size: PhysicalSize<u32>) {
-        let (width, height) = match (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
-            (Some(width), Some(height)) => (width, height),
-            _ => return,
+    fn sprite_viewer_event(&mut self, viewer_id: WindowId, event: WindowEvent) {
+        let viewer = match self.sprite_viewers.get_mut(&viewer_id) {
+            Some(viewer) => viewer,
+            None => return,
         };
 
-        println!("Resize {width} {height}");
+        match event {
+            WindowEvent::CloseRequested => {
+                self.sprite_viewers.remove(&viewer_id);
+            }
 
-        self.surface
-            .resize(width, height)
-            .expect("failed to resize inner buffer");
+            WindowEvent::RedrawRequested => {
+                if let Err(error) = viewer.draw() {
+                    error!("Failed to draw the sprite viewer: {}", error);
+                }
+            }
 
-        self.window.request_redraw();
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if viewer.handle_key(&logical_key) => {
+                self.read_sprite_memory(viewer_id);
+            }
+
+            _ => {}
+        }
     }
 
-    fn draw(&mut self, memory: &[u8; 4096]) -> Result<(), Box<dyn Error>> {
-        let size = self.window.inner_size();
+    // Commands from the debug console and remote control go to the most
+    // recently focused window, or any window if that one has closed.
+    fn target_window(&self) -> Option<WindowId> {
+        self.focused_window
+            .filter(|window_id| self.windows.contains_key(window_id))
+            .or_else(|| self.windows.keys().next().copied())
+    }
+
+    fn remote(&mut self, command: RemoteCommand) -> RemoteResponse {
+        let window_id = match self.target_window() {
+            Some(window_id) => window_id,
+            None => return RemoteResponse::text(503, "No ROM window is open"),
+        };
 
-        let scale_x = size.width / 64;
-        let scale_y = size.height / 32;
+        let window_state = self
+            .windows
+            .get_mut(&window_id)
+            .expect("The target window should be open");
 
-        let mut buffer = self.surface.buffer_mut()?;
+        match command {
+            RemoteCommand::Pause | RemoteCommand::Resume => {
+                let paused = command == RemoteCommand::Pause;
 
-        buffer.fill(0xff000000);
+                window_state.emulator.set_paused(paused);
 
-        let offset = 0xF00;
+                let message = if paused { "Paused" } else { "Resumed" };
 
-        for index in offset..offset + (64 * 32 / 8) {
-            let y = (index - offset) / (64 / 8);
-            let x = (index - offset) % (64 / 8) * 8;
+                window_state.toasts.show(message);
 
-            let byte = memory[index as usize];
+                window_state.update_title();
+            }
+
+            RemoteCommand::Key { key, pressed } => window_state.emulator.keypad.set(key, pressed),
+
+            RemoteCommand::Screen => {
+                let frame = match &window_state.frame {
+                    Some(frame) => frame,
+                    None => return RemoteResponse::text(503, "Nothing has been drawn yet"),
+                };
+
+                return match remote::encode_png(&frame.pixels, frame.width, frame.height) {
+                    Ok(png) => RemoteResponse {
+                        status: 200,
+                        content_type: "image/png",
+                        body: png,
+                    },
+                    Err(error) => RemoteResponse::text(500, &error),
+                };
+            }
 
-            for bit in 0..8 {
-                let pixel = (byte >> (7 - bit)) & 0x1;
+            RemoteCommand::Registers => {
+                let state = window_state
+                    .emulator
+                    .with_interpreter(|interpreter| interpreter.state());
+
+                return match state {
+                    Some(state) => RemoteResponse {
+                        status: 200,
+                        content_type: "application/json",
+                        body: remote::registers_json(&state).into_bytes(),
+                    },
+                    None => RemoteResponse::text(503, "The emulator has stopped"),
+                };
+            }
 
-                if pixel == 1 {
-                    let start_x = (x + bit) * scale_x;
-                    let start_y = y * scale_y;
+            RemoteCommand::Stats => {
+                let statistics = window_state
+                    .emulator
+                    .with_interpreter(|interpreter| interpreter.statistics());
+
+                return match statistics {
+                    Some(statistics) => RemoteResponse {
+                        status: 200,
+                        content_type: "application/json",
+                        body: remote::statistics_json(
+                            &statistics,
+                            window_state.perf_overlay.pacing(),
+                        )
+                        .into_bytes(),
+                    },
+                    None => RemoteResponse::text(503, "The emulator has stopped"),
+                };
+            }
 
-                    // Draw the scaled pixel
-                    for draw_y in start_y..start_y + scale_y {
-                        for draw_x in start_x..start_x + scale_x {
-                            let index =
-                                (draw_y as usize) * (size.width as usize) + (draw_x as usize);
+            RemoteCommand::Load(argument) => {
+                let loaded =
+                    RomSource::parse(&argument).and_then(|source| self.open_rom(window_id, source));
 
-                            buffer[index] = 0xFFFFFFFF;
-                        }
-                    }
+                if let Err(error) = loaded {
+                    return RemoteResponse::text(400, &error);
                 }
             }
         }
 
-        self.window.pre_present_notify();
+        RemoteResponse::ok()
+    }
 
-        buffer.present()?;
+    // Puts another ROM in the window in place of its current one, with the
+    // settings last used with it.
+    fn open_rom(&mut self, window_id: WindowId, source: RomSource) -> Result<(), String> {
+        let defaults = self.rom_defaults();
 
-        Ok(())
-    }
-}
+        let window_state = match self.windows.get_mut(&window_id) {
+            Some(window_state) => window_state,
+            None => return Ok(()),
+        };
 
-struct Application {
-    windows: HashMap<WindowId, WindowState>,
-    context: Option<Context<DisplayHandle<'static>>>,
-    receiver: Receiver<[u8; 4096]>,
-}
+        let (mut interpreter, rpl_flags, mut prefs) = load_rom(&source, &self.builder)?;
 
-impl Application {
-    fn new<T>(event_loop: &EventLoop<T>, receiver: Receiver<[u8; 4096]>) -> Self {
-        let context = Some(
-            Context::new(unsafe {
-                std::mem::transmute::<DisplayHandle<'_>, DisplayHandle<'static>>(
-                    event_loop.display_handle().unwrap(),
-                )
-            })
-            .unwrap(),
-        );
+        if let Some(quirks) = window_state
+            .emulator
+            .with_interpreter(|interpreter| interpreter.quirks)
+        {
+            window_state.save_prefs(quirks, &defaults);
+        }
 
-        Self {
-            context,
-            windows: Default::default(),
-            receiver,
+        let rom_settings = prefs
+            .as_mut()
+            .and_then(|prefs| rom_settings(prefs, &self.config_args, &self.args));
+
+        if let Some(settings) = rom_settings {
+            interpreter.quirks = settings.quirks;
+
+            if let Some(instructions_per_frame) = settings.instructions_per_frame {
+                interpreter.instructions_per_frame = instructions_per_frame;
+            }
+        }
+
+        window_state.builder = rom_builder(&self.builder, rom_settings);
+        window_state.prefs = prefs;
+
+        window_state.rom_name = source.name();
+        window_state.rom_source = source;
+        window_state.watch_rom(self.watcher.as_mut());
+
+        window_state.toasts.show("ROM loaded");
+
+        self.restart_emulator(window_id, interpreter, rpl_flags);
+
+        if let (Some(settings), Some(window_state)) =
+            (rom_settings, self.windows.get_mut(&window_id))
+        {
+            window_state.apply_rom_settings(settings);
         }
+
+        Ok(())
     }
 
-    fn create_window(
-        &mut self,
-        event_loop: &ActiveEventLoop,
-        _tab_id: Option<String>,
-    ) -> Result<WindowId, Box<dyn Error>> {
-        let scaling_factor = 20;
+    // A key pressed while the window's menu is open.
+    fn menu_key(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, key: &Key) {
+        let window_state = match self.windows.get_mut(&window_id) {
+            Some(window_state) => window_state,
+            None => return,
+        };
 
-        let window_attributes = Window::default_attributes()
-            .with_title("Chip8 Interpreter")
-            .with_inner_size(LogicalSize::new(64 * scaling_factor, 32 * scaling_factor))
-            .with_transparent(true);
+        let action = window_state
+            .menu
+            .as_mut()
+            .and_then(|menu| menu.handle_key(key));
 
-        let window = event_loop.create_window(window_attributes)?;
+        window_state.window.request_redraw();
 
-        let window_state = WindowState::new(self, window)?;
+        match action {
+            None => {}
 
-        let window_id = window_state.window.id();
+            Some(MenuAction::Resume) => window_state.close_menu(),
 
-        self.windows.insert(window_id, window_state);
+            Some(MenuAction::Reset) => {
+                window_state.close_menu();
 
-        Ok(window_id)
+                self.reload_rom(window_id);
+            }
+
+            Some(MenuAction::OpenRom(entry)) => {
+                window_state.close_menu();
+
+                let opened =
+                    RomSource::parse(&entry).and_then(|source| self.open_rom(window_id, source));
+
+                match opened {
+                    Ok(()) => {
+                        let mut recent_roms = RecentRoms::load();
+
+                        recent_roms.add(&entry);
+
+                        if let Err(error) = recent_roms.save() {
+                            warn!("Failed to save recent ROMs: {}", error);
+                        }
+                    }
+                    Err(error) => {
+                        error!("Failed to open {}: {}", entry, error);
+
+                        if let Some(window_state) = self.windows.get_mut(&window_id) {
+                            window_state.toasts.show("Open failed");
+                        }
+                    }
+                }
+            }
+
+            Some(MenuAction::SetPalette(palette)) => {
+                window_state.palette = palette;
+
+                window_state.redraw_all = true;
+            }
+
+            // Kept for resets, and remembered with the ROM like quirks set on
+            // the command line.
+            Some(MenuAction::SetQuirks(quirks)) => {
+                window_state
+                    .emulator
+                    .send_command(move |interpreter| interpreter.quirks = quirks);
+
+                window_state.builder = window_state.builder.clone().quirks(quirks);
+            }
+
+            Some(MenuAction::Quit) => {
+                let window_ids: Vec<WindowId> = self.windows.keys().copied().collect();
+
+                for window_id in window_ids {
+                    self.close_window(event_loop, window_id);
+                }
+            }
+        }
     }
 }
 
 impl ApplicationHandler<UserEvent> for Application {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        self.create_window(event_loop, None)
-            .expect("failed to create the initial window");
+        for rom in std::mem::take(&mut self.pending_roms) {
+            self.create_window(event_loop, rom)
+                .expect("failed to create a ROM window");
+        }
     }
 
-    fn user_event(&mut self, _event_loop: &ActiveEventLoop, user_event: UserEvent) {
+    // With --single-threaded the emulators run here, between events, and
+    // the loop waits until the next of them is due rather than polling.
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if !self.settings.single_threaded {
+            return;
+        }
+
+        let now = Instant::now();
+
+        let next_tick = self
+            .windows
+            .values()
+            .filter_map(|window_state| window_state.emulator.run_due(now))
+            .min();
+
+        event_loop.set_control_flow(match next_tick {
+            Some(next_tick) => ControlFlow::WaitUntil(next_tick),
+            None => ControlFlow::Wait,
+        });
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, user_event: UserEvent) {
         match user_event {
-            UserEvent::RedrawScreen => {
-                for window_state in self.windows.values_mut() {
+            UserEvent::RedrawScreen(window_id) => {
+                let streamed = self.target_window() == Some(window_id);
+
+                if let Some(window_state) = self.windows.get_mut(&window_id) {
+                    window_state.receive_frames();
+
+                    if let (Some(stream), Some(frame), true) =
+                        (&self.stream, &window_state.frame, streamed)
+                    {
+                        stream.broadcast(frame);
+                    }
+
                     window_state.window.request_redraw();
                 }
+
+                let viewers: Vec<WindowId> = self
+                    .sprite_viewers
+                    .iter()
+                    .filter(|(_, viewer)| viewer.target == window_id)
+                    .map(|(&viewer_id, _)| viewer_id)
+                    .collect();
+
+                for viewer_id in viewers {
+                    self.read_sprite_memory(viewer_id);
+                }
+            }
+
+            UserEvent::SpriteMemory(viewer_id, address, bytes, memory_size) => {
+                if let Some(viewer) = self.sprite_viewers.get_mut(&viewer_id) {
+                    viewer.set_memory(address, bytes, memory_size);
+                }
+            }
+
+            // The interpreter thread has stopped; the window keeps showing the
+            // last frame so the state at the point of failure can be inspected.
+            UserEvent::EmulatorError(window_id, message) => {
+                if let Some(window_state) = self.windows.get_mut(&window_id) {
+                    error!("{}: {}", window_state.rom_name, message);
+
+                    window_state.error = Some(message);
+
+                    window_state.update_title();
+                }
+            }
+
+            UserEvent::EmulatorWarning(window_id, message) => {
+                if let Some(window_state) = self.windows.get(&window_id) {
+                    warn!("{}: {}", window_state.rom_name, message);
+                }
+            }
+
+            // Like an error the window keeps the last frame, unless asked to
+            // close so a script running the ROM sees a clean exit.
+            UserEvent::EmulatorExited(window_id) => {
+                if let Some(window_state) = self.windows.get_mut(&window_id) {
+                    info!("{}: the program exited", window_state.rom_name);
+
+                    window_state.exited = true;
+
+                    window_state.update_title();
+                }
+
+                if self.close_on_exit {
+                    self.close_window(event_loop, window_id);
+                }
+            }
+
+            UserEvent::Breakpoint(window_id, address) => {
+                if let Some(window_state) = self.windows.get_mut(&window_id) {
+                    console::report_breakpoint(address, window_state.source_map.as_ref());
+
+                    window_state.toasts.show("Breakpoint");
+
+                    window_state.update_title();
+                }
+            }
+
+            UserEvent::PossibleRunaway(window_id, address) => {
+                if let Some(window_state) = self.windows.get_mut(&window_id) {
+                    warn!(
+                        "{}: possible runaway loop at {:03X}, nothing drawn and no keys or timers read for {} seconds",
+                        window_state.rom_name,
+                        address,
+                        emulator::RUNAWAY_FRAMES / 60
+                    );
+
+                    window_state
+                        .toasts
+                        .show(format!("Possible runaway loop at {:03X}", address));
+                }
+            }
+
+            UserEvent::RomChanged(path) if self.config_file.as_ref() == Some(&path) => {
+                self.reload_config();
+            }
+
+            UserEvent::RomChanged(path) => {
+                let window_ids: Vec<WindowId> = self
+                    .windows
+                    .iter()
+                    .filter(|(_, window_state)| window_state.watched_path.as_ref() == Some(&path))
+                    .map(|(window_id, _)| *window_id)
+                    .collect();
+
+                for window_id in window_ids {
+                    self.reload_rom(window_id);
+                }
+            }
+
+            UserEvent::EmulatorStatus(window_id, status) => {
+                if let Some(window_state) = self.windows.get_mut(&window_id) {
+                    window_state.status = Some(status);
+
+                    window_state.update_title();
+                }
+            }
+
+            UserEvent::ConsoleCommand(line) => {
+                let window_state = self
+                    .target_window()
+                    .and_then(|window_id| self.windows.get(&window_id));
+
+                let console = match &mut self.console {
+                    Some(console) => console,
+                    None => return,
+                };
+
+                if let Some(window_state) = window_state {
+                    let source_map = window_state.source_map.as_ref();
+
+                    if let Err(error) = console.run(&line, &window_state.emulator, source_map) {
+                        eprintln!("{}", error);
+                    }
+                }
+            }
+
+            UserEvent::Remote(command, reply) => {
+                let _ = reply.send(self.remote(command));
+            }
+
+            UserEvent::StreamKey(key, pressed) => {
+                let window_state = self
+                    .target_window()
+                    .and_then(|window_id| self.windows.get(&window_id));
+
+                if let Some(window_state) = window_state {
+                    window_state.emulator.keypad.set(key, pressed);
+                }
             }
         }
     }
 
     fn window_event(
         &mut self,
-        _event_loop: &ActiveEventLoop,
+        event_loop: &ActiveEventLoop,
         window_id: WindowId,
         event: WindowEvent,
     ) {
+        if self.sprite_viewers.contains_key(&window_id) {
+            self.sprite_viewer_event(window_id, event);
+
+            return;
+        }
+
         let window_state = match self.windows.get_mut(&window_id) {
             Some(window_state) => window_state,
             None => return,
@@ -234,20 +2581,105 @@ impl ApplicationHandler<UserEvent> for Application {
         match event {
             WindowEvent::Resized(size) => {
                 window_state.resize(size);
+
+                window_state.snap(size);
+            }
+
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                mut inner_size_writer,
+            } => {
+                let size = window_state.rescaled_size(scale_factor);
+
+                let _ = inner_size_writer.request_inner_size(size);
+            }
+
+            WindowEvent::CursorMoved { position, .. } => {
+                window_state.cursor_position = position;
+            }
+
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                window_state.mouse_button(state);
+            }
+
+            WindowEvent::Touch(touch) => {
+                window_state.touch(touch, &self.touch_zones);
+            }
+
+            WindowEvent::Focused(focused) => {
+                if focused {
+                    self.focused_window = Some(window_id);
+                }
+
+                window_state.focus_changed(focused, self.auto_pause);
             }
 
             WindowEvent::CloseRequested => {
-                println!("The close button was pressed; stopping");
+                debug!("The close button was pressed; stopping");
 
-                self.windows.remove(&window_id);
+                self.close_window(event_loop, window_id);
             }
 
             WindowEvent::RedrawRequested => {
-                println!("Redraw requested");
+                trace!("Redraw requested");
 
-                if let Ok(memory) = self.receiver.try_recv() {
-                    // TODO: Handle error here correctly
-                    window_state.draw(&memory).unwrap();
+                if let Err(error) = window_state.draw() {
+                    error!("Failed to draw frame: {}", error);
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key,
+                        logical_key,
+                        state,
+                        repeat,
+                        ..
+                    },
+                ..
+            } => {
+                // The menu takes every key while it's open, so none reach
+                // the paused ROM. Otherwise the keypad grid takes priority,
+                // since on some layouts a hotkey's character sits on one of
+                // its keys.
+                if window_state.menu.is_some() {
+                    if state == ElementState::Pressed {
+                        self.menu_key(event_loop, window_id, &logical_key);
+                    }
+                } else if let Some(key) = window_state.keys.keypad_key(physical_key) {
+                    debug!("Key {:X} {:?}", key, state);
+
+                    // Keys only reach the interpreter of the focused window.
+                    window_state
+                        .emulator
+                        .keypad
+                        .set(key, state == ElementState::Pressed);
+                } else if same_key(&logical_key, &self.turbo_key) {
+                    window_state
+                        .emulator
+                        .set_turbo(state == ElementState::Pressed);
+                } else if let Some(hotkey) = hotkey(&logical_key) {
+                    // Only frame advance repeats while held.
+                    if state != ElementState::Pressed
+                        || (repeat && !matches!(hotkey, Hotkey::FrameAdvance))
+                    {
+                        return;
+                    }
+
+                    match hotkey {
+                        Hotkey::SpriteViewer => {
+                            if let Err(error) = self.toggle_sprite_viewer(event_loop, window_id) {
+                                error!("Failed to open the sprite viewer: {}", error);
+                            }
+                        }
+                        Hotkey::Help => window_state.toggle_help(&self.turbo_key),
+                        _ => window_state.handle_hotkey(hotkey),
+                    }
                 }
             }
             _ => (),
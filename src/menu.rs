@@ -0,0 +1,327 @@
+use winit::keyboard::{Key, NamedKey};
+
+use chip8_vm::chip8::Quirks;
+
+use crate::overlay::{text_box_height, text_box_width, Canvas};
+use crate::palette::Palette;
+
+const MENU_COLOR: u32 = 0xffffffff;
+
+// Recent ROMs longer than this show only their end, where the file name is.
+const MAX_ROM_LENGTH: usize = 40;
+
+// What a choice in the menu asks of the window.
+#[derive(Debug, PartialEq)]
+pub enum MenuAction {
+    Resume,
+    Reset,
+    OpenRom(String),
+    SetPalette(Palette),
+    SetQuirks(Quirks),
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Page {
+    Main,
+    OpenRom,
+    Quirks,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Item {
+    Resume,
+    Reset,
+    OpenRom,
+    Palette,
+    Quirks,
+    Quit,
+    // An entry in the recent ROMs.
+    Rom(usize),
+    Quirk(&'static str),
+    Back,
+}
+
+// The menu Escape brings up over a paused ROM, so it can be played without
+// the command line. Up and down pick an item, Enter chooses it and Escape
+// goes back.
+pub struct PauseMenu {
+    page: Page,
+    selected: usize,
+    palette: Palette,
+    quirks: Quirks,
+    recent_roms: Vec<String>,
+    // Set when the ROM was running before the menu paused it, so it carries
+    // on when the menu closes.
+    pub was_running: bool,
+}
+
+impl PauseMenu {
+    pub fn new(
+        palette: Palette,
+        quirks: Quirks,
+        recent_roms: Vec<String>,
+        was_running: bool,
+    ) -> Self {
+        Self {
+            page: Page::Main,
+            selected: 0,
+            palette,
+            quirks,
+            recent_roms,
+            was_running,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &Key) -> Option<MenuAction> {
+        let items = self.items().len();
+
+        match key {
+            Key::Named(NamedKey::ArrowUp) => self.selected = (self.selected + items - 1) % items,
+            Key::Named(NamedKey::ArrowDown) => self.selected = (self.selected + 1) % items,
+            Key::Named(NamedKey::Enter | NamedKey::Space) => return self.choose(),
+            Key::Named(NamedKey::Escape | NamedKey::Backspace) => {
+                if self.page == Page::Main {
+                    return Some(MenuAction::Resume);
+                }
+
+                self.back();
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn choose(&mut self) -> Option<MenuAction> {
+        match self.items()[self.selected] {
+            Item::Resume => Some(MenuAction::Resume),
+            Item::Reset => Some(MenuAction::Reset),
+            Item::OpenRom => {
+                self.show(Page::OpenRom);
+
+                None
+            }
+            Item::Palette => {
+                self.palette = self.palette.next_named();
+
+                Some(MenuAction::SetPalette(self.palette))
+            }
+            Item::Quirks => {
+                self.show(Page::Quirks);
+
+                None
+            }
+            Item::Quit => Some(MenuAction::Quit),
+            Item::Rom(index) => Some(MenuAction::OpenRom(self.recent_roms[index].clone())),
+            Item::Quirk(name) => {
+                let toggle = if self.quirks.is_enabled(name) {
+                    format!("no-{}", name)
+                } else {
+                    name.to_string()
+                };
+
+                self.quirks.apply(&toggle).ok()?;
+
+                Some(MenuAction::SetQuirks(self.quirks))
+            }
+            Item::Back => {
+                self.back();
+
+                None
+            }
+        }
+    }
+
+    fn show(&mut self, page: Page) {
+        self.page = page;
+        self.selected = 0;
+    }
+
+    // Returns to the main page, on the item that led away from it.
+    fn back(&mut self) {
+        let item = match self.page {
+            Page::OpenRom => Item::OpenRom,
+            _ => Item::Quirks,
+        };
+
+        self.show(Page::Main);
+
+        self.selected = self
+            .items()
+            .iter()
+            .position(|&main_item| main_item == item)
+            .unwrap_or(0);
+    }
+
+    fn items(&self) -> Vec<Item> {
+        match self.page {
+            Page::Main => vec![
+                Item::Resume,
+                Item::Reset,
+                Item::OpenRom,
+                Item::Palette,
+                Item::Quirks,
+                Item::Quit,
+            ],
+            Page::OpenRom => (0..self.recent_roms.len())
+                .map(Item::Rom)
+                .chain([Item::Back])
+                .collect(),
+            Page::Quirks => Quirks::NAMES
+                .iter()
+                .map(|&name| Item::Quirk(name))
+                .chain([Item::Back])
+                .collect(),
+        }
+    }
+
+    fn label(&self, item: Item) -> String {
+        match item {
+            Item::Resume => "Resume".to_string(),
+            Item::Reset => "Reset".to_string(),
+            Item::OpenRom => "Open ROM".to_string(),
+            Item::Palette => format!("Palette: {}", self.palette),
+            Item::Quirks => "Quirks".to_string(),
+            Item::Quit => "Quit".to_string(),
+            Item::Rom(index) => shorten(&self.recent_roms[index]),
+            Item::Quirk(name) => format!(
+                "{}: {}",
+                name,
+                if self.quirks.is_enabled(name) {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+            Item::Back => "Back".to_string(),
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let title = match self.page {
+            Page::Main => "Paused",
+            Page::OpenRom if self.recent_roms.is_empty() => "No recent ROMs",
+            Page::OpenRom => "Open ROM",
+            Page::Quirks => "Quirks",
+        };
+
+        let items = self.items().into_iter().enumerate().map(|(index, item)| {
+            let marker = if index == self.selected { ">" } else { " " };
+
+            format!("{} {}", marker, self.label(item))
+        });
+
+        [title.to_string(), String::new()]
+            .into_iter()
+            .chain(items)
+            .collect()
+    }
+
+    // Centered in the window.
+    pub fn draw(&self, canvas: &mut Canvas<'_>) {
+        let lines = self.lines();
+
+        let x = canvas.width().saturating_sub(text_box_width(&lines)) / 2;
+        let y = canvas.height().saturating_sub(text_box_height(lines.len())) / 2;
+
+        canvas.draw_text_box(x, y, &lines, MENU_COLOR);
+    }
+}
+
+fn shorten(text: &str) -> String {
+    let length = text.chars().count();
+
+    if length <= MAX_ROM_LENGTH {
+        return text.to_string();
+    }
+
+    let end: String = text.chars().skip(length - (MAX_ROM_LENGTH - 3)).collect();
+
+    format!("...{}", end)
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::{Key, NamedKey};
+
+    use chip8_vm::chip8::Quirks;
+
+    use super::{MenuAction, PauseMenu};
+    use crate::palette::Palette;
+
+    fn press(menu: &mut PauseMenu, key: NamedKey) -> Option<MenuAction> {
+        menu.handle_key(&Key::Named(key))
+    }
+
+    #[test]
+    fn test_menu_items_choose_their_actions() {
+        let mut menu = PauseMenu::new(
+            Palette::default(),
+            Quirks::default(),
+            vec!["a.ch8".to_string(), "b.ch8".to_string()],
+            true,
+        );
+
+        assert_eq!(press(&mut menu, NamedKey::Enter), Some(MenuAction::Resume));
+
+        // Up from the top wraps around to Quit.
+        press(&mut menu, NamedKey::ArrowUp);
+
+        assert_eq!(press(&mut menu, NamedKey::Enter), Some(MenuAction::Quit));
+
+        press(&mut menu, NamedKey::ArrowUp);
+        press(&mut menu, NamedKey::ArrowUp);
+
+        assert_eq!(
+            press(&mut menu, NamedKey::Enter),
+            Some(MenuAction::SetPalette(Palette::parse("inverted").unwrap())),
+            "The palette item should move on to the next palette!"
+        );
+
+        press(&mut menu, NamedKey::ArrowUp);
+
+        assert_eq!(press(&mut menu, NamedKey::Enter), None);
+
+        press(&mut menu, NamedKey::ArrowDown);
+
+        assert_eq!(
+            press(&mut menu, NamedKey::Enter),
+            Some(MenuAction::OpenRom("b.ch8".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_quirks_toggle_and_escape_goes_back() {
+        let mut menu = PauseMenu::new(Palette::default(), Quirks::default(), Vec::new(), true);
+
+        for _ in 0..4 {
+            press(&mut menu, NamedKey::ArrowDown);
+        }
+
+        press(&mut menu, NamedKey::Enter);
+
+        let expected = Quirks {
+            vf_reset: true,
+            ..Quirks::default()
+        };
+
+        assert_eq!(
+            press(&mut menu, NamedKey::Enter),
+            Some(MenuAction::SetQuirks(expected))
+        );
+
+        assert!(
+            menu.lines().contains(&"> vf-reset: on".to_string()),
+            "The quirk should show as on!"
+        );
+
+        // Escape leaves the quirks for the main page, on the item that led
+        // there, and then closes the menu.
+        assert_eq!(press(&mut menu, NamedKey::Escape), None);
+
+        assert!(menu.lines().contains(&"> Quirks".to_string()));
+
+        assert_eq!(press(&mut menu, NamedKey::Escape), Some(MenuAction::Resume));
+    }
+}
@@ -0,0 +1,328 @@
+use std::fs;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use tracing::{debug, warn};
+
+use crate::palette::Palette;
+use crate::rom_source::RomSource;
+
+// Octo keeps a program's settings in a JSON object alongside it, as in its
+// cartridges and the CHIP-8 archive:
+//
+//   {"tickrate": 20, "fillColor": "#FFCC00", "backgroundColor": "#996600",
+//    "shiftQuirks": true, "loadStoreQuirks": false, ...}
+//
+// A game.json next to game.ch8 is read as options for the ROM, so it plays
+// here as it did in Octo. Settings with no counterpart here are skipped.
+pub fn args_for(source: &RomSource) -> Vec<String> {
+    let path = match source {
+        RomSource::File(path) => path.with_extension("json"),
+        _ => return Vec::new(),
+    };
+
+    let json = match fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(_) => return Vec::new(),
+    };
+
+    match to_args(&json) {
+        Ok(args) => args,
+        Err(error) => {
+            warn!("Ignoring the Octo options in {}: {}", path.display(), error);
+
+            Vec::new()
+        }
+    }
+}
+
+// Octo's quirk settings and the quirks they match here. Octo's load/store
+// quirk is the one that leaves I alone, the opposite of memory-increment.
+const QUIRKS: [(&str, &str, bool); 4] = [
+    ("shiftQuirks", "shift", true),
+    ("jumpQuirks", "jump", true),
+    ("logicQuirks", "vf-reset", true),
+    ("loadStoreQuirks", "memory-increment", false),
+];
+
+fn to_args(json: &str) -> Result<Vec<String>, String> {
+    let settings = parse_object(json)?;
+
+    let setting = |name: &str| {
+        settings
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    };
+
+    let mut args = Vec::new();
+
+    if let Some(value) = setting("tickrate") {
+        match value {
+            Value::Number(tickrate) if *tickrate >= 1.0 && tickrate.fract() == 0.0 => {
+                args.extend(["--ipf".to_string(), (*tickrate as u32).to_string()]);
+            }
+            _ => {
+                return Err(format!(
+                    "tickrate should be a whole number, got {:?}",
+                    value
+                ))
+            }
+        }
+    }
+
+    if let (Some(Value::String(foreground)), Some(Value::String(background))) =
+        (setting("fillColor"), setting("backgroundColor"))
+    {
+        let palette = Palette::parse(&format!("{},{}", foreground, background))?;
+
+        args.extend(["--palette".to_string(), palette.to_string()]);
+    }
+
+    let mut quirks = Vec::new();
+
+    for (key, quirk, same) in QUIRKS {
+        match setting(key) {
+            Some(Value::Bool(enabled)) if *enabled == same => quirks.push(quirk.to_string()),
+            Some(Value::Bool(_)) => quirks.push(format!("no-{}", quirk)),
+            Some(value) => return Err(format!("{} should be true or false, got {:?}", key, value)),
+            None => {}
+        }
+    }
+
+    if !quirks.is_empty() {
+        args.extend(["--quirks".to_string(), quirks.join(",")]);
+    }
+
+    for (key, value) in &settings {
+        let known = ["tickrate", "fillColor", "backgroundColor"].contains(&key.as_str())
+            || QUIRKS.iter().any(|(name, _, _)| name == key);
+
+        if !known {
+            debug!("Skipping the Octo option {} = {:?}", key, value);
+        }
+    }
+
+    Ok(args)
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Null,
+    // An array or object, which none of the settings read here are.
+    Nested,
+}
+
+// The members of a JSON object, in order.
+fn parse_object(json: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut parser = JsonParser {
+        chars: json.chars().peekable(),
+    };
+
+    let mut members = Vec::new();
+
+    parser.expect('{')?;
+
+    if parser.peek() == Some('}') {
+        parser.chars.next();
+    } else {
+        loop {
+            let key = parser.string()?;
+
+            parser.expect(':')?;
+
+            members.push((key, parser.value()?));
+
+            match parser.next()? {
+                ',' => {}
+                '}' => break,
+                character => return Err(format!("expected ',' or '}}', got '{}'", character)),
+            }
+        }
+    }
+
+    match parser.peek() {
+        None => Ok(members),
+        Some(character) => Err(format!("unexpected '{}' after the object", character)),
+    }
+}
+
+struct JsonParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        self.peek();
+
+        self.chars
+            .next()
+            .ok_or_else(|| "unexpected end of JSON".to_string())
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next()? {
+            character if character == expected => Ok(()),
+            character => Err(format!("expected '{}', got '{}'", expected, character)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('{' | '[') => {
+                self.nested()?;
+
+                Ok(Value::Nested)
+            }
+            Some(_) => {
+                let mut word = String::new();
+
+                while let Some(character) = self
+                    .chars
+                    .next_if(|c| c.is_ascii_alphanumeric() || "+-.".contains(*c))
+                {
+                    word.push(character);
+                }
+
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    number => number
+                        .parse()
+                        .map(Value::Number)
+                        .map_err(|_| format!("unexpected value '{}'", number)),
+                }
+            }
+            None => Err("unexpected end of JSON".to_string()),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+
+        let mut string = String::new();
+
+        loop {
+            let character = self.chars.next().ok_or("unterminated string")?;
+
+            match character {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = match self.chars.next().ok_or("unterminated string")? {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => {
+                            let hex: String = self.chars.by_ref().take(4).collect();
+
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        character => character,
+                    };
+
+                    string.push(escaped);
+                }
+                character => string.push(character),
+            }
+        }
+    }
+
+    // Skips an array or object, strings and all.
+    fn nested(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+
+        loop {
+            match self.peek().ok_or("unexpected end of JSON")? {
+                '"' => {
+                    self.string()?;
+                }
+                character => {
+                    self.chars.next();
+
+                    match character {
+                        '{' | '[' => depth += 1,
+                        '}' | ']' => depth -= 1,
+                        _ => {}
+                    }
+
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_object, to_args, Value};
+
+    #[test]
+    fn test_octo_options_become_command_line_options() {
+        let json = r##"{
+            "tickrate": 20,
+            "fillColor": "#FFCC00",
+            "backgroundColor": "#996600",
+            "shiftQuirks": true,
+            "loadStoreQuirks": true,
+            "jumpQuirks": false,
+            "clipQuirks": true,
+            "fontStyle": "octo",
+            "touchInputMode": "none"
+        }"##;
+
+        assert_eq!(
+            to_args(json).unwrap(),
+            [
+                "--ipf",
+                "20",
+                "--palette",
+                "FFCC00,996600",
+                "--quirks",
+                "shift,no-jump,no-memory-increment"
+            ]
+        );
+
+        assert!(
+            to_args(r#"{"tickrate": "fast"}"#).is_err(),
+            "A tickrate that isn't a number should be an error!"
+        );
+    }
+
+    #[test]
+    fn test_json_objects_parse_with_nested_values_skipped() {
+        assert_eq!(
+            parse_object(r#" {"a": "x\"yA", "b": -1.5e1, "c": [1, {"d": "]"}], "e": null} "#),
+            Ok(vec![
+                ("a".to_string(), Value::String("x\"yA".to_string())),
+                ("b".to_string(), Value::Number(-15.0)),
+                ("c".to_string(), Value::Nested),
+                ("e".to_string(), Value::Null),
+            ])
+        );
+
+        assert_eq!(parse_object("{}"), Ok(Vec::new()));
+
+        assert!(
+            parse_object(r#"{"a": 1"#).is_err(),
+            "An unterminated object should be an error!"
+        );
+    }
+}
@@ -0,0 +1,504 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chip8_vm::chip8::font::{self, FONT_SIZE};
+use chip8_vm::chip8::tone::ToneSettings;
+use chip8_vm::chip8::{
+    parse_address, Cheat, DumpFormat, Interpreter, InterpreterBuilder, Platform, Quirks, Timing,
+};
+
+use winit::keyboard::{Key, NamedKey};
+
+use crate::crt::CrtSettings;
+use crate::key_map::KeyMap;
+use crate::palette::Palette;
+use crate::scaling::ScalingFilter;
+use crate::touch::TouchZones;
+
+const DEFAULT_TURBO_SPEED: u32 = 4;
+
+const DEFAULT_SCALE: u32 = 20;
+
+// Bigger would be wider than any monitor even for a 64x32 display.
+const MAX_SCALE: u32 = 100;
+
+pub const USAGE: &str = "<path_or_url>... [--debug] [--self-test] [--frame-hash <cycles>] [--deterministic] [--seed <n>] [--ipf <n>] [--max-ipf <n>] [--platform <name>] [--quirks <list>] [--start-addr <address>] [--memory-map <name=address,...>] [--font <name|path>] [--stack-depth <n|unlimited>] [--scale <n>] [--snap-resize] [--borderless] [--always-on-top] [--position <x,y>] [--no-auto-pause] [--start-paused] [--single-threaded] [--close-on-exit] [--keys <layout>] [--touch-zones <zone=key,...>] [--turbo-key <key>] [--turbo-speed <n>] [--speed <percent>] [--timing <fixed|vip>] [--decode-cache] [--strict-sys] [--crt] [--crt-settings <name=value,...>] [--pixel-grid] [--filter <nearest|smooth>] [--timer] [--palette <name|colors>] [--reduce-flashing] [--tone <name=value,...>] [--volume <percent>] [--sound-indicator] [--record-audio <path>] [--record-video <path>] [--script <path>] [--remote <address>] [--stream <address>] [--dump-display <ascii|pbm|svg>] [--dump-file <path>] [--cheat <address=value>] [--freeze <address=value>] [--cheats <path>] [--trace <path>] [--coverage <path>] [--record-replay <path>] [--compare-trace <path>] [--log-level <filter>]";
+
+pub struct Options {
+    pub rom_paths: Vec<String>,
+    pub debug: bool,
+    // Runs the built-in opcode checks with the chosen platform and quirks
+    // instead of a ROM.
+    pub self_test: bool,
+    pub frame_hash_cycles: Option<u64>,
+    pub deterministic: bool,
+    pub seed: Option<u64>,
+    // None picks a speed to suit each ROM.
+    pub instructions_per_frame: Option<u32>,
+    // The most instructions run per 60 Hz tick, fast-forwarding and catching
+    // up included, so a ROM set too fast can't hold up the window.
+    pub max_instructions_per_frame: Option<u32>,
+    pub platform: Platform,
+    pub timing: Timing,
+    pub decode_cache: bool,
+    // Halt on 0NNN machine code calls instead of skipping them.
+    pub strict_system_calls: bool,
+    pub quirks: Option<String>,
+    pub start_address: Option<u16>,
+    // Moves the platform's font, display buffer or program, e.g. font=0x050.
+    pub memory_map: Option<String>,
+    // One of the built-in fonts or a font file, replacing the platform's.
+    pub font: Option<[u8; FONT_SIZE]>,
+    // Overrides the platform's stack depth; Some(None) is unlimited.
+    pub stack_depth: Option<Option<usize>>,
+    // Window pixels per CHIP-8 pixel when a window opens.
+    pub scale: u32,
+    // Snap resized windows to whole multiples of the display size.
+    pub snap_resize: bool,
+    // Windows without a title bar or frame.
+    pub borderless: bool,
+    // Windows kept above all others.
+    pub always_on_top: bool,
+    // Where windows open on the desktop, in screen pixels from its top left.
+    pub position: Option<(i32, i32)>,
+    pub auto_pause: bool,
+    // Open windows paused before the first instruction runs.
+    pub start_paused: bool,
+    // Run every interpreter from the event loop instead of a thread each.
+    pub single_threaded: bool,
+    // Closes a window once its program runs 00FD.
+    pub close_on_exit: bool,
+    // The keyboard keys for keypad keys 0 to F.
+    pub keys: KeyMap,
+    pub touch_zones: TouchZones,
+    pub turbo_key: Key,
+    pub turbo_speed: u32,
+    pub speed_percent: u32,
+    pub crt: Option<CrtSettings>,
+    pub pixel_grid: bool,
+    pub filter: ScalingFilter,
+    // Show the frame counter and run timer when a window opens.
+    pub timer: bool,
+    pub tone: ToneSettings,
+    pub volume: u32,
+    // The display's colors.
+    pub palette: Palette,
+    // Blend frames and limit how often the whole display can flash.
+    pub reduce_flashing: bool,
+    // Flash a border while the sound timer runs.
+    pub sound_indicator: bool,
+    // A WAV file to record the buzzer to.
+    pub record_audio: Option<PathBuf>,
+    pub record_video: Option<PathBuf>,
+    // A rhai script run after every frame in each window.
+    pub script: Option<PathBuf>,
+    // Where to serve the HTTP remote-control API, e.g. 127.0.0.1:8080.
+    pub remote: Option<String>,
+    // Where chip8-viewer clients can connect to watch and play, e.g.
+    // 0.0.0.0:8642.
+    pub stream: Option<String>,
+    pub dump_format: DumpFormat,
+    // Where display dumps go; stdout if not set.
+    pub dump_file: Option<String>,
+    // Set by --dump-display, which dumps the display after a --frame-hash run.
+    // In a window it only picks the format F12 dumps in.
+    pub dump_after_run: bool,
+    pub cheats: Vec<Cheat>,
+    // Written during a --frame-hash run, one line per instruction.
+    pub trace_file: Option<String>,
+    // A reference trace to run the ROM against instead of opening a window.
+    pub compare_trace: Option<String>,
+    // Where to write which parts of the ROM ran and which were read as data,
+    // after a --frame-hash run or when the window closes.
+    pub coverage_file: Option<String>,
+    // Where to write the keys pressed in every frame, after a --frame-hash
+    // run or when the window closes, to check with verify-replay.
+    pub replay_file: Option<String>,
+    // What to log, as a level or env-filter directives; RUST_LOG if not set.
+    pub log_level: Option<String>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            rom_paths: Vec::new(),
+            debug: false,
+            self_test: false,
+            frame_hash_cycles: None,
+            deterministic: false,
+            seed: None,
+            instructions_per_frame: None,
+            max_instructions_per_frame: None,
+            platform: Platform::default(),
+            timing: Timing::default(),
+            decode_cache: false,
+            strict_system_calls: false,
+            quirks: None,
+            start_address: None,
+            memory_map: None,
+            font: None,
+            stack_depth: None,
+            scale: DEFAULT_SCALE,
+            snap_resize: false,
+            borderless: false,
+            always_on_top: false,
+            position: None,
+            auto_pause: true,
+            start_paused: false,
+            single_threaded: false,
+            close_on_exit: false,
+            keys: KeyMap::default(),
+            touch_zones: TouchZones::default(),
+            turbo_key: Key::Named(NamedKey::Tab),
+            turbo_speed: DEFAULT_TURBO_SPEED,
+            speed_percent: 100,
+            crt: None,
+            pixel_grid: false,
+            filter: ScalingFilter::default(),
+            timer: false,
+            tone: ToneSettings::default(),
+            volume: 100,
+            palette: Palette::default(),
+            reduce_flashing: false,
+            sound_indicator: false,
+            record_audio: None,
+            record_video: None,
+            script: None,
+            remote: None,
+            stream: None,
+            dump_format: DumpFormat::default(),
+            dump_file: None,
+            dump_after_run: false,
+            cheats: Vec::new(),
+            trace_file: None,
+            coverage_file: None,
+            replay_file: None,
+            compare_trace: None,
+            log_level: None,
+        };
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--debug" => options.debug = true,
+                "--self-test" => options.self_test = true,
+                "--deterministic" => options.deterministic = true,
+                "--no-auto-pause" => options.auto_pause = false,
+                "--start-paused" => options.start_paused = true,
+                "--single-threaded" => options.single_threaded = true,
+                "--snap-resize" => options.snap_resize = true,
+                "--borderless" => options.borderless = true,
+                "--always-on-top" => options.always_on_top = true,
+                "--close-on-exit" => options.close_on_exit = true,
+                "--decode-cache" => options.decode_cache = true,
+                "--strict-sys" => options.strict_system_calls = true,
+                "--pixel-grid" => options.pixel_grid = true,
+                "--timer" => options.timer = true,
+                "--sound-indicator" => options.sound_indicator = true,
+                "--reduce-flashing" => options.reduce_flashing = true,
+                "--dump-display" => {
+                    options.dump_format = parse_value(arg, args.next())?;
+                    options.dump_after_run = true;
+                }
+                "--dump-file" => options.dump_file = Some(parse_value(arg, args.next())?),
+                "--cheat" => options.cheats.push(parse_value(arg, args.next())?),
+                "--freeze" => {
+                    let cheat: Cheat = parse_value(arg, args.next())?;
+
+                    options.cheats.push(Cheat {
+                        freeze: true,
+                        ..cheat
+                    });
+                }
+                "--cheats" => {
+                    let path: String = parse_value(arg, args.next())?;
+
+                    let text = fs::read_to_string(&path)
+                        .map_err(|error| format!("Failed to read {}: {}", path, error))?;
+
+                    let cheats =
+                        Cheat::parse_file(&text).map_err(|error| format!("{}: {}", path, error))?;
+
+                    options.cheats.extend(cheats);
+                }
+                "--remote" => options.remote = Some(parse_value(arg, args.next())?),
+                "--stream" => options.stream = Some(parse_value(arg, args.next())?),
+                "--trace" => options.trace_file = Some(parse_value(arg, args.next())?),
+                "--coverage" => options.coverage_file = Some(parse_value(arg, args.next())?),
+                "--record-replay" => options.replay_file = Some(parse_value(arg, args.next())?),
+                "--compare-trace" => options.compare_trace = Some(parse_value(arg, args.next())?),
+                "--log-level" => options.log_level = Some(parse_value(arg, args.next())?),
+                "--crt" => options.crt = Some(options.crt.unwrap_or_default()),
+                "--crt-settings" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.crt = Some(CrtSettings::parse(&value)?);
+                }
+                "--palette" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.palette = Palette::parse(&value)?;
+                }
+                "--tone" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.tone = ToneSettings::parse(&value)?;
+                }
+                "--volume" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.volume = value
+                        .trim_end_matches('%')
+                        .parse()
+                        .ok()
+                        .filter(|volume| (0..=100).contains(volume))
+                        .ok_or_else(|| format!("Invalid volume (expected 0-100%): {}", value))?;
+                }
+                "--record-audio" => {
+                    let path: String = parse_value(arg, args.next())?;
+
+                    options.record_audio = Some(PathBuf::from(path));
+                }
+                "--record-video" => {
+                    let path: String = parse_value(arg, args.next())?;
+
+                    options.record_video = Some(PathBuf::from(path));
+                }
+                "--script" => {
+                    let path: String = parse_value(arg, args.next())?;
+
+                    options.script = Some(PathBuf::from(path));
+                }
+                "--frame-hash" => options.frame_hash_cycles = Some(parse_value(arg, args.next())?),
+                "--seed" => options.seed = Some(parse_value(arg, args.next())?),
+                "--ipf" => options.instructions_per_frame = Some(parse_value(arg, args.next())?),
+                "--max-ipf" => {
+                    options.max_instructions_per_frame = Some(parse_value(arg, args.next())?)
+                }
+                "--platform" => options.platform = parse_value(arg, args.next())?,
+                "--timing" => options.timing = parse_value(arg, args.next())?,
+                "--filter" => options.filter = parse_value(arg, args.next())?,
+                "--quirks" => options.quirks = Some(parse_value(arg, args.next())?),
+                "--memory-map" => options.memory_map = Some(parse_value(arg, args.next())?),
+                "--font" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.font = Some(load_font(&value)?);
+                }
+                "--stack-depth" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.stack_depth = Some(parse_stack_depth(&value)?);
+                }
+                "--keys" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.keys = KeyMap::parse(&value)?;
+                }
+                "--touch-zones" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.touch_zones = TouchZones::parse(&value)?;
+                }
+                "--turbo-key" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.turbo_key = parse_key(&value)?;
+                }
+                "--turbo-speed" => options.turbo_speed = parse_value(arg, args.next())?,
+                "--scale" => {
+                    let scale: u32 = parse_value(arg, args.next())?;
+
+                    options.scale = scale.clamp(1, MAX_SCALE);
+                }
+                "--speed" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.speed_percent = value
+                        .trim_end_matches('%')
+                        .parse()
+                        .ok()
+                        .filter(|speed| (1..=100).contains(speed))
+                        .ok_or_else(|| format!("Invalid speed (expected 1-100%): {}", value))?;
+                }
+                "--position" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.position = Some(parse_position(&value)?);
+                }
+                "--start-addr" => {
+                    let value: String = parse_value(arg, args.next())?;
+
+                    options.start_address = Some(
+                        parse_address(&value)
+                            .ok_or_else(|| format!("Invalid address: {}", value))?,
+                    );
+                }
+                flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+                path => options.rom_paths.push(path.to_string()),
+            }
+        }
+
+        if options.trace_file.is_some() && options.frame_hash_cycles.is_none() {
+            return Err("--trace needs --frame-hash".to_string());
+        }
+
+        let traced = options.trace_file.is_some() || options.compare_trace.is_some();
+
+        if traced && options.rom_paths.len() > 1 {
+            return Err("Traces can only be taken of a single ROM".to_string());
+        }
+
+        if options.record_audio.is_some() && options.rom_paths.len() > 1 {
+            return Err("Audio can only be recorded from a single ROM".to_string());
+        }
+
+        if options.record_video.is_some() && options.rom_paths.len() > 1 {
+            return Err("Video can only be recorded from a single ROM".to_string());
+        }
+
+        // Headless hashing is always deterministic so the output is comparable
+        // between runs, and so are traces.
+        if options.frame_hash_cycles.is_some() || options.compare_trace.is_some() {
+            options.deterministic = true;
+        }
+
+        Ok(options)
+    }
+
+    // The platform's quirks with --quirks applied.
+    pub fn quirks(&self) -> Result<Quirks, String> {
+        let mut quirks = self.platform.quirks();
+
+        if let Some(names) = &self.quirks {
+            quirks.apply(names)?;
+        }
+
+        Ok(quirks)
+    }
+
+    pub fn interpreter_builder(&self) -> Result<InterpreterBuilder, String> {
+        let seed = match self.seed {
+            Some(seed) => seed,
+            None if self.deterministic => 0,
+            None => seed_from_clock(),
+        };
+
+        let quirks = self.quirks()?;
+
+        let mut memory_map = self.platform.memory_map();
+
+        if let Some(settings) = &self.memory_map {
+            memory_map.apply(settings)?;
+        }
+
+        if let Some(address) = self.start_address {
+            memory_map.program_address = address;
+        }
+
+        let mut builder = Interpreter::builder().platform(self.platform);
+
+        if let Some(font) = self.font {
+            builder = builder.font(font);
+        }
+
+        if let Some(stack_depth) = self.stack_depth {
+            builder = builder.stack_depth(stack_depth);
+        }
+
+        if let Some(max) = self.max_instructions_per_frame {
+            builder = builder.max_instructions_per_frame(max);
+        }
+
+        let builder = match self.instructions_per_frame {
+            Some(instructions_per_frame) => builder.instructions_per_frame(instructions_per_frame),
+            None => builder.speed_heuristics(true),
+        };
+
+        Ok(builder
+            .quirks(quirks)
+            .memory_map(memory_map)
+            .timing(self.timing)
+            .decode_cache(self.decode_cache)
+            .strict_system_calls(self.strict_system_calls)
+            .coverage(self.coverage_file.is_some())
+            .record_replay(self.replay_file.is_some())
+            .cheats(self.cheats.clone())
+            .seed(seed))
+    }
+}
+
+// A built-in font by name, or else a font file.
+fn load_font(value: &str) -> Result<[u8; FONT_SIZE], String> {
+    if let Some(font) = font::named_font(value) {
+        return Ok(font);
+    }
+
+    let data = fs::read(value).map_err(|error| {
+        format!(
+            "Failed to read font {} ({}); the built-in fonts are: {}",
+            value,
+            error,
+            font::FONT_NAMES.join(", ")
+        )
+    })?;
+
+    font::parse_font(&data).map_err(|error| format!("{}: {}", value, error))
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Missing value for {}", flag))?;
+
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+pub fn parse_position(value: &str) -> Result<(i32, i32), String> {
+    value
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        .ok_or_else(|| format!("Invalid position (expected x,y): {}", value))
+}
+
+fn parse_stack_depth(value: &str) -> Result<Option<usize>, String> {
+    if value == "unlimited" {
+        return Ok(None);
+    }
+
+    match value.parse() {
+        Ok(depth) if depth > 0 => Ok(Some(depth)),
+        _ => Err(format!(
+            "Invalid stack depth (expected a number or unlimited): {}",
+            value
+        )),
+    }
+}
+
+// A single character, or the name of a key such as "tab" or "space".
+fn parse_key(name: &str) -> Result<Key, String> {
+    let named_key = match name.to_ascii_lowercase().as_str() {
+        "tab" => NamedKey::Tab,
+        "space" => NamedKey::Space,
+        "enter" => NamedKey::Enter,
+        "backspace" => NamedKey::Backspace,
+        "shift" => NamedKey::Shift,
+        "control" | "ctrl" => NamedKey::Control,
+        "alt" => NamedKey::Alt,
+        _ if name.chars().count() == 1 => {
+            return Ok(Key::Character(name.to_lowercase().into()));
+        }
+        _ => return Err(format!("Unknown key: {}", name)),
+    };
+
+    Ok(Key::Named(named_key))
+}
+
+fn seed_from_clock() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default()
+}
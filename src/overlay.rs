@@ -0,0 +1,663 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::emulator::EmulatorStatus;
+
+// Overlays are drawn straight into the window's framebuffer after the CHIP-8
+// display, using a tiny built-in 3x5 pixel font.
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+// Each overlay pixel covers this many window pixels.
+pub const TEXT_SCALE: usize = 2;
+
+pub const LINE_HEIGHT: usize = (GLYPH_HEIGHT + 2) * TEXT_SCALE;
+
+const BACKGROUND_COLOR: u32 = 0xff202020;
+const TEXT_COLOR: u32 = 0xff40ff40;
+const GRAPH_COLOR: u32 = 0xff40a0ff;
+const SLOW_FRAME_COLOR: u32 = 0xffff4040;
+const TARGET_LINE_COLOR: u32 = 0xff808080;
+const SOUND_BORDER_COLOR: u32 = 0xffffc040;
+const TIMER_COLOR: u32 = 0xffffffff;
+const COLUMN_COLOR: u32 = 0xffffffff;
+
+const SOUND_BORDER_WIDTH: usize = 4 * TEXT_SCALE;
+const COLUMN_GAP: usize = 4 * TEXT_SCALE;
+
+const FRAME_HISTORY: usize = 120;
+
+const TARGET_FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+const MAX_PACING_INTERVAL: Duration = Duration::from_millis(250);
+
+// The graph is tall enough to show frames taking up to twice the target time.
+const GRAPH_HEIGHT: usize = 40;
+
+pub struct Canvas<'a> {
+    pixels: &'a mut [u32],
+    width: usize,
+    height: usize,
+    opacity: f32,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(pixels: &'a mut [u32], width: usize, height: usize) -> Self {
+        Self {
+            pixels,
+            width,
+            height,
+            opacity: 1.0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Applies to everything drawn afterwards, 0.0 (invisible) to 1.0 (opaque).
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        let end_x = (x + width).min(self.width);
+        let end_y = (y + height).min(self.height);
+
+        for row in y.min(end_y)..end_y {
+            let pixels =
+                &mut self.pixels[row * self.width + x.min(end_x)..row * self.width + end_x];
+
+            if self.opacity >= 1.0 {
+                pixels.fill(color);
+            } else {
+                for pixel in pixels {
+                    *pixel = blend(*pixel, color, self.opacity);
+                }
+            }
+        }
+    }
+
+    // Text is drawn in upper case; characters without a glyph show as '?'.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: u32) {
+        for (index, character) in text.chars().enumerate() {
+            let glyph_x = x + index * (GLYPH_WIDTH + 1) * TEXT_SCALE;
+
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits >> (GLYPH_WIDTH - 1 - column) & 1 == 1 {
+                        self.fill_rect(
+                            glyph_x + column * TEXT_SCALE,
+                            y + row * TEXT_SCALE,
+                            TEXT_SCALE,
+                            TEXT_SCALE,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Draws lines of text on a dark box, sized to fit the longest line.
+    pub fn draw_text_box(&mut self, x: usize, y: usize, lines: &[String], color: u32) {
+        let width = text_box_width(lines);
+        let height = text_box_height(lines.len());
+
+        self.fill_rect(x, y, width, height, BACKGROUND_COLOR);
+
+        for (index, line) in lines.iter().enumerate() {
+            self.draw_text(
+                x + TEXT_SCALE * 2,
+                y + TEXT_SCALE * 2 + index * LINE_HEIGHT,
+                line,
+                color,
+            );
+        }
+    }
+}
+
+// Text boxes side by side, the row of them centered in the window.
+pub fn draw_text_columns(canvas: &mut Canvas<'_>, columns: &[Vec<String>]) {
+    let widths: Vec<usize> = columns.iter().map(|lines| text_box_width(lines)).collect();
+
+    let total_width = widths.iter().sum::<usize>() + COLUMN_GAP * columns.len().saturating_sub(1);
+
+    let mut x = canvas.width().saturating_sub(total_width) / 2;
+
+    for (lines, width) in columns.iter().zip(widths) {
+        let y = canvas.height().saturating_sub(text_box_height(lines.len())) / 2;
+
+        canvas.draw_text_box(x, y, lines, COLUMN_COLOR);
+
+        x += width + COLUMN_GAP;
+    }
+}
+
+pub fn text_box_height(lines: usize) -> usize {
+    lines * LINE_HEIGHT + TEXT_SCALE * 2
+}
+
+pub fn text_box_width(lines: &[String]) -> usize {
+    let longest_line = lines.iter().map(|line| line.chars().count()).max();
+
+    longest_line.unwrap_or(0) * (GLYPH_WIDTH + 1) * TEXT_SCALE + TEXT_SCALE * 3
+}
+
+fn blend(background: u32, foreground: u32, opacity: f32) -> u32 {
+    let channel = |shift: u32| {
+        let background = (background >> shift & 0xff) as f32;
+        let foreground = (foreground >> shift & 0xff) as f32;
+
+        ((background + (foreground - background) * opacity) as u32) << shift
+    };
+
+    0xff000000 | channel(16) | channel(8) | channel(0)
+}
+
+const TOAST_DURATION: Duration = Duration::from_millis(2000);
+const TOAST_FADE: Duration = Duration::from_millis(500);
+const MAX_TOASTS: usize = 4;
+
+const TOAST_COLOR: u32 = 0xffffffff;
+
+// Short-lived messages stacked in the bottom-left corner, newest at the
+// bottom, fading out before they disappear.
+#[derive(Default)]
+pub struct Toasts {
+    messages: VecDeque<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn show(&mut self, message: impl Into<String>) {
+        if self.messages.len() == MAX_TOASTS {
+            self.messages.pop_front();
+        }
+
+        self.messages.push_back((message.into(), Instant::now()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<'_>, now: Instant) {
+        self.messages
+            .retain(|(_, shown)| now.duration_since(*shown) < TOAST_DURATION);
+
+        let box_height = text_box_height(1);
+
+        for (index, (message, shown)) in self.messages.iter().rev().enumerate() {
+            let remaining = TOAST_DURATION.saturating_sub(now.duration_since(*shown));
+
+            canvas.set_opacity(remaining.as_secs_f32() / TOAST_FADE.as_secs_f32());
+
+            let y = canvas
+                .height()
+                .saturating_sub((index + 1) * (box_height + TEXT_SCALE));
+
+            canvas.draw_text_box(0, y, std::slice::from_ref(message), TOAST_COLOR);
+        }
+
+        canvas.set_opacity(1.0);
+    }
+}
+
+// A border around the window while the buzzer sounds, so the cue games rely
+// on can be seen as well as heard.
+pub fn draw_sound_indicator(canvas: &mut Canvas<'_>) {
+    let (width, height) = (canvas.width(), canvas.height());
+
+    let (border, color) = (SOUND_BORDER_WIDTH, SOUND_BORDER_COLOR);
+
+    canvas.fill_rect(0, 0, width, border, color);
+    canvas.fill_rect(0, height.saturating_sub(border), width, border, color);
+    canvas.fill_rect(0, 0, border, height, color);
+    canvas.fill_rect(width.saturating_sub(border), 0, border, height, color);
+}
+
+// How well the window keeps to 60 Hz, counted over every frame it presents
+// against the time since the one before.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FramePacing {
+    pub presented: u64,
+    // 60 Hz frames that passed with nothing new presented.
+    pub dropped: u64,
+    // Frames presented within half a 60 Hz frame of the last, so one of
+    // them was never seen.
+    pub duplicated: u64,
+    // Frames presented more than a quarter of a 60 Hz frame early or late.
+    pub stutters: u64,
+}
+
+impl FramePacing {
+    pub fn record_interval(&mut self, interval: Duration) {
+        self.presented += 1;
+
+        // A gap this long is the window not drawing at all, e.g. after the
+        // program exits, rather than frames going missing.
+        if interval > MAX_PACING_INTERVAL {
+            return;
+        }
+
+        let frames = interval.as_secs_f64() / TARGET_FRAME_TIME.as_secs_f64();
+
+        if frames < 0.5 {
+            self.duplicated += 1;
+        } else {
+            self.dropped += (frames.round() as u64).saturating_sub(1);
+        }
+
+        if (frames - 1.0).abs() > 0.25 {
+            self.stutters += 1;
+        }
+    }
+}
+
+// Emulation speed as reported by the interpreter thread, plus how evenly the
+// window is actually being redrawn.
+#[derive(Default)]
+pub struct PerfOverlay {
+    pub visible: bool,
+    frame_times: VecDeque<Duration>,
+    last_frame: Option<Instant>,
+    pacing: FramePacing,
+}
+
+impl PerfOverlay {
+    pub fn record_frame(&mut self, now: Instant) {
+        if let Some(last_frame) = self.last_frame {
+            if self.frame_times.len() == FRAME_HISTORY {
+                self.frame_times.pop_front();
+            }
+
+            self.frame_times.push_back(now - last_frame);
+
+            self.pacing.record_interval(now - last_frame);
+        }
+
+        self.last_frame = Some(now);
+    }
+
+    // Since the window opened, whether or not the overlay was showing.
+    pub fn pacing(&self) -> FramePacing {
+        self.pacing
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<'_>, status: Option<EmulatorStatus>) {
+        let mut lines = Vec::new();
+
+        match status {
+            Some(status) => lines.push(format!(
+                "EMU {} FPS {} IPS",
+                status.frames_per_second, status.instructions_per_second
+            )),
+            None => lines.push("EMU -".to_string()),
+        }
+
+        if !self.frame_times.is_empty() {
+            let total: Duration = self.frame_times.iter().sum();
+
+            let average = total / self.frame_times.len() as u32;
+
+            let worst = self.frame_times.iter().max().copied().unwrap_or_default();
+
+            lines.push(format!(
+                "DRAW {:.0} FPS",
+                1.0 / average.as_secs_f64().max(f64::EPSILON)
+            ));
+
+            lines.push(format!(
+                "{:.1} MS AVG {:.1} MS MAX",
+                average.as_secs_f64() * 1000.0,
+                worst.as_secs_f64() * 1000.0
+            ));
+        }
+
+        lines.push(format!(
+            "{} DROPPED {} DUPLICATED {} STUTTERS",
+            self.pacing.dropped, self.pacing.duplicated, self.pacing.stutters
+        ));
+
+        canvas.draw_text_box(0, 0, &lines, TEXT_COLOR);
+
+        self.draw_graph(canvas, 0, lines.len() * LINE_HEIGHT + TEXT_SCALE * 2);
+    }
+
+    // One bar per redraw, oldest on the left. The grey line marks the 60 Hz
+    // target; bars above it are drawn in red.
+    fn draw_graph(&self, canvas: &mut Canvas<'_>, x: usize, y: usize) {
+        let bar_width = TEXT_SCALE;
+
+        canvas.fill_rect(
+            x,
+            y,
+            FRAME_HISTORY * bar_width,
+            GRAPH_HEIGHT,
+            BACKGROUND_COLOR,
+        );
+
+        let full_scale = TARGET_FRAME_TIME.as_secs_f64() * 2.0;
+
+        for (index, frame_time) in self.frame_times.iter().enumerate() {
+            let ratio = (frame_time.as_secs_f64() / full_scale).min(1.0);
+
+            let bar_height = ((ratio * GRAPH_HEIGHT as f64) as usize).max(1);
+
+            let color = if *frame_time > TARGET_FRAME_TIME + TARGET_FRAME_TIME / 4 {
+                SLOW_FRAME_COLOR
+            } else {
+                GRAPH_COLOR
+            };
+
+            canvas.fill_rect(
+                x + index * bar_width,
+                y + GRAPH_HEIGHT - bar_height,
+                bar_width,
+                bar_height,
+                color,
+            );
+        }
+
+        canvas.fill_rect(
+            x,
+            y + GRAPH_HEIGHT / 2,
+            FRAME_HISTORY * bar_width,
+            1,
+            TARGET_LINE_COLOR,
+        );
+    }
+}
+
+// Emulated frames and wall-clock time since the timer was last reset, in the
+// top-right corner, for timing runs. Game time is the frame count at 60 Hz,
+// so slow motion, turbo and pauses don't change it.
+pub struct TimerOverlay {
+    pub visible: bool,
+    start_frame: u64,
+    start_time: Instant,
+}
+
+impl TimerOverlay {
+    pub fn new(visible: bool) -> Self {
+        Self {
+            visible,
+            start_frame: 0,
+            start_time: Instant::now(),
+        }
+    }
+
+    // Counts from `frame`, the emulator's frame count as of now.
+    pub fn reset(&mut self, frame: u64) {
+        self.start_frame = frame;
+        self.start_time = Instant::now();
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<'_>, frame: u64, now: Instant) {
+        let frames = frame.saturating_sub(self.start_frame);
+
+        let lines = [
+            format!("FRAME {}", frames),
+            format!(
+                "GAME {}",
+                format_time(Duration::from_millis(frames * 1000 / 60))
+            ),
+            format!(
+                "REAL {}",
+                format_time(now.saturating_duration_since(self.start_time))
+            ),
+        ];
+
+        let x = canvas.width().saturating_sub(text_box_width(&lines));
+
+        canvas.draw_text_box(x, 0, &lines, TIMER_COLOR);
+    }
+}
+
+// e.g. 1:05.25, or 1:01:05.25 from an hour on.
+fn format_time(time: Duration) -> String {
+    let centiseconds = time.as_millis() / 10;
+
+    let (seconds, centiseconds) = (centiseconds / 100, centiseconds % 100);
+
+    let (minutes, seconds) = (seconds / 60, seconds % 60);
+
+    if minutes >= 60 {
+        format!(
+            "{}:{:02}:{:02}.{:02}",
+            minutes / 60,
+            minutes % 60,
+            seconds,
+            centiseconds
+        )
+    } else {
+        format!("{}:{:02}.{:02}", minutes, seconds, centiseconds)
+    }
+}
+
+// The COSMAC VIP keypad layout.
+pub const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+const KEY_SIZE: usize = 36;
+const KEY_GAP: usize = 4;
+const KEYPAD_SIZE: usize = KEY_GAP + 4 * (KEY_SIZE + KEY_GAP);
+
+const KEY_COLOR: u32 = 0xff404040;
+const PRESSED_KEY_COLOR: u32 = 0xffffb000;
+const KEY_LABEL_COLOR: u32 = 0xffffffff;
+
+// A 4x4 hex keypad in the bottom-right corner that shows which keys are held
+// and can be clicked or tapped.
+#[derive(Default)]
+pub struct KeypadOverlay {
+    pub visible: bool,
+}
+
+impl KeypadOverlay {
+    pub fn draw(&self, canvas: &mut Canvas<'_>, pressed_keys: [bool; 16]) {
+        let (origin_x, origin_y) = keypad_origin(canvas.width(), canvas.height());
+
+        canvas.fill_rect(
+            origin_x,
+            origin_y,
+            KEYPAD_SIZE,
+            KEYPAD_SIZE,
+            BACKGROUND_COLOR,
+        );
+
+        for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            for (column, &key) in keys.iter().enumerate() {
+                let x = origin_x + KEY_GAP + column * (KEY_SIZE + KEY_GAP);
+                let y = origin_y + KEY_GAP + row * (KEY_SIZE + KEY_GAP);
+
+                let color = if pressed_keys[key as usize] {
+                    PRESSED_KEY_COLOR
+                } else {
+                    KEY_COLOR
+                };
+
+                canvas.fill_rect(x, y, KEY_SIZE, KEY_SIZE, color);
+
+                canvas.draw_text(
+                    x + (KEY_SIZE - GLYPH_WIDTH * TEXT_SCALE) / 2,
+                    y + (KEY_SIZE - GLYPH_HEIGHT * TEXT_SCALE) / 2,
+                    &format!("{:X}", key),
+                    KEY_LABEL_COLOR,
+                );
+            }
+        }
+    }
+
+    // The key under a window position, if the overlay is showing.
+    pub fn key_at(&self, x: f64, y: f64, width: usize, height: usize) -> Option<u8> {
+        if !self.visible || x < 0.0 || y < 0.0 {
+            return None;
+        }
+
+        let (origin_x, origin_y) = keypad_origin(width, height);
+
+        let x = (x as usize).checked_sub(origin_x + KEY_GAP)?;
+        let y = (y as usize).checked_sub(origin_y + KEY_GAP)?;
+
+        let (column, row) = (x / (KEY_SIZE + KEY_GAP), y / (KEY_SIZE + KEY_GAP));
+
+        // Clicks in the gaps between keys don't count.
+        if column >= 4
+            || row >= 4
+            || x % (KEY_SIZE + KEY_GAP) >= KEY_SIZE
+            || y % (KEY_SIZE + KEY_GAP) >= KEY_SIZE
+        {
+            return None;
+        }
+
+        Some(KEYPAD_LAYOUT[row][column])
+    }
+}
+
+fn keypad_origin(width: usize, height: usize) -> (usize, usize) {
+    (
+        width.saturating_sub(KEYPAD_SIZE),
+        height.saturating_sub(KEYPAD_SIZE),
+    )
+}
+
+// Rows from top to bottom, three bits each with the leftmost pixel in the
+// most significant bit.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{format_time, FramePacing, KeypadOverlay, KEYPAD_SIZE, KEY_GAP, KEY_SIZE};
+
+    #[test]
+    fn test_keypad_overlay_maps_positions_to_keys() {
+        let keypad = KeypadOverlay { visible: true };
+
+        let (width, height) = (640, 320);
+
+        let origin_x = (width - KEYPAD_SIZE + KEY_GAP) as f64;
+        let origin_y = (height - KEYPAD_SIZE + KEY_GAP) as f64;
+
+        let key_center = |column: usize, row: usize| {
+            (
+                origin_x + (column * (KEY_SIZE + KEY_GAP) + KEY_SIZE / 2) as f64,
+                origin_y + (row * (KEY_SIZE + KEY_GAP) + KEY_SIZE / 2) as f64,
+            )
+        };
+
+        let (x, y) = key_center(0, 0);
+        assert_eq!(keypad.key_at(x, y, width, height), Some(0x1));
+
+        let (x, y) = key_center(1, 3);
+        assert_eq!(keypad.key_at(x, y, width, height), Some(0x0));
+
+        let (x, y) = key_center(3, 2);
+        assert_eq!(keypad.key_at(x, y, width, height), Some(0xE));
+
+        assert_eq!(keypad.key_at(10.0, 10.0, width, height), None);
+    }
+
+    #[test]
+    fn test_timer_formats_minutes_and_hours() {
+        assert_eq!(format_time(Duration::from_millis(65_259)), "1:05.25");
+        assert_eq!(format_time(Duration::ZERO), "0:00.00");
+        assert_eq!(
+            format_time(Duration::from_secs(3665)),
+            "1:01:05.00",
+            "Runs over an hour should show the hours!"
+        );
+    }
+
+    #[test]
+    fn test_frame_pacing_counts_drops_duplicates_and_stutters() {
+        let mut pacing = FramePacing::default();
+
+        let milliseconds = Duration::from_millis;
+
+        // On time, a few milliseconds off, two frames late, a duplicate, and
+        // a long gap that isn't counted against the pacing.
+        for interval in [16, 18, 50, 2, 1000].map(milliseconds) {
+            pacing.record_interval(interval);
+        }
+
+        assert_eq!(
+            pacing,
+            FramePacing {
+                presented: 5,
+                dropped: 2,
+                duplicated: 1,
+                stutters: 2,
+            }
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+
+// The two colors of a CHIP-8 display, as ARGB. MegaChip programs in color
+// mode bring their own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub background: u32,
+    pub foreground: u32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            background: 0xFF000000,
+            foreground: 0xFFFFFFFF,
+        }
+    }
+}
+
+// Besides the classic look, pairs chosen for contrast (all well over the 7:1
+// that WCAG asks of text) and for being told apart with common color
+// blindness, plus a softer amber that's easier on the eyes in the dark.
+const NAMED_PALETTES: [(&str, Palette); 5] = [
+    (
+        "classic",
+        Palette {
+            background: 0xFF000000,
+            foreground: 0xFFFFFFFF,
+        },
+    ),
+    (
+        "inverted",
+        Palette {
+            background: 0xFFFFFFFF,
+            foreground: 0xFF000000,
+        },
+    ),
+    (
+        "yellow",
+        Palette {
+            background: 0xFF000000,
+            foreground: 0xFFFFFF00,
+        },
+    ),
+    (
+        "blue",
+        Palette {
+            background: 0xFF00005A,
+            foreground: 0xFFFFFFFF,
+        },
+    ),
+    (
+        "amber",
+        Palette {
+            background: 0xFF1A0F00,
+            foreground: 0xFFFFB000,
+        },
+    ),
+];
+
+impl Palette {
+    // A named palette, or a foreground and background in hex such as
+    // "FFB000,1A0F00".
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if let Some((_, palette)) = NAMED_PALETTES.iter().find(|(name, _)| *name == value) {
+            return Ok(*palette);
+        }
+
+        let color = |hex: &str| {
+            let hex = hex.trim().trim_start_matches('#');
+
+            u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .map(|color| 0xFF000000 | color)
+        };
+
+        value
+            .split_once(',')
+            .and_then(|(foreground, background)| {
+                Some(Self {
+                    background: color(background)?,
+                    foreground: color(foreground)?,
+                })
+            })
+            .ok_or_else(|| {
+                let names: Vec<&str> = NAMED_PALETTES.iter().map(|(name, _)| *name).collect();
+
+                format!(
+                    "Unknown palette '{}', expected one of: {}, or two colors such as FFB000,1A0F00",
+                    value,
+                    names.join(", ")
+                )
+            })
+    }
+
+    // The named palette after this one, wrapping around, or the first for a
+    // palette of other colors.
+    pub fn next_named(self) -> Self {
+        let index = NAMED_PALETTES
+            .iter()
+            .position(|(_, palette)| *palette == self)
+            .map_or(0, |index| (index + 1) % NAMED_PALETTES.len());
+
+        NAMED_PALETTES[index].1
+    }
+
+    // Part way from the background (0.0) to the foreground (1.0).
+    pub fn mix(self, amount: f32) -> u32 {
+        let channel = |shift: u32| {
+            let background = (self.background >> shift & 0xFF) as f32;
+            let foreground = (self.foreground >> shift & 0xFF) as f32;
+
+            ((background + (foreground - background) * amount) as u32) << shift
+        };
+
+        0xFF000000 | channel(16) | channel(8) | channel(0)
+    }
+}
+
+// The palette's name, or its colors in the form parse() takes.
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match NAMED_PALETTES.iter().find(|(_, palette)| palette == self) {
+            Some((name, _)) => f.write_str(name),
+            None => write!(
+                f,
+                "{:06X},{:06X}",
+                self.foreground & 0xFFFFFF,
+                self.background & 0xFFFFFF
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Palette;
+
+    #[test]
+    fn test_palettes_by_name_or_colors() {
+        assert_eq!(Palette::parse("classic"), Ok(Palette::default()));
+
+        assert_eq!(
+            Palette::parse("#FFB000, 1a0f00"),
+            Ok(Palette {
+                background: 0xFF1A0F00,
+                foreground: 0xFFFFB000,
+            })
+        );
+
+        let custom = Palette::parse("00FF00,000000").unwrap();
+
+        assert_eq!(
+            Palette::parse(&custom.to_string()),
+            Ok(custom),
+            "A palette should read back as itself!"
+        );
+
+        assert!(Palette::parse("sepia").is_err());
+        assert!(Palette::parse("FFF,000").is_err());
+
+        assert_eq!(Palette::default().mix(0.5), 0xFF7F7F7F);
+    }
+
+    #[test]
+    fn test_next_named_cycles_through_the_named_palettes() {
+        assert_eq!(Palette::default().next_named().to_string(), "inverted");
+
+        let amber = Palette::parse("amber").unwrap();
+
+        assert_eq!(amber.next_named(), Palette::default());
+
+        assert_eq!(
+            Palette::parse("00FF00,000000").unwrap().next_named(),
+            Palette::default(),
+            "A custom palette should move to the first named one!"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::io::{self, BufRead, Write};
+
+// Asks on the terminal for one of the entries and returns its index. Output
+// goes to stderr so it doesn't end up in --frame-hash output.
+pub fn choose(heading: &str, entries: &[String]) -> Result<usize, String> {
+    eprintln!("{}", heading);
+
+    for (index, entry) in entries.iter().enumerate() {
+        eprintln!("  {}) {}", index + 1, entry);
+    }
+
+    loop {
+        eprint!("Select [1-{}]: ", entries.len());
+
+        io::stderr().flush().map_err(|error| error.to_string())?;
+
+        let mut line = String::new();
+
+        let read = io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(|error| error.to_string())?;
+
+        if read == 0 {
+            return Err("Nothing selected".to_string());
+        }
+
+        match line.trim().parse::<usize>() {
+            Ok(choice) if (1..=entries.len()).contains(&choice) => return Ok(choice - 1),
+            _ => eprintln!("Please enter a number between 1 and {}", entries.len()),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config;
+
+const MAX_RECENT_ROMS: usize = 10;
+
+// Most recently opened first, one ROM path or URL per line.
+pub struct RecentRoms {
+    entries: Vec<String>,
+}
+
+impl RecentRoms {
+    pub fn load() -> Self {
+        let entries = recent_roms_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn add(&mut self, entry: &str) {
+        self.entries.retain(|existing| existing != entry);
+
+        self.entries.insert(0, entry.to_string());
+
+        self.entries.truncate(MAX_RECENT_ROMS);
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = recent_roms_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No config directory"))?;
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        let mut contents = self.entries.join("\n");
+
+        contents.push('\n');
+
+        fs::write(path, contents)
+    }
+}
+
+fn recent_roms_path() -> Option<PathBuf> {
+    config::config_dir().map(|directory| directory.join("recent_roms"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecentRoms, MAX_RECENT_ROMS};
+
+    #[test]
+    fn test_add_moves_reopened_rom_to_front() {
+        let mut recent = RecentRoms {
+            entries: vec!["a.ch8".to_string(), "b.ch8".to_string()],
+        };
+
+        recent.add("b.ch8");
+
+        assert_eq!(recent.entries(), ["b.ch8", "a.ch8"]);
+    }
+
+    #[test]
+    fn test_add_keeps_only_the_newest_entries() {
+        let mut recent = RecentRoms {
+            entries: Vec::new(),
+        };
+
+        for index in 0..MAX_RECENT_ROMS + 2 {
+            recent.add(&format!("{}.ch8", index));
+        }
+
+        assert_eq!(recent.entries().len(), MAX_RECENT_ROMS);
+
+        assert_eq!(recent.entries()[0], format!("{}.ch8", MAX_RECENT_ROMS + 1));
+    }
+}
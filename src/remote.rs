@@ -0,0 +1,228 @@
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+use tiny_http::{Header, Response, Server};
+use winit::event_loop::EventLoopProxy;
+
+use chip8_vm::chip8::{State, Statistics};
+
+use crate::emulator::UserEvent;
+use crate::overlay::FramePacing;
+
+// Long enough for a ROM download in a /load request.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+// What an HTTP request asks of the focused window's emulator.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RemoteCommand {
+    Pause,
+    Resume,
+    // A path or URL, as given on the command line.
+    Load(String),
+    Screen,
+    Registers,
+    Stats,
+    Key { key: u8, pressed: bool },
+}
+
+pub type RemoteReply = Sender<RemoteResponse>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemoteResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl RemoteResponse {
+    pub fn text(status: u16, text: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", text).into_bytes(),
+        }
+    }
+
+    pub fn ok() -> Self {
+        Self::text(200, "OK")
+    }
+}
+
+impl RemoteCommand {
+    pub fn parse(method: &str, path: &str, body: &str) -> Result<Self, RemoteResponse> {
+        let path = path.split('?').next().unwrap_or(path);
+
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        let command = match (method, segments.as_slice()) {
+            ("POST", ["pause"]) => RemoteCommand::Pause,
+            ("POST", ["resume"]) => RemoteCommand::Resume,
+            ("POST", ["load"]) if body.trim().is_empty() => {
+                return Err(RemoteResponse::text(400, "/load needs a ROM path or URL"));
+            }
+            ("POST", ["load"]) => RemoteCommand::Load(body.trim().to_string()),
+            ("GET", ["screen.png"]) => RemoteCommand::Screen,
+            ("GET", ["registers"]) => RemoteCommand::Registers,
+            ("GET", ["stats"]) => RemoteCommand::Stats,
+            ("POST", ["keys", key, action @ ("press" | "release")]) => RemoteCommand::Key {
+                key: u8::from_str_radix(key, 16)
+                    .ok()
+                    .filter(|&key| key < 16)
+                    .ok_or_else(|| RemoteResponse::text(400, "Keys are 0 to F"))?,
+                pressed: *action == "press",
+            },
+            _ => return Err(RemoteResponse::text(404, "Not found")),
+        };
+
+        Ok(command)
+    }
+}
+
+// Serves the remote-control API on its own thread, handing each request to
+// the event loop and waiting for its reply.
+pub fn spawn_server(
+    address: &str,
+    event_loop_proxy: EventLoopProxy<UserEvent>,
+) -> Result<(), String> {
+    let server = Server::http(address)
+        .map_err(|error| format!("Failed to listen on {}: {}", address, error))?;
+
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+
+            let _ = request.as_reader().read_to_string(&mut body);
+
+            let response =
+                match RemoteCommand::parse(request.method().as_str(), request.url(), &body) {
+                    Ok(command) => {
+                        let (sender, receiver) = channel();
+
+                        if event_loop_proxy
+                            .send_event(UserEvent::Remote(command, sender))
+                            .is_err()
+                        {
+                            return;
+                        }
+
+                        receiver.recv_timeout(REPLY_TIMEOUT).unwrap_or_else(|_| {
+                            RemoteResponse::text(503, "The emulator didn't respond")
+                        })
+                    }
+                    Err(response) => response,
+                };
+
+            let content_type = Header::from_bytes("Content-Type", response.content_type)
+                .expect("Content types should be valid headers");
+
+            let _ = request.respond(
+                Response::from_data(response.body)
+                    .with_status_code(response.status)
+                    .with_header(content_type),
+            );
+        }
+    });
+
+    Ok(())
+}
+
+// The display as a 1-bit grayscale PNG, one image pixel per CHIP-8 pixel.
+pub fn encode_png(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels))
+        .map_err(|error| format!("Failed to encode the screen: {}", error))?;
+
+    Ok(png)
+}
+
+pub fn registers_json(state: &State) -> String {
+    let registers: Vec<String> = state.registers.iter().map(u8::to_string).collect();
+
+    format!(
+        "{{\"pc\":{},\"i\":{},\"v\":[{}],\"dt\":{},\"st\":{}}}",
+        state.program_counter,
+        state.index_register,
+        registers.join(","),
+        state.delay_timer,
+        state.sound_timer
+    )
+}
+
+pub fn statistics_json(statistics: &Statistics, pacing: FramePacing) -> String {
+    format!(
+        "{{\"cycles\":{},\"frames\":{},\"uptime\":{:.3},\"pacing\":{{\"presented\":{},\"dropped\":{},\"duplicated\":{},\"stutters\":{}}}}}",
+        statistics.cycles,
+        statistics.frames,
+        statistics.uptime().as_secs_f64(),
+        pacing.presented,
+        pacing.dropped,
+        pacing.duplicated,
+        pacing.stutters
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chip8_vm::chip8::Interpreter;
+
+    use super::{encode_png, registers_json, statistics_json, FramePacing, RemoteCommand};
+
+    #[test]
+    fn test_parse_routes_requests_to_commands() {
+        assert_eq!(
+            RemoteCommand::parse("POST", "/keys/a/press", ""),
+            Ok(RemoteCommand::Key {
+                key: 0xA,
+                pressed: true
+            })
+        );
+        assert_eq!(
+            RemoteCommand::parse("POST", "/load", " roms/pong.ch8\n"),
+            Ok(RemoteCommand::Load("roms/pong.ch8".to_string()))
+        );
+
+        assert_eq!(
+            RemoteCommand::parse("GET", "/pause", "")
+                .unwrap_err()
+                .status,
+            404,
+            "Pausing should need a POST!"
+        );
+        assert_eq!(
+            RemoteCommand::parse("POST", "/keys/10/press", "")
+                .unwrap_err()
+                .status,
+            400
+        );
+    }
+
+    #[test]
+    fn test_screen_and_registers_encoding() {
+        let interpreter = Interpreter::new();
+
+        let frame = interpreter.frame();
+
+        let png = encode_png(frame.pixels, frame.width as u32, frame.height as u32).unwrap();
+
+        assert!(png.starts_with(b"\x89PNG"));
+
+        assert_eq!(
+            registers_json(&interpreter.state()),
+            "{\"pc\":512,\"i\":0,\"v\":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],\"dt\":0,\"st\":0}"
+        );
+
+        assert_eq!(
+            statistics_json(&interpreter.statistics(), FramePacing::default()),
+            "{\"cycles\":0,\"frames\":0,\"uptime\":0.000,\"pacing\":{\"presented\":0,\"dropped\":0,\"duplicated\":0,\"stutters\":0}}"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chip8_vm::chip8::Quirks;
+
+use crate::config;
+use crate::key_map::KeyMap;
+use crate::options::Options;
+use crate::palette::Palette;
+
+// The settings remembered for each ROM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomSettings {
+    pub speed_percent: u32,
+    pub palette: Palette,
+    pub quirks: Quirks,
+    pub keys: KeyMap,
+    // Set from the ROM's Octo options or the options, but never changed
+    // while playing, so never remembered.
+    pub instructions_per_frame: Option<u32>,
+}
+
+impl RomSettings {
+    pub fn from_options(options: &Options) -> Result<Self, String> {
+        Ok(Self {
+            speed_percent: options.speed_percent,
+            palette: options.palette,
+            quirks: options.quirks()?,
+            keys: options.keys,
+            instructions_per_frame: options.instructions_per_frame,
+        })
+    }
+
+    // The settings that differ from the defaults, as option names and values.
+    fn changes(&self, defaults: &Self) -> Vec<(&'static str, String)> {
+        let mut changes = Vec::new();
+
+        if self.speed_percent != defaults.speed_percent {
+            changes.push(("speed", self.speed_percent.to_string()));
+        }
+
+        if self.palette != defaults.palette {
+            changes.push(("palette", self.palette.to_string()));
+        }
+
+        if self.quirks != defaults.quirks {
+            changes.push(("quirks", self.quirks.to_list()));
+        }
+
+        if self.keys != defaults.keys {
+            changes.push(("keys", self.keys.to_string()));
+        }
+
+        changes
+    }
+}
+
+// The settings last used with a ROM, kept in a file named after a hash of the
+// ROM and written like the config file:
+//
+//   speed = 50
+//   palette = amber
+//
+// They're read as options between the config file's and the command line's,
+// so they win over the config but not over the command line. Only settings
+// that differ from those two are kept, so later changes to the config still
+// reach ROMs that were played with it.
+//
+// A ROM's Octo options (see octo_options) come just before them, and take
+// the place of the config as the defaults they're compared with.
+pub struct RomPrefs {
+    path: PathBuf,
+    args: Vec<String>,
+    project_args: Vec<String>,
+    project_defaults: Option<RomSettings>,
+}
+
+impl RomPrefs {
+    pub fn for_rom(rom_data: &[u8], project_args: Vec<String>) -> Option<Self> {
+        let path = config::hashed_file("rom_prefs", rom_data)?;
+
+        // A damaged file is replaced when the ROM's window closes.
+        let args = config::read_options(&path).unwrap_or_default();
+
+        Some(Self {
+            path,
+            args,
+            project_args,
+            project_defaults: None,
+        })
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn project_args(&self) -> &[String] {
+        &self.project_args
+    }
+
+    pub fn set_project_defaults(&mut self, defaults: RomSettings) {
+        self.project_defaults = Some(defaults);
+    }
+
+    // Only touches the file when the settings have changed, and removes it
+    // once they're back to the defaults.
+    pub fn save(&mut self, settings: &RomSettings, defaults: &RomSettings) -> io::Result<()> {
+        let changes = settings.changes(self.project_defaults.as_ref().unwrap_or(defaults));
+
+        let args: Vec<String> = changes
+            .iter()
+            .flat_map(|(name, value)| [format!("--{}", name), value.clone()])
+            .collect();
+
+        if args == self.args {
+            return Ok(());
+        }
+
+        if changes.is_empty() {
+            match fs::remove_file(&self.path) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                _ => {}
+            }
+        } else {
+            if let Some(directory) = self.path.parent() {
+                fs::create_dir_all(directory)?;
+            }
+
+            let lines: String = changes
+                .iter()
+                .map(|(name, value)| format!("{} = {}\n", name, value))
+                .collect();
+
+            fs::write(&self.path, lines)?;
+        }
+
+        self.args = args;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RomSettings;
+    use crate::key_map::KeyMap;
+    use crate::options::Options;
+    use crate::palette::Palette;
+
+    #[test]
+    fn test_changed_settings_read_back_as_options() {
+        let defaults = RomSettings::from_options(&Options::parse(&[]).unwrap()).unwrap();
+
+        let mut settings = defaults;
+
+        assert!(settings.changes(&defaults).is_empty());
+
+        settings.speed_percent = 50;
+        settings.palette = Palette::parse("amber").unwrap();
+        settings.quirks.shift = !settings.quirks.shift;
+        settings.keys = KeyMap::parse("0123ijkl456789ab").unwrap();
+
+        let args: Vec<String> = settings
+            .changes(&defaults)
+            .into_iter()
+            .flat_map(|(name, value)| [format!("--{}", name), value])
+            .collect();
+
+        assert_eq!(
+            RomSettings::from_options(&Options::parse(&args).unwrap()),
+            Ok(settings),
+            "Saved settings should come back as they were!"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+use zip::ZipArchive;
+
+use crate::demo;
+use crate::download;
+use crate::prompt;
+
+// Same limit as for downloads: generous for any CHIP-8 program, but a
+// mislabelled archive entry can't exhaust memory.
+const MAX_ROM_SIZE: u64 = 64 * 1024;
+
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "sc8", "xo8"];
+
+// Where a ROM's bytes come from. Archives are resolved to a single entry up
+// front, so reloading never has to ask again, and are written archive#entry.
+pub enum RomSource {
+    File(PathBuf),
+    Url(String),
+    Archive { archive: PathBuf, entry: String },
+    // The built-in demo, when there's nothing else to run.
+    Demo,
+}
+
+impl RomSource {
+    pub fn parse(argument: &str) -> Result<Self, String> {
+        if download::is_url(argument) {
+            return Ok(RomSource::Url(argument.to_string()));
+        }
+
+        if let Some((archive, entry)) = argument.split_once('#') {
+            if has_extension(Path::new(archive), "zip") {
+                return Ok(RomSource::Archive {
+                    archive: PathBuf::from(archive),
+                    entry: entry.to_string(),
+                });
+            }
+        }
+
+        let path = PathBuf::from(argument);
+
+        if !has_extension(&path, "zip") {
+            return Ok(RomSource::File(path));
+        }
+
+        let entries = archive_roms(&path)?;
+
+        let entry = match entries.len() {
+            0 => return Err(format!("No CHIP-8 ROMs found in {}", argument)),
+            1 => entries[0].clone(),
+            // Without a terminal there's no one to ask, and the window's event
+            // loop mustn't wait on one.
+            _ if !io::stdin().is_terminal() => {
+                return Err(format!(
+                    "{} contains several ROMs, pick one with {}#<entry>: {}",
+                    argument,
+                    argument,
+                    entries.join(", ")
+                ))
+            }
+            _ => {
+                let heading = format!("{} contains several ROMs:", argument);
+
+                entries[prompt::choose(&heading, &entries)?].clone()
+            }
+        };
+
+        Ok(RomSource::Archive {
+            archive: path,
+            entry,
+        })
+    }
+
+    pub fn read(&self) -> Result<Vec<u8>, String> {
+        match self {
+            RomSource::File(path) => {
+                fs::read(path).map_err(|error| format!("Failed to read {}: {}", self, error))
+            }
+
+            RomSource::Url(url) => download::download(url),
+
+            RomSource::Archive { archive, entry } => {
+                let mut archive = open_archive(archive)?;
+
+                let file = archive
+                    .by_name(entry)
+                    .map_err(|error| format!("Failed to read {}: {}", self, error))?;
+
+                let mut rom_data = Vec::new();
+
+                file.take(MAX_ROM_SIZE + 1)
+                    .read_to_end(&mut rom_data)
+                    .map_err(|error| format!("Failed to read {}: {}", self, error))?;
+
+                if rom_data.len() as u64 > MAX_ROM_SIZE {
+                    return Err(format!("{} is larger than {} bytes", self, MAX_ROM_SIZE));
+                }
+
+                Ok(rom_data)
+            }
+
+            RomSource::Demo => Ok(demo::rom_data()),
+        }
+    }
+
+    // Short name for window titles.
+    pub fn name(&self) -> String {
+        let name = match self {
+            RomSource::File(path) => path.file_name(),
+            RomSource::Url(url) => Path::new(url).file_name(),
+            RomSource::Archive { entry, .. } => Path::new(entry).file_name(),
+            RomSource::Demo => None,
+        };
+
+        name.map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.to_string())
+    }
+
+    // How to open this ROM again later, from any working directory and
+    // without asking for an archive's entry again. The demo isn't remembered.
+    pub fn recent_entry(&self) -> Option<String> {
+        let entry = match self {
+            RomSource::File(path) => absolute(path),
+            RomSource::Url(url) => url.clone(),
+            RomSource::Archive { archive, entry } => format!("{}#{}", absolute(archive), entry),
+            RomSource::Demo => return None,
+        };
+
+        Some(entry)
+    }
+
+    // The file on disk to watch for changes, if there is one.
+    pub fn local_path(&self) -> Option<&Path> {
+        match self {
+            RomSource::File(path) => Some(path),
+            RomSource::Url(_) | RomSource::Demo => None,
+            RomSource::Archive { archive, .. } => Some(archive),
+        }
+    }
+}
+
+impl fmt::Display for RomSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomSource::File(path) => write!(f, "{}", path.display()),
+            RomSource::Url(url) => write!(f, "{}", url),
+            RomSource::Archive { archive, entry } => write!(f, "{}#{}", archive.display(), entry),
+            RomSource::Demo => write!(f, "demo"),
+        }
+    }
+}
+
+fn absolute(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|found| found.eq_ignore_ascii_case(extension))
+}
+
+fn open_archive(path: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(path)
+        .map_err(|error| format!("Failed to open {}: {}", path.display(), error))?;
+
+    ZipArchive::new(file).map_err(|error| format!("Failed to open {}: {}", path.display(), error))
+}
+
+fn archive_roms(path: &Path) -> Result<Vec<String>, String> {
+    let archive = open_archive(path)?;
+
+    let mut entries: Vec<String> = archive
+        .file_names()
+        .filter(|name| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|extension| has_extension(Path::new(name), extension))
+        })
+        .map(String::from)
+        .collect();
+
+    entries.sort();
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RomSource;
+
+    #[test]
+    fn test_archive_entries_are_written_after_a_hash() {
+        let source = RomSource::parse("roms.zip#games/pong.ch8").unwrap();
+
+        assert!(
+            matches!(&source, RomSource::Archive { entry, .. } if entry == "games/pong.ch8"),
+            "The entry after the hash should be picked without asking!"
+        );
+        assert_eq!(source.to_string(), "roms.zip#games/pong.ch8");
+        assert_eq!(source.name(), "pong.ch8");
+
+        assert!(
+            matches!(
+                RomSource::parse("my#roms/pong.ch8").unwrap(),
+                RomSource::File(_)
+            ),
+            "A hash after anything but a zip should stay part of the path!"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chip8_vm::chip8::Interpreter;
+
+use crate::config;
+
+const OCTO_FLAGS: usize = 16;
+
+// The RPL user flags a ROM saved with FX75, kept in a small file named after a
+// hash of the ROM so they survive between sessions as they did on the HP-48.
+pub struct RplFlags {
+    path: PathBuf,
+    saved: [u8; Interpreter::RPL_FLAGS],
+}
+
+impl RplFlags {
+    pub fn for_rom(rom_data: &[u8]) -> Option<Self> {
+        Some(Self::open(&config::hashed_file("rpl_flags", rom_data)?))
+    }
+
+    fn open(path: &Path) -> Self {
+        let saved = fs::read(path)
+            .ok()
+            .and_then(|contents| contents.try_into().ok())
+            .unwrap_or_default();
+
+        Self {
+            path: path.to_path_buf(),
+            saved,
+        }
+    }
+
+    pub fn restore(&self, interpreter: &mut Interpreter) {
+        interpreter.rpl_flags = self.saved;
+    }
+
+    // Only touches the file when the program has changed the flags.
+    pub fn save(&mut self, interpreter: &Interpreter) -> io::Result<()> {
+        if interpreter.rpl_flags == self.saved {
+            return Ok(());
+        }
+
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        fs::write(&self.path, interpreter.rpl_flags)?;
+
+        self.saved = interpreter.rpl_flags;
+
+        Ok(())
+    }
+}
+
+// Flags in the form Octo keeps them, a JSON array of XO-CHIP's 16 flag
+// registers, the first 8 of which are the HP-48's.
+pub fn to_octo(flags: &[u8; Interpreter::RPL_FLAGS]) -> String {
+    let registers: Vec<String> = (0..OCTO_FLAGS)
+        .map(|index| flags.get(index).copied().unwrap_or(0).to_string())
+        .collect();
+
+    format!("[{}]", registers.join(","))
+}
+
+pub fn from_octo(text: &str) -> Result<[u8; Interpreter::RPL_FLAGS], String> {
+    let invalid = || "Octo flags should be a JSON array of numbers".to_string();
+
+    let list = text
+        .trim()
+        .strip_prefix('[')
+        .and_then(|list| list.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+
+    let registers = list
+        .split(',')
+        .map(str::trim)
+        .filter(|register| !register.is_empty())
+        .map(|register| register.parse::<u8>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u8>, String>>()?;
+
+    if registers.len() > OCTO_FLAGS {
+        return Err(format!(
+            "Octo has {} flags, not {}",
+            OCTO_FLAGS,
+            registers.len()
+        ));
+    }
+
+    if registers
+        .iter()
+        .skip(Interpreter::RPL_FLAGS)
+        .any(|&register| register != 0)
+    {
+        return Err(format!(
+            "Only the first {} flags can be kept, but later ones are set",
+            Interpreter::RPL_FLAGS
+        ));
+    }
+
+    let mut flags = [0; Interpreter::RPL_FLAGS];
+
+    for (flag, register) in flags.iter_mut().zip(registers) {
+        *flag = register;
+    }
+
+    Ok(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chip8_vm::chip8::Interpreter;
+
+    use super::{from_octo, to_octo, RplFlags};
+
+    #[test]
+    fn test_saved_flags_are_restored() {
+        let path = std::env::temp_dir().join("chip8_vm_test_rpl_flags");
+
+        let _ = fs::remove_file(&path);
+
+        let mut interpreter = Interpreter::new();
+
+        interpreter.rpl_flags[2] = 0x42;
+
+        RplFlags::open(&path).save(&interpreter).unwrap();
+
+        let mut restored = Interpreter::new();
+
+        RplFlags::open(&path).restore(&mut restored);
+
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            restored.rpl_flags, interpreter.rpl_flags,
+            "Flags should be read back from the file!"
+        );
+    }
+
+    #[test]
+    fn test_octo_flags_convert_both_ways() {
+        let flags = [1, 2, 3, 0, 0, 0, 0, 255];
+
+        assert_eq!(to_octo(&flags), "[1,2,3,0,0,0,0,255,0,0,0,0,0,0,0,0]");
+
+        assert_eq!(from_octo(&to_octo(&flags)), Ok(flags));
+
+        assert_eq!(from_octo(" [ 7, 8 ]\n"), Ok([7, 8, 0, 0, 0, 0, 0, 0]));
+
+        assert!(
+            from_octo("[0,0,0,0,0,0,0,0,9]").is_err(),
+            "Flags the HP-48 doesn't have shouldn't be dropped silently!"
+        );
+    }
+}
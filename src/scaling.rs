@@ -0,0 +1,184 @@
+use std::fmt;
+use std::str::FromStr;
+
+// How the display is scaled up to the window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScalingFilter {
+    // By the largest whole number that fits, every pixel a sharp square.
+    #[default]
+    Nearest,
+    // To fill the window, each window pixel averaging the display pixels it
+    // covers. Edges blur by at most a pixel, but no pixel comes out a line
+    // wider than the rest, which shimmers as things move at odd scales.
+    Smooth,
+}
+
+impl ScalingFilter {
+    pub fn toggled(self) -> Self {
+        match self {
+            ScalingFilter::Nearest => ScalingFilter::Smooth,
+            ScalingFilter::Smooth => ScalingFilter::Nearest,
+        }
+    }
+}
+
+impl FromStr for ScalingFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "nearest" => Ok(ScalingFilter::Nearest),
+            "smooth" => Ok(ScalingFilter::Smooth),
+            _ => Err(format!(
+                "Unknown filter '{}', expected nearest or smooth",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ScalingFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ScalingFilter::Nearest => "nearest",
+            ScalingFilter::Smooth => "smooth",
+        })
+    }
+}
+
+// Draws `pixels`, `width` colors to a row, as large as fits in the buffer
+// without changing their shape, and centered. Scaling is done one direction
+// at a time: each row across, then the rows down, where most lines lie within
+// a single row and are a copy of it.
+pub fn draw_smooth(
+    buffer: &mut [u32],
+    buffer_width: usize,
+    buffer_height: usize,
+    pixels: &[u32],
+    width: usize,
+    height: usize,
+) {
+    let scale = (buffer_width as f32 / width as f32).min(buffer_height as f32 / height as f32);
+
+    let scaled_width = ((width as f32 * scale).round() as usize).min(buffer_width);
+    let scaled_height = ((height as f32 * scale).round() as usize).min(buffer_height);
+
+    if scaled_width == 0 || scaled_height == 0 {
+        return;
+    }
+
+    let left = (buffer_width - scaled_width) / 2;
+    let top = (buffer_height - scaled_height) / 2;
+
+    let across = coverage(width, scaled_width);
+    let down = coverage(height, scaled_height);
+
+    let mut rows = vec![0; height * scaled_width];
+
+    for (y, row) in rows.chunks_exact_mut(scaled_width).enumerate() {
+        for (pixel, taps) in row.iter_mut().zip(&across) {
+            *pixel = mix(taps
+                .iter()
+                .map(|&(x, weight)| (pixels[y * width + x], weight)));
+        }
+    }
+
+    for (line, taps) in down.iter().enumerate() {
+        let start = (top + line) * buffer_width + left;
+
+        let output = &mut buffer[start..start + scaled_width];
+
+        match taps.as_slice() {
+            &[(y, _)] => output.copy_from_slice(&rows[y * scaled_width..][..scaled_width]),
+            taps => {
+                for (x, pixel) in output.iter_mut().enumerate() {
+                    *pixel = mix(taps
+                        .iter()
+                        .map(|&(y, weight)| (rows[y * scaled_width + x], weight)));
+                }
+            }
+        }
+    }
+}
+
+// For each of `scaled` pixels spread over `size` source pixels, the source
+// pixels it covers and how much of it each covers.
+fn coverage(size: usize, scaled: usize) -> Vec<Vec<(usize, f32)>> {
+    let step = size as f32 / scaled as f32;
+
+    (0..scaled)
+        .map(|index| {
+            let start = index as f32 * step;
+            let end = start + step;
+
+            (start as usize..(end.ceil() as usize).min(size))
+                .map(|source| {
+                    let covered = end.min(source as f32 + 1.0) - start.max(source as f32);
+
+                    (source, covered / step)
+                })
+                .filter(|&(_, weight)| weight > 0.0)
+                .collect()
+        })
+        .collect()
+}
+
+// Adds up colors, each taking its share of the result.
+fn mix(colors: impl Iterator<Item = (u32, f32)>) -> u32 {
+    let mut mixed = [0.0f32; 3];
+
+    for (color, weight) in colors {
+        for (channel, shift) in mixed.iter_mut().zip([16, 8, 0]) {
+            *channel += (color >> shift & 0xFF) as f32 * weight;
+        }
+    }
+
+    let [red, green, blue] = mixed.map(|channel| channel.round().clamp(0.0, 255.0) as u32);
+
+    0xFF000000 | red << 16 | green << 8 | blue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{draw_smooth, ScalingFilter};
+
+    #[test]
+    fn test_smooth_scaling_averages_covered_pixels() {
+        // Two pixels, black and white, scaled by 1.5: the middle column is
+        // half of each.
+        let mut buffer = vec![0; 6];
+
+        draw_smooth(&mut buffer, 3, 2, &[0xFF000000, 0xFFFFFFFF], 2, 1);
+
+        assert_eq!(buffer, [0xFF000000, 0xFF808080, 0xFFFFFFFF].repeat(2));
+
+        // A 2x1 display in a 4x4 window fills the middle two rows, leaving the
+        // rest untouched.
+        let mut buffer = vec![0; 16];
+
+        draw_smooth(&mut buffer, 4, 4, &[0xFFFF0000, 0xFF0000FF], 2, 1);
+
+        assert_eq!(
+            buffer,
+            [
+                [0; 4],
+                [0xFFFF0000, 0xFFFF0000, 0xFF0000FF, 0xFF0000FF],
+                [0xFFFF0000, 0xFFFF0000, 0xFF0000FF, 0xFF0000FF],
+                [0; 4],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_filters_parse_and_print_by_name() {
+        for filter in [ScalingFilter::Nearest, ScalingFilter::Smooth] {
+            assert_eq!(filter.to_string().parse(), Ok(filter));
+        }
+
+        assert!(
+            "bilinear".parse::<ScalingFilter>().is_err(),
+            "Unknown filters should be an error!"
+        );
+    }
+}
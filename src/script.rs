@@ -0,0 +1,288 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use chip8_vm::chip8::{Cheat, Interpreter};
+
+use crate::emulator::FrameScript;
+
+// What a script sees of the machine: a copy taken before each call, with its
+// pokes queued up to go through the interpreter afterwards. Memory is lent
+// for the call rather than copied, as MegaChip's is 16 MiB.
+#[derive(Default)]
+struct Machine {
+    memory: Box<[u8]>,
+    registers: [u8; 16],
+    index_register: u32,
+    program_counter: u16,
+    frames: i64,
+    pokes: Vec<Cheat>,
+    // Held down on top of the player's keys until released.
+    held_keys: [bool; 16],
+}
+
+type SharedMachine = Arc<Mutex<Machine>>;
+
+// A rhai script run alongside the emulator. Its top-level statements run once,
+// before the first frame, and its `fn frame()` after every frame.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    machine: SharedMachine,
+    started: bool,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let machine = SharedMachine::default();
+
+        let engine = engine(&machine);
+
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+
+        Ok(Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            machine,
+            started: false,
+        })
+    }
+
+    fn run(&mut self) -> Result<(), Box<EvalAltResult>> {
+        if !self.started {
+            self.started = true;
+
+            self.engine.run_ast_with_scope(&mut self.scope, &self.ast)?;
+        }
+
+        if self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == "frame")
+        {
+            // The top-level statements have already run, and whatever frame()
+            // returns is ignored.
+            let options = CallFnOptions::new().eval_ast(false);
+
+            let _: Dynamic = self.engine.call_fn_with_options(
+                options,
+                &mut self.scope,
+                &self.ast,
+                "frame",
+                (),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FrameScript for Script {
+    fn after_frame(&mut self, interpreter: &mut Interpreter) -> Result<(), String> {
+        {
+            let mut machine = self.machine.lock().unwrap();
+
+            std::mem::swap(&mut machine.memory, &mut interpreter.memory);
+            machine.registers = interpreter.registers;
+            machine.index_register = interpreter.index_register;
+            machine.program_counter = interpreter.program_counter;
+            machine.frames += 1;
+        }
+
+        let result = self.run();
+
+        let mut machine = self.machine.lock().unwrap();
+
+        std::mem::swap(&mut machine.memory, &mut interpreter.memory);
+
+        for cheat in machine.pokes.drain(..) {
+            interpreter.add_cheat(cheat);
+        }
+
+        interpreter.apply_cheats();
+
+        interpreter.registers = machine.registers;
+
+        result.map_err(|error| format!("Script error: {}", error))
+    }
+
+    fn keys(&self, mut keys: [bool; 16]) -> [bool; 16] {
+        let machine = self.machine.lock().unwrap();
+
+        for (key, held) in keys.iter_mut().zip(machine.held_keys) {
+            *key |= held;
+        }
+
+        keys
+    }
+}
+
+fn engine(machine: &SharedMachine) -> Engine {
+    let mut engine = Engine::new();
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("peek", move |address: i64| {
+        let machine = shared.lock().unwrap();
+
+        let address = checked(address, machine.memory.len(), "Address")?;
+
+        Ok::<_, Box<EvalAltResult>>(machine.memory[address] as i64)
+    });
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("poke", move |address: i64, value: i64| {
+        let mut machine = shared.lock().unwrap();
+
+        let address = checked(address, machine.memory.len(), "Address")?;
+        let value = checked(value, 256, "Value")? as u8;
+
+        // Later peeks in the same call see the new value.
+        machine.memory[address] = value;
+
+        machine.pokes.push(Cheat {
+            address: address as u32,
+            value,
+            freeze: false,
+        });
+
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("reg", move |x: i64| {
+        let machine = shared.lock().unwrap();
+
+        Ok::<_, Box<EvalAltResult>>(machine.registers[checked(x, 16, "Register")?] as i64)
+    });
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("set_reg", move |x: i64, value: i64| {
+        let mut machine = shared.lock().unwrap();
+
+        machine.registers[checked(x, 16, "Register")?] = checked(value, 256, "Value")? as u8;
+
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("index", move || {
+        shared.lock().unwrap().index_register as i64
+    });
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("pc", move || shared.lock().unwrap().program_counter as i64);
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("frames", move || shared.lock().unwrap().frames);
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("press", move |key: i64| {
+        shared.lock().unwrap().held_keys[checked(key, 16, "Key")?] = true;
+
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+
+    let shared = Arc::clone(machine);
+
+    engine.register_fn("release", move |key: i64| {
+        shared.lock().unwrap().held_keys[checked(key, 16, "Key")?] = false;
+
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+
+    engine
+}
+
+fn checked(value: i64, limit: usize, what: &str) -> Result<usize, Box<EvalAltResult>> {
+    usize::try_from(value)
+        .ok()
+        .filter(|&value| value < limit)
+        .ok_or_else(|| format!("{} out of range: {}", what, value).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chip8_vm::chip8::{Interpreter, Platform};
+
+    use super::Script;
+    use crate::emulator::FrameScript;
+
+    #[test]
+    fn test_script_can_poke_set_registers_and_hold_keys() {
+        let path = std::env::temp_dir().join("chip8_vm_test_script.rhai");
+
+        fs::write(
+            &path,
+            "fn frame() {
+                 poke(0x300, peek(0x300) + 1);
+                 set_reg(3, frames());
+                 if frames() == 2 { press(0xA); }
+             }",
+        )
+        .unwrap();
+
+        let mut script = Script::load(&path).unwrap();
+
+        fs::remove_file(&path).unwrap();
+
+        let mut interpreter = Interpreter::new();
+
+        for _ in 0..2 {
+            script.after_frame(&mut interpreter).unwrap();
+        }
+
+        assert_eq!(interpreter.memory[0x300], 2);
+        assert_eq!(interpreter.registers[3], 2);
+        assert!(
+            script.keys([false; 16])[0xA],
+            "The script should be holding key A down!"
+        );
+
+        assert!(
+            interpreter.cheats().is_empty(),
+            "Pokes shouldn't stay frozen!"
+        );
+    }
+
+    #[test]
+    fn test_script_can_poke_megachip_memory_past_0xffff() {
+        let path = std::env::temp_dir().join("chip8_vm_test_script_megachip.rhai");
+
+        fs::write(&path, "fn frame() { poke(0x123456, peek(0x123456) + 7); }").unwrap();
+
+        let mut script = Script::load(&path).unwrap();
+
+        fs::remove_file(&path).unwrap();
+
+        let mut interpreter = Interpreter::builder()
+            .platform(Platform::MegaChip)
+            .build()
+            .unwrap();
+
+        script.after_frame(&mut interpreter).unwrap();
+
+        assert_eq!(
+            interpreter.memory[0x123456], 7,
+            "The poke should reach the address it was given!"
+        );
+        assert_eq!(
+            interpreter.memory[0x3456], 0,
+            "The address shouldn't be cut down to 16 bits!"
+        );
+    }
+}
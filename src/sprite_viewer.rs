@@ -0,0 +1,263 @@
+use std::error::Error;
+use std::num::NonZeroU32;
+use std::ops::Range;
+use std::sync::Arc;
+
+use softbuffer::{Context, Surface};
+use winit::keyboard::{Key, NamedKey};
+use winit::raw_window_handle::DisplayHandle;
+use winit::window::{Window, WindowId};
+
+use crate::overlay::{Canvas, LINE_HEIGHT, TEXT_SCALE};
+
+// Sprites across and down one page.
+const COLUMNS: usize = 16;
+const ROWS: usize = 8;
+
+// DXYN draws up to 15 rows and SCHIP's DXY0 16.
+const MAX_SPRITE_HEIGHT: usize = 16;
+const DEFAULT_SPRITE_HEIGHT: usize = 8;
+
+// Room for a six digit address, as on a MegaChip, and a space.
+const LABEL_WIDTH: usize = 7 * 4 * TEXT_SCALE;
+
+const SET_COLOR: u32 = 0xFFFFFFFF;
+const CLEAR_COLOR: u32 = 0xFF303030;
+const LABEL_COLOR: u32 = 0xFFA0A0A0;
+
+// A second window showing an emulator's memory as a page of 8 pixel wide
+// sprites, one byte per row, refreshed every frame. The arrow keys scroll by
+// a byte or a row of sprites, Page Up and Page Down by a page, and + and -
+// change how many rows each sprite has.
+pub struct SpriteViewer {
+    surface: Surface<DisplayHandle<'static>, Arc<Window>>,
+    pub window: Arc<Window>,
+    // The emulator window whose memory is shown.
+    pub target: WindowId,
+    rom_name: String,
+    address: usize,
+    sprite_height: usize,
+    // The last bytes read from the interpreter thread and where they start,
+    // which can lag behind `address` after scrolling.
+    memory_address: usize,
+    memory: Vec<u8>,
+    memory_size: usize,
+}
+
+impl SpriteViewer {
+    pub fn new(
+        context: &Context<DisplayHandle<'static>>,
+        window: Window,
+        target: WindowId,
+        rom_name: String,
+        address: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let window = Arc::new(window);
+
+        let surface = Surface::new(context, Arc::clone(&window))?;
+
+        let viewer = Self {
+            surface,
+            window,
+            target,
+            rom_name,
+            address,
+            sprite_height: DEFAULT_SPRITE_HEIGHT,
+            memory_address: address,
+            memory: Vec::new(),
+            memory_size: usize::MAX,
+        };
+
+        viewer.update_title();
+
+        Ok(viewer)
+    }
+
+    // The memory one page shows.
+    pub fn page(&self) -> Range<usize> {
+        self.address..self.address + self.page_size()
+    }
+
+    fn page_size(&self) -> usize {
+        COLUMNS * ROWS * self.sprite_height
+    }
+
+    pub fn set_memory(&mut self, address: usize, memory: Vec<u8>, memory_size: usize) {
+        self.memory_address = address;
+        self.memory = memory;
+        self.memory_size = memory_size;
+
+        self.window.request_redraw();
+    }
+
+    // Returns whether the page moved or changed shape, so memory has to be
+    // read again.
+    pub fn handle_key(&mut self, key: &Key) -> bool {
+        let row = COLUMNS * self.sprite_height;
+
+        let last = self.memory_size.saturating_sub(1);
+
+        let address = match key {
+            Key::Named(NamedKey::ArrowLeft) => self.address.saturating_sub(1),
+            Key::Named(NamedKey::ArrowRight) => self.address + 1,
+            Key::Named(NamedKey::ArrowUp) => self.address.saturating_sub(row),
+            Key::Named(NamedKey::ArrowDown) => self.address + row,
+            Key::Named(NamedKey::PageUp) => self.address.saturating_sub(self.page_size()),
+            Key::Named(NamedKey::PageDown) => self.address + self.page_size(),
+            Key::Named(NamedKey::Home) => 0,
+            Key::Character(character) if character == "+" || character == "=" => {
+                self.sprite_height = (self.sprite_height + 1).min(MAX_SPRITE_HEIGHT);
+
+                self.address
+            }
+            Key::Character(character) if character == "-" => {
+                self.sprite_height = (self.sprite_height - 1).max(1);
+
+                self.address
+            }
+            _ => return false,
+        };
+
+        self.address = address.min(last);
+
+        self.update_title();
+
+        self.window.request_redraw();
+
+        true
+    }
+
+    fn update_title(&self) {
+        let page = self.page();
+
+        self.window.set_title(&format!(
+            "Sprites — {} — {:03X}-{:03X} — {} rows",
+            self.rom_name,
+            page.start,
+            page.end - 1,
+            self.sprite_height
+        ));
+    }
+
+    pub fn draw(&mut self) -> Result<(), Box<dyn Error>> {
+        let size = self.window.inner_size();
+
+        let (width, height) = match (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+            (Some(width), Some(height)) => (width, height),
+            _ => return Ok(()),
+        };
+
+        self.surface.resize(width, height)?;
+
+        let mut buffer = self.surface.buffer_mut()?;
+
+        buffer.fill(0xFF000000);
+
+        let mut canvas = Canvas::new(&mut buffer, size.width as usize, size.height as usize);
+
+        let bytes = |address: usize| {
+            address
+                .checked_sub(self.memory_address)
+                .and_then(|offset| self.memory.get(offset).copied())
+        };
+
+        draw_sprites(&mut canvas, self.address, self.sprite_height, bytes);
+
+        buffer.present()?;
+
+        Ok(())
+    }
+}
+
+// Lays the page out in rows of sprites, each scaled up as far as the canvas
+// allows and labelled on the left with the address it starts at. Bytes that
+// `byte` has no value for, past the end of memory or not read yet, are left
+// out.
+fn draw_sprites(
+    canvas: &mut Canvas<'_>,
+    address: usize,
+    sprite_height: usize,
+    byte: impl Fn(usize) -> Option<u8>,
+) {
+    // One pixel between sprites, and at least a line of text per row.
+    let cell_width = canvas.width().saturating_sub(LABEL_WIDTH) / COLUMNS;
+    let cell_height = canvas.height() / ROWS;
+
+    let scale = (cell_width / 9)
+        .min(cell_height / (sprite_height + 1))
+        .max(1);
+
+    let row_height = ((sprite_height + 1) * scale).max(LINE_HEIGHT);
+
+    for row in 0..ROWS {
+        let row_address = address + row * COLUMNS * sprite_height;
+
+        let top = row * row_height;
+
+        if byte(row_address).is_some() {
+            canvas.draw_text(0, top, &format!("{:03X}", row_address), LABEL_COLOR);
+        }
+
+        for column in 0..COLUMNS {
+            let left = LABEL_WIDTH + column * 9 * scale;
+
+            let sprite = row_address + column * sprite_height;
+
+            for line in 0..sprite_height {
+                let bits = match byte(sprite + line) {
+                    Some(bits) => bits,
+                    None => continue,
+                };
+
+                for bit in 0..8 {
+                    let color = if bits >> (7 - bit) & 1 == 1 {
+                        SET_COLOR
+                    } else {
+                        CLEAR_COLOR
+                    };
+
+                    canvas.fill_rect(left + bit * scale, top + line * scale, scale, scale, color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{draw_sprites, CLEAR_COLOR, LABEL_WIDTH, SET_COLOR};
+    use crate::overlay::Canvas;
+
+    #[test]
+    fn test_sprites_are_drawn_a_byte_per_row() {
+        let (width, height) = (LABEL_WIDTH + 16 * 9, 8 * 16);
+
+        let mut pixels = vec![0; width * height];
+
+        let mut canvas = Canvas::new(&mut pixels, width, height);
+
+        // Sprites of 2 rows starting at 0x200: the first is F0 00, the second
+        // 01 00.
+        let memory = [0xF0, 0x00, 0x01, 0x00];
+
+        draw_sprites(&mut canvas, 0x200, 2, |address| {
+            memory.get(address.checked_sub(0x200)?).copied()
+        });
+
+        let pixel = |x: usize, y: usize| pixels[y * width + LABEL_WIDTH + x];
+
+        assert_eq!(pixel(0, 0), SET_COLOR);
+        assert_eq!(pixel(4, 0), CLEAR_COLOR);
+        assert_eq!(pixel(0, 1), CLEAR_COLOR, "The second byte is the next row!");
+        assert_eq!(
+            pixel(9 + 7, 0),
+            SET_COLOR,
+            "The third byte starts the second sprite!"
+        );
+        assert_eq!(
+            pixel(18, 0),
+            0,
+            "Bytes past the ones read should be left out!"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::{BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use winit::event_loop::EventLoopProxy;
+
+use chip8_vm::chip8::stream::STREAM_MAGIC;
+use chip8_vm::chip8::StreamMessage;
+
+use crate::emulator::{DisplayFrame, UserEvent};
+
+// Frames queued per viewer before new ones are dropped, so a slow connection
+// never holds up the window.
+const QUEUED_FRAMES: usize = 2;
+
+// Where to queue encoded frames for one viewer.
+type FrameQueue = SyncSender<Arc<Vec<u8>>>;
+
+// Broadcasts the focused window's display to chip8-viewer clients over TCP,
+// and passes their key presses back to the event loop.
+pub struct StreamServer {
+    viewers: Arc<Mutex<Vec<FrameQueue>>>,
+}
+
+impl StreamServer {
+    pub fn listen(
+        address: &str,
+        event_loop_proxy: EventLoopProxy<UserEvent>,
+    ) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|error| format!("Failed to listen on {}: {}", address, error))?;
+
+        let viewers = Arc::new(Mutex::new(Vec::new()));
+
+        let shared = Arc::clone(&viewers);
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Some(sender) = serve(stream, event_loop_proxy.clone()) {
+                    shared.lock().unwrap().push(sender);
+                }
+            }
+        });
+
+        Ok(Self { viewers })
+    }
+
+    pub fn broadcast(&self, frame: &DisplayFrame) {
+        let mut viewers = self.viewers.lock().unwrap();
+
+        if viewers.is_empty() {
+            return;
+        }
+
+        let message = StreamMessage::Frame {
+            width: frame.width as u16,
+            height: frame.height as u16,
+            pixels: frame.pixels.clone(),
+        };
+
+        let mut bytes = Vec::new();
+
+        message
+            .write_to(&mut bytes)
+            .expect("Writing to a Vec shouldn't fail");
+
+        let bytes = Arc::new(bytes);
+
+        viewers.retain(|sender| match sender.try_send(Arc::clone(&bytes)) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+// Starts a writer and a reader thread for one viewer.
+fn serve(mut stream: TcpStream, event_loop_proxy: EventLoopProxy<UserEvent>) -> Option<FrameQueue> {
+    let _ = stream.set_nodelay(true);
+
+    stream.write_all(STREAM_MAGIC).ok()?;
+
+    let reader = stream.try_clone().ok()?;
+
+    let (sender, receiver) = sync_channel::<Arc<Vec<u8>>>(QUEUED_FRAMES);
+
+    thread::spawn(move || {
+        for bytes in receiver {
+            if stream.write_all(&bytes).is_err() {
+                break;
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+
+        while let Ok(message) = StreamMessage::read_from(&mut reader) {
+            if let StreamMessage::Key { key, pressed } = message {
+                if event_loop_proxy
+                    .send_event(UserEvent::StreamKey(key, pressed))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+    });
+
+    Some(sender)
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+// The window is split into a 3x3 grid of zones, each of which can be mapped to
+// a keypad key for touch screens.
+const ZONE_NAMES: [&str; 9] = [
+    "top-left",
+    "top",
+    "top-right",
+    "left",
+    "center",
+    "right",
+    "bottom-left",
+    "bottom",
+    "bottom-right",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TouchZones {
+    keys: [Option<u8>; 9],
+}
+
+// Many games steer with 2/4/6/8 and use 5 as the action key.
+impl Default for TouchZones {
+    fn default() -> Self {
+        let mut keys = [None; 9];
+
+        keys[1] = Some(0x2);
+        keys[3] = Some(0x4);
+        keys[4] = Some(0x5);
+        keys[5] = Some(0x6);
+        keys[7] = Some(0x8);
+
+        Self { keys }
+    }
+}
+
+impl TouchZones {
+    // Parses a comma separated list of zone=key pairs, e.g. "left=4,right=6".
+    // Zones that aren't listed are left unmapped.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut keys = [None; 9];
+
+        for mapping in spec.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+            let (zone, key) = mapping
+                .split_once('=')
+                .ok_or_else(|| format!("Expected zone=key, got: {}", mapping))?;
+
+            let zone_index = ZONE_NAMES
+                .iter()
+                .position(|name| *name == zone.trim())
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown touch zone: {} (expected one of: {})",
+                        zone,
+                        ZONE_NAMES.join(", ")
+                    )
+                })?;
+
+            let key = u8::from_str_radix(key.trim(), 16)
+                .ok()
+                .filter(|key| *key <= 0xF)
+                .ok_or_else(|| format!("Invalid keypad key: {}", key))?;
+
+            keys[zone_index] = Some(key);
+        }
+
+        Ok(Self { keys })
+    }
+
+    pub fn key_at(&self, x: f64, y: f64, width: u32, height: u32) -> Option<u8> {
+        if width == 0 || height == 0 || x < 0.0 || y < 0.0 {
+            return None;
+        }
+
+        let column = ((x * 3.0 / width as f64) as usize).min(2);
+        let row = ((y * 3.0 / height as f64) as usize).min(2);
+
+        self.keys[row * 3 + column]
+    }
+}
+
+// Which key each finger currently holds, so several can be down at once and a
+// finger sliding into another zone moves its press along with it.
+#[derive(Default)]
+pub struct TouchState {
+    pressed: HashMap<u64, u8>,
+}
+
+impl TouchState {
+    // Returns the key the finger released (if any) and the key it now holds.
+    pub fn update(&mut self, id: u64, key: Option<u8>) -> (Option<u8>, Option<u8>) {
+        let released = match key {
+            Some(key) => self.pressed.insert(id, key),
+            None => self.pressed.remove(&id),
+        };
+
+        if released == key {
+            (None, None)
+        } else {
+            (released, key)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pressed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TouchState, TouchZones};
+
+    #[test]
+    fn test_parse_touch_zones() {
+        let zones = TouchZones::parse("left=4, right=6,bottom=a").unwrap();
+
+        assert_eq!(zones.key_at(10.0, 150.0, 300, 300), Some(0x4));
+        assert_eq!(zones.key_at(290.0, 150.0, 300, 300), Some(0x6));
+        assert_eq!(zones.key_at(150.0, 290.0, 300, 300), Some(0xA));
+        assert_eq!(zones.key_at(150.0, 150.0, 300, 300), None);
+
+        assert!(TouchZones::parse("middle=4").is_err());
+        assert!(TouchZones::parse("left=10").is_err());
+    }
+
+    #[test]
+    fn test_touch_moving_between_zones_moves_the_press() {
+        let mut touches = TouchState::default();
+
+        assert_eq!(touches.update(1, Some(0x4)), (None, Some(0x4)));
+        assert_eq!(touches.update(1, Some(0x4)), (None, None));
+        assert_eq!(touches.update(1, Some(0x6)), (Some(0x4), Some(0x6)));
+        assert_eq!(touches.update(1, None), (Some(0x6), None));
+    }
+}
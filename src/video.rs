@@ -0,0 +1,338 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use tracing::{error, info};
+
+use chip8_vm::chip8::tone::ToneSettings;
+use chip8_vm::chip8::{Frame, Interpreter};
+
+use crate::emulator::Recorder;
+use crate::palette::Palette;
+use crate::wav::WavRecorder;
+
+// Videos are scaled up from the display by a whole number, so every pixel
+// stays a sharp square, to at least this wide.
+const MIN_VIDEO_WIDTH: usize = 1024;
+
+// Records the session to a video with ffmpeg, one frame of video per emulated
+// frame at 60 frames a second, so like a WAV recording it follows emulated
+// time. ffmpeg encodes the frames as they come, while the buzzer goes to a
+// WAV file alongside; the two are put together when the recorder is dropped.
+pub struct VideoRecorder {
+    path: PathBuf,
+    video_path: PathBuf,
+    audio_path: PathBuf,
+    ffmpeg: Child,
+    // Raw frames for ffmpeg, closed to end the video.
+    input: Option<BufWriter<ChildStdin>>,
+    audio: Option<WavRecorder>,
+    palette: Palette,
+    width: usize,
+    height: usize,
+    // The frame being written, as ffmpeg's rgb24.
+    pixels: Vec<u8>,
+}
+
+impl VideoRecorder {
+    // The video is sized for the display as it is now. Frames after a change
+    // of resolution are stretched to fit.
+    pub fn create(
+        path: &Path,
+        tone: ToneSettings,
+        palette: Palette,
+        display_width: usize,
+        display_height: usize,
+    ) -> io::Result<Self> {
+        let scale = MIN_VIDEO_WIDTH.div_ceil(display_width.max(1));
+
+        let (width, height) = (display_width * scale, display_height * scale);
+
+        let (video_path, audio_path) = partial_paths(path);
+
+        let audio = WavRecorder::create(&audio_path, tone)?;
+
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(encode_args(&video_path, width, height))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|error| {
+                io::Error::new(error.kind(), format!("couldn't start ffmpeg: {}", error))
+            })?;
+
+        let input = ffmpeg.stdin.take().map(BufWriter::new);
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            video_path,
+            audio_path,
+            ffmpeg,
+            input,
+            audio: Some(audio),
+            palette,
+            width,
+            height,
+            pixels: Vec::new(),
+        })
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.input = None;
+
+        let status = self.ffmpeg.wait()?;
+
+        // Dropping the audio recorder completes its file.
+        self.audio = None;
+
+        if !status.success() {
+            return Err(io::Error::other(format!("ffmpeg failed ({})", status)));
+        }
+
+        let status = Command::new("ffmpeg")
+            .args(mux_args(&self.video_path, &self.audio_path, &self.path))
+            .stdout(Stdio::null())
+            .status()?;
+
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "ffmpeg failed to add the audio ({}), the video is in {}",
+                status,
+                self.video_path.display()
+            )));
+        }
+
+        fs::remove_file(&self.video_path)?;
+        fs::remove_file(&self.audio_path)?;
+
+        info!("Recorded video to {}", self.path.display());
+
+        Ok(())
+    }
+}
+
+impl Recorder for VideoRecorder {
+    fn record_frame(&mut self, interpreter: &Interpreter) -> io::Result<()> {
+        if let Some(input) = &mut self.input {
+            write_frame(
+                &mut self.pixels,
+                &interpreter.frame(),
+                self.palette,
+                self.width,
+                self.height,
+            );
+
+            input.write_all(&self.pixels)?;
+        }
+
+        if let Some(audio) = &mut self.audio {
+            audio.record_frame(interpreter)?;
+        }
+
+        Ok(())
+    }
+
+    fn kind(&self) -> &'static str {
+        "video"
+    }
+}
+
+impl Drop for VideoRecorder {
+    fn drop(&mut self) {
+        if let Err(error) = self.finish() {
+            error!("Failed to finish the video recording: {}", error);
+        }
+    }
+}
+
+// Where the video and audio go until they're put together, e.g.
+// "run.video.mp4" and "run.audio.wav" for "run.mp4".
+fn partial_paths(path: &Path) -> (PathBuf, PathBuf) {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("mp4");
+
+    (
+        path.with_extension(format!("video.{}", extension)),
+        path.with_extension("audio.wav"),
+    )
+}
+
+fn is_webm(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("webm"))
+}
+
+// VP9 in a WebM, or H.264 in anything else. Either way the quality is high,
+// as flat colors with sharp edges compress well.
+fn encode_args(video_path: &Path, width: usize, height: usize) -> Vec<OsString> {
+    let codec: &[&str] = if is_webm(video_path) {
+        &[
+            "-c:v",
+            "libvpx-vp9",
+            "-crf",
+            "15",
+            "-b:v",
+            "0",
+            "-deadline",
+            "realtime",
+            "-cpu-used",
+            "8",
+        ]
+    } else {
+        &["-c:v", "libx264", "-preset", "veryfast", "-crf", "15"]
+    };
+
+    let size = format!("{}x{}", width, height);
+
+    let input = [
+        "-loglevel",
+        "error",
+        "-y",
+        "-f",
+        "rawvideo",
+        "-pixel_format",
+        "rgb24",
+        "-video_size",
+        &size,
+        "-framerate",
+        "60",
+        "-i",
+        "-",
+    ];
+
+    input
+        .iter()
+        .chain(codec)
+        .chain(&["-pix_fmt", "yuv420p"])
+        .map(OsString::from)
+        .chain([video_path.as_os_str().to_os_string()])
+        .collect()
+}
+
+// Copies the video as it is, adding the audio as Opus for WebM or AAC.
+fn mux_args(video_path: &Path, audio_path: &Path, path: &Path) -> Vec<OsString> {
+    let audio_codec = if is_webm(path) { "libopus" } else { "aac" };
+
+    [
+        "-loglevel".into(),
+        "error".into(),
+        "-y".into(),
+        "-i".into(),
+        video_path.into(),
+        "-i".into(),
+        audio_path.into(),
+        "-c:v".into(),
+        "copy".into(),
+        "-c:a".into(),
+        audio_codec.into(),
+        path.into(),
+    ]
+    .to_vec()
+}
+
+// The frame scaled to `width` by `height` as rgb24, each row drawn once and
+// copied for the lines that repeat it.
+fn write_frame(pixels: &mut Vec<u8>, frame: &Frame, palette: Palette, width: usize, height: usize) {
+    pixels.clear();
+
+    for y in 0..height {
+        let source_y = y * frame.height / height;
+
+        if y > 0 && source_y == (y - 1) * frame.height / height {
+            pixels.extend_from_within(pixels.len() - width * 3..);
+
+            continue;
+        }
+
+        for x in 0..width {
+            let source_x = x * frame.width / width;
+
+            let color = if let Some(colors) = frame.colors {
+                colors[source_y * frame.width + source_x]
+            } else if frame.pixels[source_y * (frame.width / 8) + source_x / 8]
+                >> (7 - source_x % 8)
+                & 1
+                == 1
+            {
+                palette.foreground
+            } else {
+                palette.background
+            };
+
+            pixels.extend_from_slice(&[(color >> 16) as u8, (color >> 8) as u8, color as u8]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use chip8_vm::chip8::Frame;
+
+    use super::{encode_args, mux_args, partial_paths, write_frame};
+    use crate::palette::Palette;
+
+    #[test]
+    fn test_frames_are_scaled_to_the_video_size() {
+        let frame = Frame {
+            pixels: &[0b1000_0001],
+            width: 8,
+            height: 1,
+            dirty: None,
+            colors: None,
+        };
+
+        let mut pixels = Vec::new();
+
+        write_frame(&mut pixels, &frame, Palette::default(), 16, 2);
+
+        // Each pixel two wide, white, six black, white, and two lines of it.
+        let row = [vec![0xFF; 6], vec![0; 36], vec![0xFF; 6]].concat();
+
+        assert_eq!(pixels, [row.clone(), row].concat());
+
+        let colors = [0xFF123456; 8];
+
+        let frame = Frame {
+            colors: Some(&colors),
+            ..frame
+        };
+
+        write_frame(&mut pixels, &frame, Palette::default(), 8, 1);
+
+        assert_eq!(
+            pixels,
+            [0x12, 0x34, 0x56].repeat(8),
+            "MegaChip colors should be used as they are!"
+        );
+    }
+
+    #[test]
+    fn test_ffmpeg_encodes_for_the_file_type() {
+        let (video_path, audio_path) = partial_paths(Path::new("run.webm"));
+
+        assert_eq!(video_path, Path::new("run.video.webm"));
+        assert_eq!(audio_path, Path::new("run.audio.wav"));
+
+        let args = encode_args(&video_path, 1024, 512);
+
+        assert!(args.contains(&"libvpx-vp9".into()));
+        assert!(args.contains(&"1024x512".into()));
+        assert_eq!(args.last(), Some(&"run.video.webm".into()));
+
+        assert!(
+            encode_args(Path::new("run.video.mp4"), 1024, 512).contains(&"libx264".into()),
+            "Anything but WebM should be H.264!"
+        );
+
+        let args = mux_args(&video_path, &audio_path, Path::new("run.webm"));
+
+        assert!(args.contains(&"libopus".into()));
+        assert_eq!(args.last(), Some(&"run.webm".into()));
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use winit::event_loop::EventLoopProxy;
+
+use crate::emulator::UserEvent;
+
+// How long a file has to go without changing before it's reloaded; editors
+// and assemblers often write a file in several steps.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+// Watches the directories containing ROM files, and the config file, rather
+// than the files themselves, since editors and assemblers often write a new
+// file and rename it over the old one, which would silently end a watch on the
+// file.
+pub struct RomWatcher {
+    watcher: RecommendedWatcher,
+}
+
+impl RomWatcher {
+    pub fn new(event_loop_proxy: EventLoopProxy<UserEvent>) -> notify::Result<Self> {
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || report_changes(receiver, event_loop_proxy));
+
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let event = match result {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+
+            let changed = match event.kind {
+                EventKind::Modify(ModifyKind::Metadata(_)) => false,
+                EventKind::Create(_) | EventKind::Modify(_) => true,
+                _ => false,
+            };
+
+            if changed {
+                for path in event.paths {
+                    let _ = sender.send(path);
+                }
+            }
+        })?;
+
+        Ok(Self { watcher })
+    }
+
+    // Returns the canonical path that change events will be reported with.
+    pub fn watch(&mut self, rom_path: &Path) -> notify::Result<PathBuf> {
+        let rom_path = rom_path.canonicalize()?;
+
+        if let Some(directory) = rom_path.parent() {
+            self.watcher.watch(directory, RecursiveMode::NonRecursive)?;
+        }
+
+        Ok(rom_path)
+    }
+}
+
+// Sends each changed file once it has settled, so a file written in several
+// steps is reloaded once rather than for every step, and never half-written.
+// Ends when the watcher is dropped.
+fn report_changes(receiver: Receiver<PathBuf>, event_loop_proxy: EventLoopProxy<UserEvent>) {
+    while let Ok(path) = receiver.recv() {
+        let mut changed = vec![path];
+
+        while let Ok(path) = receiver.recv_timeout(SETTLE_TIME) {
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+
+        for path in changed {
+            if event_loop_proxy
+                .send_event(UserEvent::RomChanged(path))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
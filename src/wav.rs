@@ -0,0 +1,153 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use tracing::error;
+
+use chip8_vm::chip8::tone::{Tone, ToneSettings};
+use chip8_vm::chip8::Interpreter;
+
+use crate::emulator::Recorder;
+
+const SAMPLE_RATE: u32 = 44_100;
+
+// Frames run at 60 Hz, which divides the sample rate evenly.
+const SAMPLES_PER_FRAME: u32 = SAMPLE_RATE / 60;
+
+const HEADER_SIZE: u32 = 44;
+
+// Records the buzzer to a 16-bit mono WAV file, one frame of samples per
+// emulated frame, so the recording follows emulated rather than wall-clock
+// time. The sizes in the header are filled in when the recorder is dropped.
+pub struct WavRecorder {
+    output: BufWriter<File>,
+    tone: Tone,
+    samples: u32,
+}
+
+impl WavRecorder {
+    pub fn create(path: &Path, settings: ToneSettings) -> io::Result<Self> {
+        let mut output = BufWriter::new(File::create(path)?);
+
+        write_header(&mut output, 0)?;
+
+        Ok(Self {
+            output,
+            tone: Tone::new(settings, SAMPLE_RATE as f32),
+            samples: 0,
+        })
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.output.seek(SeekFrom::Start(0))?;
+
+        write_header(&mut self.output, self.samples * 2)?;
+
+        self.output.flush()
+    }
+}
+
+impl Recorder for WavRecorder {
+    fn record_frame(&mut self, interpreter: &Interpreter) -> io::Result<()> {
+        if let Some(pattern) = interpreter.audio_pattern {
+            self.tone.set_pattern(pattern, interpreter.pitch);
+        }
+
+        let playing = interpreter.sound_timer > 0;
+
+        for _ in 0..SAMPLES_PER_FRAME {
+            let sample = (self.tone.next_sample(playing) * i16::MAX as f32) as i16;
+
+            self.output.write_all(&sample.to_le_bytes())?;
+        }
+
+        self.samples += SAMPLES_PER_FRAME;
+
+        Ok(())
+    }
+
+    fn kind(&self) -> &'static str {
+        "audio"
+    }
+}
+
+impl Drop for WavRecorder {
+    fn drop(&mut self) {
+        if let Err(error) = self.finish() {
+            error!("Failed to finish the audio recording: {}", error);
+        }
+    }
+}
+
+fn write_header(output: &mut impl Write, data_size: u32) -> io::Result<()> {
+    let channels: u16 = 1;
+    let bits_per_sample: u16 = 16;
+    let block_align = channels * bits_per_sample / 8;
+
+    output.write_all(b"RIFF")?;
+    output.write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+    output.write_all(b"WAVE")?;
+
+    output.write_all(b"fmt ")?;
+    output.write_all(&16u32.to_le_bytes())?;
+    // Uncompressed PCM.
+    output.write_all(&1u16.to_le_bytes())?;
+    output.write_all(&channels.to_le_bytes())?;
+    output.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    output.write_all(&(SAMPLE_RATE * block_align as u32).to_le_bytes())?;
+    output.write_all(&block_align.to_le_bytes())?;
+    output.write_all(&bits_per_sample.to_le_bytes())?;
+
+    output.write_all(b"data")?;
+    output.write_all(&data_size.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chip8_vm::chip8::tone::ToneSettings;
+    use chip8_vm::chip8::Interpreter;
+
+    use super::{WavRecorder, HEADER_SIZE, SAMPLES_PER_FRAME};
+    use crate::emulator::Recorder;
+
+    #[test]
+    fn test_recording_has_one_frame_of_samples_per_frame() {
+        let path = std::env::temp_dir().join("chip8_vm_test_recording.wav");
+
+        let mut interpreter = Interpreter::new();
+
+        {
+            let mut recorder = WavRecorder::create(&path, ToneSettings::default()).unwrap();
+
+            recorder.record_frame(&interpreter).unwrap();
+
+            interpreter.sound_timer = 10;
+
+            recorder.record_frame(&interpreter).unwrap();
+        }
+
+        let wav = fs::read(&path).unwrap();
+
+        fs::remove_file(&path).unwrap();
+
+        let data_size = 2 * 2 * SAMPLES_PER_FRAME;
+
+        assert_eq!(wav.len() as u32, HEADER_SIZE + data_size);
+        assert_eq!(
+            wav[40..44],
+            data_size.to_le_bytes(),
+            "Data size should be filled in!"
+        );
+
+        let (silent, playing) =
+            wav[HEADER_SIZE as usize..].split_at(2 * SAMPLES_PER_FRAME as usize);
+
+        assert!(silent.iter().all(|&byte| byte == 0));
+        assert!(
+            playing.iter().any(|&byte| byte != 0),
+            "The tone should be recorded!"
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{
+    AudioBufferSourceNode, AudioContext, AudioScheduledSourceNode, CanvasRenderingContext2d, File,
+    FileReader, HtmlCanvasElement, HtmlInputElement, ImageData, KeyboardEvent, OscillatorNode,
+    OscillatorType,
+};
+
+use crate::chip8::tone::ToneSettings;
+use crate::chip8::{AudioSink, DisplayDriver, Frame, InputSource, Interpreter};
+
+const FRAME_MILLISECONDS: f64 = 1000.0 / 60.0;
+
+// After the tab has been in the background, carry on from where it was
+// rather than running every frame that was missed.
+const MAX_CATCH_UP_FRAMES: u32 = 4;
+
+// Keyboard keys for keypad keys 0 to F, matched by physical position like the
+// desktop's default layout.
+const KEY_CODES: [&str; 16] = [
+    "KeyX", "Digit1", "Digit2", "Digit3", "KeyQ", "KeyW", "KeyE", "KeyA", "KeyS", "KeyD", "KeyZ",
+    "KeyC", "Digit4", "KeyR", "KeyF", "KeyV",
+];
+
+// Runs ROMs picked with `rom_input` on `canvas`, which is sized to the
+// display and left to CSS to scale. Loading, frame timing, the keyboard and
+// sound are all handled here, through the same frontend traits as the
+// desktop, so the page only has to call this once.
+#[wasm_bindgen]
+pub fn run(canvas: HtmlCanvasElement, rom_input: HtmlInputElement) -> Result<(), JsValue> {
+    let emulator = Rc::new(RefCell::new(WebEmulator {
+        interpreter: None,
+        display: CanvasDisplay::new(canvas)?,
+        input: KeyboardInput::listen()?,
+        audio: WebAudio::default(),
+        last_time: None,
+        lag: 0.0,
+    }));
+
+    let loading = emulator.clone();
+    let input = rom_input.clone();
+
+    let on_change = Closure::<dyn FnMut()>::new(move || {
+        if let Some(file) = input.files().and_then(|files| files.get(0)) {
+            // Picking a file is the user gesture browsers want before they'll
+            // play sound.
+            loading.borrow_mut().audio.resume();
+
+            load_rom(loading.clone(), &file);
+        }
+    });
+
+    rom_input.add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref())?;
+
+    // The listener lives as long as the page.
+    on_change.forget();
+
+    request_frame(emulator);
+
+    Ok(())
+}
+
+struct WebEmulator {
+    interpreter: Option<Interpreter>,
+    display: CanvasDisplay,
+    input: KeyboardInput,
+    audio: WebAudio,
+    last_time: Option<f64>,
+    // Time not yet run, in milliseconds.
+    lag: f64,
+}
+
+impl WebEmulator {
+    // Runs as many whole 60 Hz frames as have passed since the last call, so
+    // the speed doesn't depend on the monitor's refresh rate.
+    fn animate(&mut self, time: f64) {
+        let elapsed = self
+            .last_time
+            .map_or(FRAME_MILLISECONDS, |last| time - last);
+
+        self.last_time = Some(time);
+
+        self.lag = (self.lag + elapsed).min(FRAME_MILLISECONDS * MAX_CATCH_UP_FRAMES as f64);
+
+        while self.lag >= FRAME_MILLISECONDS {
+            self.lag -= FRAME_MILLISECONDS;
+
+            let Some(interpreter) = &mut self.interpreter else {
+                break;
+            };
+
+            let result =
+                interpreter.run_frame_with(&mut self.display, &mut self.input, &mut self.audio);
+
+            if let Err(error) = result {
+                web_sys::console::error_1(&JsValue::from_str(&error));
+
+                self.interpreter = None;
+
+                self.audio.stop_tone();
+                self.audio.stop_sample();
+            }
+        }
+    }
+}
+
+fn request_frame(emulator: Rc<RefCell<WebEmulator>>) {
+    let callback = Closure::once_into_js(move |time: f64| {
+        emulator.borrow_mut().animate(time);
+
+        request_frame(emulator);
+    });
+
+    let window = web_sys::window().expect("The page should have a window");
+
+    window
+        .request_animation_frame(callback.unchecked_ref())
+        .expect("Animation frames should be available");
+}
+
+fn load_rom(emulator: Rc<RefCell<WebEmulator>>, file: &File) {
+    let Ok(reader) = FileReader::new() else {
+        return;
+    };
+
+    let loaded = reader.clone();
+
+    let on_load = Closure::once_into_js(move || {
+        let Ok(buffer) = loaded.result() else {
+            return;
+        };
+
+        let rom_data = js_sys::Uint8Array::new(&buffer).to_vec();
+
+        let seed = (js_sys::Math::random() * u32::MAX as f64) as u64;
+
+        let mut interpreter = Interpreter::builder()
+            .seed(seed)
+            .build()
+            .expect("Default interpreter configuration should be valid");
+
+        match interpreter.load_program(&rom_data) {
+            Ok(()) => {
+                let mut emulator = emulator.borrow_mut();
+
+                emulator.audio.stop_sample();
+
+                emulator.interpreter = Some(interpreter);
+            }
+            Err(error) => {
+                if let Some(window) = web_sys::window() {
+                    let _ = window.alert_with_message(&error);
+                }
+            }
+        }
+    });
+
+    reader.set_onload(Some(on_load.unchecked_ref()));
+
+    let _ = reader.read_as_array_buffer(file);
+}
+
+struct CanvasDisplay {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    rgba: Vec<u8>,
+}
+
+impl CanvasDisplay {
+    fn new(canvas: HtmlCanvasElement) -> Result<Self, JsValue> {
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("The canvas has no 2D context"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+
+        Ok(Self {
+            canvas,
+            context,
+            rgba: Vec::new(),
+        })
+    }
+}
+
+impl DisplayDriver for CanvasDisplay {
+    fn present(&mut self, frame: Frame<'_>) {
+        if frame.dirty.is_none() {
+            return;
+        }
+
+        let (width, height) = (frame.width as u32, frame.height as u32);
+
+        if (self.canvas.width(), self.canvas.height()) != (width, height) {
+            self.canvas.set_width(width);
+            self.canvas.set_height(height);
+        }
+
+        self.rgba.clear();
+
+        for pixel in 0..frame.width * frame.height {
+            let color = match frame.colors {
+                Some(colors) => colors[pixel],
+                None if frame.pixels[pixel / 8] >> (7 - pixel % 8) & 1 != 0 => 0xFFFFFF,
+                None => 0x000000,
+            };
+
+            let [_, red, green, blue] = color.to_be_bytes();
+
+            self.rgba.extend_from_slice(&[red, green, blue, 0xFF]);
+        }
+
+        if let Ok(image) =
+            ImageData::new_with_u8_clamped_array_and_sh(Clamped(&self.rgba), width, height)
+        {
+            let _ = self.context.put_image_data(&image, 0.0, 0.0);
+        }
+    }
+}
+
+struct KeyboardInput {
+    keys: Rc<RefCell<[bool; 16]>>,
+}
+
+impl KeyboardInput {
+    fn listen() -> Result<Self, JsValue> {
+        let keys = Rc::new(RefCell::new([false; 16]));
+
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window"))?;
+
+        for (event, pressed) in [("keydown", true), ("keyup", false)] {
+            let keys = keys.clone();
+
+            let listener = Closure::<dyn FnMut(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+                let code = event.code();
+
+                if let Some(key) = KEY_CODES.iter().position(|&name| name == code) {
+                    keys.borrow_mut()[key] = pressed;
+
+                    event.prevent_default();
+                }
+            });
+
+            window.add_event_listener_with_callback(event, listener.as_ref().unchecked_ref())?;
+
+            listener.forget();
+        }
+
+        Ok(Self { keys })
+    }
+}
+
+impl InputSource for KeyboardInput {
+    fn poll_keys(&mut self) -> [bool; 16] {
+        *self.keys.borrow()
+    }
+}
+
+// Web Audio, once a user gesture has allowed it. The tone is the desktop's
+// default one.
+#[derive(Default)]
+struct WebAudio {
+    context: Option<AudioContext>,
+    oscillator: Option<OscillatorNode>,
+    sample: Option<AudioBufferSourceNode>,
+}
+
+impl WebAudio {
+    fn resume(&mut self) {
+        match &self.context {
+            Some(context) => {
+                let _ = context.resume();
+            }
+            None => self.context = AudioContext::new().ok(),
+        }
+    }
+
+    fn start_oscillator(context: &AudioContext) -> Result<OscillatorNode, JsValue> {
+        let oscillator = context.create_oscillator()?;
+
+        oscillator.set_type(OscillatorType::Square);
+        oscillator
+            .frequency()
+            .set_value(ToneSettings::default().frequency);
+        oscillator.connect_with_audio_node(&context.destination())?;
+        oscillator.start()?;
+
+        Ok(oscillator)
+    }
+
+    fn start_sample(
+        context: &AudioContext,
+        samples: &[u8],
+        rate: u32,
+        looped: bool,
+    ) -> Result<AudioBufferSourceNode, JsValue> {
+        let buffer = context.create_buffer(1, samples.len() as u32, rate as f32)?;
+
+        let data: Vec<f32> = samples
+            .iter()
+            .map(|&sample| (sample as f32 - 128.0) / 128.0)
+            .collect();
+
+        buffer.copy_to_channel(&data, 0)?;
+
+        let source = context.create_buffer_source()?;
+
+        source.set_buffer(Some(&buffer));
+        source.set_loop(looped);
+        source.connect_with_audio_node(&context.destination())?;
+        source.start()?;
+
+        Ok(source)
+    }
+}
+
+impl AudioSink for WebAudio {
+    fn start_tone(&mut self) {
+        if self.oscillator.is_some() {
+            return;
+        }
+
+        if let Some(context) = &self.context {
+            self.oscillator = Self::start_oscillator(context).ok();
+        }
+    }
+
+    fn stop_tone(&mut self) {
+        if let Some(oscillator) = self.oscillator.take() {
+            let _ = oscillator.stop();
+        }
+    }
+
+    fn play_sample(&mut self, samples: &[u8], rate: u32, looped: bool) {
+        self.stop_sample();
+
+        // Browsers reject empty buffers and rates outside what they support,
+        // which leaves the program silent rather than stopping it.
+        if let Some(context) = &self.context {
+            self.sample = Self::start_sample(context, samples, rate, looped).ok();
+        }
+    }
+
+    fn stop_sample(&mut self) {
+        if let Some(sample) = self.sample.take() {
+            // AudioBufferSourceNode's own stop is a deprecated overload.
+            let source: &AudioScheduledSourceNode = &sample;
+
+            let _ = source.stop();
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use tracing::warn;
+use winit::event_loop::ActiveEventLoop;
+
+use crate::config;
+use crate::options::parse_position;
+
+// How a ROM window was left: where it was, how big, and which overlays were
+// up, so the next run opens the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowLayout {
+    pub position: Option<(i32, i32)>,
+    // The inside of the window, in physical pixels.
+    pub size: Option<(u32, u32)>,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    pub perf_overlay: bool,
+    pub keypad_overlay: bool,
+    pub timer_overlay: bool,
+    pub pixel_grid: bool,
+}
+
+impl WindowLayout {
+    // The layout last left with these monitors. Each arrangement of monitors
+    // has its own, kept in a file named after a hash of `monitors` and written
+    // like the config file:
+    //
+    //   position = 120,80
+    //   size = 1280x640
+    //   overlays = performance,timer
+    pub fn load(monitors: &str) -> Option<Self> {
+        let path = layout_path(monitors)?;
+
+        let args = config::read_options(&path).ok()?;
+
+        if args.is_empty() {
+            return None;
+        }
+
+        match Self::from_args(&args) {
+            Ok(layout) => Some(layout),
+            Err(error) => {
+                warn!(
+                    "Ignoring the window layout in {}: {}",
+                    path.display(),
+                    error
+                );
+
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, monitors: &str) -> io::Result<()> {
+        let path = layout_path(monitors)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No config directory"))?;
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        fs::write(path, self.lines())
+    }
+
+    fn overlays(&self) -> [(&'static str, bool); 4] {
+        [
+            ("performance", self.perf_overlay),
+            ("keypad", self.keypad_overlay),
+            ("timer", self.timer_overlay),
+            ("grid", self.pixel_grid),
+        ]
+    }
+
+    fn lines(&self) -> String {
+        let mut lines = String::new();
+
+        if let Some((x, y)) = self.position {
+            lines.push_str(&format!("position = {},{}\n", x, y));
+        }
+
+        if let Some((width, height)) = self.size {
+            lines.push_str(&format!("size = {}x{}\n", width, height));
+        }
+
+        if self.maximized {
+            lines.push_str("maximized\n");
+        }
+
+        if self.fullscreen {
+            lines.push_str("fullscreen\n");
+        }
+
+        let overlays: Vec<&str> = self
+            .overlays()
+            .into_iter()
+            .filter(|&(_, visible)| visible)
+            .map(|(name, _)| name)
+            .collect();
+
+        if !overlays.is_empty() {
+            lines.push_str(&format!("overlays = {}\n", overlays.join(",")));
+        }
+
+        lines
+    }
+
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut layout = Self::default();
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--position" => layout.position = Some(parse_position(value()?)?),
+                "--size" => {
+                    let size = value()?;
+
+                    layout.size = Some(
+                        size.split_once('x')
+                            .and_then(|(width, height)| {
+                                Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+                            })
+                            .ok_or_else(|| {
+                                format!("Invalid size (expected widthxheight): {}", size)
+                            })?,
+                    );
+                }
+                "--maximized" => layout.maximized = true,
+                "--fullscreen" => layout.fullscreen = true,
+                "--overlays" => {
+                    for name in value()?.split(',').map(str::trim) {
+                        match name {
+                            "performance" => layout.perf_overlay = true,
+                            "keypad" => layout.keypad_overlay = true,
+                            "timer" => layout.timer_overlay = true,
+                            "grid" => layout.pixel_grid = true,
+                            name => return Err(format!("Unknown overlay '{}'", name)),
+                        }
+                    }
+                }
+                arg => return Err(format!("Unknown setting '{}'", arg.trim_start_matches('-'))),
+            }
+        }
+
+        Ok(layout)
+    }
+}
+
+// Names the monitors attached and where they are, to tell one arrangement of
+// them from another, e.g. a laptop on its own from the laptop at a desk.
+pub fn monitors(event_loop: &ActiveEventLoop) -> String {
+    let mut monitors: Vec<String> = event_loop
+        .available_monitors()
+        .map(|monitor| {
+            let position = monitor.position();
+            let size = monitor.size();
+
+            format!(
+                "{} {}x{} at {},{}",
+                monitor.name().unwrap_or_default(),
+                size.width,
+                size.height,
+                position.x,
+                position.y
+            )
+        })
+        .collect();
+
+    monitors.sort();
+
+    monitors.join("\n")
+}
+
+fn layout_path(monitors: &str) -> Option<PathBuf> {
+    config::hashed_file("window_layouts", monitors.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WindowLayout;
+    use crate::config::parse_options;
+
+    #[test]
+    fn test_layouts_read_back_as_they_were_saved() {
+        let layout = WindowLayout {
+            position: Some((-1280, 80)),
+            size: Some((1280, 640)),
+            maximized: false,
+            fullscreen: true,
+            perf_overlay: true,
+            keypad_overlay: false,
+            timer_overlay: true,
+            pixel_grid: false,
+        };
+
+        let lines = layout.lines();
+
+        assert_eq!(
+            lines,
+            "position = -1280,80\nsize = 1280x640\nfullscreen\noverlays = performance,timer\n"
+        );
+
+        let args = parse_options(&lines).unwrap();
+
+        assert_eq!(WindowLayout::from_args(&args), Ok(layout));
+
+        assert!(
+            WindowLayout::from_args(&["--size".to_string(), "big".to_string()]).is_err(),
+            "A size that isn't widthxheight should be an error!"
+        );
+    }
+}